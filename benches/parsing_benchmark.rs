@@ -19,15 +19,18 @@ fn load_lines(path: &PathBuf) -> io::Result<Vec<Vec<u8>>> {
     Ok(result)
 }
 
+#[allow(dead_code)]
 fn parse_lines<T: OptFields>(input: &[Vec<u8>]) -> GFA<BString, T> {
     let parser: GFAParser<BString, T> = GFAParser::new();
     parser.parse_lines(input.iter()).unwrap()
 }
 
+#[allow(dead_code)]
 fn parse_lines_noopt(input: &[Vec<u8>]) -> GFA<BString, ()> {
     parse_lines(input)
 }
 
+#[allow(dead_code)]
 fn parse_lines_withopt(input: &[Vec<u8>]) -> GFA<BString, OptionalFields> {
     parse_lines(input)
 }
@@ -0,0 +1,380 @@
+//! Derive macro for the `OptFields` trait of the `gfa` crate. Use it
+//! through the `derive` feature of `gfa`, which re-exports it as
+//! `gfa::optfields::OptFields`.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Fields, GenericArgument, Ident, Lit,
+    Meta, NestedMeta, PathArguments, Type,
+};
+
+/// Derive `OptFields` for a struct with named fields, where each
+/// field of type `Option<V>` holds the optional field with the tag
+/// given by the field name in upper case, or by an `#[opt(tag =
+/// "..")]` attribute. `V` must implement `gfa::optfields::TagValue`.
+///
+/// At most one other field is allowed, which must implement
+/// `OptFields` itself, e.g. `OptionalFields`, and stores all fields
+/// whose tags aren't named in the struct, or whose values have the
+/// wrong type. Fields that can't be parsed are dropped.
+///
+/// ```ignore
+/// #[derive(Debug, Default, Clone, OptFields)]
+/// struct MyTags {
+///     ln: Option<i64>,
+///     #[opt(tag = "cg")]
+///     cigar: Option<BString>,
+///     rest: OptionalFields,
+/// }
+/// ```
+///
+/// `get_value`, `get_typed`, `to_fields`, and `try_to_fields` see
+/// both the typed fields and the rest field, while `get_field` and
+/// `fields`, which return references to `OptField`s, only see the
+/// rest field. A typed field whose value can't be written, such as a
+/// `char` that isn't ASCII, is left out by `to_fields` and is an
+/// error from `try_to_fields`, which the writer uses. The typed
+/// fields always come first, in the order of the struct, so
+/// `sort_by_tag` only sorts the rest field.
+#[proc_macro_derive(OptFields, attributes(opt))]
+pub fn derive_opt_fields(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+struct TaggedField<'a> {
+    ident: &'a Ident,
+    tag: Vec<u8>,
+}
+
+struct RestField<'a> {
+    ident: &'a Ident,
+    ty: &'a Type,
+}
+
+/// The `T` in `Option<T>`, if the type is an `Option`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+    let last = path.segments.last()?;
+    if last.ident != "Option" {
+        return None;
+    }
+    match &last.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => {
+            match args.args.first()? {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The tag given in an `#[opt(tag = "..")]` attribute, if any.
+fn tag_attribute(field: &syn::Field) -> syn::Result<Option<String>> {
+    let attr = match field.attrs.iter().find(|a| a.path.is_ident("opt")) {
+        Some(attr) => attr,
+        None => return Ok(None),
+    };
+    let meta = attr.parse_meta()?;
+    if let Meta::List(list) = &meta {
+        if let (1, Some(NestedMeta::Meta(Meta::NameValue(nv)))) =
+            (list.nested.len(), list.nested.first())
+        {
+            if nv.path.is_ident("tag") {
+                return match &nv.lit {
+                    Lit::Str(tag) => Ok(Some(tag.value())),
+                    lit => Err(syn::Error::new_spanned(
+                        lit,
+                        "the tag must be a string",
+                    )),
+                };
+            }
+        }
+    }
+    Err(syn::Error::new_spanned(
+        meta,
+        "expected #[opt(tag = \"..\")]",
+    ))
+}
+
+fn valid_tag(tag: &[u8]) -> bool {
+    tag.len() == 2
+        && tag[0].is_ascii_alphabetic()
+        && tag[1].is_ascii_alphanumeric()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(
+                input,
+                "OptFields can only be derived for structs with named fields",
+            )),
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "OptFields can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut tagged: Vec<TaggedField> = Vec::new();
+    let mut rest: Option<RestField> = None;
+
+    for field in fields.iter() {
+        let ident = field.ident.as_ref().unwrap();
+        if option_inner(&field.ty).is_none() {
+            if rest.is_some() {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "only one field that isn't an Option is allowed, \
+                     to store the remaining optional fields",
+                ));
+            }
+            rest = Some(RestField {
+                ident,
+                ty: &field.ty,
+            });
+            continue;
+        }
+
+        let tag = match tag_attribute(field)? {
+            Some(tag) => tag,
+            None => ident.to_string().to_uppercase(),
+        };
+        if !valid_tag(tag.as_bytes()) {
+            return Err(syn::Error::new_spanned(
+                field,
+                format!(
+                    "`{}` is not a valid tag; tags must match \
+                     [A-Za-z][A-Za-z0-9], use #[opt(tag = \"..\")] to set it",
+                    tag
+                ),
+            ));
+        }
+        if tagged.iter().any(|t| t.tag == tag.as_bytes()) {
+            return Err(syn::Error::new_spanned(
+                field,
+                format!("tag `{}` is used by more than one field", tag),
+            ));
+        }
+        tagged.push(TaggedField {
+            ident,
+            tag: tag.into_bytes(),
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+
+    let optfields = quote!(::gfa::optfields);
+
+    let idents: Vec<_> = tagged.iter().map(|t| t.ident).collect();
+    let tags: Vec<_> = tagged
+        .iter()
+        .map(|t| syn::LitByteStr::new(&t.tag, proc_macro2::Span::call_site()))
+        .collect();
+    let tag_arrays: Vec<_> = tagged
+        .iter()
+        .map(|t| {
+            let (a, b) = (t.tag[0], t.tag[1]);
+            quote!([#a, #b])
+        })
+        .collect();
+
+    let (
+        get_field,
+        get_value_rest,
+        get_typed_rest,
+        fields_body,
+        push_rest,
+        build_rest,
+        merge_rest,
+        sort_rest,
+        rest_fields,
+        try_rest_fields,
+    ) = match &rest {
+        Some(RestField { ident, ty }) => (
+            quote!(#optfields::OptFields::get_field(&self.#ident, tag)),
+            quote!(#optfields::OptFields::get_value(&self.#ident, tag)),
+            quote!(#optfields::OptFields::get_typed(&self.#ident, tag)),
+            quote!(#optfields::OptFields::fields(&self.#ident)),
+            quote!(rest.push(field.to_vec());),
+            quote!(#ident: <#ty as #optfields::OptFields>::parse(rest),),
+            quote!(#optfields::OptFields::merge(&mut self.#ident, other.#ident);),
+            quote!(#optfields::OptFields::sort_by_tag(&mut self.#ident);),
+            quote! {
+                fields.extend(
+                    #optfields::OptFields::to_fields(&self.#ident)
+                        .iter()
+                        .cloned(),
+                );
+            },
+            quote! {
+                fields.extend(
+                    #optfields::OptFields::try_to_fields(&self.#ident)?
+                        .iter()
+                        .cloned(),
+                );
+            },
+        ),
+        None => (
+            quote!({
+                let _ = tag;
+                None
+            }),
+            quote!({
+                let _ = tag;
+                None
+            }),
+            quote!({
+                let _ = tag;
+                None
+            }),
+            quote!(&[]),
+            quote!(),
+            quote!(),
+            quote!(),
+            quote!(),
+            quote!(),
+            quote!(),
+        ),
+    };
+
+    Ok(quote! {
+        impl #impl_generics #optfields::OptFields for #name #ty_generics
+        #where_clause
+        {
+            fn get_field(&self, tag: &[u8]) -> Option<&#optfields::OptField> {
+                #get_field
+            }
+
+            fn get_value(
+                &self,
+                tag: &[u8],
+            ) -> Option<::std::borrow::Cow<'_, #optfields::OptFieldVal>> {
+                #(
+                    if tag == #tags {
+                        if let Some(value) = &self.#idents {
+                            return #optfields::TagValue::to_tag_value(value)
+                                .map(::std::borrow::Cow::Owned);
+                        }
+                    }
+                )*
+                #get_value_rest
+            }
+
+            fn get_typed<'a, V>(&'a self, tag: &[u8]) -> Option<V>
+            where
+                V: #optfields::FromOptFieldVal<'a>,
+            {
+                #(
+                    if tag == #tags {
+                        if let Some(value) = &self.#idents {
+                            return #optfields::TagValue::get_as(value);
+                        }
+                    }
+                )*
+                #get_typed_rest
+            }
+
+            fn fields(&self) -> &[#optfields::OptField] {
+                #fields_body
+            }
+
+            fn parse<T>(input: T) -> Self
+            where
+                T: IntoIterator,
+                T::Item: AsRef<[u8]>,
+            {
+                #(let mut #idents = None;)*
+                let mut rest: Vec<Vec<u8>> = Vec::new();
+                for field in input {
+                    let field = field.as_ref();
+                    let tag = field.get(0..2).unwrap_or(b"");
+                    #(
+                        if tag == #tags && #idents.is_none() {
+                            #idents = #optfields::OptField::parse(field)
+                                .and_then(|f| {
+                                    #optfields::TagValue::from_tag_value(f.value)
+                                });
+                            if #idents.is_some() {
+                                continue;
+                            }
+                        }
+                    )*
+                    #push_rest
+                }
+                let _ = &mut rest;
+                Self {
+                    #(#idents,)*
+                    #build_rest
+                }
+            }
+
+            fn merge(&mut self, other: Self) {
+                #(
+                    if self.#idents.is_none() {
+                        self.#idents = other.#idents;
+                    }
+                )*
+                #merge_rest
+            }
+
+            fn sort_by_tag(&mut self) {
+                #sort_rest
+            }
+
+            fn to_fields(&self) -> ::std::borrow::Cow<'_, [#optfields::OptField]> {
+                let mut fields = Vec::new();
+                #(
+                    if let Some(value) = self
+                        .#idents
+                        .as_ref()
+                        .and_then(#optfields::TagValue::to_tag_value)
+                    {
+                        fields.push(#optfields::OptField {
+                            tag: #tag_arrays,
+                            value,
+                        });
+                    }
+                )*
+                #rest_fields
+                ::std::borrow::Cow::Owned(fields)
+            }
+
+            fn try_to_fields(
+                &self,
+            ) -> Result<
+                ::std::borrow::Cow<'_, [#optfields::OptField]>,
+                #optfields::InvalidTagValue,
+            > {
+                let mut fields = Vec::new();
+                #(
+                    if let Some(value) = &self.#idents {
+                        let tag = #tag_arrays;
+                        let value = #optfields::TagValue::to_tag_value(value)
+                            .ok_or(#optfields::InvalidTagValue { tag })?;
+                        fields.push(#optfields::OptField { tag, value });
+                    }
+                )*
+                #try_rest_fields
+                Ok(::std::borrow::Cow::Owned(fields))
+            }
+        }
+    })
+}
@@ -0,0 +1,320 @@
+//! Reading and writing BGZF, the blocked gzip format used by
+//! samtools and tabix. A BGZF file is a series of gzip members that
+//! each hold at most 64KiB of data, so any position in the
+//! decompressed data can be reached by seeking to the start of its
+//! block and decompressing only that block. Such positions are
+//! addressed by virtual offsets, which are the offset of the block in
+//! the compressed file shifted 16 bits to the left, combined with the
+//! offset within the decompressed block.
+
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Crc};
+
+/// The most data put in a single block, as in htslib, leaving room
+/// for the block to grow a little if the data is incompressible.
+const MAX_BLOCK_DATA: usize = 0xff00;
+
+const HEADER_LEN: usize = 18;
+const FOOTER_LEN: usize = 8;
+
+/// The empty block that marks the end of a BGZF file.
+const EOF_BLOCK: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00,
+    0x42, 0x43, 0x02, 0x00, 0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00,
+];
+
+/// Combine a block's offset in the compressed file and an offset in
+/// its decompressed data into a virtual offset.
+pub fn virtual_offset(block_offset: u64, data_offset: u16) -> u64 {
+    (block_offset << 16) | data_offset as u64
+}
+
+/// Split a virtual offset into the block offset in the compressed
+/// file and the offset in the decompressed block.
+pub fn split_virtual_offset(offset: u64) -> (u64, u16) {
+    (offset >> 16, (offset & 0xffff) as u16)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Writes BGZF, compressing the data in blocks. The end-of-file
+/// marker block is written by `finish`, or when the writer is
+/// dropped.
+pub struct BgzfWriter<W: Write> {
+    inner: Option<W>,
+    buf: Vec<u8>,
+    block_offset: u64,
+    level: flate2::Compression,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_level(writer, flate2::Compression::default())
+    }
+
+    pub fn with_level(writer: W, level: flate2::Compression) -> Self {
+        BgzfWriter {
+            inner: Some(writer),
+            buf: Vec::with_capacity(MAX_BLOCK_DATA),
+            block_offset: 0,
+            level,
+        }
+    }
+
+    /// The virtual offset that the next byte written will have.
+    pub fn virtual_offset(&self) -> u64 {
+        virtual_offset(self.block_offset, self.buf.len() as u16)
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let inner = self.inner.as_mut().expect("BGZF writer is finished");
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), self.level);
+        encoder.write_all(&self.buf)?;
+        let mut data = encoder.finish()?;
+        if HEADER_LEN + data.len() + FOOTER_LEN > 1 << 16 {
+            let none = flate2::Compression::none();
+            let mut encoder = DeflateEncoder::new(Vec::new(), none);
+            encoder.write_all(&self.buf)?;
+            data = encoder.finish()?;
+        }
+
+        let mut crc = Crc::new();
+        crc.update(&self.buf);
+
+        let block_size = HEADER_LEN + data.len() + FOOTER_LEN;
+        let mut header = EOF_BLOCK[..HEADER_LEN].to_vec();
+        header[16..].copy_from_slice(&((block_size - 1) as u16).to_le_bytes());
+
+        inner.write_all(&header)?;
+        inner.write_all(&data)?;
+        inner.write_all(&crc.sum().to_le_bytes())?;
+        inner.write_all(&(self.buf.len() as u32).to_le_bytes())?;
+
+        self.block_offset += block_size as u64;
+        self.buf.clear();
+        Ok(())
+    }
+
+    /// Write the remaining data and the end-of-file marker, and
+    /// return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_block()?;
+        let mut inner = self.inner.take().expect("BGZF writer is finished");
+        inner.write_all(&EOF_BLOCK)?;
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(MAX_BLOCK_DATA - self.buf.len());
+        self.buf.extend_from_slice(&buf[..len]);
+        if self.buf.len() == MAX_BLOCK_DATA {
+            self.write_block()?;
+        }
+        Ok(len)
+    }
+
+    /// Write the data buffered so far as a block, even if it's short.
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.inner.as_mut().map_or(Ok(()), |inner| inner.flush())
+    }
+}
+
+impl<W: Write> Drop for BgzfWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.write_block();
+            if let Some(inner) = self.inner.as_mut() {
+                let _ = inner.write_all(&EOF_BLOCK);
+                let _ = inner.flush();
+            }
+        }
+    }
+}
+
+/// Reads BGZF one block at a time, keeping track of the virtual
+/// offset, and supporting seeking to virtual offsets when the
+/// underlying reader is seekable.
+pub struct BgzfReader<R: Read> {
+    inner: R,
+    block: Vec<u8>,
+    pos: usize,
+    block_offset: u64,
+    next_block_offset: u64,
+}
+
+impl<R: Read> BgzfReader<R> {
+    pub fn new(reader: R) -> Self {
+        BgzfReader {
+            inner: reader,
+            block: Vec::new(),
+            pos: 0,
+            block_offset: 0,
+            next_block_offset: 0,
+        }
+    }
+
+    /// The virtual offset of the next byte that will be read.
+    pub fn virtual_offset(&self) -> u64 {
+        if self.pos < self.block.len() {
+            virtual_offset(self.block_offset, self.pos as u16)
+        } else {
+            virtual_offset(self.next_block_offset, 0)
+        }
+    }
+
+    /// Read and decompress the next block, returning false at the end
+    /// of the input.
+    fn read_block(&mut self) -> io::Result<bool> {
+        let mut header = [0u8; 12];
+        let mut read = 0;
+        while read < header.len() {
+            match self.inner.read(&mut header[read..])? {
+                0 if read == 0 => return Ok(false),
+                0 => return Err(invalid_data("truncated BGZF block")),
+                n => read += n,
+            }
+        }
+        if header[..4] != [0x1f, 0x8b, 0x08, 0x04] {
+            return Err(invalid_data("invalid BGZF block header"));
+        }
+
+        let extra_len = u16::from_le_bytes([header[10], header[11]]) as usize;
+        let mut extra = vec![0u8; extra_len];
+        self.inner.read_exact(&mut extra)?;
+
+        let mut block_size = None;
+        let mut subfields = &extra[..];
+        while subfields.len() >= 4 {
+            let len = u16::from_le_bytes([subfields[2], subfields[3]]) as usize;
+            if subfields[..2] == *b"BC" && len == 2 && subfields.len() >= 6 {
+                let size = u16::from_le_bytes([subfields[4], subfields[5]]);
+                block_size = Some(size as usize + 1);
+            }
+            subfields = &subfields[(4 + len).min(subfields.len())..];
+        }
+        let block_size = block_size
+            .filter(|&size| size >= 12 + extra_len + FOOTER_LEN)
+            .ok_or_else(|| invalid_data("missing BGZF block size"))?;
+
+        let mut rest = vec![0u8; block_size - 12 - extra_len];
+        self.inner.read_exact(&mut rest)?;
+        let (data, footer) = rest.split_at(rest.len() - FOOTER_LEN);
+
+        self.block.clear();
+        DeflateDecoder::new(data).read_to_end(&mut self.block)?;
+
+        let mut crc = Crc::new();
+        crc.update(&self.block);
+        if footer[..4] != crc.sum().to_le_bytes()
+            || footer[4..] != (self.block.len() as u32).to_le_bytes()
+        {
+            return Err(invalid_data("BGZF block checksum mismatch"));
+        }
+
+        self.pos = 0;
+        self.block_offset = self.next_block_offset;
+        self.next_block_offset += block_size as u64;
+        Ok(true)
+    }
+}
+
+impl<R: Read + Seek> BgzfReader<R> {
+    /// Seek to the given virtual offset.
+    pub fn seek_virtual(&mut self, offset: u64) -> io::Result<()> {
+        let (block_offset, data_offset) = split_virtual_offset(offset);
+        self.inner.seek(SeekFrom::Start(block_offset))?;
+        self.next_block_offset = block_offset;
+        self.block.clear();
+        self.pos = 0;
+        if self.read_block()? {
+            if data_offset as usize > self.block.len() {
+                return Err(invalid_data("virtual offset is past block end"));
+            }
+            self.pos = data_offset as usize;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for BgzfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: Read> BufRead for BgzfReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos >= self.block.len() {
+            if !self.read_block()? {
+                break;
+            }
+        }
+        Ok(&self.block[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.block.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::Compression;
+    use std::io::Cursor;
+
+    #[test]
+    fn bgzf_round_trip_and_seek() {
+        let lines: Vec<String> =
+            (0..20000).map(|i| format!("S\t{}\tACGT\n", i)).collect();
+
+        let mut writer = BgzfWriter::new(Vec::new());
+        let mut offsets = Vec::new();
+        for line in lines.iter() {
+            offsets.push(writer.virtual_offset());
+            writer.write_all(line.as_bytes()).unwrap();
+        }
+        let compressed = writer.finish().unwrap();
+        let mut reader = &compressed[..];
+        let compression = Compression::detect(&mut reader).unwrap();
+        assert_eq!(compression, Compression::Bgzf);
+        assert!(compressed.ends_with(&EOF_BLOCK));
+
+        let mut decoded = String::new();
+        let reader = crate::reader::decompress_reader(&compressed[..]);
+        reader.unwrap().read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, lines.concat());
+
+        let mut reader = BgzfReader::new(Cursor::new(&compressed));
+        let mut line = String::new();
+        for (ix, &offset) in offsets.iter().enumerate() {
+            assert_eq!(reader.virtual_offset(), offset);
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, lines[ix]);
+        }
+
+        for &ix in [15000, 3, 19999].iter() {
+            reader.seek_virtual(offsets[ix]).unwrap();
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, lines[ix]);
+        }
+    }
+}
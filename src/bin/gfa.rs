@@ -0,0 +1,362 @@
+//! Command line tool for working with GFA files, built on the `gfa`
+//! library. Input files may be gzip or BGZF compressed, and output
+//! files are compressed based on their extension.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, Write},
+    process,
+};
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+
+use gfa::{
+    fasta::{write_paths_fasta, FastaOptions, PathHeader},
+    gfa::GFA,
+    graph::{neighborhood_subgraph, path_range_subgraph, Radius},
+    optfields::OptionalFields,
+    parser::{GFAParser, ParseWarning, WarningSink},
+    reader::{compress_writer, CompressedWriter, Compression},
+    stats::graph_composition,
+    validation::{segment_length, validate},
+    writer::{write_gfa, write_gfa2},
+};
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+type Graph = GFA<Vec<u8>, OptionalFields>;
+
+/// Load a GFA, or a graph in the JSON format of `GFA::to_json` if the
+/// path ends with `.json`.
+fn load_graph(path: &str) -> CliResult<Graph> {
+    load_graph_with_warnings(path, &mut ())
+}
+
+/// Load a graph like `load_graph`, reporting the recoverable problems
+/// the parser skipped over, such as malformed optional fields, to
+/// `warnings`.
+fn load_graph_with_warnings<W: WarningSink>(
+    path: &str,
+    warnings: &mut W,
+) -> CliResult<Graph> {
+    if path.ends_with(".json") {
+        let json = std::fs::read_to_string(path)?;
+        Ok(GFA::from_json(&json)?)
+    } else {
+        let parser = GFAParser::new();
+        Ok(parser.parse_file_with_warnings(path, warnings)?)
+    }
+}
+
+type Output = CompressedWriter<Box<dyn Write>>;
+
+/// Open the output file, compressed based on its extension, or stdout
+/// if none was given. The output must be completed with `finish`.
+fn open_output(matches: &ArgMatches) -> CliResult<Output> {
+    let (writer, compression): (Box<dyn Write>, _) =
+        match matches.value_of("output") {
+            Some(path) => (
+                Box::new(io::BufWriter::new(File::create(path)?)),
+                Compression::from_extension(path),
+            ),
+            None => (
+                Box::new(io::BufWriter::new(io::stdout())),
+                Compression::None,
+            ),
+        };
+    Ok(compress_writer(writer, compression)?)
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, name: &str) -> CliResult<T> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid {}: `{}`", name, value).into())
+}
+
+fn write_graph(graph: &Graph, out: &mut dyn Write) -> CliResult<()> {
+    let mut string = String::new();
+    write_gfa(graph, &mut string)
+        .map_err(|_| "A segment, link, or path couldn't be written")?;
+    out.write_all(string.as_bytes())?;
+    Ok(())
+}
+
+fn write_graph_gfa2(graph: &Graph, out: &mut dyn Write) -> CliResult<()> {
+    let mut string = String::new();
+    write_gfa2(graph, &mut string)
+        .map_err(|_| "A segment, edge, or group couldn't be written")?;
+    out.write_all(string.as_bytes())?;
+    Ok(())
+}
+
+/// The N50 of the given lengths, i.e. the length such that the
+/// lengths at least as long cover half the total.
+fn n50(lengths: &mut [usize]) -> usize {
+    let total: usize = lengths.iter().sum();
+    lengths.sort_unstable_by(|a, b| b.cmp(a));
+    let mut covered = 0;
+    for &len in lengths.iter() {
+        covered += len;
+        if covered * 2 >= total {
+            return len;
+        }
+    }
+    0
+}
+
+fn stats(matches: &ArgMatches) -> CliResult<()> {
+    let graph = load_graph(matches.value_of("input").unwrap())?;
+    let mut out = open_output(matches)?;
+
+    let mut lengths: Vec<usize> =
+        graph.segments.iter().filter_map(segment_length).collect();
+    let total: usize = lengths.iter().sum();
+
+    writeln!(out, "segments\t{}", graph.segments.len())?;
+    writeln!(out, "links\t{}", graph.links.len())?;
+    writeln!(out, "containments\t{}", graph.containments.len())?;
+    writeln!(out, "paths\t{}", graph.paths.len())?;
+    writeln!(out, "walks\t{}", graph.walks.len())?;
+    writeln!(out, "total_length\t{}", total)?;
+    if let Some(&min) = lengths.iter().min() {
+        writeln!(out, "min_length\t{}", min)?;
+    }
+    if let Some(&max) = lengths.iter().max() {
+        writeln!(out, "max_length\t{}", max)?;
+    }
+    writeln!(out, "n50\t{}", n50(&mut lengths))?;
+    let composition = graph_composition(&graph);
+    writeln!(out, "n_bases\t{}", composition.n)?;
+    if let Some(gc) = composition.gc_content() {
+        writeln!(out, "gc_content\t{:.4}", gc)?;
+    }
+    out.finish()?;
+    Ok(())
+}
+
+fn validate_cmd(matches: &ArgMatches) -> CliResult<()> {
+    let input = matches.value_of("input").unwrap();
+    let mut warnings: Vec<ParseWarning> = Vec::new();
+    let graph = load_graph_with_warnings(input, &mut warnings)?;
+    let mut out = open_output(matches)?;
+
+    // the parser drops malformed optional fields, so they're only
+    // found in its warnings
+    for warning in warnings.iter() {
+        writeln!(out, "{}", warning)?;
+    }
+    let issues = validate(&graph);
+    for issue in issues.iter() {
+        writeln!(out, "{}", issue)?;
+    }
+    out.finish()?;
+    let count = warnings.len() + issues.len();
+    if count == 0 {
+        Ok(())
+    } else {
+        Err(format!("Found {} issues", count).into())
+    }
+}
+
+fn convert(matches: &ArgMatches) -> CliResult<()> {
+    let graph = load_graph(matches.value_of("input").unwrap())?;
+    let mut out = open_output(matches)?;
+
+    match matches.value_of("to").unwrap() {
+        "gfa1" => write_graph(&graph, &mut out)?,
+        "gfa2" => write_graph_gfa2(&graph, &mut out)?,
+        "json" => writeln!(out, "{}", graph.to_json()?)?,
+        format => return Err(format!("Unknown format `{}`", format).into()),
+    }
+    out.finish()?;
+    Ok(())
+}
+
+fn extract(matches: &ArgMatches) -> CliResult<()> {
+    let graph = load_graph(matches.value_of("input").unwrap())?;
+    let mut out = open_output(matches)?;
+
+    let subgraph = if let Some(path) = matches.value_of("path") {
+        let start = match matches.value_of("start") {
+            Some(start) => parse_number(start, "start")?,
+            None => 0,
+        };
+        let end = match matches.value_of("end") {
+            Some(end) => parse_number(end, "end")?,
+            None => usize::MAX,
+        };
+        path_range_subgraph(&graph, path.as_bytes(), start, end)
+            .ok_or_else(|| format!("No path or walk named `{}`", path))?
+    } else {
+        let seeds: Vec<Vec<u8>> = matches
+            .value_of("segments")
+            .unwrap()
+            .split(',')
+            .map(|name| name.as_bytes().to_vec())
+            .collect();
+        let radius =
+            parse_number(matches.value_of("radius").unwrap(), "radius")?;
+        let radius = if matches.is_present("bp") {
+            Radius::BasePairs(radius)
+        } else {
+            Radius::Hops(radius)
+        };
+        neighborhood_subgraph(&graph, &seeds, radius)
+    };
+
+    write_graph(&subgraph, &mut out)?;
+    out.finish()?;
+    Ok(())
+}
+
+fn paths_to_fasta(matches: &ArgMatches) -> CliResult<()> {
+    let graph = load_graph(matches.value_of("input").unwrap())?;
+    let mut out = open_output(matches)?;
+
+    let line_width = match matches.value_of("width") {
+        Some(width) => Some(parse_number(width, "line width")?),
+        None => None,
+    };
+    let options = FastaOptions {
+        line_width,
+        skip_missing: matches.is_present("skip-missing"),
+    };
+    let header = if matches.is_present("lengths") {
+        PathHeader::NameLength
+    } else {
+        PathHeader::Name
+    };
+    write_paths_fasta(&graph, header, &options, &mut out)?;
+    out.finish()?;
+    Ok(())
+}
+
+fn main() {
+    let input = Arg::with_name("input")
+        .help("The input GFA, or JSON if it ends with .json")
+        .required(true);
+    let output = Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .takes_value(true)
+        .help("Write to this file instead of stdout");
+
+    let app = App::new("gfa")
+        .about("Tools for working with GFA files")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Print record counts and segment length statistics")
+                .arg(input.clone())
+                .arg(output.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Check the graph for consistency, printing any issues")
+                .arg(input.clone())
+                .arg(output.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("convert")
+                .about("Convert the graph to another format")
+                .arg(input.clone())
+                .arg(output.clone())
+                .arg(
+                    Arg::with_name("to")
+                        .short("t")
+                        .long("to")
+                        .takes_value(true)
+                        .possible_values(&["gfa1", "gfa2", "json"])
+                        .default_value("gfa1")
+                        .help("The output format"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("extract")
+                .about("Extract a path range or the neighborhood of segments")
+                .arg(input.clone())
+                .arg(output.clone())
+                .arg(
+                    Arg::with_name("path")
+                        .short("p")
+                        .long("path")
+                        .takes_value(true)
+                        .required_unless("segments")
+                        .conflicts_with("segments")
+                        .help("Extract the segments of this path or walk"),
+                )
+                .arg(
+                    Arg::with_name("start")
+                        .long("start")
+                        .takes_value(true)
+                        .requires("path")
+                        .help("The start of the path range, in base pairs"),
+                )
+                .arg(
+                    Arg::with_name("end")
+                        .long("end")
+                        .takes_value(true)
+                        .requires("path")
+                        .help("The end of the path range, in base pairs"),
+                )
+                .arg(
+                    Arg::with_name("segments")
+                        .short("s")
+                        .long("segments")
+                        .takes_value(true)
+                        .help("Comma-separated segments to extract around"),
+                )
+                .arg(
+                    Arg::with_name("radius")
+                        .short("r")
+                        .long("radius")
+                        .takes_value(true)
+                        .default_value("1")
+                        .help("The size of the neighborhood, in links"),
+                )
+                .arg(
+                    Arg::with_name("bp")
+                        .long("bp")
+                        .help("Measure the radius in base pairs instead"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("paths-to-fasta")
+                .about("Write the sequence spelled by each path as FASTA")
+                .arg(input)
+                .arg(output)
+                .arg(
+                    Arg::with_name("width")
+                        .short("w")
+                        .long("width")
+                        .takes_value(true)
+                        .help("Wrap sequence lines at this width"),
+                )
+                .arg(
+                    Arg::with_name("lengths")
+                        .long("lengths")
+                        .help("Add the sequence length to the headers"),
+                )
+                .arg(
+                    Arg::with_name("skip-missing")
+                        .long("skip-missing")
+                        .help("Skip paths whose sequence can't be spelled"),
+                ),
+        );
+
+    let matches = app.get_matches();
+    let result = match matches.subcommand() {
+        ("stats", Some(m)) => stats(m),
+        ("validate", Some(m)) => validate_cmd(m),
+        ("convert", Some(m)) => convert(m),
+        ("extract", Some(m)) => extract(m),
+        ("paths-to-fasta", Some(m)) => paths_to_fasta(m),
+        _ => unreachable!("clap requires a subcommand"),
+    };
+
+    if let Err(err) = result {
+        eprintln!("gfa: {}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,150 @@
+//! SHA-256 checksums of segment sequences, stored in the SH tag.
+
+use crate::{
+    gfa::GFA,
+    optfields::*,
+    validation::{Record, ValidationError, ValidationIssue},
+};
+
+use bstr::ByteSlice;
+use sha2::{Digest, Sha256};
+
+use std::fmt::Write;
+
+/// Compute the SHA-256 checksum of a sequence.
+pub fn sequence_sha256(seq: &[u8]) -> [u8; 32] {
+    Sha256::digest(seq).into()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        write!(hex, "{:02x}", b).unwrap();
+    }
+    hex
+}
+
+/// The checksum stored in an SH tag, as a lowercase hex string.
+fn tag_hex(value: &OptFieldVal) -> String {
+    match value {
+        OptFieldVal::H(bytes) => to_hex(bytes),
+        OptFieldVal::Z(hex) => hex.to_str_lossy().to_lowercase(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Produce the SH tag for a sequence.
+pub fn sha256_field(seq: &[u8]) -> OptField {
+    let checksum = sequence_sha256(seq).to_vec();
+    OptField::new(b"SH", OptFieldVal::H(checksum))
+}
+
+impl<N> GFA<N, OptionalFields> {
+    /// Set the SH tag of every segment that has a sequence to the
+    /// SHA-256 checksum of the sequence, replacing any existing SH
+    /// tag. Returns the number of segments that were tagged.
+    pub fn set_sha256_tags(&mut self) -> usize {
+        let mut tagged = 0;
+        for seg in self.segments.iter_mut() {
+            if seg.sequence == b"*" {
+                continue;
+            }
+            seg.optional.retain(|f| &f.tag != b"SH");
+            seg.optional.push(sha256_field(&seg.sequence));
+            tagged += 1;
+        }
+        tagged
+    }
+}
+
+/// Check the SH tags of all segments that have both a sequence and
+/// an SH tag against the checksums of their sequences, returning one
+/// issue for each mismatch.
+pub fn verify_sha256_tags<N, T>(gfa: &GFA<N, T>) -> Vec<ValidationIssue>
+where
+    T: OptFields,
+{
+    gfa.segments
+        .iter()
+        .enumerate()
+        .filter(|(_, seg)| seg.sequence != b"*")
+        .filter_map(|(ix, seg)| {
+            let expected = tag_hex(seg.optional.get_value(b"SH")?.as_ref());
+            let computed = to_hex(&sequence_sha256(&seg.sequence));
+            if expected == computed {
+                None
+            } else {
+                let error =
+                    ValidationError::ChecksumMismatch { expected, computed };
+                Some(ValidationIssue::new(Record::Segment(ix), error))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::GFAParser;
+
+    /// A well-formed checksum that isn't the checksum of `ACGT`.
+    const WRONG_SHA256: &str =
+        "c6c1a8a1e8bbe0ba3e2ffb5e6f4bc5ad1c6f9c2f2b1c2c1b1c1de0e6bd2ccafe";
+
+    #[test]
+    fn sha256_of_sequence() {
+        let hex = to_hex(&sequence_sha256(b""));
+        assert_eq!(
+            hex,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        let hex = to_hex(&sequence_sha256(b"ACGT"));
+        assert_eq!(
+            hex,
+            "1dff3e84fe7877e0673b69bbddcf40124e396e3f9943dd890c91b6a09adb9af0"
+        );
+    }
+
+    #[test]
+    fn set_and_verify_tags() {
+        let parser: GFAParser<usize, OptionalFields> = GFAParser::new();
+        let mut gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+
+        assert!(verify_sha256_tags(&gfa).is_empty());
+        assert_eq!(gfa.set_sha256_tags(), 15);
+        assert!(verify_sha256_tags(&gfa).is_empty());
+
+        let tag = gfa.segments[0].optional.get_field(b"SH").unwrap();
+        let expected = to_hex(&sequence_sha256(b"CAAATAAG"));
+        assert_eq!(
+            tag.to_string(),
+            format!("SH:H:{}", expected.to_uppercase())
+        );
+
+        gfa.segments[1].sequence = b"C".to_vec();
+        let issues = verify_sha256_tags(&gfa);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].record, Record::Segment(1));
+    }
+
+    #[test]
+    fn verify_parsed_tags() {
+        let parser: GFAParser<usize, OptionalFields> = GFAParser::new();
+        let good = to_hex(&sequence_sha256(b"ACGT")).to_uppercase();
+        let good = format!("S\t1\tACGT\tSH:H:{}", good);
+        let bad = format!("S\t2\tACGT\tSH:H:{}", WRONG_SHA256.to_uppercase());
+        let lines = [good.as_str(), bad.as_str(), "S\t3\tACGT"];
+        let gfa = parser
+            .parse_lines(lines.iter().map(|l| l.as_bytes()))
+            .unwrap();
+
+        let issues = verify_sha256_tags(&gfa);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].record, Record::Segment(1));
+        assert!(matches!(
+            &issues[0].error,
+            ValidationError::ChecksumMismatch { expected, .. }
+                if expected == WRONG_SHA256
+        ));
+    }
+}
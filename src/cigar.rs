@@ -59,6 +59,7 @@ impl CIGAROp {
     }
 
     #[inline]
+    #[allow(clippy::match_like_matches_macro)]
     pub fn consumes_query(&self) -> bool {
         use CIGAROp::*;
         match self {
@@ -68,6 +69,7 @@ impl CIGAROp {
     }
 
     #[inline]
+    #[allow(clippy::match_like_matches_macro)]
     pub fn consumes_reference(&self) -> bool {
         use CIGAROp::*;
         match self {
@@ -77,6 +79,7 @@ impl CIGAROp {
     }
 
     #[inline]
+    #[allow(clippy::match_like_matches_macro)]
     pub fn is_match_or_mismatch(&self) -> bool {
         use CIGAROp::*;
         match self {
@@ -96,6 +99,7 @@ impl std::fmt::Display for CIGAROp {
 impl std::str::FromStr for CIGAROp {
     type Err = &'static str;
 
+    #[allow(clippy::get_first)]
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         s.as_bytes()
             .get(0)
@@ -184,9 +188,40 @@ impl std::fmt::Display for CIGARPair {
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 // pub struct CIGAR(pub Vec<(u32, CIGAROp)>);
-#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
 pub struct CIGAR(pub Vec<CIGARPair>);
 
+/// CIGARs are serialized as CIGAR strings, e.g. `"4M1I"`.
+#[cfg(feature = "serde1")]
+impl Serialize for CIGAR {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde1")]
+impl<'de> Deserialize<'de> for CIGAR {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        use serde::de::{Error, Unexpected};
+        let cigar = String::deserialize(deserializer)?;
+        // an empty CIGAR is serialized as an empty string
+        if cigar.is_empty() {
+            return Ok(CIGAR::default());
+        }
+        match CIGAR::parser_bytestring(cigar.as_bytes()) {
+            Ok((b"", parsed)) => Ok(parsed),
+            _ => Err(D::Error::invalid_value(
+                Unexpected::Str(&cigar),
+                &"a CIGAR string",
+            )),
+        }
+    }
+}
+
 impl CIGAR {
     pub fn from_pairs<I>(pairs: I) -> Self
     where
@@ -213,15 +248,21 @@ impl CIGAR {
 
     pub(crate) fn parser_bytestring(i: &[u8]) -> IResult<&[u8], Self> {
         use nom::{
-            character::complete::digit1, combinator::map, multi::many1,
+            character::complete::digit1,
+            combinator::{map, map_opt},
+            multi::many1,
             sequence::pair,
         };
         map(
             many1(map(
                 pair(
-                    map(digit1, |bs| {
-                        let s = unsafe { std::str::from_utf8_unchecked(bs) };
-                        s.parse::<u32>().unwrap()
+                    // lengths must fit in the 28 bits of a CIGARPair
+                    map_opt(digit1, |bs: &[u8]| {
+                        let len = std::str::from_utf8(bs)
+                            .ok()?
+                            .parse::<u32>()
+                            .ok()?;
+                        Some(len).filter(|&len| len < (1 << 28))
                     }),
                     Self::parse_op_cmd,
                 ),
@@ -246,15 +287,146 @@ impl CIGAR {
         self.0.is_empty()
     }
 
+    /// The number of query bases covered by the alignment, i.e. the
+    /// total length of the operations that consume the query.
+    pub fn query_len(&self) -> usize {
+        self.0
+            .iter()
+            .filter(|pair| pair.op().consumes_query())
+            .map(|pair| pair.len() as usize)
+            .sum()
+    }
+
+    /// The number of target, or reference, bases covered by the
+    /// alignment, i.e. the total length of the operations that
+    /// consume the reference.
+    pub fn target_len(&self) -> usize {
+        self.0
+            .iter()
+            .filter(|pair| pair.op().consumes_reference())
+            .map(|pair| pair.len() as usize)
+            .sum()
+    }
+
+    /// The total length of the operations of the given kind.
+    pub fn count(&self, op: CIGAROp) -> usize {
+        self.0
+            .iter()
+            .filter(|pair| pair.op() == op)
+            .map(|pair| pair.len() as usize)
+            .sum()
+    }
+
+    /// The number of `=` columns. `M` columns may be matches or
+    /// mismatches, so they're not counted.
+    pub fn matches(&self) -> usize {
+        self.count(CIGAROp::E)
+    }
+
+    /// The number of `X` columns.
+    pub fn mismatches(&self) -> usize {
+        self.count(CIGAROp::X)
+    }
+
+    /// The number of inserted bases, i.e. the total length of the `I`
+    /// operations.
+    pub fn insertions(&self) -> usize {
+        self.count(CIGAROp::I)
+    }
+
+    /// The number of deleted bases, i.e. the total length of the `D`
+    /// operations.
+    pub fn deletions(&self) -> usize {
+        self.count(CIGAROp::D)
+    }
+
+    /// Add an operation to the end, extending the last operation if
+    /// it's of the same kind. Zero-length operations are dropped.
+    fn push_merged(&mut self, pair: CIGARPair) {
+        let (len, op) = pair.into_pair();
+        if len == 0 {
+            return;
+        }
+        match self.0.last_mut() {
+            Some(last) if last.op() == op && last.len() + len < (1 << 28) => {
+                last.set_len(last.len() + len)
+            }
+            _ => self.0.push(pair),
+        }
+    }
+
+    /// Append the operations of another CIGAR, merging the adjacent
+    /// operations at the junction if they're of the same kind, e.g.
+    /// appending 2M1I to 3M produces 5M1I.
+    pub fn append(&mut self, other: &CIGAR) {
+        for pair in other.0.iter() {
+            self.push_merged(*pair);
+        }
+    }
+
+    /// Concatenate two CIGARs, as with `append`.
+    pub fn concat(&self, other: &CIGAR) -> Self {
+        let mut cigar = self.clone();
+        cigar.append(other);
+        cigar
+    }
+
+    /// Collapse runs of operations of the same kind into a single
+    /// operation and drop zero-length operations, e.g. 2M0I3M1D1D
+    /// becomes 5M2D.
+    pub fn normalize(&self) -> Self {
+        let mut cigar = CIGAR(Vec::with_capacity(self.0.len()));
+        cigar.append(self);
+        cigar
+    }
+
+    /// Reverse the order of the operations and swap insertions and
+    /// deletions. This is the alignment of the reverse complements
+    /// with the query and reference swapped, e.g. the overlap of a
+    /// GFA link after flipping the link around.
+    pub fn reverse_swap(&self) -> Self {
+        use CIGAROp::*;
+        let pairs = self.0.iter().rev().map(|pair| {
+            let (len, op) = pair.into_pair();
+            let op = match op {
+                I => D,
+                D => I,
+                op => op,
+            };
+            CIGARPair::from_pair((len, op))
+        });
+        CIGAR(pairs.collect())
+    }
+
     /// Produces an iterator over the individual CIGAR operations in
     /// the string, e.g. an iterator over "3M2D" would produce [M, M,
     /// M, D, D]
+    #[allow(clippy::manual_repeat_n)]
     pub fn iter(&self) -> impl Iterator<Item = CIGAROp> + '_ {
         self.0.iter().copied().flat_map(|pair| {
             std::iter::repeat(pair.op()).take(pair.len() as usize)
         })
     }
 
+    /// Produces an iterator over the aligned positions of the query
+    /// and target, starting at the given offsets, like pysam's
+    /// `get_aligned_pairs`. Each column of the alignment yields the
+    /// query and target positions, where an insertion or soft clip
+    /// has no target position, and a deletion or skip has no query
+    /// position. Hard clips and padding are skipped.
+    pub fn aligned_pairs(
+        &self,
+        query_start: usize,
+        target_start: usize,
+    ) -> AlignedPairs<'_> {
+        AlignedPairs {
+            pairs: self.0.iter(),
+            current: None,
+            query_pos: query_start,
+            target_pos: target_start,
+        }
+    }
+
     /// Given an index along the cigar string, return a pair of
     /// indices, where the first is the index to the cigar operation
     /// in this cigar that includes the given index, and the second is
@@ -337,6 +509,53 @@ impl CIGAR {
     }
 }
 
+/// Iterator over the columns of a CIGAR, as `(query_pos, target_pos,
+/// op)`, created by `CIGAR::aligned_pairs`.
+#[derive(Debug, Clone)]
+pub struct AlignedPairs<'a> {
+    pairs: std::slice::Iter<'a, CIGARPair>,
+    current: Option<(u32, CIGAROp)>,
+    query_pos: usize,
+    target_pos: usize,
+}
+
+impl<'a> Iterator for AlignedPairs<'a> {
+    type Item = (Option<usize>, Option<usize>, CIGAROp);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let op = loop {
+            match self.current {
+                Some((len, op)) if len > 0 => {
+                    self.current = Some((len - 1, op));
+                    break op;
+                }
+                _ => {
+                    let (len, op) = self.pairs.next()?.into_pair();
+                    if op.consumes_query() || op.consumes_reference() {
+                        self.current = Some((len, op));
+                    }
+                }
+            }
+        };
+
+        let query_pos = if op.consumes_query() {
+            self.query_pos += 1;
+            Some(self.query_pos - 1)
+        } else {
+            None
+        };
+        let target_pos = if op.consumes_reference() {
+            self.target_pos += 1;
+            Some(self.target_pos - 1)
+        } else {
+            None
+        };
+        Some((query_pos, target_pos, op))
+    }
+}
+
+impl<'a> std::iter::FusedIterator for AlignedPairs<'a> {}
+
 impl std::fmt::Display for CIGAR {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for pair in self.0.iter() {
@@ -392,6 +611,70 @@ mod tests {
         assert!(CIGAR::parser_bytestring(b"").is_err());
     }
 
+    #[test]
+    fn cigar_reverse_swap() {
+        let cigar = CIGAR::from_bytestring(b"3M2I1M4D5S").unwrap();
+        assert_eq!(cigar.reverse_swap().to_string(), "5S4I1M2D3M");
+        assert_eq!(cigar.reverse_swap().reverse_swap(), cigar);
+    }
+
+    #[test]
+    fn cigar_lengths_and_counts() {
+        let cigar = CIGAR::from_bytestring(b"5S3=1X2I4M3D2N1=").unwrap();
+        assert_eq!(cigar.query_len(), 16);
+        assert_eq!(cigar.target_len(), 14);
+        assert_eq!(cigar.matches(), 4);
+        assert_eq!(cigar.mismatches(), 1);
+        assert_eq!(cigar.insertions(), 2);
+        assert_eq!(cigar.deletions(), 3);
+        assert_eq!(cigar.count(CIGAROp::M), 4);
+        assert!(!cigar.is_empty());
+
+        let empty = CIGAR::default();
+        assert!(empty.is_empty());
+        assert_eq!(empty.query_len(), 0);
+        assert_eq!(empty.target_len(), 0);
+    }
+
+    #[test]
+    fn cigar_concat_normalize() {
+        let cg = |s: &str| CIGAR::from_bytestring(s.as_bytes()).unwrap();
+
+        assert_eq!(cg("3M").concat(&cg("2M1I")).to_string(), "5M1I");
+        assert_eq!(cg("3M1D").concat(&cg("2M")).to_string(), "3M1D2M");
+        assert_eq!(CIGAR::default().concat(&cg("0M2I")).to_string(), "2I");
+
+        let mut stitched = CIGAR::default();
+        for step in ["4M", "0D", "2M1X", "1X1D", "3M"].iter() {
+            stitched.append(&cg(step));
+        }
+        assert_eq!(stitched.to_string(), "6M2X1D3M");
+
+        assert_eq!(cg("2M0I3M1D1D").normalize().to_string(), "5M2D");
+        assert_eq!(cg("0M").normalize(), CIGAR::default());
+    }
+
+    #[test]
+    fn cigar_aligned_pairs() {
+        use CIGAROp::*;
+        let cigar = CIGAR::from_bytestring(b"1H1S2M1I1D1N1=1P").unwrap();
+        let pairs: Vec<_> = cigar.aligned_pairs(10, 100).collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (Some(10), None, S),
+                (Some(11), Some(100), M),
+                (Some(12), Some(101), M),
+                (Some(13), None, I),
+                (None, Some(102), D),
+                (None, Some(103), N),
+                (Some(14), Some(104), E),
+            ]
+        );
+
+        assert_eq!(CIGAR::default().aligned_pairs(0, 0).next(), None);
+    }
+
     #[test]
     fn temp_split_test() {
         let input = b"6M3I4D";
@@ -0,0 +1,601 @@
+//! Reading and writing the sequences of a GFA as FASTA.
+
+#[cfg(feature = "mmap")]
+pub mod indexed;
+
+use crate::{gfa::*, graph::segment_indices, optfields::*, seq::revcomp_iter};
+
+use bstr::ByteSlice;
+use fnv::FnvHashMap;
+
+use std::{
+    borrow::Cow,
+    hash::Hash,
+    io::{self, BufRead, Write},
+};
+#[cfg(feature = "fs")]
+use std::{fs::File, io::BufWriter};
+
+/// Options for writing FASTA records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FastaOptions {
+    /// Wrap sequence lines at this many characters. `None` writes
+    /// each sequence on a single line.
+    pub line_width: Option<usize>,
+    /// Skip segments whose sequence is `*`, rather than writing them
+    /// as empty records.
+    pub skip_missing: bool,
+}
+
+impl Default for FastaOptions {
+    fn default() -> Self {
+        FastaOptions {
+            line_width: None,
+            skip_missing: true,
+        }
+    }
+}
+
+/// A FASTA record. The name is the part of the header line before
+/// the first whitespace.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FastaRecord {
+    pub name: Vec<u8>,
+    pub sequence: Vec<u8>,
+}
+
+/// Streaming FASTA parser, producing one record at a time so only a
+/// single sequence is held in memory. Multi-line sequences are
+/// joined, and anything before the first header is ignored.
+pub struct FastaReader<R: BufRead> {
+    reader: R,
+    buf: Vec<u8>,
+    next_name: Option<Vec<u8>>,
+}
+
+impl<R: BufRead> FastaReader<R> {
+    pub fn new(reader: R) -> Self {
+        FastaReader {
+            reader,
+            buf: Vec::new(),
+            next_name: None,
+        }
+    }
+}
+
+impl FastaReader<Box<dyn BufRead>> {
+    /// Open a FASTA file, which may be gzip-compressed if the `gzip`
+    /// feature is enabled.
+    #[cfg(feature = "fs")]
+    pub fn from_path<P: AsRef<std::path::Path>>(path: P) -> io::Result<Self> {
+        Ok(FastaReader::new(crate::reader::open_file(path)?))
+    }
+}
+
+impl<R: BufRead> Iterator for FastaReader<R> {
+    type Item = io::Result<FastaRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record: Option<FastaRecord> =
+            self.next_name.take().map(|name| FastaRecord {
+                name,
+                ..Default::default()
+            });
+
+        loop {
+            self.buf.clear();
+            match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(0) => return record.map(Ok),
+                Ok(_) => (),
+                Err(err) => return Some(Err(err)),
+            }
+
+            let line = self.buf.trim_end();
+            if let Some(header) = line.strip_prefix(b">") {
+                let name = header.fields().next().unwrap_or(b"").to_vec();
+                if record.is_some() {
+                    self.next_name = Some(name);
+                    return record.map(Ok);
+                }
+                record = Some(FastaRecord {
+                    name,
+                    ..Default::default()
+                });
+            } else if let Some(record) = record.as_mut() {
+                record.sequence.extend_from_slice(line.trim_start());
+            }
+        }
+    }
+}
+
+/// Write a single FASTA record, wrapping the sequence if a line
+/// width is given. Empty sequences are written as just the header.
+pub fn write_fasta_record<W: Write>(
+    stream: &mut W,
+    header: &[u8],
+    seq: &[u8],
+    line_width: Option<usize>,
+) -> io::Result<()> {
+    stream.write_all(b">")?;
+    stream.write_all(header)?;
+    stream.write_all(b"\n")?;
+    let width = match line_width {
+        Some(width) if width > 0 => width,
+        _ => seq.len().max(1),
+    };
+    for line in seq.chunks(width) {
+        stream.write_all(line)?;
+        stream.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Write one FASTA record per segment, named by the segment name,
+/// using the default options.
+pub fn write_segments_fasta<N, T, W>(
+    gfa: &GFA<N, T>,
+    stream: &mut W,
+) -> io::Result<()>
+where
+    N: SegmentId,
+    T: OptFields,
+    W: Write,
+{
+    write_segments_fasta_with(gfa, &FastaOptions::default(), stream)
+}
+
+/// Write one FASTA record per segment, named by the segment name.
+pub fn write_segments_fasta_with<N, T, W>(
+    gfa: &GFA<N, T>,
+    options: &FastaOptions,
+    stream: &mut W,
+) -> io::Result<()>
+where
+    N: SegmentId,
+    T: OptFields,
+    W: Write,
+{
+    for seg in gfa.segments.iter() {
+        let seq: &[u8] = if seg.sequence == b"*" {
+            if options.skip_missing {
+                continue;
+            }
+            b""
+        } else {
+            &seg.sequence
+        };
+        let name = seg.name.display();
+        write_fasta_record(stream, name.as_bytes(), seq, options.line_width)?;
+    }
+    Ok(())
+}
+
+impl<N: SegmentId, T: OptFields> GFA<N, T> {
+    /// Fill in the sequences of the segments whose sequence is `*`
+    /// using the records with the same name in the given FASTA file.
+    /// The FASTA file is streamed, so only one of its sequences is
+    /// kept in memory at a time. Returns the number of segments that
+    /// were filled in.
+    #[cfg(feature = "fs")]
+    pub fn attach_sequences<P: AsRef<std::path::Path>>(
+        &mut self,
+        fasta_path: P,
+    ) -> io::Result<usize> {
+        let missing: Vec<_> = (0..self.segments.len())
+            .filter(|&ix| self.segments[ix].sequence == b"*")
+            .collect();
+        self.attach_sequences_to(&missing, fasta_path)
+    }
+
+    /// Fill in the sequences of the segments whose sequence is `*`
+    /// and which have a UR tag, from the FASTA file that the tag
+    /// points to. Each file is read once. Returns the number of
+    /// segments that were filled in.
+    #[cfg(feature = "fs")]
+    pub fn attach_sequences_from_ur(&mut self) -> io::Result<usize> {
+        let mut by_file: FnvHashMap<Vec<u8>, Vec<usize>> = Default::default();
+        for (ix, seg) in self.segments.iter().enumerate() {
+            if seg.sequence != b"*" {
+                continue;
+            }
+            if let Some(uri) = seg.optional.get_typed::<&[u8]>(b"UR") {
+                let path = uri.strip_prefix(b"file://").unwrap_or(uri);
+                by_file.entry(path.to_vec()).or_default().push(ix);
+            }
+        }
+
+        let mut filled = 0;
+        for (path, segments) in by_file {
+            let path = path.to_path().map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidData, "Invalid UR path")
+            })?;
+            filled += self.attach_sequences_to(&segments, path)?;
+        }
+        Ok(filled)
+    }
+
+    #[cfg(feature = "fs")]
+    fn attach_sequences_to<P: AsRef<std::path::Path>>(
+        &mut self,
+        segments: &[usize],
+        fasta_path: P,
+    ) -> io::Result<usize> {
+        let mut wanted: FnvHashMap<Vec<u8>, usize> = segments
+            .iter()
+            .map(|&ix| (self.segments[ix].name.display().into_bytes(), ix))
+            .collect();
+
+        let mut filled = 0;
+        for record in FastaReader::from_path(fasta_path)? {
+            if wanted.is_empty() {
+                break;
+            }
+            let record = record?;
+            if let Some(ix) = wanted.remove(&record.name) {
+                self.segments[ix].sequence = record.sequence;
+                filled += 1;
+            }
+        }
+        Ok(filled)
+    }
+}
+
+impl<N: SegmentId> GFA<N, OptionalFields> {
+    /// Move all segment sequences to a new FASTA file at the given
+    /// path, leaving a topology-only GFA. Each segment with a
+    /// sequence gets the sequence replaced with `*`, an LN tag with
+    /// its length, and a UR tag pointing to the FASTA file, so the
+    /// sequences can be restored with `attach_sequences_from_ur`.
+    /// Existing LN and UR tags are replaced. Returns the number of
+    /// segments that were stripped.
+    #[cfg(feature = "fs")]
+    pub fn strip_sequences<P: AsRef<std::path::Path>>(
+        &mut self,
+        fasta_path: P,
+    ) -> io::Result<usize> {
+        let fasta_path = fasta_path.as_ref();
+        let uri = fasta_path.to_string_lossy().into_owned().into_bytes();
+        let mut stream = BufWriter::new(File::create(fasta_path)?);
+
+        let mut stripped = 0;
+        for seg in self.segments.iter_mut() {
+            if seg.sequence == b"*" {
+                continue;
+            }
+            let name = seg.name.display();
+            write_fasta_record(
+                &mut stream,
+                name.as_bytes(),
+                &seg.sequence,
+                None,
+            )?;
+
+            let len = seg.sequence.len() as i64;
+            seg.optional.retain(|f| &f.tag != b"LN" && &f.tag != b"UR");
+            seg.optional
+                .push(OptField::new(b"LN", OptFieldVal::Int(len)));
+            seg.optional
+                .push(OptField::new(b"UR", OptFieldVal::Z(uri.clone())));
+            seg.sequence = b"*".to_vec();
+            stripped += 1;
+        }
+
+        stream.flush()?;
+        Ok(stripped)
+    }
+}
+
+/// How to name the FASTA records of paths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathHeader {
+    /// Only the path name, e.g. `>x`.
+    Name,
+    /// The path name followed by the sequence length, e.g.
+    /// `>x LN:i:42`.
+    NameLength,
+}
+
+/// Spell out the sequence of a list of steps, using the map from
+/// segment names to indices of `segment_indices`.
+fn spell_steps<N, T, I>(
+    gfa: &GFA<N, T>,
+    indices: &FnvHashMap<&N, usize>,
+    steps: I,
+) -> Option<Vec<u8>>
+where
+    N: Eq + Hash,
+    T: OptFields,
+    I: Iterator<Item = (N, Orientation)>,
+{
+    let mut seq = Vec::new();
+    for (name, orient) in steps {
+        let segment = &gfa.segments[*indices.get(&name)?];
+        if segment.sequence == b"*" {
+            return None;
+        }
+        match orient {
+            Orientation::Forward => seq.extend_from_slice(&segment.sequence),
+            Orientation::Backward => {
+                seq.extend(revcomp_iter(segment.sequence.iter().copied()))
+            }
+        }
+    }
+    Some(seq)
+}
+
+/// Spell out the sequence of a path by concatenating the sequences
+/// of its steps, reverse complementing the steps in reverse
+/// orientation. Overlaps between steps are ignored. Returns `None` if
+/// a step refers to a missing segment, or one without a sequence.
+pub fn path_sequence<N, T>(
+    gfa: &GFA<N, T>,
+    path: &Path<N, T>,
+) -> Option<Vec<u8>>
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    spell_steps(gfa, &segment_indices(gfa), path.steps())
+}
+
+/// Spell out the sequence of a walk, as `path_sequence` does for a
+/// path.
+pub fn walk_sequence<N, T>(
+    gfa: &GFA<N, T>,
+    walk: &Walk<N, T>,
+) -> Option<Vec<u8>>
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    spell_steps(gfa, &segment_indices(gfa), walk.steps())
+}
+
+/// Write the spelled sequence of each path, and then of each walk,
+/// as a FASTA record. Walks are named `sample#haplotype#sequence`, as
+/// by `Walk::name`. If `options.skip_missing` is true, paths and
+/// walks whose sequence can't be reconstructed are skipped, otherwise
+/// they produce an error.
+pub fn write_paths_fasta<N, T, W>(
+    gfa: &GFA<N, T>,
+    header: PathHeader,
+    options: &FastaOptions,
+    stream: &mut W,
+) -> io::Result<()>
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+    W: Write,
+{
+    let indices = segment_indices(gfa);
+    let paths = gfa.paths.iter().map(|path| {
+        let seq = spell_steps(gfa, &indices, path.steps());
+        (Cow::Borrowed(&path.path_name), seq)
+    });
+    let walks = gfa.walks.iter().map(|walk| {
+        let seq = spell_steps(gfa, &indices, walk.steps());
+        (Cow::Owned(walk.name()), seq)
+    });
+
+    for (name, seq) in paths.chain(walks) {
+        let seq = match seq {
+            Some(seq) => seq,
+            None if options.skip_missing => continue,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Could not spell the sequence of path {}",
+                        name.as_bstr()
+                    ),
+                ))
+            }
+        };
+
+        let mut name = name.into_owned();
+        if header == PathHeader::NameLength {
+            name.extend(format!(" LN:i:{}", seq.len()).as_bytes());
+        }
+        write_fasta_record(stream, &name, &seq, options.line_width)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::GFAParser;
+    use crate::test_util::parse;
+    use bstr::BStr;
+
+    #[test]
+    fn segments_to_fasta() {
+        let gfa: GFA<Vec<u8>, ()> =
+            parse(&["S\ts1\tACGTACGTAC", "S\ts2\t*", "S\ts3\tGG"]);
+
+        let mut out = Vec::new();
+        write_segments_fasta(&gfa, &mut out).unwrap();
+        assert_eq!(out, b">s1\nACGTACGTAC\n>s3\nGG\n");
+
+        let options = FastaOptions {
+            line_width: Some(4),
+            skip_missing: false,
+        };
+        let mut out = Vec::new();
+        write_segments_fasta_with(&gfa, &options, &mut out).unwrap();
+        assert_eq!(out, b">s1\nACGT\nACGT\nAC\n>s2\n>s3\nGG\n");
+    }
+
+    #[test]
+    fn spell_paths() {
+        let gfa: GFA<Vec<u8>, ()> = parse(&[
+            "S\t1\tAAC",
+            "S\t2\tGT",
+            "S\t3\t*",
+            "P\tp1\t1+,2-,1-\t*",
+            "P\tp2\t1+,3+\t*",
+            "P\tp3\t2+,4+\t*",
+        ]);
+
+        assert_eq!(path_sequence(&gfa, &gfa.paths[0]).unwrap(), b"AACACGTT");
+        assert!(path_sequence(&gfa, &gfa.paths[1]).is_none());
+        assert!(path_sequence(&gfa, &gfa.paths[2]).is_none());
+
+        let mut out = Vec::new();
+        let options = FastaOptions::default();
+        write_paths_fasta(&gfa, PathHeader::NameLength, &options, &mut out)
+            .unwrap();
+        assert_eq!(out, b">p1 LN:i:8\nAACACGTT\n");
+
+        let options = FastaOptions {
+            line_width: Some(5),
+            skip_missing: false,
+        };
+        let mut out = Vec::new();
+        let result =
+            write_paths_fasta(&gfa, PathHeader::Name, &options, &mut out);
+        assert!(result.is_err());
+        assert_eq!(out, b">p1\nAACAC\nGTT\n");
+    }
+
+    #[test]
+    fn spell_walks() {
+        let gfa: GFA<Vec<u8>, ()> = parse(&[
+            "S\t1\tAAC",
+            "S\t2\tGT",
+            "P\tp1\t1+,2-\t*",
+            "W\tHG002\t1\tchr1\t0\t7\t>1<2>1",
+            "W\tHG002\t2\tchr1\t*\t*\t>1>3",
+        ]);
+
+        assert_eq!(walk_sequence(&gfa, &gfa.walks[0]).unwrap(), b"AACACAAC");
+        assert!(walk_sequence(&gfa, &gfa.walks[1]).is_none());
+
+        let mut out = Vec::new();
+        let options = FastaOptions::default();
+        write_paths_fasta(&gfa, PathHeader::NameLength, &options, &mut out)
+            .unwrap();
+        assert_eq!(
+            out.as_bstr(),
+            ">p1 LN:i:5\nAACAC\n>HG002#1#chr1 LN:i:8\nAACACAAC\n"
+        );
+    }
+
+    #[test]
+    fn lil_gfa_paths_to_fasta() {
+        let parser: GFAParser<usize, ()> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+
+        let mut out = Vec::new();
+        let options = FastaOptions::default();
+        write_paths_fasta(&gfa, PathHeader::Name, &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], ">x");
+        assert!(lines[1].starts_with("CAAATAAGGCTTGG"));
+        assert!(lines[1].ends_with("CCAACTCTCTG"));
+    }
+
+    #[test]
+    fn read_fasta() {
+        let input: &[u8] = b"ignored\n>a desc\r\nAC\r\nGT\n\n>b\n>c\nT";
+        let records = FastaReader::new(input)
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        let records: Vec<_> = records
+            .iter()
+            .map(|r| (r.name.as_bstr(), r.sequence.as_bstr()))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                ("a".into(), "ACGT".into()),
+                ("b".into(), "".into()),
+                ("c".into(), "T".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn attach_sequences_by_name() {
+        let mut gfa: GFA<Vec<u8>, ()> = parse(&[
+            "S\t1\t*",
+            "S\t3\t*",
+            "S\t4\tT",
+            "S\t6\t*\tUR:Z:./test/gfas/lil_seqs.fa",
+            "S\t7\t*",
+        ]);
+        let filled = gfa.attach_sequences("./test/gfas/lil_seqs.fa").unwrap();
+        assert_eq!(filled, 3);
+
+        let seqs: Vec<_> =
+            gfa.segments.iter().map(|s| s.sequence.as_bstr()).collect();
+        let expected: Vec<&BStr> = vec![
+            "CAAATAAG".into(),
+            "G".into(),
+            "T".into(),
+            "TTG".into(),
+            "*".into(),
+        ];
+        assert_eq!(seqs, expected);
+    }
+
+    #[test]
+    fn attach_sequences_from_ur_tags() {
+        let lines = [
+            "S\t1\t*\tUR:Z:file://./test/gfas/lil_seqs.fa",
+            "S\t3\t*",
+            "S\t6\t*\tUR:Z:./test/gfas/lil_seqs.fa",
+        ];
+        let mut gfa: GFA<usize, OptionalFields> = parse(&lines);
+
+        assert_eq!(gfa.attach_sequences_from_ur().unwrap(), 2);
+        assert_eq!(gfa.segments[0].sequence, b"CAAATAAG");
+        assert_eq!(gfa.segments[1].sequence, b"*");
+        assert_eq!(gfa.segments[2].sequence, b"TTG");
+    }
+
+    #[test]
+    fn strip_and_restore_sequences() {
+        let parser: GFAParser<usize, OptionalFields> = GFAParser::new();
+        let orig = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+        let mut gfa = orig.clone();
+
+        let fasta = crate::test_util::temp_path("strip_sequences.fa");
+        assert_eq!(gfa.strip_sequences(&fasta).unwrap(), 15);
+
+        let seg = &gfa.segments[0];
+        assert_eq!(seg.sequence, b"*");
+        assert_eq!(
+            seg.optional.get_field(b"LN").unwrap().value,
+            OptFieldVal::Int(8)
+        );
+        let uri = fasta.to_string_lossy().into_owned().into_bytes();
+        assert_eq!(
+            seg.optional.get_field(b"UR").unwrap().value,
+            OptFieldVal::Z(uri)
+        );
+
+        assert_eq!(gfa.attach_sequences_from_ur().unwrap(), 15);
+        for (restored, orig) in gfa.segments.iter().zip(orig.segments.iter()) {
+            assert_eq!(restored.sequence, orig.sequence);
+        }
+
+        std::fs::remove_file(fasta).unwrap();
+    }
+
+    #[test]
+    fn lil_gfa_to_fasta() {
+        let parser: GFAParser<usize, ()> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+
+        let mut out = Vec::new();
+        write_segments_fasta(&gfa, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert_eq!(out.lines().count(), 2 * gfa.segments.len());
+        assert!(out.starts_with(">1\nCAAATAAG\n>2\nA\n"));
+    }
+}
@@ -0,0 +1,324 @@
+//! Random access to the sequences of FASTA files through a memory
+//! map, using a samtools `.fai` index, so the sequences of a
+//! topology-only GFA can be fetched on demand without loading them.
+
+use crate::{gfa::*, optfields::*};
+
+use bstr::ByteSlice;
+use fnv::FnvHashMap;
+use memmap2::Mmap;
+
+use std::{
+    borrow::Cow,
+    fs::File,
+    io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// An entry of a `.fai` index, describing where a sequence is in the
+/// FASTA file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaiEntry {
+    /// The number of bases in the sequence.
+    pub length: usize,
+    /// The byte offset of the first base.
+    pub offset: usize,
+    /// The number of bases on each line.
+    pub line_bases: usize,
+    /// The number of bytes on each line, including the line break.
+    pub line_width: usize,
+}
+
+impl FaiEntry {
+    /// The byte offset of the base at the given position.
+    fn byte_offset(&self, pos: usize) -> usize {
+        if self.line_bases == 0 {
+            return self.offset;
+        }
+        self.offset
+            + (pos / self.line_bases) * self.line_width
+            + pos % self.line_bases
+    }
+}
+
+/// Parse the lines of a `.fai` index.
+pub fn parse_fai(bytes: &[u8]) -> io::Result<FnvHashMap<Vec<u8>, FaiEntry>> {
+    let mut index = FnvHashMap::default();
+    for line in bytes.lines().filter(|l| !l.is_empty()) {
+        let fields: Vec<_> = line.split_str(b"\t").collect();
+        if fields.len() < 5 {
+            return Err(invalid_data("FAI line with fewer than 5 fields"));
+        }
+        let number = |field: &[u8]| -> io::Result<usize> {
+            field
+                .to_str()
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(|| invalid_data("Invalid number in FAI line"))
+        };
+        let entry = FaiEntry {
+            length: number(fields[1])?,
+            offset: number(fields[2])?,
+            line_bases: number(fields[3])?,
+            line_width: number(fields[4])?,
+        };
+        index.insert(fields[0].to_vec(), entry);
+    }
+    Ok(index)
+}
+
+/// Build the index of a FASTA file from its contents. As with
+/// `samtools faidx`, all lines of a sequence but the last must have
+/// the same length, and a sequence with a line that is longer than
+/// the first, or a short line that isn't the last, is an error.
+fn build_fai(bytes: &[u8]) -> io::Result<FnvHashMap<Vec<u8>, FaiEntry>> {
+    let mut index = FnvHashMap::default();
+    // the entry being built, and whether its last line has been seen
+    let mut current: Option<(Vec<u8>, FaiEntry, bool)> = None;
+    let mut pos = 0;
+    for line in bytes.lines_with_terminator() {
+        let start = pos;
+        pos += line.len();
+        if let Some(header) = line.strip_prefix(b">") {
+            if let Some((name, entry, _)) = current.take() {
+                index.insert(name, entry);
+            }
+            let name = header.fields().next().unwrap_or_default().to_vec();
+            let entry = FaiEntry {
+                length: 0,
+                offset: pos,
+                line_bases: 0,
+                line_width: 0,
+            };
+            current = Some((name, entry, false));
+        } else if let Some((name, entry, last)) = current.as_mut() {
+            let bases = line.trim_end_with(|c| c == '\n' || c == '\r').len();
+            if entry.line_bases == 0 {
+                if bases == 0 {
+                    continue;
+                }
+                entry.offset = start;
+                entry.line_bases = bases;
+                entry.line_width = line.len();
+            } else if (*last && bases > 0) || bases > entry.line_bases {
+                let msg = format!(
+                    "Different line length in sequence `{}`",
+                    name.as_bstr()
+                );
+                return Err(invalid_data(&msg));
+            } else if bases < entry.line_bases || line.len() != entry.line_width
+            {
+                *last = true;
+            }
+            entry.length += bases;
+        }
+    }
+    if let Some((name, entry, _)) = current {
+        index.insert(name, entry);
+    }
+    Ok(index)
+}
+
+/// A memory-mapped FASTA file with its index.
+pub struct IndexedFasta {
+    mmap: Mmap,
+    index: FnvHashMap<Vec<u8>, FaiEntry>,
+}
+
+impl IndexedFasta {
+    /// Map the FASTA file at the given path, using the index at the
+    /// same path with `.fai` appended. If there's no index, it's
+    /// built by scanning the file once.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut fai_path = path.as_os_str().to_owned();
+        fai_path.push(".fai");
+        let fai_path = PathBuf::from(fai_path);
+
+        let file = File::open(path)?;
+        // SAFETY: the map is only read, and the FASTA file is assumed
+        // not to be modified while it's open, as with any mmap.
+        let mmap = unsafe { Mmap::map(&file)? };
+        let index = if fai_path.exists() {
+            parse_fai(&std::fs::read(&fai_path)?)?
+        } else {
+            build_fai(&mmap)?
+        };
+        Ok(IndexedFasta { mmap, index })
+    }
+
+    pub fn contains(&self, name: &[u8]) -> bool {
+        self.index.contains_key(name)
+    }
+
+    pub fn entry(&self, name: &[u8]) -> Option<&FaiEntry> {
+        self.index.get(name)
+    }
+
+    /// The bases in the given range of the named sequence. The bases
+    /// are borrowed from the map if they're all on a single line.
+    /// Returns `None` if there's no such sequence, or the range is out
+    /// of bounds.
+    pub fn fetch(
+        &self,
+        name: &[u8],
+        range: Range<usize>,
+    ) -> Option<Cow<'_, [u8]>> {
+        let entry = self.index.get(name)?;
+        if range.start > range.end || range.end > entry.length {
+            return None;
+        }
+        if range.is_empty() {
+            return Some(Cow::Borrowed(&[]));
+        }
+        let start = entry.byte_offset(range.start);
+        let end = entry.byte_offset(range.end - 1) + 1;
+        let bytes = self.mmap.get(start..end)?;
+        if end - start == range.len() {
+            Some(Cow::Borrowed(bytes))
+        } else {
+            let bases = bytes.iter().filter(|&&b| b != b'\n' && b != b'\r');
+            Some(Cow::Owned(bases.copied().collect()))
+        }
+    }
+
+    /// The whole named sequence.
+    pub fn sequence(&self, name: &[u8]) -> Option<Cow<'_, [u8]>> {
+        let length = self.index.get(name)?.length;
+        self.fetch(name, 0..length)
+    }
+}
+
+/// Segment sequences served on demand from memory-mapped FASTA files,
+/// for GFAs whose segments have `*` as the sequence.
+pub struct LazySequences {
+    files: Vec<IndexedFasta>,
+    /// The file of each segment, by segment name, for segments with a
+    /// UR tag. Other segments are looked up in every file.
+    by_segment: FnvHashMap<Vec<u8>, usize>,
+}
+
+impl LazySequences {
+    /// Serve the sequences from a single FASTA file, with the records
+    /// named after the segments.
+    pub fn from_fasta<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(LazySequences {
+            files: vec![IndexedFasta::open(path)?],
+            by_segment: FnvHashMap::default(),
+        })
+    }
+
+    /// Serve the sequences from the FASTA files that the UR tags of
+    /// the segments point to, as written by `strip_sequences`. Each
+    /// file is mapped once.
+    pub fn from_ur<N: SegmentId>(
+        gfa: &GFA<N, OptionalFields>,
+    ) -> io::Result<Self> {
+        let mut file_ids: FnvHashMap<&[u8], usize> = FnvHashMap::default();
+        let mut files = Vec::new();
+        let mut by_segment = FnvHashMap::default();
+        for seg in gfa.segments.iter() {
+            let uri = match seg.optional.get_typed::<&[u8]>(b"UR") {
+                Some(uri) => uri,
+                None => continue,
+            };
+            let path = uri.strip_prefix(b"file://").unwrap_or(uri);
+            let file = match file_ids.get(path) {
+                Some(&file) => file,
+                None => {
+                    let fs_path = path
+                        .to_path()
+                        .map_err(|_| invalid_data("Invalid UR path"))?;
+                    files.push(IndexedFasta::open(fs_path)?);
+                    file_ids.insert(path, files.len() - 1);
+                    files.len() - 1
+                }
+            };
+            by_segment.insert(seg.name.display().into_bytes(), file);
+        }
+        Ok(LazySequences { files, by_segment })
+    }
+
+    /// The sequence of the segment: its own sequence if it has one,
+    /// otherwise the FASTA record with the segment's name.
+    pub fn sequence<'a, N: SegmentId, T: OptFields>(
+        &'a self,
+        segment: &'a Segment<N, T>,
+    ) -> Option<Cow<'a, [u8]>> {
+        if segment.sequence != b"*" {
+            return Some(Cow::Borrowed(&segment.sequence));
+        }
+        let name = segment.name.display().into_bytes();
+        match self.by_segment.get(&name) {
+            Some(&file) => self.files[file].sequence(&name),
+            None => self.files.iter().find_map(|f| f.sequence(&name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fasta::{write_segments_fasta_with, FastaOptions},
+        parser::GFAParser,
+    };
+
+    #[test]
+    fn lazy_sequences_from_mmap() {
+        let parser: GFAParser<Vec<u8>, OptionalFields> = GFAParser::new();
+        let mut gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+        let original = gfa.clone();
+
+        let fasta = crate::test_util::temp_path("lazy_sequences.fa");
+        gfa.strip_sequences(&fasta).unwrap();
+        // rewrite the file with wrapped lines, without an index
+        let options = FastaOptions {
+            line_width: Some(4),
+            ..Default::default()
+        };
+        let mut stream = File::create(&fasta).unwrap();
+        write_segments_fasta_with(&original, &options, &mut stream).unwrap();
+
+        let lazy = LazySequences::from_ur(&gfa).unwrap();
+        for (seg, orig) in gfa.segments.iter().zip(original.segments.iter()) {
+            assert_eq!(lazy.sequence(seg).unwrap(), orig.sequence.as_slice());
+        }
+
+        let file = IndexedFasta::open(&fasta).unwrap();
+        assert_eq!(file.entry(b"9").unwrap().length, 19);
+        assert_eq!(&*file.fetch(b"9", 1..3).unwrap(), b"AA");
+        assert!(matches!(file.fetch(b"9", 1..3), Some(Cow::Borrowed(_))));
+        assert_eq!(&*file.fetch(b"9", 2..10).unwrap(), b"ATTTTCTG");
+        assert!(file.fetch(b"9", 5..20).is_none());
+        assert!(file.sequence(b"missing").is_none());
+
+        let fai = parse_fai(b"9\t19\t10\t4\t5\n").unwrap();
+        assert_eq!(fai[&b"9".to_vec()].line_width, 5);
+        assert!(parse_fai(b"9\t19\n").is_err());
+    }
+
+    #[test]
+    fn fai_requires_uniform_lines() {
+        let fasta = b">a\nACGT\nACGT\nAC\n\n>b desc\r\nACG\r\nA\r\n";
+        let index = build_fai(fasta).unwrap();
+        let a = index[&b"a".to_vec()];
+        assert_eq!(
+            (a.length, a.offset, a.line_bases, a.line_width),
+            (10, 3, 4, 5)
+        );
+        let b = index[&b"b".to_vec()];
+        assert_eq!((b.length, b.line_bases, b.line_width), (4, 3, 5));
+
+        // a short line that isn't the last, and a long line
+        assert!(build_fai(b">a\nACGT\nAC\nACGT\n").is_err());
+        assert!(build_fai(b">a\nACGT\nACGTA\n").is_err());
+        assert!(build_fai(b">a\nACGT\n\nACGT\n").is_err());
+        assert!(build_fai(b">a\nACGT\r\nACGT\nAC\n").is_err());
+    }
+}
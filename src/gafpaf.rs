@@ -1,10 +1,36 @@
 use bstr::{BString, ByteSlice};
 
-use std::fmt::Display;
+use std::{fmt::Display, sync::OnceLock};
 
 use nom::{bytes::complete::*, IResult};
 
-use crate::{gfa::*, optfields::*};
+use crate::{cigar::CIGAR, gfa::*, optfields::*};
+
+pub mod classify;
+pub mod coverage;
+pub mod cs;
+pub mod filter;
+pub mod group;
+pub mod overlap;
+pub mod records;
+pub mod sort;
+pub mod summary;
+pub mod tags;
+pub mod target;
+pub mod validate;
+
+pub use self::classify::*;
+pub use self::coverage::*;
+pub use self::cs::*;
+pub use self::filter::*;
+pub use self::group::*;
+pub use self::overlap::*;
+pub use self::records::*;
+pub use self::sort::*;
+pub use self::summary::*;
+pub use self::tags::*;
+pub use self::target::*;
+pub use self::validate::*;
 
 /// A GAF record, with optional fields T. Can be created by using
 /// `parse_gaf`, and the Display implementation produces
@@ -48,7 +74,12 @@ impl<T: OptFields> Display for GAF<T> {
             self.quality
         )?;
 
-        for opt in self.optional.fields() {
+        for opt in self
+            .optional
+            .try_to_fields()
+            .map_err(|_| std::fmt::Error)?
+            .iter()
+        {
             write!(f, "\t{}", opt)?;
         }
 
@@ -85,18 +116,14 @@ impl GAFStep {
     // strand of a path step, so we need another Orientation parser to
     // reuse that type here
     fn parse_orient(bytes: &[u8]) -> IResult<&[u8], Orientation> {
-        use nom::{branch::alt, combinator::map};
-        use Orientation::*;
-
-        let fwd = map(tag(">"), |_| Forward);
-        let bwd = map(tag("<"), |_| Backward);
-        alt((fwd, bwd))(bytes)
+        use nom::{bytes::complete::take, combinator::map_opt};
+        map_opt(take(1usize), Orientation::from_bytes_gt_ln)(bytes)
     }
 
     pub(crate) fn parse_step(i: &[u8]) -> IResult<&[u8], GAFStep> {
         use nom::{
             character::complete::digit1,
-            combinator::{map, opt},
+            combinator::{map_opt, opt},
             sequence::{preceded, separated_pair},
         };
 
@@ -104,9 +131,8 @@ impl GAFStep {
         let (i, name) = is_not("<>: \t\r\n")(i)?;
         let name = name.into();
 
-        let parse_digits = map(digit1, |bs| {
-            let s = unsafe { std::str::from_utf8_unchecked(bs) };
-            s.parse::<usize>().unwrap()
+        let parse_digits = map_opt(digit1, |bs: &[u8]| {
+            bs.to_str().ok()?.parse::<usize>().ok()
         });
 
         let parse_range = preceded(
@@ -210,7 +236,12 @@ impl<T: OptFields> Display for PAF<T> {
             self.quality
         )?;
 
-        for opt in self.optional.fields() {
+        for opt in self
+            .optional
+            .try_to_fields()
+            .map_err(|_| std::fmt::Error)?
+            .iter()
+        {
             write!(f, "\t{}", opt)?;
         }
 
@@ -218,6 +249,82 @@ impl<T: OptFields> Display for PAF<T> {
     }
 }
 
+/// Parse the CIGAR in the cg tag of the given optional fields.
+pub(crate) fn parse_cigar_tag<T: OptFields>(optional: &T) -> Option<CIGAR> {
+    let cg: &[u8] = optional.get_typed(b"cg")?;
+    match CIGAR::parser_bytestring(cg) {
+        Ok((b"", cigar)) => Some(cigar),
+        _ => None,
+    }
+}
+
+impl<T: OptFields> GAF<T> {
+    /// The CIGAR in the cg tag of the record, or `None` if there is
+    /// no cg tag or it's not a valid CIGAR. The tag is parsed on every
+    /// call; wrap the record in a `CachedCigar` to parse it only once.
+    pub fn cigar(&self) -> Option<CIGAR> {
+        parse_cigar_tag(&self.optional)
+    }
+}
+
+impl<T: OptFields> PAF<T> {
+    /// The CIGAR in the cg tag of the record, parsed as in
+    /// `GAF::cigar`.
+    pub fn cigar(&self) -> Option<CIGAR> {
+        parse_cigar_tag(&self.optional)
+    }
+}
+
+/// A GAF or PAF record along with the CIGAR in its cg tag, which is
+/// parsed the first time it's requested. The record can't be modified
+/// through the wrapper, so the CIGAR always matches its tags.
+#[derive(Debug, Clone)]
+pub struct CachedCigar<R> {
+    record: R,
+    cigar: OnceLock<Option<CIGAR>>,
+}
+
+impl<R> CachedCigar<R> {
+    pub fn new(record: R) -> Self {
+        CachedCigar {
+            record,
+            cigar: OnceLock::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.record
+    }
+}
+
+impl<R> std::ops::Deref for CachedCigar<R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        &self.record
+    }
+}
+
+impl<R: PartialEq> PartialEq for CachedCigar<R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.record == other.record
+    }
+}
+
+impl<T: OptFields> CachedCigar<GAF<T>> {
+    /// The CIGAR in the cg tag of the record, as in `GAF::cigar`.
+    pub fn cigar(&self) -> Option<&CIGAR> {
+        self.cigar.get_or_init(|| self.record.cigar()).as_ref()
+    }
+}
+
+impl<T: OptFields> CachedCigar<PAF<T>> {
+    /// The CIGAR in the cg tag of the record, as in `PAF::cigar`.
+    pub fn cigar(&self) -> Option<&CIGAR> {
+        self.cigar.get_or_init(|| self.record.cigar()).as_ref()
+    }
+}
+
 fn parse_next<I, T>(mut input: I) -> Option<T>
 where
     I: Iterator,
@@ -311,6 +418,7 @@ where
 mod tests {
     use super::*;
 
+    #[allow(clippy::upper_case_acronyms)]
     #[test]
     fn parse_gaf_lines() {
         use GAFStep::*;
@@ -381,6 +489,33 @@ mod tests {
         assert_eq!(Some(expected_3), gaf_3);
     }
 
+    #[test]
+    fn cached_cigar() {
+        let gaf_in =
+            b"read1\t6\t0\t6\t+\t>s2>s3\t12\t2\t8\t5\t6\t60\tcg:Z:3M1I2M";
+        let gaf: super::GAF<OptionalFields> =
+            parse_gaf(gaf_in.split_str("\t")).unwrap();
+        assert_eq!(gaf.cigar().unwrap().to_string(), "3M1I2M");
+
+        let cached = CachedCigar::new(gaf.clone());
+        let cigar = cached.cigar().unwrap();
+        assert_eq!(cigar.to_string(), "3M1I2M");
+        assert!(std::ptr::eq(cigar, cached.cigar().unwrap()));
+        assert_eq!(cached.seq_name, gaf.seq_name);
+
+        // comparisons ignore whether the tag has been parsed
+        assert_eq!(cached, CachedCigar::new(gaf));
+
+        let paf_in = b"q\t6\t0\t6\t+\tt\t12\t2\t8\t5\t6\t60\tcg:Z:3M1Q";
+        let paf: PAF<OptionalFields> =
+            parse_paf(paf_in.split_str("\t")).unwrap();
+        assert!(paf.cigar().is_none());
+
+        let paf_in = b"q\t6\t0\t6\t+\tt\t12\t2\t8\t5\t6\t60";
+        let paf: PAF<()> = parse_paf(paf_in.split_str("\t")).unwrap();
+        assert!(paf.cigar().is_none());
+    }
+
     #[test]
     fn parse_gaf_step() {
         use GAFStep::*;
@@ -0,0 +1,161 @@
+use crate::optfields::*;
+
+use super::{
+    group_by_read, AlignmentResult, AlignmentType, ReadAlignments, GAF,
+};
+
+/// The fraction of the query range of an alignment that must overlap
+/// the primary alignment for it to be secondary rather than
+/// supplementary, as the default mask level of minimap2.
+const SECONDARY_OVERLAP: f64 = 0.5;
+
+/// The role of an alignment among the alignments of its read, with the
+/// same meaning as the SAM flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlignmentClass {
+    /// The representative alignment of the read; there is exactly one
+    /// per read.
+    Primary,
+    /// An alternative alignment of the same part of the read.
+    Secondary,
+    /// An alignment of another part of the read, e.g. in a chimeric
+    /// read.
+    Supplementary,
+}
+
+/// The length of the overlap of two ranges.
+fn overlap(
+    (a_start, a_end): (usize, usize),
+    (b_start, b_end): (usize, usize),
+) -> usize {
+    a_end.min(b_end).saturating_sub(a_start.max(b_start))
+}
+
+impl<T: OptFields> ReadAlignments<T> {
+    /// Classify each alignment of the read. The primary alignment is
+    /// the best alignment, as in `best`, among those that the tp tag
+    /// marks as primary, or among all of them if none does. The other
+    /// alignments are secondary if their tp tag says so, or if at
+    /// least half of their query range overlaps the query range of the
+    /// primary alignment, and supplementary otherwise.
+    pub fn classify(&self) -> Vec<AlignmentClass> {
+        let tp = |gaf: &GAF<T>| gaf.tags().alignment_type();
+        let candidate = |gaf: &GAF<T>| tp(gaf).is_none_or(|t| t.is_primary());
+        let score = |g: &GAF<T>| (g.residue_matches, g.quality);
+
+        let mut primary: Option<usize> = None;
+        for (ix, gaf) in self.records.iter().enumerate() {
+            if !candidate(gaf) {
+                continue;
+            }
+            match primary {
+                Some(p) if score(gaf) <= score(&self.records[p]) => (),
+                _ => primary = Some(ix),
+            }
+        }
+        let primary = match primary.or_else(|| self.best()) {
+            Some(primary) => primary,
+            None => return Vec::new(),
+        };
+        let primary_range = self.records[primary].seq_range;
+
+        self.records
+            .iter()
+            .enumerate()
+            .map(|(ix, gaf)| {
+                if ix == primary {
+                    return AlignmentClass::Primary;
+                }
+                if matches!(
+                    tp(gaf),
+                    Some(AlignmentType::Secondary)
+                        | Some(AlignmentType::InversionSecondary)
+                ) {
+                    return AlignmentClass::Secondary;
+                }
+                let (start, end) = gaf.seq_range;
+                let len = end.saturating_sub(start);
+                let shared = overlap(gaf.seq_range, primary_range);
+                if len > 0 && shared as f64 >= SECONDARY_OVERLAP * len as f64 {
+                    AlignmentClass::Secondary
+                } else {
+                    AlignmentClass::Supplementary
+                }
+            })
+            .collect()
+    }
+}
+
+/// Keep only the alignments of the given classes from a stream of
+/// GAF records, classifying the alignments of each read as in
+/// `ReadAlignments::classify`, e.g. `&[AlignmentClass::Primary]` to
+/// keep only primary alignments. The records of each read must be
+/// consecutive, as for `group_by_read`. Errors are passed through.
+pub fn filter_by_class<'a, I, T>(
+    records: I,
+    classes: &'a [AlignmentClass],
+) -> impl Iterator<Item = AlignmentResult<GAF<T>>> + 'a
+where
+    I: IntoIterator<Item = AlignmentResult<GAF<T>>>,
+    I::IntoIter: 'a,
+    T: OptFields + 'a,
+{
+    group_by_read(records).flat_map(move |group| {
+        let kept: Vec<_> = match group {
+            Ok(group) => {
+                let labels = group.classify();
+                group
+                    .records
+                    .into_iter()
+                    .zip(labels)
+                    .filter(|(_, class)| classes.contains(class))
+                    .map(|(gaf, _)| Ok(gaf))
+                    .collect()
+            }
+            Err(err) => vec![Err(err)],
+        };
+        kept
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gafpaf::{AlignmentIter, GAFIter};
+    use AlignmentClass::*;
+
+    #[test]
+    fn classify_read_alignments() {
+        let lines = [
+            // a chimeric read, with a secondary alignment of its start
+            "r1\t100\t0\t60\t+\t>1\t80\t0\t60\t58\t60\t60\ttp:A:P",
+            "r1\t100\t0\t60\t+\t>2\t80\t0\t60\t59\t60\t0\ttp:A:S",
+            "r1\t100\t60\t100\t+\t>3\t80\t0\t40\t40\t40\t60\ttp:A:P",
+            // no tp tags, so the overlaps decide
+            "r2\t100\t0\t100\t+\t>1\t100\t0\t100\t90\t100\t30",
+            "r2\t100\t10\t90\t+\t>2\t100\t0\t80\t70\t80\t10",
+            "r2\t100\t70\t100\t+\t>3\t100\t0\t30\t30\t30\t5",
+        ]
+        .join("\n");
+
+        let records: GAFIter<_, OptionalFields> =
+            AlignmentIter::new(lines.as_bytes());
+        let classes: Vec<_> = group_by_read(records)
+            .map(|group| group.unwrap().classify())
+            .collect();
+        assert_eq!(
+            classes,
+            vec![
+                vec![Primary, Secondary, Supplementary],
+                vec![Primary, Secondary, Secondary]
+            ]
+        );
+
+        let records: GAFIter<_, OptionalFields> =
+            AlignmentIter::new(lines.as_bytes());
+        let primary: Vec<_> = filter_by_class(records, &[Primary])
+            .map(|r| r.unwrap().path.to_string())
+            .collect();
+        assert_eq!(primary, vec![">1", ">1"]);
+    }
+}
@@ -0,0 +1,251 @@
+use fnv::FnvHashMap;
+
+use crate::{
+    gfa::{Orientation, SegmentId, GFA},
+    graph::segment_name_indices,
+    optfields::*,
+    validation::segment_length,
+};
+
+#[cfg(feature = "fs")]
+use super::AlignmentResult;
+use super::{GAFPath, GAFStep, GAF};
+
+/// Alignment coverage of the segments of a GFA, indexed like
+/// `GFA::segments`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    /// The number of aligned bases on each segment.
+    pub bases: Vec<u64>,
+    /// The number of records aligned to each segment.
+    pub reads: Vec<u64>,
+    /// The depth at each base of each segment, in the forward
+    /// orientation of the segment, if it was requested.
+    pub per_base: Option<Vec<Vec<u32>>>,
+    /// The number of records that were skipped because their path
+    /// isn't a list of segments in the GFA, or its range doesn't fit
+    /// the path.
+    pub skipped: usize,
+}
+
+impl Coverage {
+    /// The mean depth of a segment, i.e. its aligned bases divided by
+    /// its length.
+    pub fn mean_depth<N, T: OptFields>(
+        &self,
+        gfa: &GFA<N, T>,
+        segment: usize,
+    ) -> Option<f64> {
+        let len = segment_length(gfa.segments.get(segment)?)?;
+        if len == 0 {
+            return None;
+        }
+        Some(self.bases[segment] as f64 / len as f64)
+    }
+}
+
+/// Accumulates the coverage of GAF records on a GFA, one record at a
+/// time.
+#[derive(Debug, Clone)]
+pub struct CoverageCounter<'a, N, T: OptFields> {
+    pub(super) gfa: &'a GFA<N, T>,
+    indices: FnvHashMap<Vec<u8>, usize>,
+    coverage: Coverage,
+}
+
+impl<'a, N: SegmentId, T: OptFields> CoverageCounter<'a, N, T> {
+    /// Prepare to count coverage on the segments of the GFA. If
+    /// `per_base` is true, the depth at each base is also kept.
+    pub fn new(gfa: &'a GFA<N, T>, per_base: bool) -> Self {
+        let indices = segment_name_indices(gfa);
+
+        let per_base = if per_base {
+            let depths = gfa
+                .segments
+                .iter()
+                .map(|seg| vec![0; segment_length(seg).unwrap_or(0)])
+                .collect();
+            Some(depths)
+        } else {
+            None
+        };
+
+        let coverage = Coverage {
+            bases: vec![0; gfa.segments.len()],
+            reads: vec![0; gfa.segments.len()],
+            per_base,
+            skipped: 0,
+        };
+
+        CoverageCounter {
+            gfa,
+            indices,
+            coverage,
+        }
+    }
+
+    /// The segment index, orientation, and length of each step of a
+    /// record's path, or `None` if the path can't be placed on the
+    /// GFA.
+    fn steps<U: OptFields>(
+        &self,
+        gaf: &GAF<U>,
+    ) -> Option<Vec<(usize, Orientation, usize)>> {
+        let steps = match &gaf.path {
+            GAFPath::OrientIntv(steps) => steps,
+            GAFPath::StableId(_) => return None,
+        };
+        steps
+            .iter()
+            .map(|step| match step {
+                GAFStep::SegId(orient, name) => {
+                    let ix = *self.indices.get(name.as_slice())?;
+                    let len = segment_length(&self.gfa.segments[ix])?;
+                    Some((ix, *orient, len))
+                }
+                GAFStep::StableIntv(..) => None,
+            })
+            .collect()
+    }
+
+    /// Add the coverage of one record. The aligned interval of the
+    /// path is split across the steps it overlaps. Records on stable
+    /// sequences must be resolved to segment paths first, e.g. with
+    /// `rgfa::resolve_gaf`, or they're skipped.
+    pub fn add<U: OptFields>(&mut self, gaf: &GAF<U>) {
+        let (start, end) = gaf.path_range;
+        let steps = match self.steps(gaf) {
+            Some(steps) => steps,
+            None => {
+                self.coverage.skipped += 1;
+                return;
+            }
+        };
+        let path_len: usize = steps.iter().map(|(_, _, len)| len).sum();
+        if start > end || end > path_len {
+            self.coverage.skipped += 1;
+            return;
+        }
+
+        let mut offset = 0;
+        let mut covered = Vec::new();
+        for (ix, orient, len) in steps {
+            let step_end = offset + len;
+            let from = start.max(offset);
+            let to = end.min(step_end);
+            if from < to {
+                let (a, b) = (from - offset, to - offset);
+                let (a, b) = match orient {
+                    Orientation::Forward => (a, b),
+                    Orientation::Backward => (len - b, len - a),
+                };
+                self.coverage.bases[ix] += (b - a) as u64;
+                covered.push(ix);
+                if let Some(per_base) = &mut self.coverage.per_base {
+                    per_base[ix][a..b].iter_mut().for_each(|d| *d += 1);
+                }
+            }
+            offset = step_end;
+        }
+
+        covered.sort_unstable();
+        covered.dedup();
+        covered.iter().for_each(|&ix| self.coverage.reads[ix] += 1);
+    }
+
+    pub fn finish(self) -> Coverage {
+        self.coverage
+    }
+}
+
+/// Compute the coverage of GAF records on the segments of a GFA.
+pub fn gaf_coverage<'a, N, T, U, I>(
+    gfa: &GFA<N, T>,
+    records: I,
+    per_base: bool,
+) -> Coverage
+where
+    N: SegmentId,
+    T: OptFields,
+    U: OptFields + 'a,
+    I: IntoIterator<Item = &'a GAF<U>>,
+{
+    let mut counter = CoverageCounter::new(gfa, per_base);
+    for gaf in records {
+        counter.add(gaf);
+    }
+    counter.finish()
+}
+
+/// Compute the coverage of the records in a GAF file on the segments
+/// of a GFA, streaming the file rather than loading it. Optional
+/// fields aren't parsed.
+#[cfg(feature = "fs")]
+pub fn gaf_file_coverage<N, T, P>(
+    gfa: &GFA<N, T>,
+    path: P,
+    per_base: bool,
+) -> AlignmentResult<Coverage>
+where
+    N: SegmentId,
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+{
+    let mut counter = CoverageCounter::new(gfa, per_base);
+    for gaf in super::gaf_iter::<(), _>(path)? {
+        counter.add(&gaf?);
+    }
+    Ok(counter.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::GFAParser,
+        test_util::{gaf, parse},
+    };
+
+    #[test]
+    fn coverage_on_segments() {
+        let lines = ["S\ta\tACGT", "S\tb\tGG", "S\tc\tTTA"];
+        let gfa: GFA<Vec<u8>, ()> = parse(&lines);
+
+        let records = vec![
+            // the last 2 bases of a, all of b, and the first base of c
+            gaf(">a>b>c", 0, 2, 7),
+            // on the reverse strand, the first base of the path is the
+            // last base of c
+            gaf("<c<a", 0, 0, 4),
+            gaf(">a>x", 0, 0, 4),
+            gaf("chr1", 0, 0, 4),
+            gaf(">a", 0, 2, 6),
+        ];
+
+        let coverage = gaf_coverage(&gfa, &records, true);
+        assert_eq!(coverage.bases, vec![3, 2, 4]);
+        assert_eq!(coverage.reads, vec![2, 1, 2]);
+        assert_eq!(coverage.skipped, 3);
+
+        let per_base = coverage.per_base.as_ref().unwrap();
+        assert_eq!(per_base[0], vec![0, 0, 1, 2]);
+        assert_eq!(per_base[1], vec![1, 1]);
+        assert_eq!(per_base[2], vec![2, 1, 1]);
+
+        assert_eq!(coverage.mean_depth(&gfa, 1), Some(1.0));
+    }
+
+    #[test]
+    fn coverage_from_file() {
+        let parser: GFAParser<Vec<u8>, ()> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+        let coverage =
+            gaf_file_coverage(&gfa, "./test/gafs/lil.gaf", false).unwrap();
+
+        assert!(coverage.per_base.is_none());
+        // read3 is on a stable sequence
+        assert_eq!(coverage.skipped, 1);
+        let total: u64 = coverage.bases.iter().sum();
+        assert_eq!(total, 6 + 5);
+    }
+}
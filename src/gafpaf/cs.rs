@@ -0,0 +1,320 @@
+use bstr::ByteSlice;
+
+use nom::{bytes::complete::*, IResult};
+
+use crate::cigar::{CIGAROp, CIGAR};
+
+/// A single operation of a cs difference string. Sequences are
+/// stored as they appear in the string, i.e. lowercase, except for
+/// the bases of long-form matches.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CSOp {
+    /// `:n`, a match of the given length.
+    Match(u32),
+    /// `=ACGT`, a match including the matched bases.
+    MatchSeq(Vec<u8>),
+    /// `*ag`, a substitution of the reference base with the query
+    /// base.
+    Mismatch(u8, u8),
+    /// `+ag`, bases inserted in the query.
+    Insertion(Vec<u8>),
+    /// `-ag`, bases deleted from the reference.
+    Deletion(Vec<u8>),
+    /// `~gt100ag`, an intron with the two bases on either end, and
+    /// its length.
+    Intron([u8; 2], u32, [u8; 2]),
+}
+
+impl CSOp {
+    /// The number of reference bases the operation covers.
+    pub fn ref_len(&self) -> usize {
+        match self {
+            CSOp::Match(len) => *len as usize,
+            CSOp::MatchSeq(seq) | CSOp::Deletion(seq) => seq.len(),
+            CSOp::Mismatch(..) => 1,
+            CSOp::Insertion(_) => 0,
+            CSOp::Intron(_, len, _) => *len as usize,
+        }
+    }
+
+    /// The number of query bases the operation covers.
+    pub fn query_len(&self) -> usize {
+        match self {
+            CSOp::Match(len) => *len as usize,
+            CSOp::MatchSeq(seq) | CSOp::Insertion(seq) => seq.len(),
+            CSOp::Mismatch(..) => 1,
+            CSOp::Deletion(_) | CSOp::Intron(..) => 0,
+        }
+    }
+}
+
+impl std::fmt::Display for CSOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CSOp::Match(len) => write!(f, ":{}", len),
+            CSOp::MatchSeq(seq) => write!(f, "={}", seq.as_bstr()),
+            CSOp::Mismatch(r, q) => write!(f, "*{}{}", *r as char, *q as char),
+            CSOp::Insertion(seq) => write!(f, "+{}", seq.as_bstr()),
+            CSOp::Deletion(seq) => write!(f, "-{}", seq.as_bstr()),
+            CSOp::Intron(donor, len, acceptor) => {
+                write!(f, "~{}{}{}", donor.as_bstr(), len, acceptor.as_bstr())
+            }
+        }
+    }
+}
+
+/// A cs difference string, as found in the `cs:Z` tag produced by
+/// minimap2 and other aligners.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CS(pub Vec<CSOp>);
+
+fn is_base(c: u8) -> bool {
+    c.is_ascii_alphabetic()
+}
+
+fn parse_len(i: &[u8]) -> IResult<&[u8], u32> {
+    use nom::{
+        character::complete::digit1, combinator::map_res, error::ErrorKind,
+    };
+    map_res(digit1, |bs: &[u8]| {
+        let s = bs.to_str().map_err(|_| ErrorKind::Digit)?;
+        s.parse::<u32>().map_err(|_| ErrorKind::Digit)
+    })(i)
+}
+
+fn parse_pair(i: &[u8]) -> IResult<&[u8], [u8; 2]> {
+    use nom::combinator::map;
+    map(take_while_m_n(2, 2, is_base), |bs: &[u8]| [bs[0], bs[1]])(i)
+}
+
+fn parse_op(i: &[u8]) -> IResult<&[u8], CSOp> {
+    use nom::{
+        branch::alt,
+        combinator::map,
+        sequence::{preceded, tuple},
+    };
+
+    let bases = |i| take_while1(is_base)(i);
+
+    alt((
+        map(preceded(tag(":"), parse_len), CSOp::Match),
+        map(preceded(tag("="), bases), |bs: &[u8]| {
+            CSOp::MatchSeq(bs.to_vec())
+        }),
+        map(preceded(tag("*"), parse_pair), |[r, q]| {
+            CSOp::Mismatch(r, q)
+        }),
+        map(preceded(tag("+"), bases), |bs: &[u8]| {
+            CSOp::Insertion(bs.to_vec())
+        }),
+        map(preceded(tag("-"), bases), |bs: &[u8]| {
+            CSOp::Deletion(bs.to_vec())
+        }),
+        map(
+            preceded(tag("~"), tuple((parse_pair, parse_len, parse_pair))),
+            |(donor, len, acceptor)| CSOp::Intron(donor, len, acceptor),
+        ),
+    ))(i)
+}
+
+impl CS {
+    pub(crate) fn parser_bytestring(i: &[u8]) -> IResult<&[u8], Self> {
+        use nom::{combinator::map, multi::many1};
+        map(many1(parse_op), CS)(i)
+    }
+
+    /// Parse a cs string from an ASCII byte slice, e.g. the value of
+    /// a `cs:Z` tag. The whole input must be a valid cs string.
+    pub fn from_bytestring(i: &[u8]) -> Option<Self> {
+        match Self::parser_bytestring(i) {
+            Ok((b"", cs)) => Some(cs),
+            _ => None,
+        }
+    }
+
+    /// The number of reference bases covered by the alignment.
+    pub fn ref_len(&self) -> usize {
+        self.0.iter().map(CSOp::ref_len).sum()
+    }
+
+    /// The number of query bases covered by the alignment.
+    pub fn query_len(&self) -> usize {
+        self.0.iter().map(CSOp::query_len).sum()
+    }
+
+    /// Convert the cs string to a CIGAR. Matches and mismatches are
+    /// merged into `M` operations, unless `extended` is true, in which
+    /// case they become `=` and `X`. Introns become `N` operations.
+    pub fn to_cigar(&self, extended: bool) -> CIGAR {
+        use CIGAROp::*;
+        let (match_op, mismatch_op) = if extended { (E, X) } else { (M, M) };
+
+        let mut pairs: Vec<(u32, CIGAROp)> = Vec::new();
+        for op in self.0.iter() {
+            let (len, cg_op) = match op {
+                CSOp::Match(len) => (*len, match_op),
+                CSOp::MatchSeq(seq) => (seq.len() as u32, match_op),
+                CSOp::Mismatch(..) => (1, mismatch_op),
+                CSOp::Insertion(seq) => (seq.len() as u32, I),
+                CSOp::Deletion(seq) => (seq.len() as u32, D),
+                CSOp::Intron(_, len, _) => (*len, N),
+            };
+            match pairs.last_mut() {
+                Some((last_len, last_op)) if *last_op == cg_op => {
+                    *last_len += len
+                }
+                _ => pairs.push((len, cg_op)),
+            }
+        }
+        CIGAR::from_pairs(pairs)
+    }
+
+    /// Build the short form cs string of an alignment from its CIGAR
+    /// and the aligned parts of the query and reference sequences,
+    /// which are needed to find mismatches and the inserted and
+    /// deleted bases. Soft clips skip query bases, and hard clips and
+    /// padding are ignored.
+    ///
+    /// Returns `None` if the CIGAR runs past the end of either
+    /// sequence.
+    pub fn from_cigar(
+        cigar: &CIGAR,
+        query: &[u8],
+        reference: &[u8],
+    ) -> Option<Self> {
+        use CIGAROp::*;
+
+        let lower = |bs: &[u8]| bs.to_ascii_lowercase();
+
+        let push_match = |ops: &mut Vec<CSOp>| {
+            if let Some(CSOp::Match(last)) = ops.last_mut() {
+                *last += 1;
+            } else {
+                ops.push(CSOp::Match(1));
+            }
+        };
+
+        let mut ops: Vec<CSOp> = Vec::new();
+
+        let (mut q, mut r) = (0, 0);
+        for pair in cigar.0.iter() {
+            let (len, op) = pair.into_pair();
+            let n = len as usize;
+            match op {
+                M | E | X => {
+                    let q_seq = query.get(q..q + n)?;
+                    let r_seq = reference.get(r..r + n)?;
+                    for (&qb, &rb) in q_seq.iter().zip(r_seq) {
+                        if qb.eq_ignore_ascii_case(&rb) {
+                            push_match(&mut ops);
+                        } else {
+                            ops.push(CSOp::Mismatch(
+                                rb.to_ascii_lowercase(),
+                                qb.to_ascii_lowercase(),
+                            ));
+                        }
+                    }
+                    q += n;
+                    r += n;
+                }
+                I => {
+                    ops.push(CSOp::Insertion(lower(query.get(q..q + n)?)));
+                    q += n;
+                }
+                D => {
+                    ops.push(CSOp::Deletion(lower(reference.get(r..r + n)?)));
+                    r += n;
+                }
+                N => {
+                    let intron = reference.get(r..r + n)?;
+                    if n < 2 {
+                        return None;
+                    }
+                    let donor = lower(&intron[..2]);
+                    let acceptor = lower(&intron[n - 2..]);
+                    ops.push(CSOp::Intron(
+                        [donor[0], donor[1]],
+                        len,
+                        [acceptor[0], acceptor[1]],
+                    ));
+                    r += n;
+                }
+                S => q += n,
+                H | P => (),
+            }
+        }
+
+        Some(CS(ops))
+    }
+}
+
+impl std::fmt::Display for CS {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for op in self.0.iter() {
+            write!(f, "{}", op)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display_cs() {
+        use CSOp::*;
+
+        let input = b":6-ata:10+gtc:4*at:3~gt120ag=ACGT";
+        let cs = CS::from_bytestring(input).unwrap();
+        assert_eq!(
+            cs.0,
+            vec![
+                Match(6),
+                Deletion(b"ata".to_vec()),
+                Match(10),
+                Insertion(b"gtc".to_vec()),
+                Match(4),
+                Mismatch(b'a', b't'),
+                Match(3),
+                Intron(*b"gt", 120, *b"ag"),
+                MatchSeq(b"ACGT".to_vec()),
+            ]
+        );
+        assert_eq!(cs.to_string().as_bytes(), &input[..]);
+        assert_eq!(cs.ref_len(), 6 + 3 + 10 + 4 + 1 + 3 + 120 + 4);
+        assert_eq!(cs.query_len(), 6 + 10 + 3 + 4 + 1 + 3 + 4);
+
+        assert!(CS::from_bytestring(b"").is_none());
+        assert!(CS::from_bytestring(b":6-").is_none());
+        assert!(CS::from_bytestring(b"*a").is_none());
+        assert!(CS::from_bytestring(b"6M").is_none());
+    }
+
+    #[test]
+    fn cs_to_cigar() {
+        let cs = CS::from_bytestring(b":6-ata:10+gtc:4*at:3").unwrap();
+        assert_eq!(cs.to_cigar(false).to_string(), "6M3D10M3I8M");
+        assert_eq!(cs.to_cigar(true).to_string(), "6=3D10=3I4=1X3=");
+    }
+
+    #[test]
+    fn cs_from_cigar() {
+        let reference = b"ACGTACGTATGCA";
+        let query = b"ACCTACGAATTGCA";
+        let cigar = CIGAR::from_bytestring(b"7M2I1M1D4M").unwrap();
+
+        let cs = CS::from_cigar(&cigar, query, reference).unwrap();
+        assert_eq!(cs.to_string(), ":2*gc:4+aa:1-a:4");
+        assert_eq!(cs.to_cigar(false), cigar);
+        assert_eq!(cs.ref_len(), reference.len());
+        assert_eq!(cs.query_len(), query.len());
+
+        let cigar = CIGAR::from_bytestring(b"2S3M4N2M").unwrap();
+        let cs = CS::from_cigar(&cigar, b"TTACGCA", b"ACGGTAGCA").unwrap();
+        assert_eq!(cs.to_string(), ":3~gt4ag:2");
+
+        let too_long = CIGAR::from_bytestring(b"20M").unwrap();
+        assert!(CS::from_cigar(&too_long, query, reference).is_none());
+    }
+}
@@ -0,0 +1,415 @@
+use fnv::FnvHashSet;
+
+use crate::{
+    cigar::{CIGAROp, CIGAR},
+    optfields::*,
+};
+
+use super::{AlignmentResult, GAF, PAF};
+
+/// The identity of an alignment, as described for `GAF::identity`.
+fn alignment_identity(
+    cigar: Option<&CIGAR>,
+    edit_distance: Option<usize>,
+    residue_matches: usize,
+    block_length: usize,
+) -> Option<f64> {
+    if let Some(cigar) = cigar {
+        let matches = cigar.matches();
+        let mismatches = cigar.mismatches();
+        let columns = matches
+            + mismatches
+            + cigar.count(CIGAROp::M)
+            + cigar.insertions()
+            + cigar.deletions();
+        if matches + mismatches > 0 {
+            return Some(matches as f64 / columns as f64);
+        }
+    }
+
+    if block_length == 0 {
+        return None;
+    }
+    let block_length = block_length as f64;
+
+    match edit_distance {
+        Some(nm) => Some(1.0 - nm as f64 / block_length),
+        None => Some(residue_matches as f64 / block_length),
+    }
+}
+
+impl<T: OptFields> GAF<T> {
+    /// The identity of the alignment, as a fraction between 0 and 1.
+    ///
+    /// If the cg tag has `=` and `X` operations, this is the number of
+    /// matches divided by the number of alignment columns. Otherwise,
+    /// if there is an NM tag, it's one minus the edit distance divided
+    /// by the block length. If there's neither, the residue matches
+    /// and block length columns are used. Returns `None` if the block
+    /// length is zero.
+    pub fn identity(&self) -> Option<f64> {
+        alignment_identity(
+            self.cigar().as_ref(),
+            self.tags().edit_distance(),
+            self.residue_matches,
+            self.block_length,
+        )
+    }
+}
+
+impl<T: OptFields> PAF<T> {
+    /// The identity of the alignment, computed as in `GAF::identity`.
+    pub fn identity(&self) -> Option<f64> {
+        alignment_identity(
+            self.cigar().as_ref(),
+            self.tags().edit_distance(),
+            self.residue_matches,
+            self.block_length,
+        )
+    }
+}
+
+/// A set of conditions that GAF records must all satisfy, used to
+/// filter streams of records without loading them into memory.
+#[derive(Debug, Clone, Default)]
+pub struct GAFFilter {
+    min_quality: Option<u8>,
+    min_block_length: Option<usize>,
+    min_identity: Option<f64>,
+    read_names: Option<FnvHashSet<Vec<u8>>>,
+}
+
+impl GAFFilter {
+    /// A filter that accepts every record.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only accept records with at least this mapping quality.
+    pub fn min_quality(&mut self, quality: u8) -> &mut Self {
+        self.min_quality = Some(quality);
+        self
+    }
+
+    /// Only accept records with at least this block length.
+    pub fn min_block_length(&mut self, length: usize) -> &mut Self {
+        self.min_block_length = Some(length);
+        self
+    }
+
+    /// Only accept records with at least this identity, as computed
+    /// by `GAF::identity`.
+    pub fn min_identity(&mut self, identity: f64) -> &mut Self {
+        self.min_identity = Some(identity);
+        self
+    }
+
+    /// Only accept records of reads with one of the given names.
+    pub fn read_names<I, S>(&mut self, names: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Vec<u8>>,
+    {
+        self.read_names = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Check if a record satisfies all conditions of the filter.
+    pub fn matches<T: OptFields>(&self, gaf: &GAF<T>) -> bool {
+        if matches!(self.min_quality, Some(q) if gaf.quality < q) {
+            return false;
+        }
+        if matches!(self.min_block_length, Some(l) if gaf.block_length < l) {
+            return false;
+        }
+        if let Some(names) = &self.read_names {
+            if !names.contains(gaf.seq_name.as_slice()) {
+                return false;
+            }
+        }
+        if let Some(min_identity) = self.min_identity {
+            if !matches!(gaf.identity(), Some(id) if id >= min_identity) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Filter a stream of parsed records, such as the one produced by
+    /// `gaf_iter`. Errors are always passed through, so the consumer
+    /// can decide whether to stop.
+    pub fn apply<'a, I, T>(
+        &'a self,
+        records: I,
+    ) -> impl Iterator<Item = AlignmentResult<GAF<T>>> + 'a
+    where
+        I: IntoIterator<Item = AlignmentResult<GAF<T>>>,
+        I::IntoIter: 'a,
+        T: OptFields + 'a,
+    {
+        records.into_iter().filter(move |record| match record {
+            Ok(gaf) => self.matches(gaf),
+            Err(_) => true,
+        })
+    }
+}
+
+/// A set of conditions that PAF records must all satisfy, used to
+/// filter streams of records without loading them into memory.
+#[derive(Debug, Clone, Default)]
+pub struct PAFFilter {
+    min_quality: Option<u8>,
+    min_block_length: Option<usize>,
+    min_identity: Option<f64>,
+    primary_only: bool,
+}
+
+impl PAFFilter {
+    /// A filter that accepts every record.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only accept records with at least this mapping quality.
+    pub fn min_quality(&mut self, quality: u8) -> &mut Self {
+        self.min_quality = Some(quality);
+        self
+    }
+
+    /// Only accept records with at least this block length, i.e.
+    /// alignment length.
+    pub fn min_block_length(&mut self, length: usize) -> &mut Self {
+        self.min_block_length = Some(length);
+        self
+    }
+
+    /// Only accept records with at least this identity, as computed
+    /// by `PAF::identity`.
+    pub fn min_identity(&mut self, identity: f64) -> &mut Self {
+        self.min_identity = Some(identity);
+        self
+    }
+
+    /// Only accept primary alignments, according to the tp tag.
+    /// Records without a tp tag are accepted.
+    pub fn primary_only(&mut self) -> &mut Self {
+        self.primary_only = true;
+        self
+    }
+
+    /// Check if a record satisfies all conditions of the filter.
+    pub fn matches<T: OptFields>(&self, paf: &PAF<T>) -> bool {
+        if matches!(self.min_quality, Some(q) if paf.quality < q) {
+            return false;
+        }
+        if matches!(self.min_block_length, Some(l) if paf.block_length < l) {
+            return false;
+        }
+        if self.primary_only {
+            let tp = paf.tags().alignment_type();
+            if matches!(tp, Some(tp) if !tp.is_primary()) {
+                return false;
+            }
+        }
+        if let Some(min_identity) = self.min_identity {
+            if !matches!(paf.identity(), Some(id) if id >= min_identity) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Filter a stream of parsed records, such as the one produced by
+    /// `paf_iter`. Errors are always passed through, so the consumer
+    /// can decide whether to stop.
+    pub fn apply<'a, I, T>(
+        &'a self,
+        records: I,
+    ) -> impl Iterator<Item = AlignmentResult<PAF<T>>> + 'a
+    where
+        I: IntoIterator<Item = AlignmentResult<PAF<T>>>,
+        I::IntoIter: 'a,
+        T: OptFields + 'a,
+    {
+        records.into_iter().filter(move |record| match record {
+            Ok(paf) => self.matches(paf),
+            Err(_) => true,
+        })
+    }
+}
+
+/// Iterator adapter that keeps the best record of each run of
+/// consecutive PAF records with the same query name, created by
+/// `best_per_query`.
+pub struct BestPerQuery<I, T: OptFields> {
+    records: I,
+    pending: Option<AlignmentResult<PAF<T>>>,
+}
+
+impl<I, T> Iterator for BestPerQuery<I, T>
+where
+    I: Iterator<Item = AlignmentResult<PAF<T>>>,
+    T: OptFields,
+{
+    type Item = AlignmentResult<PAF<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut best =
+            match self.pending.take().or_else(|| self.records.next())? {
+                Ok(paf) => paf,
+                Err(err) => return Some(Err(err)),
+            };
+        for record in self.records.by_ref() {
+            match record {
+                Ok(paf) if paf.query_seq_name == best.query_seq_name => {
+                    let score = |p: &PAF<T>| (p.residue_matches, p.quality);
+                    if score(&paf) > score(&best) {
+                        best = paf;
+                    }
+                }
+                other => {
+                    self.pending = Some(other);
+                    break;
+                }
+            }
+        }
+        Some(Ok(best))
+    }
+}
+
+/// Keep only the best alignment of each query, i.e. the one with the
+/// most residue matches, then the highest mapping quality, or the
+/// first of those that are tied. The records of each query must be
+/// consecutive, as in the output of minimap2 or a file sorted by
+/// query. Errors are passed through, and end the current query.
+pub fn best_per_query<I, T>(records: I) -> BestPerQuery<I::IntoIter, T>
+where
+    I: IntoIterator<Item = AlignmentResult<PAF<T>>>,
+    T: OptFields,
+{
+    BestPerQuery {
+        records: records.into_iter(),
+        pending: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gafpaf::{
+        gaf_iter, parse_gaf, parse_paf, AlignmentIter, GAFIter, PAFIter,
+    };
+    use bstr::ByteSlice;
+
+    fn gaf(line: &str) -> GAF<OptionalFields> {
+        parse_gaf(line.as_bytes().split_str("\t")).unwrap()
+    }
+
+    #[test]
+    fn gaf_identity() {
+        let record =
+            gaf("r\t10\t0\t10\t+\t>1\t10\t0\t10\t8\t10\t60\tcg:Z:8=1X1I");
+        assert_eq!(record.identity(), Some(0.8));
+
+        let record = gaf("r\t10\t0\t10\t+\t>1\t10\t0\t10\t8\t10\t60\tNM:i:1");
+        assert_eq!(record.identity(), Some(0.9));
+
+        let record = gaf("r\t10\t0\t10\t+\t>1\t10\t0\t10\t7\t10\t60\tcg:Z:10M");
+        assert_eq!(record.identity(), Some(0.7));
+
+        let record = gaf("r\t0\t0\t0\t+\t>1\t10\t0\t0\t0\t0\t60");
+        assert_eq!(record.identity(), None);
+    }
+
+    #[test]
+    fn filter_records() {
+        let all = gaf_iter::<OptionalFields, _>("./test/gafs/lil.gaf")
+            .unwrap()
+            .count();
+        assert_eq!(all, 3);
+
+        let mut filter = GAFFilter::new();
+        filter.min_quality(60);
+        let records = gaf_iter::<OptionalFields, _>("./test/gafs/lil.gaf");
+        let names: Vec<_> = filter
+            .apply(records.unwrap())
+            .map(|r| r.unwrap().seq_name)
+            .collect();
+        assert_eq!(names, vec!["read1", "read2"]);
+
+        filter.min_block_length(6);
+        let records = gaf_iter::<OptionalFields, _>("./test/gafs/lil.gaf");
+        let names: Vec<_> = filter
+            .apply(records.unwrap())
+            .map(|r| r.unwrap().seq_name)
+            .collect();
+        assert_eq!(names, vec!["read1"]);
+
+        let mut filter = GAFFilter::new();
+        filter.read_names(vec!["read2", "read3"]).min_identity(1.0);
+        let records = gaf_iter::<OptionalFields, _>("./test/gafs/lil.gaf");
+        let names: Vec<_> = filter
+            .apply(records.unwrap())
+            .map(|r| r.unwrap().seq_name)
+            .collect();
+        assert_eq!(names, vec!["read2", "read3"]);
+    }
+
+    #[test]
+    fn filter_passes_errors_through() {
+        let input: &[u8] = b"r1\t4\t0\t4\t+\t>1\t8\t0\t4\t4\t4\t10\n\
+                             r2\t4\t0\t4\t+\t>1\tx\t0\t4\t4\t4\t60\n";
+        let records: GAFIter<_, ()> = AlignmentIter::new(input);
+        let mut filter = GAFFilter::new();
+        filter.min_quality(20);
+        let results: Vec<_> = filter.apply(records).collect();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+    }
+
+    #[test]
+    fn filter_paf_records() {
+        let lines = [
+            "q1\t100\t0\t100\t+\tt1\t1000\t0\t100\t90\t100\t60\ttp:A:P",
+            "q1\t100\t0\t100\t+\tt2\t1000\t0\t100\t95\t100\t0\ttp:A:S",
+            "q2\t100\t0\t50\t-\tt1\t1000\t0\t50\t40\t50\t30",
+            "q2\t100\t50\t100\t-\tt1\t1000\t50\t100\t40\t50\t40",
+            "q3\t100\t0\t20\t+\tt1\t1000\t0\t20\t20\t20\t60\ttp:A:P",
+        ]
+        .join("\n");
+        let input = lines.as_bytes();
+        let names = |records: Vec<AlignmentResult<PAF<OptionalFields>>>| {
+            records
+                .into_iter()
+                .map(|r| {
+                    let r = r.unwrap();
+                    format!("{}:{}", r.query_seq_name, r.target_seq_range.0)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut filter = PAFFilter::new();
+        filter.primary_only().min_block_length(50);
+        let records: PAFIter<_, OptionalFields> = AlignmentIter::new(input);
+        let kept = filter.apply(records).collect();
+        assert_eq!(names(kept), vec!["q1:0", "q2:0", "q2:50"]);
+
+        let mut filter = PAFFilter::new();
+        filter.min_quality(30).min_identity(0.85);
+        let records: PAFIter<_, OptionalFields> = AlignmentIter::new(input);
+        let kept = filter.apply(records).collect();
+        assert_eq!(names(kept), vec!["q1:0", "q3:0"]);
+
+        // the secondary alignment of q1 has more matches, and the
+        // second alignment of q2 has the higher quality
+        let records: PAFIter<_, OptionalFields> = AlignmentIter::new(input);
+        let best = best_per_query(records).collect();
+        assert_eq!(names(best), vec!["q1:0", "q2:50", "q3:0"]);
+
+        let paf: PAF<OptionalFields> = parse_paf(
+            b"q\t10\t0\t10\t+\tt\t10\t0\t10\t8\t10\t60\tNM:i:1".split_str("\t"),
+        )
+        .unwrap();
+        assert_eq!(paf.identity(), Some(0.9));
+    }
+}
@@ -0,0 +1,124 @@
+use bstr::BString;
+
+use crate::optfields::*;
+
+use super::{AlignmentResult, GAF};
+
+/// The alignments of a single read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadAlignments<T: OptFields> {
+    pub name: BString,
+    /// The records of the read, in the order they were read.
+    pub records: Vec<GAF<T>>,
+}
+
+impl<T: OptFields> ReadAlignments<T> {
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// The index of the best alignment of the read, i.e. the one with
+    /// the most residue matches, then the highest mapping quality, or
+    /// the first of those that are tied.
+    pub fn best(&self) -> Option<usize> {
+        let mut best: Option<usize> = None;
+        for (ix, gaf) in self.records.iter().enumerate() {
+            let score = |g: &GAF<T>| (g.residue_matches, g.quality);
+            match best {
+                Some(b) if score(gaf) <= score(&self.records[b]) => (),
+                _ => best = Some(ix),
+            }
+        }
+        best
+    }
+}
+
+/// Iterator adapter that groups consecutive GAF records with the same
+/// read name, created by `group_by_read`.
+pub struct ReadGroups<I, T: OptFields> {
+    records: I,
+    pending: Option<AlignmentResult<GAF<T>>>,
+}
+
+impl<I, T> Iterator for ReadGroups<I, T>
+where
+    I: Iterator<Item = AlignmentResult<GAF<T>>>,
+    T: OptFields,
+{
+    type Item = AlignmentResult<ReadAlignments<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = match self.pending.take().or_else(|| self.records.next())? {
+            Ok(gaf) => gaf,
+            Err(err) => return Some(Err(err)),
+        };
+        let mut group = ReadAlignments {
+            name: first.seq_name.clone(),
+            records: vec![first],
+        };
+        for record in self.records.by_ref() {
+            match record {
+                Ok(gaf) if gaf.seq_name == group.name => {
+                    group.records.push(gaf)
+                }
+                other => {
+                    self.pending = Some(other);
+                    break;
+                }
+            }
+        }
+        Some(Ok(group))
+    }
+}
+
+/// Group a stream of GAF records, such as the one produced by
+/// `gaf_iter`, into the alignments of each read. The records of each
+/// read must be consecutive, as in the output of most aligners or a
+/// file sorted with `GAFSortKey::ReadName`; otherwise a read is
+/// yielded once for each run of its records. Errors are passed
+/// through, and end the current group.
+pub fn group_by_read<I, T>(records: I) -> ReadGroups<I::IntoIter, T>
+where
+    I: IntoIterator<Item = AlignmentResult<GAF<T>>>,
+    T: OptFields,
+{
+    ReadGroups {
+        records: records.into_iter(),
+        pending: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gafpaf::{AlignmentIter, GAFIter};
+
+    #[test]
+    fn group_records_by_read() {
+        let lines = [
+            "r1\t10\t0\t5\t+\t>1\t8\t0\t5\t5\t5\t60",
+            "r1\t10\t5\t10\t+\t>2\t8\t0\t5\t4\t5\t10",
+            "r2\t10\t0\t10\t+\t>1\t8\t0\t8\t7\t10\t20",
+            "r2\t10\t0\t10\t+\t>3\t8\t0\t8\t7\t10\t30",
+            "r3\t10\t0\t10\t+\t>1\tx\t0\t8\t7\t10\t30",
+            "r1\t10\t0\t5\t+\t>1\t8\t0\t5\t5\t5\t60",
+        ]
+        .join("\n");
+        let records: GAFIter<_, ()> = AlignmentIter::new(lines.as_bytes());
+        let groups: Vec<_> = group_by_read(records).collect();
+        assert_eq!(groups.len(), 4);
+
+        let r1 = groups[0].as_ref().unwrap();
+        assert_eq!(r1.name, "r1");
+        assert_eq!(r1.len(), 2);
+        assert_eq!(r1.best(), Some(0));
+        let r2 = groups[1].as_ref().unwrap();
+        assert_eq!(r2.best(), Some(1));
+        assert!(groups[2].is_err());
+        assert_eq!(groups[3].as_ref().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,249 @@
+use bstr::BString;
+use fnv::{FnvHashMap, FnvHashSet};
+
+use crate::{
+    gfa::{Link, Orientation, Segment, GFA},
+    optfields::*,
+};
+
+use super::PAF;
+
+/// Thresholds used to classify read-to-read overlaps, with the same
+/// meaning as in miniasm.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverlapGraphOptions {
+    /// The longest overhang, i.e. unaligned sequence at an end of the
+    /// overlap that should have aligned, for the overlap to be used.
+    pub max_overhang: usize,
+    /// The smallest fraction of the overlap, including the overhangs,
+    /// that must be aligned for the overlap to be used.
+    pub min_aligned_fraction: f64,
+}
+
+impl Default for OverlapGraphOptions {
+    fn default() -> Self {
+        OverlapGraphOptions {
+            max_overhang: 1000,
+            min_aligned_fraction: 0.8,
+        }
+    }
+}
+
+/// How a PAF record relates its two reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OverlapKind {
+    /// The alignment is internal to both reads, e.g. a repeat.
+    Internal,
+    QueryContained,
+    TargetContained,
+    /// The end of the query overlaps the start of the target, with
+    /// the given overlap length on the query.
+    QueryToTarget(usize),
+    /// The end of the target overlaps the start of the query.
+    TargetToQuery(usize),
+}
+
+fn classify<T: OptFields>(
+    paf: &PAF<T>,
+    options: &OverlapGraphOptions,
+) -> OverlapKind {
+    let (qs, qe) = paf.query_seq_range;
+    let ql = paf.query_seq_len;
+    let (ts, te) = paf.target_seq_range;
+    let tl = paf.target_seq_len;
+
+    // the unaligned lengths of the target before and after the
+    // alignment, in the orientation of the query
+    let (t5, t3) = match paf.strand {
+        Orientation::Forward => (ts, tl.saturating_sub(te)),
+        Orientation::Backward => (tl.saturating_sub(te), ts),
+    };
+    let q3 = ql.saturating_sub(qe);
+
+    let ext5 = qs.min(t5);
+    let ext3 = q3.min(t3);
+    let aligned = qe.saturating_sub(qs);
+    let total = (aligned + ext5 + ext3) as f64;
+    if ext5 > options.max_overhang
+        || ext3 > options.max_overhang
+        || (aligned as f64) < total * options.min_aligned_fraction
+    {
+        OverlapKind::Internal
+    } else if qs <= t5 && q3 <= t3 {
+        OverlapKind::QueryContained
+    } else if qs >= t5 && q3 >= t3 {
+        OverlapKind::TargetContained
+    } else if qs > t5 {
+        OverlapKind::QueryToTarget(ql - (qs - t5))
+    } else {
+        OverlapKind::TargetToQuery(qe + t3)
+    }
+}
+
+/// Build an overlap graph from read-to-read alignments, in the style
+/// of miniasm. Every read becomes a segment without sequence, with
+/// an LN tag, and every dovetail overlap becomes a link with an
+/// overlap CIGAR of the overlap length on the query. Reads contained
+/// in other reads are removed, along with their links, and duplicate
+/// links, e.g. from both the A-B and B-A alignments, are merged.
+///
+/// The reads in `read_lengths` are added first, in order, so reads
+/// without overlaps are kept; other reads get their lengths from the
+/// PAF records.
+pub fn overlap_graph<T: OptFields>(
+    records: &[PAF<T>],
+    read_lengths: &[(BString, usize)],
+    options: &OverlapGraphOptions,
+) -> GFA<BString, OptionalFields> {
+    let mut gfa: GFA<BString, OptionalFields> = GFA::new();
+    let mut indices: FnvHashMap<BString, usize> = FnvHashMap::default();
+
+    let mut add_read = |gfa: &mut GFA<_, _>, name: &BString, len: usize| {
+        indices.entry(name.clone()).or_insert_with(|| {
+            let optional =
+                vec![OptField::new(b"LN", OptFieldVal::Int(len as i64))];
+            gfa.segments.push(Segment {
+                name: name.clone(),
+                sequence: b"*".to_vec(),
+                optional,
+            });
+            gfa.segments.len() - 1
+        });
+    };
+
+    for (name, len) in read_lengths {
+        add_read(&mut gfa, name, *len);
+    }
+
+    let mut contained: FnvHashSet<BString> = FnvHashSet::default();
+    for paf in records {
+        let (query, target) = (&paf.query_seq_name, &paf.target_seq_name);
+        add_read(&mut gfa, query, paf.query_seq_len);
+        add_read(&mut gfa, target, paf.target_seq_len);
+        if query == target {
+            continue;
+        }
+
+        let link =
+            |from: &BString, from_orient, to: &BString, to_orient, len| Link {
+                from_segment: from.clone(),
+                from_orient,
+                to_segment: to.clone(),
+                to_orient,
+                overlap: format!("{}M", len).into_bytes(),
+                optional: OptionalFields::new(),
+            };
+
+        let forward = Orientation::Forward;
+        match classify(paf, options) {
+            OverlapKind::Internal => (),
+            OverlapKind::QueryContained => {
+                contained.insert(query.clone());
+            }
+            OverlapKind::TargetContained => {
+                contained.insert(target.clone());
+            }
+            OverlapKind::QueryToTarget(len) => {
+                gfa.links
+                    .push(link(query, forward, target, paf.strand, len));
+            }
+            OverlapKind::TargetToQuery(len) => {
+                gfa.links
+                    .push(link(target, paf.strand, query, forward, len));
+            }
+        }
+    }
+
+    gfa.segments.retain(|seg| !contained.contains(&seg.name));
+    gfa.links.retain(|link| {
+        !contained.contains(&link.from_segment)
+            && !contained.contains(&link.to_segment)
+    });
+    gfa.merge_links();
+
+    gfa
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gafpaf::parse_paf;
+    use bstr::ByteSlice;
+
+    fn paf(line: &str) -> PAF<()> {
+        parse_paf(line.as_bytes().split_str("\t")).unwrap()
+    }
+
+    fn links(gfa: &GFA<BString, OptionalFields>) -> Vec<String> {
+        gfa.links
+            .iter()
+            .map(|l| {
+                format!(
+                    "{}{}{}{}{}",
+                    l.from_segment,
+                    l.from_orient,
+                    l.to_segment,
+                    l.to_orient,
+                    l.overlap.as_bstr()
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn classify_overlaps() {
+        let options = OverlapGraphOptions::default();
+        let kind = |line| classify(&paf(line), &options);
+
+        // the end of a overlaps the start of b
+        assert_eq!(
+            kind("a\t1000\t600\t1000\t+\tb\t1000\t0\t400\t400\t400\t60"),
+            OverlapKind::QueryToTarget(400)
+        );
+        assert_eq!(
+            kind("b\t1000\t0\t400\t+\ta\t1000\t600\t1000\t400\t400\t60"),
+            OverlapKind::TargetToQuery(400)
+        );
+        // the end of a overlaps the end of c, so c is reversed
+        assert_eq!(
+            kind("a\t1000\t700\t1000\t-\tc\t800\t500\t800\t300\t300\t60"),
+            OverlapKind::QueryToTarget(300)
+        );
+        assert_eq!(
+            kind("d\t300\t0\t300\t+\ta\t1000\t200\t500\t300\t300\t60"),
+            OverlapKind::QueryContained
+        );
+        assert_eq!(
+            kind("a\t1000\t200\t500\t+\td\t300\t0\t300\t300\t300\t60"),
+            OverlapKind::TargetContained
+        );
+        // a repeat in the middle of both reads
+        assert_eq!(
+            kind("a\t5000\t2000\t2500\t+\te\t5000\t3000\t3500\t500\t500\t60"),
+            OverlapKind::Internal
+        );
+    }
+
+    #[test]
+    fn build_overlap_graph() {
+        let records = vec![
+            paf("a\t1000\t600\t1000\t+\tb\t1000\t0\t400\t400\t400\t60"),
+            paf("b\t1000\t0\t400\t+\ta\t1000\t600\t1000\t400\t400\t60"),
+            paf("b\t1000\t700\t1000\t-\tc\t800\t500\t800\t300\t300\t60"),
+            paf("d\t300\t0\t300\t+\ta\t1000\t200\t500\t300\t300\t60"),
+            paf("d\t300\t0\t100\t+\tb\t1000\t900\t1000\t100\t100\t60"),
+        ];
+        let lengths = vec![("x".into(), 50)];
+        let gfa = overlap_graph(&records, &lengths, &Default::default());
+
+        let names: Vec<_> =
+            gfa.segments.iter().map(|s| s.name.to_string()).collect();
+        assert_eq!(names, vec!["x", "a", "b", "c"]);
+        assert_eq!(
+            gfa.segments[3].optional.get_field(b"LN").unwrap().value,
+            OptFieldVal::Int(800)
+        );
+
+        assert_eq!(links(&gfa), vec!["a+b+400M", "b+c-300M"]);
+    }
+}
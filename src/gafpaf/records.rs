@@ -0,0 +1,283 @@
+use std::{
+    error, fmt,
+    io::{self, BufRead, Write},
+    marker::PhantomData,
+};
+
+use bstr::ByteSlice;
+
+use crate::optfields::*;
+
+use super::{parse_gaf, parse_paf, GAF, PAF};
+
+/// Error produced when reading GAF or PAF records from a file.
+#[derive(Debug)]
+pub enum AlignmentParseError {
+    /// A line couldn't be parsed as a record. Includes the 1-based
+    /// line number and the line itself.
+    InvalidRecord(usize, String),
+    /// Wrapper for an IO error.
+    IOError(io::Error),
+}
+
+impl fmt::Display for AlignmentParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlignmentParseError::InvalidRecord(line_no, line) => {
+                write!(
+                    f,
+                    "Failed to parse record on line {}: {}",
+                    line_no, line
+                )
+            }
+            AlignmentParseError::IOError(err) => write!(f, "IO error: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for AlignmentParseError {
+    fn from(err: io::Error) -> Self {
+        Self::IOError(err)
+    }
+}
+
+impl error::Error for AlignmentParseError {}
+
+pub type AlignmentResult<T> = Result<T, AlignmentParseError>;
+
+/// A GAF or PAF record that can be parsed from the tab-separated
+/// fields of a line.
+pub trait AlignmentRecord: Sized {
+    fn parse_fields<'a, I>(fields: I) -> Option<Self>
+    where
+        I: Iterator<Item = &'a [u8]>;
+}
+
+impl<T: OptFields> AlignmentRecord for GAF<T> {
+    fn parse_fields<'a, I>(fields: I) -> Option<Self>
+    where
+        I: Iterator<Item = &'a [u8]>,
+    {
+        parse_gaf(fields)
+    }
+}
+
+impl<T: OptFields> AlignmentRecord for PAF<T> {
+    fn parse_fields<'a, I>(fields: I) -> Option<Self>
+    where
+        I: Iterator<Item = &'a [u8]>,
+    {
+        parse_paf(fields)
+    }
+}
+
+/// Iterator over the records of a GAF or PAF file, one per line.
+/// Empty lines are skipped, and the line buffer is reused between
+/// records.
+pub struct AlignmentIter<R: BufRead, A: AlignmentRecord> {
+    reader: R,
+    buf: Vec<u8>,
+    line_no: usize,
+    _record: PhantomData<A>,
+}
+
+pub type GAFIter<R, T> = AlignmentIter<R, GAF<T>>;
+pub type PAFIter<R, T> = AlignmentIter<R, PAF<T>>;
+
+impl<R: BufRead, A: AlignmentRecord> AlignmentIter<R, A> {
+    pub fn new(reader: R) -> Self {
+        AlignmentIter {
+            reader,
+            buf: Vec::new(),
+            line_no: 0,
+            _record: PhantomData,
+        }
+    }
+
+    /// The 1-based number of the last line that was read.
+    pub fn line_number(&self) -> usize {
+        self.line_no
+    }
+}
+
+impl<R: BufRead, A: AlignmentRecord> Iterator for AlignmentIter<R, A> {
+    type Item = AlignmentResult<A>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buf.clear();
+            match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(0) => return None,
+                Ok(_) => self.line_no += 1,
+                Err(err) => return Some(Err(err.into())),
+            }
+
+            let line = self.buf.trim_end_with(|c| c == '\n' || c == '\r');
+            if line.is_empty() {
+                continue;
+            }
+
+            let line_no = self.line_no;
+            let record =
+                A::parse_fields(line.split_str(b"\t")).ok_or_else(|| {
+                    let line = line.to_str_lossy().into_owned();
+                    AlignmentParseError::InvalidRecord(line_no, line)
+                });
+            return Some(record);
+        }
+    }
+}
+
+impl<R: BufRead, A: AlignmentRecord> std::iter::FusedIterator
+    for AlignmentIter<R, A>
+{
+}
+
+/// Stream the records of a GAF file.
+#[cfg(feature = "fs")]
+pub fn gaf_iter<T, P>(path: P) -> io::Result<GAFIter<Box<dyn BufRead>, T>>
+where
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+{
+    let reader = crate::reader::open_file(path)?;
+    Ok(AlignmentIter::new(reader))
+}
+
+/// Stream the records of a PAF file.
+#[cfg(feature = "fs")]
+pub fn paf_iter<T, P>(path: P) -> io::Result<PAFIter<Box<dyn BufRead>, T>>
+where
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+{
+    let reader = crate::reader::open_file(path)?;
+    Ok(AlignmentIter::new(reader))
+}
+
+/// Parse all records of a GAF file, stopping at the first malformed
+/// record.
+#[cfg(feature = "fs")]
+pub fn parse_gaf_file<T, P>(path: P) -> AlignmentResult<Vec<GAF<T>>>
+where
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+{
+    gaf_iter(path)?.collect()
+}
+
+/// Parse all records of a PAF file, stopping at the first malformed
+/// record.
+#[cfg(feature = "fs")]
+pub fn parse_paf_file<T, P>(path: P) -> AlignmentResult<Vec<PAF<T>>>
+where
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+{
+    paf_iter(path)?.collect()
+}
+
+/// Write GAF or PAF records to a stream, one per line.
+pub fn write_records<'a, A, I, W>(records: I, stream: &mut W) -> io::Result<()>
+where
+    A: fmt::Display + 'a,
+    I: IntoIterator<Item = &'a A>,
+    W: Write,
+{
+    for record in records {
+        writeln!(stream, "{}", record)?;
+    }
+    stream.flush()
+}
+
+/// Write GAF records to a file, BGZF-compressing it if the path ends
+/// with `.gz`, `.bgz`, or `.bgzf`.
+#[cfg(feature = "fs")]
+pub fn write_gaf_file<T, P>(path: P, records: &[GAF<T>]) -> io::Result<()>
+where
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+{
+    let mut file = crate::reader::create_file(path)?;
+    write_records(records, &mut file)?;
+    file.finish()
+}
+
+/// Write PAF records to a file, BGZF-compressing it if the path ends
+/// with `.gz`, `.bgz`, or `.bgzf`.
+#[cfg(feature = "fs")]
+pub fn write_paf_file<T, P>(path: P, records: &[PAF<T>]) -> io::Result<()>
+where
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+{
+    let mut file = crate::reader::create_file(path)?;
+    write_records(records, &mut file)?;
+    file.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gafpaf::GAFPath;
+
+    #[test]
+    fn parse_gaf_and_paf_files() {
+        let gafs: Vec<GAF<OptionalFields>> =
+            parse_gaf_file("./test/gafs/lil.gaf").unwrap();
+        assert_eq!(gafs.len(), 3);
+        assert_eq!(gafs[1].seq_name, "read2");
+        assert_eq!(gafs[1].path.to_string(), "<6<4<2");
+        assert!(matches!(&gafs[2].path, GAFPath::StableId(id) if id == "x"));
+
+        let pafs: Vec<PAF<()>> = parse_paf_file("./test/gafs/lil.paf").unwrap();
+        assert_eq!(pafs.len(), 2);
+        assert_eq!(pafs[1].target_seq_name, "chr2");
+    }
+
+    #[test]
+    fn invalid_records_have_line_numbers() {
+        let input: &[u8] = b"r1\t4\t0\t4\t+\t>1\t8\t0\t4\t4\t4\t60\r\n\n\
+                             r2\t4\t0\t4\t+\t>1\tx\t0\t4\t4\t4\t60\n";
+        let mut gafs: GAFIter<_, ()> = AlignmentIter::new(input);
+
+        assert_eq!(gafs.next().unwrap().unwrap().quality, 60);
+        match gafs.next() {
+            Some(Err(AlignmentParseError::InvalidRecord(3, line))) => {
+                assert!(line.starts_with("r2\t"))
+            }
+            other => panic!("expected an invalid record, got {:?}", other),
+        }
+        assert!(gafs.next().is_none());
+        assert_eq!(gafs.line_number(), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn compressed_gaf_round_trip() {
+        let gafs: Vec<GAF<OptionalFields>> =
+            parse_gaf_file("./test/gafs/lil.gaf").unwrap();
+
+        let path = crate::test_util::temp_path("round_trip.gaf.gz");
+        write_gaf_file(&path, &gafs).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        let mut reader = std::io::BufReader::new(bytes.as_slice());
+        let compression = crate::reader::Compression::detect(&mut reader);
+        assert_eq!(compression.unwrap(), crate::reader::Compression::Bgzf);
+
+        let parsed: Vec<GAF<OptionalFields>> = parse_gaf_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(gafs, parsed);
+    }
+
+    #[test]
+    fn write_paf_records() {
+        let pafs: Vec<PAF<OptionalFields>> =
+            parse_paf_file("./test/gafs/lil.paf").unwrap();
+        let mut output = Vec::new();
+        write_records(&pafs, &mut output).unwrap();
+        let expected = std::fs::read("./test/gafs/lil.paf").unwrap();
+        assert_eq!(output, expected);
+    }
+}
@@ -0,0 +1,524 @@
+use std::path::PathBuf;
+#[cfg(feature = "fs")]
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(feature = "fs")]
+use bstr::ByteSlice;
+
+use crate::optfields::*;
+
+#[cfg(feature = "fs")]
+use super::{parse_gaf, parse_paf, AlignmentParseError, AlignmentResult};
+use super::{GAFPath, GAFStep, GAF, PAF};
+
+/// The order to sort GAF records in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GAFSortKey {
+    /// By the name of the first segment or stable sequence of the
+    /// path, then by the start of the path range.
+    Position,
+    /// By read name, then by the start of the read range.
+    ReadName,
+}
+
+impl GAFSortKey {
+    /// The key of a record. Names are compared as bytes.
+    pub fn key<T: OptFields>(&self, gaf: &GAF<T>) -> (Vec<u8>, usize) {
+        match self {
+            GAFSortKey::Position => {
+                let first = match &gaf.path {
+                    GAFPath::StableId(id) => id.as_slice(),
+                    GAFPath::OrientIntv(steps) => match steps.first() {
+                        Some(GAFStep::SegId(_, name)) => name.as_slice(),
+                        Some(GAFStep::StableIntv(_, name, _, _)) => {
+                            name.as_slice()
+                        }
+                        None => b"",
+                    },
+                };
+                (first.to_vec(), gaf.path_range.0)
+            }
+            GAFSortKey::ReadName => (gaf.seq_name.to_vec(), gaf.seq_range.0),
+        }
+    }
+}
+
+/// Sort GAF records in memory. The sort is stable.
+pub fn sort_gaf_records<T: OptFields>(records: &mut [GAF<T>], key: GAFSortKey) {
+    records.sort_by_cached_key(|gaf| key.key(gaf));
+}
+
+/// The order to sort PAF records in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PAFSortKey {
+    /// By target name, then by the start of the target range.
+    Target,
+    /// By query name, then by the start of the query range.
+    Query,
+}
+
+impl PAFSortKey {
+    /// The key of a record. Names are compared as bytes.
+    pub fn key<T: OptFields>(&self, paf: &PAF<T>) -> (Vec<u8>, usize) {
+        match self {
+            PAFSortKey::Target => {
+                (paf.target_seq_name.to_vec(), paf.target_seq_range.0)
+            }
+            PAFSortKey::Query => {
+                (paf.query_seq_name.to_vec(), paf.query_seq_range.0)
+            }
+        }
+    }
+}
+
+/// Sort PAF records in memory. The sort is stable.
+pub fn sort_paf_records<T: OptFields>(records: &mut [PAF<T>], key: PAFSortKey) {
+    records.sort_by_cached_key(|paf| key.key(paf));
+}
+
+/// Options for sorting GAF files that may not fit in memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GAFSortOptions {
+    pub key: GAFSortKey,
+    /// The number of bytes of records to sort in memory at a time.
+    /// Larger inputs are sorted in chunks of this size that are
+    /// written to temporary files and merged.
+    pub max_chunk_bytes: usize,
+    /// The number of temporary files to merge at once. If there are
+    /// more, they are merged in several passes. Values less than 2
+    /// are treated as 2.
+    pub max_merge_files: usize,
+    /// The directory to write the temporary files to.
+    pub temp_dir: PathBuf,
+}
+
+impl Default for GAFSortOptions {
+    fn default() -> Self {
+        GAFSortOptions {
+            key: GAFSortKey::Position,
+            max_chunk_bytes: 256 * 1024 * 1024,
+            max_merge_files: 64,
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+/// Options for sorting PAF files that may not fit in memory, as for
+/// GAF files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PAFSortOptions {
+    pub key: PAFSortKey,
+    pub max_chunk_bytes: usize,
+    pub max_merge_files: usize,
+    pub temp_dir: PathBuf,
+}
+
+impl Default for PAFSortOptions {
+    fn default() -> Self {
+        PAFSortOptions {
+            key: PAFSortKey::Target,
+            max_chunk_bytes: 256 * 1024 * 1024,
+            max_merge_files: 64,
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+/// A line with its sort key and its line number in the input. Lines
+/// are ordered by key, then by line number, which keeps sorts stable.
+#[cfg(feature = "fs")]
+type KeyedLine = ((Vec<u8>, usize), usize, Vec<u8>);
+
+/// Removes the temporary chunk files when dropped, including when
+/// sorting fails partway.
+#[cfg(feature = "fs")]
+struct ChunkFiles(Vec<PathBuf>);
+
+#[cfg(feature = "fs")]
+impl Drop for ChunkFiles {
+    fn drop(&mut self) {
+        for path in self.0.iter() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Parse a line with `parse` and compute its sort key.
+#[cfg(feature = "fs")]
+fn line_key<R>(
+    parse: impl Fn(&[u8]) -> Option<R>,
+    key: impl Fn(&R) -> (Vec<u8>, usize),
+    line: &[u8],
+    line_no: usize,
+) -> AlignmentResult<(Vec<u8>, usize)> {
+    let record = parse(line).ok_or_else(|| {
+        let line = line.to_str_lossy().into_owned();
+        AlignmentParseError::InvalidRecord(line_no, line)
+    })?;
+    Ok(key(&record))
+}
+
+/// Create a new chunk file in `temp_dir`, to be removed with `files`.
+#[cfg(feature = "fs")]
+fn create_chunk(
+    temp_dir: &std::path::Path,
+    files: &mut ChunkFiles,
+) -> AlignmentResult<BufWriter<File>> {
+    static CHUNK_ID: AtomicUsize = AtomicUsize::new(0);
+    let id = CHUNK_ID.fetch_add(1, Ordering::Relaxed);
+    let name = format!("rs-gfa-sort-{}-{}.chunk", std::process::id(), id);
+    let path = temp_dir.join(name);
+    files.0.push(path.clone());
+    Ok(BufWriter::new(File::create(&path)?))
+}
+
+/// Write a line to a chunk file, prefixed with the position of its
+/// key, its line number, and the name of its key, so they don't have
+/// to be computed again when merging. Names come from tab-separated
+/// fields, so they can't contain tabs or newlines.
+#[cfg(feature = "fs")]
+fn write_chunk_line<W: Write>(
+    writer: &mut W,
+    ((name, pos), line_no, line): &KeyedLine,
+) -> std::io::Result<()> {
+    write!(writer, "{}\t{}\t", pos, line_no)?;
+    writer.write_all(name)?;
+    writer.write_all(b"\t")?;
+    writer.write_all(line)?;
+    writer.write_all(b"\n")
+}
+
+/// Read the next line of a chunk file written by `write_chunk_line`.
+#[cfg(feature = "fs")]
+fn read_chunk_line<R: BufRead>(
+    reader: &mut std::io::Split<R>,
+) -> AlignmentResult<Option<KeyedLine>> {
+    let mut line = match reader.next() {
+        Some(line) => line?,
+        None => return Ok(None),
+    };
+    let invalid = || {
+        let error = "Invalid line in a temporary sort file";
+        std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+    };
+    let number = |field: &[u8]| field.to_str().ok()?.parse().ok();
+
+    let mut fields = line.splitn_str(4, b"\t");
+    let pos = fields.next().and_then(number).ok_or_else(invalid)?;
+    let line_no = fields.next().and_then(number).ok_or_else(invalid)?;
+    let name = fields.next().ok_or_else(invalid)?.to_vec();
+    let rest = fields.next().ok_or_else(invalid)?;
+    let start = line.len() - rest.len();
+    line.drain(..start);
+    Ok(Some(((name, pos), line_no, line)))
+}
+
+/// Sort a chunk of lines in memory and write it to a new chunk file.
+#[cfg(feature = "fs")]
+fn write_chunk(
+    temp_dir: &std::path::Path,
+    files: &mut ChunkFiles,
+    chunk: &mut Vec<KeyedLine>,
+) -> AlignmentResult<()> {
+    chunk.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut writer = create_chunk(temp_dir, files)?;
+    for line in chunk.drain(..) {
+        write_chunk_line(&mut writer, &line)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Merge the sorted chunk files, passing each line to `write` in
+/// order.
+#[cfg(feature = "fs")]
+fn merge_chunks<F>(paths: &[PathBuf], mut write: F) -> AlignmentResult<()>
+where
+    F: FnMut(&KeyedLine) -> std::io::Result<()>,
+{
+    let mut readers = paths
+        .iter()
+        .map(|path| Ok(BufReader::new(File::open(path)?).split(b'\n')))
+        .collect::<AlignmentResult<Vec<_>>>()?;
+
+    let mut heap = BinaryHeap::new();
+    for (ix, reader) in readers.iter_mut().enumerate() {
+        if let Some(next) = read_chunk_line(reader)? {
+            heap.push(Reverse((next, ix)));
+        }
+    }
+
+    while let Some(Reverse((line, ix))) = heap.pop() {
+        write(&line)?;
+        if let Some(next) = read_chunk_line(&mut readers[ix])? {
+            heap.push(Reverse((next, ix)));
+        }
+    }
+    Ok(())
+}
+
+/// Sort the lines of a file by the keys computed by `line_key`,
+/// holding at most `max_chunk_bytes` of lines in memory at once, and
+/// merging at most `max_merge_files` chunk files at once.
+#[cfg(feature = "fs")]
+fn sort_lines<P, Q, K>(
+    input: P,
+    output: Q,
+    max_chunk_bytes: usize,
+    max_merge_files: usize,
+    temp_dir: &std::path::Path,
+    line_key: K,
+) -> AlignmentResult<usize>
+where
+    P: AsRef<std::path::Path>,
+    Q: AsRef<std::path::Path>,
+    K: Fn(&[u8], usize) -> AlignmentResult<(Vec<u8>, usize)>,
+{
+    let mut reader = crate::reader::open_file(input)?;
+    let mut files = ChunkFiles(Vec::new());
+    let mut chunk: Vec<KeyedLine> = Vec::new();
+    let mut chunk_bytes = 0;
+    let mut records = 0;
+
+    let mut buf = Vec::new();
+    let mut line_no = 0;
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        line_no += 1;
+        let line = buf.trim_end_with(|c| c == '\n' || c == '\r');
+        if line.is_empty() {
+            continue;
+        }
+
+        let key = line_key(line, line_no)?;
+        chunk_bytes += line.len();
+        chunk.push((key, line_no, line.to_vec()));
+        records += 1;
+
+        if chunk_bytes >= max_chunk_bytes {
+            write_chunk(temp_dir, &mut files, &mut chunk)?;
+            chunk_bytes = 0;
+        }
+    }
+
+    let mut out = crate::reader::create_file(output)?;
+
+    if files.0.is_empty() {
+        chunk.sort_by(|a, b| a.0.cmp(&b.0));
+        for (_, _, line) in chunk {
+            out.write_all(&line)?;
+            out.write_all(b"\n")?;
+        }
+        out.finish()?;
+        return Ok(records);
+    }
+
+    if !chunk.is_empty() {
+        write_chunk(temp_dir, &mut files, &mut chunk)?;
+    }
+
+    // merge groups of chunks into larger chunks until they can all be
+    // merged at once; the merged chunks are removed after each pass
+    let fan_in = max_merge_files.max(2);
+    while files.0.len() > fan_in {
+        let merged = ChunkFiles(std::mem::take(&mut files.0));
+        for group in merged.0.chunks(fan_in) {
+            let mut writer = create_chunk(temp_dir, &mut files)?;
+            merge_chunks(group, |line| write_chunk_line(&mut writer, line))?;
+            writer.flush()?;
+        }
+    }
+
+    merge_chunks(&files.0, |(_, _, line)| {
+        out.write_all(line)?;
+        out.write_all(b"\n")
+    })?;
+    out.finish()?;
+
+    Ok(records)
+}
+
+/// Sort a GAF file, writing the sorted records to `output`. The
+/// records are sorted as lines, so they are written exactly as they
+/// appear in the input, and empty lines are dropped. Input and output
+/// are compressed as with `reader::open_file` and
+/// `reader::create_file`. The sort is stable.
+///
+/// At most `options.max_chunk_bytes` of records are held in memory at
+/// once, and at most `options.max_merge_files` temporary files are
+/// open at once. Returns the number of records.
+#[cfg(feature = "fs")]
+pub fn sort_gaf_file<P, Q>(
+    input: P,
+    output: Q,
+    options: &GAFSortOptions,
+) -> AlignmentResult<usize>
+where
+    P: AsRef<std::path::Path>,
+    Q: AsRef<std::path::Path>,
+{
+    let parse = |line: &[u8]| parse_gaf::<_, ()>(line.split_str(b"\t"));
+    let key = |gaf: &GAF<()>| options.key.key(gaf);
+    sort_lines(
+        input,
+        output,
+        options.max_chunk_bytes,
+        options.max_merge_files,
+        &options.temp_dir,
+        |line, line_no| line_key(parse, key, line, line_no),
+    )
+}
+
+/// Sort a PAF file, writing the sorted records to `output`, in the
+/// same way as `sort_gaf_file`.
+#[cfg(feature = "fs")]
+pub fn sort_paf_file<P, Q>(
+    input: P,
+    output: Q,
+    options: &PAFSortOptions,
+) -> AlignmentResult<usize>
+where
+    P: AsRef<std::path::Path>,
+    Q: AsRef<std::path::Path>,
+{
+    let parse = |line: &[u8]| parse_paf::<_, ()>(line.split_str(b"\t"));
+    let key = |paf: &PAF<()>| options.key.key(paf);
+    sort_lines(
+        input,
+        output,
+        options.max_chunk_bytes,
+        options.max_merge_files,
+        &options.temp_dir,
+        |line, line_no| line_key(parse, key, line, line_no),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gafpaf::parse_gaf_file;
+
+    fn read_names(path: &std::path::Path) -> Vec<String> {
+        let gafs: Vec<GAF<()>> = parse_gaf_file(path).unwrap();
+        gafs.iter().map(|g| g.seq_name.to_string()).collect()
+    }
+
+    fn write_input(name: &str) -> PathBuf {
+        let lines = [
+            "r3\t4\t0\t4\t+\t>2>3\t9\t1\t5\t4\t4\t60",
+            "r1\t4\t2\t4\t+\t>1\t9\t5\t9\t4\t4\t60",
+            "r2\t4\t0\t4\t+\t<2\t9\t0\t4\t4\t4\t60",
+            "",
+            "r1\t4\t0\t2\t+\t>1>2\t9\t0\t4\t4\t4\t60",
+            "r4\t4\t0\t4\t+\t>10\t9\t0\t4\t4\t4\t60",
+        ];
+        let path = crate::test_util::temp_path(name);
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        path
+    }
+
+    #[test]
+    fn sort_by_position_and_name() {
+        let input = write_input("sort_input.gaf");
+        let output = crate::test_util::temp_path("sort_output.gaf");
+
+        // tiny chunks force the external merge
+        let mut options = GAFSortOptions {
+            max_chunk_bytes: 50,
+            ..GAFSortOptions::default()
+        };
+        let count = sort_gaf_file(&input, &output, &options).unwrap();
+        assert_eq!(count, 5);
+        assert_eq!(read_names(&output), vec!["r1", "r1", "r4", "r2", "r3"]);
+
+        options.key = GAFSortKey::ReadName;
+        sort_gaf_file(&input, &output, &options).unwrap();
+        assert_eq!(read_names(&output), vec!["r1", "r1", "r2", "r3", "r4"]);
+        let sorted: Vec<GAF<()>> = parse_gaf_file(&output).unwrap();
+        assert_eq!(sorted[0].seq_range, (0, 2));
+
+        // several merge passes
+        options.max_chunk_bytes = 1;
+        options.max_merge_files = 2;
+        sort_gaf_file(&input, &output, &options).unwrap();
+        assert_eq!(read_names(&output), vec!["r1", "r1", "r2", "r3", "r4"]);
+        let sorted: Vec<GAF<()>> = parse_gaf_file(&output).unwrap();
+        assert_eq!(sorted[0].seq_range, (0, 2));
+
+        // in memory
+        options.max_chunk_bytes = usize::MAX;
+        sort_gaf_file(&input, &output, &options).unwrap();
+        assert_eq!(read_names(&output), vec!["r1", "r1", "r2", "r3", "r4"]);
+
+        let mut records: Vec<GAF<()>> = parse_gaf_file(&input).unwrap();
+        sort_gaf_records(&mut records, GAFSortKey::Position);
+        let names: Vec<_> =
+            records.iter().map(|g| g.seq_name.to_string()).collect();
+        assert_eq!(names, vec!["r1", "r1", "r4", "r2", "r3"]);
+
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn sort_reports_invalid_lines() {
+        let input = crate::test_util::temp_path("sort_invalid.gaf");
+        std::fs::write(&input, "r1\t4\t0\t4\t+\t>1\t9\t0\t4\t4\t4\t60\nbad\n")
+            .unwrap();
+        let output = crate::test_util::temp_path("sort_invalid_out.gaf");
+        let result = sort_gaf_file(&input, &output, &GAFSortOptions::default());
+        assert!(matches!(
+            result,
+            Err(AlignmentParseError::InvalidRecord(2, _))
+        ));
+        std::fs::remove_file(&input).unwrap();
+    }
+
+    #[test]
+    fn sort_paf_by_target_and_query() {
+        let lines = [
+            "q2\t50\t5\t45\t-\tchr2\t500\t10\t50\t40\t40\t0",
+            "q1\t100\t0\t100\t+\tchr2\t500\t5\t100\t95\t100\t60",
+            "q1\t100\t0\t100\t+\tchr1\t1000\t200\t300\t95\t100\t60",
+        ];
+        let input = crate::test_util::temp_path("sort_input.paf");
+        let output = crate::test_util::temp_path("sort_output.paf");
+        std::fs::write(&input, lines.join("\n")).unwrap();
+
+        let targets = |path: &std::path::Path| -> Vec<String> {
+            let pafs: Vec<PAF<()>> =
+                crate::gafpaf::parse_paf_file(path).unwrap();
+            pafs.iter()
+                .map(|p| format!("{}:{}", p.target_seq_name, p.query_seq_name))
+                .collect()
+        };
+
+        let mut options = PAFSortOptions {
+            max_chunk_bytes: 50,
+            ..PAFSortOptions::default()
+        };
+        assert_eq!(sort_paf_file(&input, &output, &options).unwrap(), 3);
+        assert_eq!(targets(&output), vec!["chr1:q1", "chr2:q1", "chr2:q2"]);
+
+        options.key = PAFSortKey::Query;
+        sort_paf_file(&input, &output, &options).unwrap();
+        assert_eq!(targets(&output), vec!["chr2:q1", "chr1:q1", "chr2:q2"]);
+
+        let mut records: Vec<PAF<()>> =
+            crate::gafpaf::parse_paf_file(&input).unwrap();
+        sort_paf_records(&mut records, PAFSortKey::Target);
+        assert_eq!(records[0].target_seq_name, "chr1");
+
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+}
@@ -0,0 +1,425 @@
+use bstr::BString;
+use fnv::FnvHashMap;
+
+use crate::{
+    gfa::{SegmentId, GFA},
+    optfields::*,
+    validation::segment_length,
+};
+
+#[cfg(feature = "fs")]
+use super::AlignmentResult;
+use super::{CoverageCounter, GAFPath, GAFStep, GAF, PAF};
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+/// The number of bins of the identity histogram; bin `i` counts the
+/// alignments with identity in `[i / 100, (i + 1) / 100)`, and the
+/// last bin those with identity 1.
+const IDENTITY_BINS: usize = 101;
+
+/// Alignment summary of a single target sequence.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetSummary {
+    /// The number of alignments to the target.
+    pub alignments: u64,
+    /// The total length of the target ranges of the alignments.
+    pub aligned_bases: u64,
+    /// The length of the target, if known.
+    pub length: Option<usize>,
+}
+
+impl TargetSummary {
+    /// The aligned bases divided by the length of the target.
+    pub fn mean_depth(&self) -> Option<f64> {
+        match self.length? {
+            0 => None,
+            len => Some(self.aligned_bases as f64 / len as f64),
+        }
+    }
+}
+
+/// Summary statistics of a set of GAF or PAF records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlignmentSummary {
+    /// The number of records, including unmapped ones.
+    pub records: u64,
+    /// The number of records without a target, i.e. with `*` as the
+    /// target or path.
+    pub unmapped_records: u64,
+    /// The number of distinct reads.
+    pub reads: u64,
+    /// The number of distinct reads with at least one alignment.
+    pub mapped_reads: u64,
+    /// The number of alignments with each mapping quality. Alignments
+    /// with quality 255, i.e. without a known quality, aren't counted.
+    pub mapq_histogram: Vec<u64>,
+    /// The number of alignments in each identity percentile, as
+    /// computed by `GAF::identity` and `PAF::identity`.
+    pub identity_histogram: Vec<u64>,
+    /// The mean identity of the alignments with a known identity.
+    pub mean_identity: Option<f64>,
+    pub targets: BTreeMap<BString, TargetSummary>,
+}
+
+impl AlignmentSummary {
+    /// The number of records that are alignments.
+    pub fn alignments(&self) -> u64 {
+        self.records - self.unmapped_records
+    }
+
+    /// The fraction of the reads that have at least one alignment.
+    pub fn mapped_fraction(&self) -> Option<f64> {
+        if self.reads == 0 {
+            None
+        } else {
+            Some(self.mapped_reads as f64 / self.reads as f64)
+        }
+    }
+
+    /// Write the summary as a tab-separated table: the counts as
+    /// `name value` lines, followed by the nonzero bins of the
+    /// histograms, and a line per target with its alignments, aligned
+    /// bases, length, and mean depth.
+    pub fn write_report<W: Write>(&self, stream: &mut W) -> io::Result<()> {
+        writeln!(stream, "records\t{}", self.records)?;
+        writeln!(stream, "alignments\t{}", self.alignments())?;
+        writeln!(stream, "unmapped_records\t{}", self.unmapped_records)?;
+        writeln!(stream, "reads\t{}", self.reads)?;
+        writeln!(stream, "mapped_reads\t{}", self.mapped_reads)?;
+        if let Some(fraction) = self.mapped_fraction() {
+            writeln!(stream, "mapped_fraction\t{:.4}", fraction)?;
+        }
+        if let Some(identity) = self.mean_identity {
+            writeln!(stream, "mean_identity\t{:.4}", identity)?;
+        }
+        for (mapq, &count) in self.mapq_histogram.iter().enumerate() {
+            if count > 0 {
+                writeln!(stream, "mapq\t{}\t{}", mapq, count)?;
+            }
+        }
+        for (bin, &count) in self.identity_histogram.iter().enumerate() {
+            if count > 0 {
+                let low = bin as f64 / 100.0;
+                writeln!(stream, "identity\t{:.2}\t{}", low, count)?;
+            }
+        }
+        for (name, target) in self.targets.iter() {
+            let length = target.length.map(|l| l.to_string());
+            let depth = target.mean_depth().map(|d| format!("{:.4}", d));
+            writeln!(
+                stream,
+                "target\t{}\t{}\t{}\t{}\t{}",
+                name,
+                target.alignments,
+                target.aligned_bases,
+                length.as_deref().unwrap_or("*"),
+                depth.as_deref().unwrap_or("*")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Accumulates the summary of GAF or PAF records, one record at a
+/// time. The aligned bases of GAF records on paths of several segments
+/// can only be counted if the counter is given the GFA the records
+/// were aligned to, with `with_gfa`.
+#[derive(Debug, Clone)]
+pub struct SummaryCounter<'a, N = Vec<u8>, T: OptFields = ()> {
+    summary: AlignmentSummary,
+    /// Whether each read seen has been aligned.
+    read_mapped: FnvHashMap<BString, bool>,
+    identity_sum: f64,
+    identity_count: u64,
+    /// The coverage of GAF records on the segments of the GFA, if
+    /// there is one.
+    coverage: Option<CoverageCounter<'a, N, T>>,
+}
+
+impl<'a, N, T: OptFields> Default for SummaryCounter<'a, N, T> {
+    fn default() -> Self {
+        SummaryCounter {
+            summary: AlignmentSummary {
+                records: 0,
+                unmapped_records: 0,
+                reads: 0,
+                mapped_reads: 0,
+                mapq_histogram: vec![0; 255],
+                identity_histogram: vec![0; IDENTITY_BINS],
+                mean_identity: None,
+                targets: BTreeMap::new(),
+            },
+            read_mapped: FnvHashMap::default(),
+            identity_sum: 0.0,
+            identity_count: 0,
+            coverage: None,
+        }
+    }
+}
+
+impl SummaryCounter<'static> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<'a, N: SegmentId, T: OptFields> SummaryCounter<'a, N, T> {
+    /// Prepare to summarize GAF records aligned to the given GFA,
+    /// whose segment lengths are used to split the aligned bases of
+    /// each record across the segments of its path, as in
+    /// `gaf_coverage`.
+    pub fn with_gfa(gfa: &'a GFA<N, T>) -> Self {
+        SummaryCounter {
+            coverage: Some(CoverageCounter::new(gfa, false)),
+            ..Self::default()
+        }
+    }
+
+    fn add_read(&mut self, name: &BString, mapped: bool) {
+        self.summary.records += 1;
+        let seen = self.read_mapped.entry(name.clone()).or_insert(false);
+        *seen |= mapped;
+        if !mapped {
+            self.summary.unmapped_records += 1;
+        }
+    }
+
+    fn add_alignment(&mut self, quality: u8, identity: Option<f64>) {
+        if let Some(count) =
+            self.summary.mapq_histogram.get_mut(quality as usize)
+        {
+            *count += 1;
+        }
+        if let Some(identity) = identity {
+            let bin = (identity.clamp(0.0, 1.0) * 100.0) as usize;
+            self.summary.identity_histogram[bin.min(IDENTITY_BINS - 1)] += 1;
+            self.identity_sum += identity;
+            self.identity_count += 1;
+        }
+    }
+
+    fn add_target(
+        &mut self,
+        name: &[u8],
+        aligned_bases: Option<usize>,
+        length: Option<usize>,
+    ) {
+        let target = self.summary.targets.entry(name.into()).or_default();
+        target.alignments += 1;
+        target.aligned_bases += aligned_bases.unwrap_or(0) as u64;
+        if length.is_some() {
+            target.length = length;
+        }
+    }
+
+    /// Add a GAF record. Records with `*` as the path are unmapped.
+    /// An alignment counts toward the targets of each distinct
+    /// segment or stable sequence in its path. Its aligned bases are
+    /// split across the segments of its path if the counter has a GFA,
+    /// and otherwise only counted if the path is a stable ID or a
+    /// single segment, as the lengths of the steps aren't known.
+    pub fn add_gaf<U: OptFields>(&mut self, gaf: &GAF<U>) {
+        let mapped = !matches!(&gaf.path, GAFPath::StableId(id) if id == "*");
+        self.add_read(&gaf.seq_name, mapped);
+        if !mapped {
+            return;
+        }
+        self.add_alignment(gaf.quality, gaf.identity());
+
+        let (start, end) = gaf.path_range;
+        let aligned = end.checked_sub(start);
+        match &gaf.path {
+            GAFPath::StableId(id) => {
+                self.add_target(id, aligned, Some(gaf.path_len))
+            }
+            GAFPath::OrientIntv(steps) => {
+                let mut names: Vec<&[u8]> = steps
+                    .iter()
+                    .map(|step| match step {
+                        GAFStep::SegId(_, name) => name.as_slice(),
+                        GAFStep::StableIntv(_, name, _, _) => name.as_slice(),
+                    })
+                    .collect();
+                names.sort_unstable();
+                names.dedup();
+                if let Some(coverage) = &mut self.coverage {
+                    coverage.add(gaf);
+                } else if let ([name], [GAFStep::SegId(..)]) =
+                    (names.as_slice(), steps.as_slice())
+                {
+                    self.add_target(name, aligned, Some(gaf.path_len));
+                    return;
+                }
+                for name in names {
+                    self.add_target(name, None, None);
+                }
+            }
+        }
+    }
+
+    /// Add a PAF record. Records with `*` as the target are unmapped.
+    pub fn add_paf<U: OptFields>(&mut self, paf: &PAF<U>) {
+        let mapped = paf.target_seq_name != "*";
+        self.add_read(&paf.query_seq_name, mapped);
+        if !mapped {
+            return;
+        }
+        self.add_alignment(paf.quality, paf.identity());
+        let (start, end) = paf.target_seq_range;
+        self.add_target(
+            &paf.target_seq_name,
+            end.checked_sub(start),
+            Some(paf.target_seq_len),
+        );
+    }
+
+    pub fn finish(mut self) -> AlignmentSummary {
+        if let Some(counter) = self.coverage.take() {
+            let gfa = counter.gfa;
+            let coverage = counter.finish();
+            for (ix, seg) in gfa.segments.iter().enumerate() {
+                if coverage.reads[ix] > 0 {
+                    let name = seg.name.display().into_bytes();
+                    let target = self.summary.targets.entry(name.into());
+                    let target = target.or_default();
+                    target.aligned_bases += coverage.bases[ix];
+                    target.length = segment_length(seg);
+                }
+            }
+        }
+        self.summary.reads = self.read_mapped.len() as u64;
+        self.summary.mapped_reads =
+            self.read_mapped.values().filter(|&&m| m).count() as u64;
+        if self.identity_count > 0 {
+            let mean = self.identity_sum / self.identity_count as f64;
+            self.summary.mean_identity = Some(mean);
+        }
+        self.summary
+    }
+}
+
+/// Summarize the records in a GAF file, streaming the file rather
+/// than loading it.
+#[cfg(feature = "fs")]
+pub fn gaf_file_summary<P>(path: P) -> AlignmentResult<AlignmentSummary>
+where
+    P: AsRef<std::path::Path>,
+{
+    let mut counter = SummaryCounter::new();
+    for gaf in super::gaf_iter::<OptionalFields, _>(path)? {
+        counter.add_gaf(&gaf?);
+    }
+    Ok(counter.finish())
+}
+
+/// Summarize the records in a GAF file aligned to the given GFA, as
+/// `SummaryCounter::with_gfa` does, streaming the file rather than
+/// loading it.
+#[cfg(feature = "fs")]
+pub fn gaf_file_summary_with_gfa<N, T, P>(
+    gfa: &GFA<N, T>,
+    path: P,
+) -> AlignmentResult<AlignmentSummary>
+where
+    N: SegmentId,
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+{
+    let mut counter = SummaryCounter::with_gfa(gfa);
+    for gaf in super::gaf_iter::<OptionalFields, _>(path)? {
+        counter.add_gaf(&gaf?);
+    }
+    Ok(counter.finish())
+}
+
+/// Summarize the records in a PAF file, streaming the file rather
+/// than loading it.
+#[cfg(feature = "fs")]
+pub fn paf_file_summary<P>(path: P) -> AlignmentResult<AlignmentSummary>
+where
+    P: AsRef<std::path::Path>,
+{
+    let mut counter = SummaryCounter::new();
+    for paf in super::paf_iter::<OptionalFields, _>(path)? {
+        counter.add_paf(&paf?);
+    }
+    Ok(counter.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gafpaf::parse_gaf, parser::GFAParser};
+    use bstr::ByteSlice;
+
+    #[test]
+    fn summarize_alignments() {
+        let summary = paf_file_summary("./test/gafs/lil.paf").unwrap();
+        assert_eq!(summary.records, 2);
+        assert_eq!(summary.mapped_fraction(), Some(1.0));
+        assert_eq!(summary.mapq_histogram[60], 1);
+        assert_eq!(summary.mapq_histogram[0], 1);
+        // q1 has NM:i:5 over 100 columns, and q2 40 matches in 40
+        assert_eq!(summary.identity_histogram[95], 1);
+        assert_eq!(summary.identity_histogram[100], 1);
+        let chr1 = &summary.targets[b"chr1".as_bstr()];
+        assert_eq!((chr1.alignments, chr1.aligned_bases), (1, 100));
+        assert_eq!(chr1.mean_depth(), Some(0.1));
+
+        let mut counter = SummaryCounter::new();
+        for gaf in
+            crate::gafpaf::gaf_iter::<(), _>("./test/gafs/lil.gaf").unwrap()
+        {
+            counter.add_gaf(&gaf.unwrap());
+        }
+        let unmapped = "read4\t9\t0\t0\t+\t*\t0\t0\t0\t0\t0\t0";
+        let unmapped: GAF<()> =
+            parse_gaf(unmapped.as_bytes().split_str("\t")).unwrap();
+        counter.add_gaf(&unmapped);
+        let summary = counter.finish();
+
+        assert_eq!(summary.records, 4);
+        assert_eq!(summary.alignments(), 3);
+        assert_eq!((summary.reads, summary.mapped_reads), (4, 3));
+        assert_eq!(summary.mapped_fraction(), Some(0.75));
+        // read3 is on the stable sequence x
+        let x = &summary.targets[b"x".as_bstr()];
+        assert_eq!((x.alignments, x.aligned_bases), (1, 3));
+        assert_eq!(summary.targets[b"2".as_bstr()].alignments, 2);
+
+        let mut report = Vec::new();
+        summary.write_report(&mut report).unwrap();
+        let report = report.to_str().unwrap();
+        assert!(report.starts_with("records\t4\nalignments\t3\n"));
+        assert!(report.contains("mapq\t30\t1\n"));
+        assert!(report.contains("target\tx\t1\t3\t20\t0.1500\n"));
+
+        // with the GFA, the bases of read2 are split across 6, 4, and 2
+        let parser: GFAParser<Vec<u8>, ()> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+        let summary =
+            gaf_file_summary_with_gfa(&gfa, "./test/gafs/lil.gaf").unwrap();
+        let target = |name: &str| {
+            let t = &summary.targets[name.as_bytes().as_bstr()];
+            (t.alignments, t.aligned_bases, t.length)
+        };
+        assert_eq!(target("1"), (1, 6, Some(8)));
+        assert_eq!(target("2"), (2, 1, Some(1)));
+        assert_eq!(target("6"), (1, 3, Some(3)));
+        assert_eq!(target("x"), (1, 3, Some(20)));
+
+        // a quality of 255 is missing, so it isn't in the histogram
+        let mut counter = SummaryCounter::new();
+        let missing = "read\t9\t0\t9\t+\tx\t20\t0\t9\t9\t9\t255";
+        let missing: GAF<()> =
+            parse_gaf(missing.as_bytes().split_str("\t")).unwrap();
+        counter.add_gaf(&missing);
+        let summary = counter.finish();
+        assert_eq!(summary.alignments(), 1);
+        assert_eq!(summary.mapq_histogram.iter().sum::<u64>(), 0);
+    }
+}
@@ -0,0 +1,152 @@
+//! Typed views of the optional fields that minimap2 and other mappers
+//! write in PAF and GAF records. Like the GFA metadata views, they
+//! borrow the optional fields of a record and look up each tag when
+//! it's requested.
+
+use crate::{cigar::CIGAR, optfields::*};
+
+use super::{parse_cigar_tag, GAF, PAF};
+
+/// The type of an alignment, from the tp tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlignmentType {
+    /// `P`
+    Primary,
+    /// `S`
+    Secondary,
+    /// `I`, a primary alignment that is part of an inversion.
+    InversionPrimary,
+    /// `i`
+    InversionSecondary,
+}
+
+impl AlignmentType {
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'P' => Some(AlignmentType::Primary),
+            'S' => Some(AlignmentType::Secondary),
+            'I' => Some(AlignmentType::InversionPrimary),
+            'i' => Some(AlignmentType::InversionSecondary),
+            _ => None,
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            AlignmentType::Primary => 'P',
+            AlignmentType::Secondary => 'S',
+            AlignmentType::InversionPrimary => 'I',
+            AlignmentType::InversionSecondary => 'i',
+        }
+    }
+
+    pub fn is_primary(self) -> bool {
+        matches!(
+            self,
+            AlignmentType::Primary | AlignmentType::InversionPrimary
+        )
+    }
+}
+
+/// The optional fields of a PAF or GAF record set by minimap2.
+#[derive(Debug, Clone)]
+pub struct AlignmentTags<'a, T: OptFields> {
+    optional: &'a T,
+}
+
+impl<'a, T: OptFields> AlignmentTags<'a, T> {
+    /// Number of mismatches and gaps in the alignment, from the NM
+    /// tag.
+    pub fn edit_distance(&self) -> Option<usize> {
+        self.optional.get_typed(b"NM")
+    }
+
+    /// Whether the alignment is primary or secondary, from the tp tag.
+    pub fn alignment_type(&self) -> Option<AlignmentType> {
+        AlignmentType::from_char(self.optional.get_typed(b"tp")?)
+    }
+
+    /// Number of minimizers on the chain, from the cm tag.
+    pub fn chain_minimizers(&self) -> Option<usize> {
+        self.optional.get_typed(b"cm")
+    }
+
+    /// Chaining score, from the s1 tag.
+    pub fn chaining_score(&self) -> Option<i64> {
+        self.optional.get_typed(b"s1")
+    }
+
+    /// Best secondary chaining score, from the s2 tag.
+    pub fn secondary_score(&self) -> Option<i64> {
+        self.optional.get_typed(b"s2")
+    }
+
+    /// Approximate per-base divergence, from the dv tag.
+    pub fn divergence(&self) -> Option<f64> {
+        self.optional.get_typed(b"dv")
+    }
+
+    /// Gap-compressed per-base divergence, from the de tag.
+    pub fn gap_compressed_divergence(&self) -> Option<f64> {
+        self.optional.get_typed(b"de")
+    }
+
+    /// The CIGAR of the alignment, from the cg tag.
+    pub fn cigar(&self) -> Option<CIGAR> {
+        parse_cigar_tag(self.optional)
+    }
+}
+
+impl<T: OptFields> PAF<T> {
+    pub fn tags(&self) -> AlignmentTags<'_, T> {
+        AlignmentTags {
+            optional: &self.optional,
+        }
+    }
+}
+
+impl<T: OptFields> GAF<T> {
+    pub fn tags(&self) -> AlignmentTags<'_, T> {
+        AlignmentTags {
+            optional: &self.optional,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gafpaf::parse_paf;
+    use bstr::ByteSlice;
+
+    #[test]
+    fn minimap2_tags() {
+        let line = "q\t100\t0\t90\t+\tt\t1000\t10\t100\t85\t92\t60\
+                    \tNM:i:7\ttp:A:S\tcm:i:12\ts1:i:80\ts2:i:40\
+                    \tdv:f:0.05\tde:f:0.0312\tcg:Z:45M2I45M";
+        let paf: PAF<OptionalFields> =
+            parse_paf(line.as_bytes().split_str("\t")).unwrap();
+        let tags = paf.tags();
+
+        assert_eq!(tags.edit_distance(), Some(7));
+        assert_eq!(tags.alignment_type(), Some(AlignmentType::Secondary));
+        assert!(!tags.alignment_type().unwrap().is_primary());
+        assert_eq!(tags.chain_minimizers(), Some(12));
+        assert_eq!(tags.chaining_score(), Some(80));
+        assert_eq!(tags.secondary_score(), Some(40));
+        assert!((tags.divergence().unwrap() - 0.05).abs() < 1e-6);
+        let de = tags.gap_compressed_divergence().unwrap();
+        assert!((de - 0.0312).abs() < 1e-6);
+        assert_eq!(tags.cigar().unwrap().to_string(), "45M2I45M");
+        assert_eq!(
+            AlignmentType::from_char('I').map(|t| t.to_char()),
+            Some('I')
+        );
+
+        let line = "q\t100\t0\t90\t+\tt\t1000\t10\t100\t85\t92\t60\ttp:Z:P";
+        let paf: PAF<OptionalFields> =
+            parse_paf(line.as_bytes().split_str("\t")).unwrap();
+        assert_eq!(paf.tags().alignment_type(), None);
+        assert_eq!(paf.tags().edit_distance(), None);
+    }
+}
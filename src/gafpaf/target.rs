@@ -0,0 +1,117 @@
+use bstr::BString;
+use fnv::FnvHashMap;
+
+use crate::{
+    gfa::{Orientation, SegmentId, GFA},
+    graph::segment_name_indices,
+    optfields::*,
+    seq::revcomp_iter,
+};
+
+use super::{GAFPath, GAFStep, GAF};
+
+/// Spells the target sequences of GAF records from the segments of a
+/// GFA, i.e. the part of the graph that each record is aligned to.
+pub struct TargetSequences<'a, N, T: OptFields> {
+    gfa: &'a GFA<N, T>,
+    indices: FnvHashMap<Vec<u8>, usize>,
+}
+
+impl<'a, N: SegmentId, T: OptFields> TargetSequences<'a, N, T> {
+    pub fn new(gfa: &'a GFA<N, T>) -> Self {
+        let indices = segment_name_indices(gfa);
+        TargetSequences { gfa, indices }
+    }
+
+    /// The sequence of the path of the record in the path range,
+    /// with the steps on the reverse strand reverse complemented.
+    /// Overlaps between the steps are ignored, as in the path length
+    /// of a GAF record.
+    ///
+    /// Returns `None` if the path is on stable sequences, or a step
+    /// is on a segment that isn't in the GFA or has no sequence, or
+    /// the path range doesn't fit in the path length of the record or
+    /// in the path itself.
+    pub fn spell<U: OptFields>(&self, gaf: &GAF<U>) -> Option<BString> {
+        let (start, end) = gaf.path_range;
+        if start > end || end > gaf.path_len {
+            return None;
+        }
+        let steps = match &gaf.path {
+            GAFPath::OrientIntv(steps) => steps,
+            GAFPath::StableId(_) => return None,
+        };
+
+        let mut segments = Vec::with_capacity(steps.len());
+        let mut path_len = 0usize;
+        for step in steps.iter() {
+            let (orient, name) = match step {
+                GAFStep::SegId(orient, name) => (*orient, name),
+                GAFStep::StableIntv(..) => return None,
+            };
+            let ix = *self.indices.get(name.as_slice())?;
+            let segment = self.gfa.segments[ix].sequence.as_slice();
+            if segment == b"*" {
+                return None;
+            }
+            path_len += segment.len();
+            segments.push((orient, segment));
+        }
+        if end > path_len {
+            return None;
+        }
+
+        let mut seq = Vec::with_capacity(end - start);
+        let mut offset = 0;
+        for (orient, segment) in segments {
+            let len = segment.len();
+            let from = start.max(offset);
+            let to = end.min(offset + len);
+            if from < to {
+                let (a, b) = (from - offset, to - offset);
+                match orient {
+                    Orientation::Forward => {
+                        seq.extend_from_slice(&segment[a..b])
+                    }
+                    Orientation::Backward => {
+                        let bases = segment[len - b..len - a].iter().copied();
+                        seq.extend(revcomp_iter(bases))
+                    }
+                }
+            }
+            offset += len;
+        }
+        Some(seq.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{gaf, parse};
+
+    #[test]
+    fn spell_target_sequences() {
+        let lines = ["S\ta\tACGT", "S\tb\tGG", "S\tc\tTTA", "S\td\t*"];
+        let gfa: GFA<Vec<u8>, ()> = parse(&lines);
+        let targets = TargetSequences::new(&gfa);
+
+        let spell = |path, start, end| {
+            targets
+                .spell(&gaf(path, 9, start, end))
+                .map(|s| s.to_string())
+        };
+        assert_eq!(spell(">a>b>c", 2, 7).unwrap(), "GTGGT");
+        // <c is TAA, and <a is ACGT
+        assert_eq!(spell("<c<a", 1, 5).unwrap(), "AAAC");
+        assert_eq!(spell(">b<c", 0, 5).unwrap(), "GGTAA");
+        assert_eq!(spell(">a", 2, 2).unwrap(), "");
+        assert!(spell(">a", 2, 6).is_none());
+        assert!(spell(">a>x", 0, 4).is_none());
+        assert!(spell(">a>d", 0, 4).is_none());
+        assert!(spell("chr1", 0, 4).is_none());
+        // ranges past the path length are rejected before allocating
+        assert!(spell(">a", 0, usize::MAX).is_none());
+        assert!(spell(">a>b>c", 2, 10).is_none());
+    }
+}
@@ -0,0 +1,267 @@
+use fnv::{FnvHashMap, FnvHashSet};
+
+use crate::{
+    gfa::{Orientation, SegmentId, GFA},
+    graph::{in_side, out_side, segment_name_indices, Side},
+    optfields::*,
+    validation::segment_length,
+};
+
+use super::{GAFPath, GAFStep, GAF};
+
+use std::fmt;
+
+/// The kinds of problems found when checking a GAF record against a
+/// GFA.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GAFError {
+    /// A step refers to a segment that isn't in the GFA. Includes the
+    /// segment name.
+    MissingSegment(String),
+    /// A step is on a segment of unknown length, so the length of the
+    /// path can't be checked.
+    UnknownLength(String),
+    /// Two consecutive steps aren't connected by a link in the
+    /// orientations they're traversed in. Includes the index of the
+    /// first of the two steps.
+    MissingLink { step: usize },
+    /// The path length of the record isn't the sum of the lengths of
+    /// its steps.
+    PathLengthMismatch { path_len: usize, steps_len: usize },
+    /// The path range is reversed, or extends past the end of the
+    /// path.
+    RangeOutOfBounds {
+        start: usize,
+        end: usize,
+        path_len: usize,
+    },
+}
+
+impl fmt::Display for GAFError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GAFError::MissingSegment(seg) => {
+                write!(f, "Step on missing segment `{}`", seg)
+            }
+            GAFError::UnknownLength(seg) => {
+                write!(f, "Step on segment `{}` of unknown length", seg)
+            }
+            GAFError::MissingLink { step } => {
+                write!(f, "No link between steps {} and {}", step, step + 1)
+            }
+            GAFError::PathLengthMismatch {
+                path_len,
+                steps_len,
+            } => write!(
+                f,
+                "Path length is {}, but the steps have length {}",
+                path_len, steps_len
+            ),
+            GAFError::RangeOutOfBounds {
+                start,
+                end,
+                path_len,
+            } => write!(
+                f,
+                "Path range {}-{} doesn't fit in path of length {}",
+                start, end, path_len
+            ),
+        }
+    }
+}
+
+/// A problem found in a GAF record, and the index of the record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GAFIssue {
+    pub record: usize,
+    pub error: GAFError,
+}
+
+impl fmt::Display for GAFIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "record {}: {}", self.record, self.error)
+    }
+}
+
+/// Checks GAF records against the segments and links of a GFA.
+pub struct GAFValidator<'a, N, T: OptFields> {
+    gfa: &'a GFA<N, T>,
+    indices: FnvHashMap<Vec<u8>, usize>,
+    /// The pairs of sides joined by a link, in both orders.
+    links: FnvHashSet<(Side, Side)>,
+}
+
+impl<'a, N: SegmentId, T: OptFields> GAFValidator<'a, N, T> {
+    pub fn new(gfa: &'a GFA<N, T>) -> Self {
+        let indices = segment_name_indices(gfa);
+
+        let mut links = FnvHashSet::default();
+        for link in gfa.links.iter() {
+            let from = indices.get(link.from_segment.display().as_bytes());
+            let to = indices.get(link.to_segment.display().as_bytes());
+            if let (Some(&from), Some(&to)) = (from, to) {
+                let a = out_side((from, link.from_orient));
+                let b = in_side((to, link.to_orient));
+                links.insert((a, b));
+                links.insert((b, a));
+            }
+        }
+
+        GAFValidator {
+            gfa,
+            indices,
+            links,
+        }
+    }
+
+    /// Whether a link allows going from the first handle to the
+    /// second.
+    fn connected(
+        &self,
+        from: (usize, Orientation),
+        to: (usize, Orientation),
+    ) -> bool {
+        self.links.contains(&(out_side(from), in_side(to)))
+    }
+
+    /// Check a single record. Every step must be on a segment of the
+    /// GFA, consecutive steps must be joined by a link, the path
+    /// length must be the total length of the steps, ignoring
+    /// overlaps, and the path range must fit in the path. Paths on
+    /// stable sequences rather than segments can't be checked against
+    /// the GFA, so only their range is checked.
+    pub fn check<U: OptFields>(&self, gaf: &GAF<U>) -> Vec<GAFError> {
+        let mut errors = Vec::new();
+        let (start, end) = gaf.path_range;
+        if start > end || end > gaf.path_len {
+            errors.push(GAFError::RangeOutOfBounds {
+                start,
+                end,
+                path_len: gaf.path_len,
+            });
+        }
+
+        let steps = match &gaf.path {
+            GAFPath::OrientIntv(steps) => steps,
+            GAFPath::StableId(_) => return errors,
+        };
+
+        let mut handles = Vec::with_capacity(steps.len());
+        let mut steps_len = Some(0);
+        for step in steps.iter() {
+            let (orient, name) = match step {
+                GAFStep::SegId(orient, name) => (*orient, name),
+                GAFStep::StableIntv(..) => return errors,
+            };
+            let ix = match self.indices.get(name.as_slice()) {
+                Some(&ix) => ix,
+                None => {
+                    errors.push(GAFError::MissingSegment(name.to_string()));
+                    steps_len = None;
+                    handles.push(None);
+                    continue;
+                }
+            };
+            match segment_length(&self.gfa.segments[ix]) {
+                Some(len) => steps_len = steps_len.map(|l| l + len),
+                None => {
+                    errors.push(GAFError::UnknownLength(name.to_string()));
+                    steps_len = None;
+                }
+            }
+            handles.push(Some((ix, orient)));
+        }
+
+        for (step, pair) in handles.windows(2).enumerate() {
+            if let (Some(from), Some(to)) = (pair[0], pair[1]) {
+                if !self.connected(from, to) {
+                    errors.push(GAFError::MissingLink { step });
+                }
+            }
+        }
+
+        if let Some(steps_len) = steps_len {
+            if steps_len != gaf.path_len {
+                errors.push(GAFError::PathLengthMismatch {
+                    path_len: gaf.path_len,
+                    steps_len,
+                });
+            }
+        }
+
+        errors
+    }
+}
+
+/// Check every GAF record against the GFA, as in
+/// `GAFValidator::check`, and report the problems found along with
+/// the index of the record.
+pub fn validate_gaf<'a, N, T, U, I>(
+    gfa: &GFA<N, T>,
+    records: I,
+) -> Vec<GAFIssue>
+where
+    N: SegmentId,
+    T: OptFields,
+    U: OptFields + 'a,
+    I: IntoIterator<Item = &'a GAF<U>>,
+{
+    let validator = GAFValidator::new(gfa);
+    records
+        .into_iter()
+        .enumerate()
+        .flat_map(|(record, gaf)| {
+            validator
+                .check(gaf)
+                .into_iter()
+                .map(move |error| GAFIssue { record, error })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gafpaf::parse_gaf_file, parser::GFAParser};
+
+    #[test]
+    fn validate_lil_gaf() {
+        let parser: GFAParser<usize, ()> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+        let mut records: Vec<GAF<()>> =
+            parse_gaf_file("./test/gafs/lil.gaf").unwrap();
+
+        // read3 is on a stable sequence, so only its range is checked
+        assert!(validate_gaf(&gfa, &records).is_empty());
+
+        let validator = GAFValidator::new(&gfa);
+        let stable = &mut records[2];
+        stable.path_range = (stable.path_len, stable.path_len + 1);
+        assert!(matches!(
+            validator.check(stable)[..],
+            [GAFError::RangeOutOfBounds { .. }]
+        ));
+
+        let read = &mut records[0];
+        assert!(validator.check(read).is_empty());
+        read.path_len = 10;
+        read.path_range = (2, 11);
+        read.path = GAFPath::OrientIntv(vec![
+            GAFStep::SegId(Orientation::Forward, "1".into()),
+            GAFStep::SegId(Orientation::Backward, "2".into()),
+            GAFStep::SegId(Orientation::Forward, "x".into()),
+        ]);
+        assert_eq!(
+            validator.check(read),
+            vec![
+                GAFError::RangeOutOfBounds {
+                    start: 2,
+                    end: 11,
+                    path_len: 10,
+                },
+                GAFError::MissingSegment("x".to_string()),
+                GAFError::MissingLink { step: 0 },
+            ]
+        );
+    }
+}
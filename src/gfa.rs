@@ -1,38 +1,83 @@
+pub mod builder;
+pub mod canonical;
+pub mod clean;
+pub mod compact;
+pub mod compact_gfa;
+pub mod dedup;
+pub mod edit;
+#[cfg(feature = "serde1")]
+pub mod json;
+pub mod merge;
+pub mod metadata;
 pub mod name_conversion;
+pub mod normalize;
 pub mod orientation;
+pub mod rename;
+pub mod shared;
 pub mod traits;
 
+pub use self::builder::{BuildError, GfaBuilder, PathBuilder};
+pub use self::clean::{CoverageThresholds, RedundantLinks};
+pub use self::compact::{CompactIds, IdOrder, UnassignedIdError};
+pub use self::compact_gfa::CompactGFA;
+pub use self::dedup::Duplicates;
+pub use self::edit::{PathPolicy, PathTraversalError};
+pub use self::merge::{MergeError, RenamePolicy};
+pub use self::metadata::*;
 pub use self::orientation::*;
+pub use self::shared::{SharedBytes, SharedGFA};
 pub use self::traits::*;
 
-use crate::{cigar::CIGAR, optfields::*};
+use crate::{
+    cigar::CIGAR,
+    optfields::*,
+    parser::{GFAFieldResult, ParseFieldError},
+};
 
 use bstr::{BStr, ByteSlice};
+
 #[cfg(feature = "serde1")]
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 /// This module defines the various GFA line types, the GFA object,
 /// and some utility functions and types.
 
 /// Simple representation of a parsed GFA file, using a Vec<T> to
-/// store each separate GFA line type.
+/// store each separate GFA line type. The segment sequences are
+/// stored as `S`, which is `Vec<u8>` unless the sequences are moved
+/// into a shared buffer with `GFA::into_shared`.
+#[allow(clippy::empty_line_after_doc_comments)]
 #[derive(Default, Debug, Clone, PartialEq, PartialOrd)]
-pub struct GFA<N, T: OptFields> {
+#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde1",
+    serde(bound(
+        serialize = "N: Serialize, T: Serialize, S: AsRef<[u8]>",
+        deserialize = "N: Deserialize<'de>, T: Deserialize<'de>, \
+                       S: From<Vec<u8>>"
+    ))
+)]
+pub struct GFA<N, T: OptFields, S = Vec<u8>> {
     pub header: Header<T>,
-    pub segments: Vec<Segment<N, T>>,
+    pub segments: Vec<Segment<N, T, S>>,
     pub links: Vec<Link<N, T>>,
     pub containments: Vec<Containment<N, T>>,
     pub paths: Vec<Path<N, T>>,
+    #[cfg_attr(feature = "serde1", serde(default))]
+    pub walks: Vec<Walk<N, T>>,
 }
 
 /// Enum containing the different kinds of GFA lines.
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
 pub enum Line<N, T: OptFields> {
     Header(Header<T>),
     Segment(Segment<N, T>),
     Link(Link<N, T>),
     Containment(Containment<N, T>),
     Path(Path<N, T>),
+    Walk(Walk<N, T>),
 }
 
 macro_rules! some_line_fn {
@@ -54,6 +99,7 @@ some_line_fn!(some_segment, Segment<N, T>, Line::Segment);
 some_line_fn!(some_link, Link<N, T>, Line::Link);
 some_line_fn!(some_containment, Containment<N, T>, Line::Containment);
 some_line_fn!(some_path, Path<N, T>, Line::Path);
+some_line_fn!(some_walk, Walk<N, T>, Line::Walk);
 
 macro_rules! some_line_ref_fn {
     ($name:ident, $tgt:ty, $variant:path) => {
@@ -74,6 +120,7 @@ some_line_ref_fn!(some_segment, Segment<N, T>, LineRef::Segment);
 some_line_ref_fn!(some_link, Link<N, T>, LineRef::Link);
 some_line_ref_fn!(some_containment, Containment<N, T>, LineRef::Containment);
 some_line_ref_fn!(some_path, Path<N, T>, LineRef::Path);
+some_line_ref_fn!(some_walk, Walk<N, T>, LineRef::Walk);
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum LineRef<'a, N, T: OptFields> {
@@ -82,35 +129,38 @@ pub enum LineRef<'a, N, T: OptFields> {
     Link(&'a Link<N, T>),
     Containment(&'a Containment<N, T>),
     Path(&'a Path<N, T>),
+    Walk(&'a Walk<N, T>),
 }
 
 impl<N, T: OptFields> GFA<N, T> {
     /// Insert a GFA line (wrapped in the Line enum) into an existing
     /// GFA. Simply pushes it into the corresponding Vec in the GFA,
-    /// or replaces the header, so there's no deduplication or sorting
-    /// taking place.
+    /// or merges it into the header with `Header::merge`, so there's
+    /// no deduplication or sorting taking place.
     pub fn insert_line(&mut self, line: Line<N, T>) {
         use Line::*;
         match line {
-            Header(h) => self.header = h,
+            Header(h) => self.header.merge(h),
             Segment(s) => self.segments.push(s),
             Link(s) => self.links.push(s),
             Containment(s) => self.containments.push(s),
             Path(s) => self.paths.push(s),
+            Walk(s) => self.walks.push(s),
         }
     }
 
     /// Consume a GFA object to produce an iterator over all the lines
     /// contained within. The iterator first produces all segments, then
-    /// links, then containments, and finally paths.
+    /// links, then containments, then paths, and finally walks.
     pub fn lines_into_iter(self) -> impl Iterator<Item = Line<N, T>> {
         use Line::*;
         let segs = self.segments.into_iter().map(Segment);
         let links = self.links.into_iter().map(Link);
         let conts = self.containments.into_iter().map(Containment);
         let paths = self.paths.into_iter().map(Path);
+        let walks = self.walks.into_iter().map(Walk);
 
-        segs.chain(links).chain(conts).chain(paths)
+        segs.chain(links).chain(conts).chain(paths).chain(walks)
     }
 
     /// Return an iterator over references to the lines in the GFA
@@ -120,8 +170,9 @@ impl<N, T: OptFields> GFA<N, T> {
         let links = self.links.iter().map(Link);
         let conts = self.containments.iter().map(Containment);
         let paths = self.paths.iter().map(Path);
+        let walks = self.walks.iter().map(Walk);
 
-        segs.chain(links).chain(conts).chain(paths)
+        segs.chain(links).chain(conts).chain(paths).chain(walks)
     }
 }
 
@@ -131,13 +182,34 @@ impl<N: SegmentId, T: OptFields> GFA<N, T> {
     }
 }
 
-/// The header line of a GFA graph
+/// The header line of a GFA graph. A GFA with several `H` lines is
+/// parsed into a single header holding the tags of all of them, and
+/// is written back with a single `H` line.
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
 pub struct Header<T: OptFields> {
+    #[cfg_attr(
+        feature = "serde1",
+        serde(with = "crate::serde_impls::opt_bytes")
+    )]
     pub version: Option<Vec<u8>>,
     pub optional: T,
 }
 
+impl<T: OptFields> Header<T> {
+    /// Merge another header line into this one, as when a GFA has
+    /// several `H` lines. The version of `other` is only used if this
+    /// header has none, and its optional fields are added, keeping the
+    /// existing field for any tag that's already present, so the first
+    /// line wins in both cases.
+    pub fn merge(&mut self, other: Header<T>) {
+        if self.version.is_none() {
+            self.version = other.version;
+        }
+        self.optional.merge(other.optional);
+    }
+}
+
 impl<T: OptFields> Default for Header<T> {
     fn default() -> Self {
         Header {
@@ -147,13 +219,24 @@ impl<T: OptFields> Default for Header<T> {
     }
 }
 
-/// A segment in a GFA graph. Generic over the name type, but
-/// currently the parser is only defined for N = Vec<u8>
+/// A segment in a GFA graph. Generic over the name type; the parser
+/// is defined for any N that implements SegmentId. The sequence is
+/// an owned `Vec<u8>` by default, or a `SharedBytes` in a GFA whose
+/// sequences share one buffer.
 #[derive(Default, Debug, Clone, PartialEq, PartialOrd, Hash)]
 #[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
-pub struct Segment<N, T: OptFields> {
+#[cfg_attr(
+    feature = "serde1",
+    serde(bound(
+        serialize = "N: Serialize, T: Serialize, S: AsRef<[u8]>",
+        deserialize = "N: Deserialize<'de>, T: Deserialize<'de>, \
+                       S: From<Vec<u8>>"
+    ))
+)]
+pub struct Segment<N, T: OptFields, S = Vec<u8>> {
     pub name: N,
-    pub sequence: Vec<u8>,
+    #[cfg_attr(feature = "serde1", serde(with = "crate::serde_impls::bytes"))]
+    pub sequence: S,
     pub optional: T,
 }
 
@@ -174,6 +257,7 @@ pub struct Link<N, T: OptFields> {
     pub from_orient: Orientation,
     pub to_segment: N,
     pub to_orient: Orientation,
+    #[cfg_attr(feature = "serde1", serde(with = "crate::serde_impls::bytes"))]
     pub overlap: Vec<u8>,
     pub optional: T,
 }
@@ -205,6 +289,7 @@ pub struct Containment<N, T: OptFields> {
     pub contained_name: N,
     pub contained_orient: Orientation,
     pub pos: usize,
+    #[cfg_attr(feature = "serde1", serde(with = "crate::serde_impls::bytes"))]
     pub overlap: Vec<u8>,
     pub optional: T,
 }
@@ -215,14 +300,17 @@ pub struct Containment<N, T: OptFields> {
 #[derive(Default, Debug, Clone, PartialEq, PartialOrd, Hash)]
 #[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
 pub struct Path<N, T: OptFields> {
+    #[cfg_attr(feature = "serde1", serde(with = "crate::serde_impls::bytes"))]
     pub path_name: Vec<u8>,
+    #[cfg_attr(feature = "serde1", serde(with = "crate::serde_impls::bytes"))]
     pub segment_names: Vec<u8>,
     pub overlaps: Vec<Option<CIGAR>>,
     pub optional: T,
+    #[cfg_attr(feature = "serde1", serde(skip))]
     _segment_names: std::marker::PhantomData<N>,
 }
 
-impl<N: SegmentId, T: OptFields> Path<N, T> {
+impl<N, T: OptFields> Path<N, T> {
     pub fn new(
         path_name: Vec<u8>,
         segment_names: Vec<u8>,
@@ -242,52 +330,285 @@ impl<N: SegmentId, T: OptFields> Path<N, T> {
 impl<N: SegmentId, T: OptFields> Path<N, T> {
     /// Parses (and copies!) a segment ID in the path segment list
     fn parse_segment_id(input: &[u8]) -> Option<(N, Orientation)> {
-        use Orientation::*;
-        let last = input.len() - 1;
-        let orient = match input[last] {
-            b'+' => Forward,
-            b'-' => Backward,
-            _ => panic!("Path segment did not include orientation"),
-        };
-        let seg = &input[..last];
+        let (seg, orient) = split_step(input)?;
         let id = N::parse_id(seg)?;
         Some((id, orient))
     }
+
+    /// Parses the path steps into segment IDs and orientations for
+    /// any ID type, skipping steps whose ID can't be parsed.
+    pub(crate) fn steps(&self) -> impl Iterator<Item = (N, Orientation)> + '_ {
+        self.segment_names
+            .split_str(b",")
+            .filter_map(Self::parse_segment_id)
+    }
 }
 
 impl<T: OptFields> Path<Vec<u8>, T> {
     /// Produces an iterator over the segments of the given path,
     /// parsing the orientation and producing a slice to each segment
-    /// name
+    /// name. Steps without a name or orientation are skipped; use
+    /// `try_iter` to catch them.
     pub fn iter(&self) -> impl Iterator<Item = (&'_ BStr, Orientation)> {
-        self.segment_names.split_str(b",").map(Self::segment_id_ref)
+        self.try_iter().filter_map(Result::ok)
     }
 
-    fn segment_id_ref(input: &[u8]) -> (&'_ BStr, Orientation) {
-        use Orientation::*;
-        let last = input.len() - 1;
-        let orient = match input[last] {
-            b'+' => Forward,
-            b'-' => Backward,
-            _ => panic!("Path segment did not include orientation"),
-        };
-        let seg = &input[..last];
-        (seg.as_ref(), orient)
+    /// Like `iter`, but produces an error for each step that lacks a
+    /// name or an orientation.
+    pub fn try_iter(
+        &self,
+    ) -> impl Iterator<Item = GFAFieldResult<(&'_ BStr, Orientation)>> {
+        self.segment_names
+            .split_str(b",")
+            .map(|step| parse_step(step).map(|(seg, o)| (seg.as_bstr(), o)))
     }
 }
 
-impl<T: OptFields> Path<usize, T> {
-    /// Produces an iterator over the usize segments of the given
-    /// path.
-    pub fn iter<'a>(
-        &'a self,
-    ) -> impl Iterator<Item = (usize, Orientation)> + 'a {
-        self.segment_names
-            .split_str(b",")
-            .filter_map(Self::parse_segment_id)
+/// Splits a path step into the segment name and its orientation.
+pub(crate) fn parse_step(input: &[u8]) -> GFAFieldResult<(&[u8], Orientation)> {
+    let (last, seg) = input
+        .split_last()
+        .ok_or(ParseFieldError::InvalidField("segment_names"))?;
+    let orient = Orientation::from_bytes_plus_minus(std::slice::from_ref(last))
+        .ok_or(ParseFieldError::OrientationError)?;
+    if seg.is_empty() {
+        Err(ParseFieldError::InvalidField("segment_names"))
+    } else {
+        Ok((seg, orient))
+    }
+}
+
+/// Splits a path step into the segment name and its orientation,
+/// returning `None` if the name is empty or the orientation is
+/// missing.
+pub(crate) fn split_step(input: &[u8]) -> Option<(&[u8], Orientation)> {
+    parse_step(input).ok()
+}
+
+/// A walk (a `W` line, from GFA 1.1) is the path of one sequence of
+/// a sample through the graph, identified by the sample, the index of
+/// the haplotype, and the name of the sequence, with the range of the
+/// sequence that it covers if it's known.
+///
+/// Like the steps of a path, the walk is stored unparsed, as in
+/// `>s1<s2>s3`; use walk.iter() to get an iterator over the parsed
+/// segments and orientations.
+#[derive(Default, Debug, Clone, PartialEq, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
+pub struct Walk<N, T: OptFields> {
+    #[cfg_attr(feature = "serde1", serde(with = "crate::serde_impls::bytes"))]
+    pub sample_id: Vec<u8>,
+    pub hap_index: usize,
+    #[cfg_attr(feature = "serde1", serde(with = "crate::serde_impls::bytes"))]
+    pub seq_id: Vec<u8>,
+    pub seq_start: Option<usize>,
+    pub seq_end: Option<usize>,
+    #[cfg_attr(feature = "serde1", serde(with = "crate::serde_impls::bytes"))]
+    pub walk: Vec<u8>,
+    pub optional: T,
+    #[cfg_attr(feature = "serde1", serde(skip))]
+    _segment_names: std::marker::PhantomData<N>,
+}
+
+impl<N, T: OptFields> Walk<N, T> {
+    pub fn new(
+        sample_id: Vec<u8>,
+        hap_index: usize,
+        seq_id: Vec<u8>,
+        seq_start: Option<usize>,
+        seq_end: Option<usize>,
+        walk: Vec<u8>,
+        optional: T,
+    ) -> Self {
+        Walk {
+            sample_id,
+            hap_index,
+            seq_id,
+            seq_start,
+            seq_end,
+            walk,
+            optional,
+            _segment_names: std::marker::PhantomData,
+        }
+    }
+
+    /// The name of the walk as a path name following the PanSN
+    /// convention, `sample#haplotype#sequence`.
+    pub fn name(&self) -> Vec<u8> {
+        let mut name = self.sample_id.clone();
+        name.push(b'#');
+        name.extend(self.hap_index.to_string().bytes());
+        name.push(b'#');
+        name.extend_from_slice(&self.seq_id);
+        name
+    }
+
+    /// Clone the walk with the given steps, and the segment name
+    /// type changed to `M`.
+    pub(crate) fn with_walk<M>(&self, walk: Vec<u8>) -> Walk<M, T> {
+        Walk::new(
+            self.sample_id.clone(),
+            self.hap_index,
+            self.seq_id.clone(),
+            self.seq_start,
+            self.seq_end,
+            walk,
+            self.optional.clone(),
+        )
+    }
+}
+
+impl<N: SegmentId, T: OptFields> Walk<N, T> {
+    /// Parses the walk steps into segment IDs and orientations for
+    /// any ID type, skipping steps whose ID can't be parsed.
+    pub(crate) fn steps(&self) -> impl Iterator<Item = (N, Orientation)> + '_ {
+        walk_steps(&self.walk).filter_map(|step| {
+            let (seg, orient) = step.ok()?;
+            Some((N::parse_id(seg)?, orient))
+        })
+    }
+}
+
+impl<T: OptFields> Walk<Vec<u8>, T> {
+    /// Produces an iterator over the segments of the walk, parsing
+    /// the orientation and producing a slice to each segment name.
+    /// Steps without a name or orientation are skipped; use
+    /// `try_iter` to catch them.
+    pub fn iter(&self) -> impl Iterator<Item = (&'_ BStr, Orientation)> {
+        self.try_iter().filter_map(Result::ok)
+    }
+
+    /// Like `iter`, but produces an error for each step that lacks a
+    /// name or an orientation.
+    pub fn try_iter(
+        &self,
+    ) -> impl Iterator<Item = GFAFieldResult<(&'_ BStr, Orientation)>> {
+        walk_steps(&self.walk)
+            .map(|step| step.map(|(seg, o)| (seg.as_bstr(), o)))
+    }
+}
+
+/// Splits a walk into its unparsed steps, each of which starts with
+/// its orientation byte, and ends right before the next `>` or `<`.
+pub(crate) fn raw_walk_steps(walk: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let is_orient = |c: &u8| *c == b'>' || *c == b'<';
+    let mut rest = walk;
+    std::iter::from_fn(move || {
+        let tail = rest.get(1..)?;
+        let end = 1 + tail.iter().position(is_orient).unwrap_or(tail.len());
+        let (step, next) = rest.split_at(end);
+        rest = next;
+        Some(step)
+    })
+}
+
+/// Splits the steps of a walk, each of which is a `>` or `<` followed
+/// by a segment name, into the segment names and orientations.
+pub(crate) fn walk_steps(
+    walk: &[u8],
+) -> impl Iterator<Item = GFAFieldResult<(&[u8], Orientation)>> {
+    raw_walk_steps(walk).map(|step| {
+        let (first, seg) = step.split_at(1);
+        match Orientation::from_bytes_gt_ln(first) {
+            None => Err(ParseFieldError::OrientationError),
+            Some(_) if seg.is_empty() => {
+                Err(ParseFieldError::InvalidField("walk"))
+            }
+            Some(orient) => Ok((seg, orient)),
+        }
+    })
+}
+
+/// A reference to a path or a walk, the two kinds of records that
+/// describe a sequence as a series of oriented segments, for code
+/// that handles both the same way. A walk is named by `Walk::name`.
+#[derive(Debug, PartialEq)]
+pub enum PathRef<'a, N, T: OptFields> {
+    Path(&'a Path<N, T>),
+    Walk(&'a Walk<N, T>),
+}
+
+impl<'a, N, T: OptFields> Clone for PathRef<'a, N, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, N, T: OptFields> Copy for PathRef<'a, N, T> {}
+
+impl<'a, N, T: OptFields> PathRef<'a, N, T> {
+    /// The name of the path, or the PanSN name of the walk.
+    pub fn name(&self) -> Cow<'a, [u8]> {
+        match self {
+            PathRef::Path(path) => Cow::Borrowed(&path.path_name),
+            PathRef::Walk(walk) => Cow::Owned(walk.name()),
+        }
+    }
+
+    pub fn optional(&self) -> &'a T {
+        match self {
+            PathRef::Path(path) => &path.optional,
+            PathRef::Walk(walk) => &walk.optional,
+        }
+    }
+}
+
+impl<'a, N: SegmentId, T: OptFields> PathRef<'a, N, T> {
+    /// Parses the steps into segment IDs and orientations, skipping
+    /// steps whose ID can't be parsed.
+    pub(crate) fn steps(
+        &self,
+    ) -> Box<dyn Iterator<Item = (N, Orientation)> + 'a> {
+        match *self {
+            PathRef::Path(path) => Box::new(path.steps()),
+            PathRef::Walk(walk) => Box::new(walk.steps()),
+        }
+    }
+}
+
+impl<N, T: OptFields, S> GFA<N, T, S> {
+    /// Iterate over the paths and then the walks, e.g. to treat each
+    /// of them as a haplotype.
+    pub fn path_refs(&self) -> impl Iterator<Item = PathRef<'_, N, T>> {
+        let paths = self.paths.iter().map(PathRef::Path);
+        paths.chain(self.walks.iter().map(PathRef::Walk))
     }
 }
 
+macro_rules! numeric_path_iter {
+    ($id:ty) => {
+        impl<T: OptFields> Path<$id, T> {
+            /// Produces an iterator over the integer segments of the
+            /// given path, skipping steps that can't be parsed.
+            pub fn iter<'a>(
+                &'a self,
+            ) -> impl Iterator<Item = ($id, Orientation)> + 'a {
+                self.segment_names
+                    .split_str(b",")
+                    .filter_map(Self::parse_segment_id)
+            }
+
+            /// Like `iter`, but produces an error for each step that
+            /// can't be parsed.
+            pub fn try_iter<'a>(
+                &'a self,
+            ) -> impl Iterator<Item = GFAFieldResult<($id, Orientation)>> + 'a
+            {
+                self.segment_names.split_str(b",").map(|step| {
+                    let (seg, orient) = parse_step(step)?;
+                    let id = <$id>::parse_id(seg)
+                        .ok_or(ParseFieldError::UintIdError)?;
+                    Ok((id, orient))
+                })
+            }
+        }
+    };
+}
+
+numeric_path_iter!(usize);
+numeric_path_iter!(u32);
+numeric_path_iter!(u64);
+
 impl<N, T: OptFields> Segment<N, T> {
     pub(crate) fn nameless_clone<M: Default>(&self) -> Segment<M, T> {
         Segment {
@@ -298,7 +619,24 @@ impl<N, T: OptFields> Segment<N, T> {
     }
 }
 
+/// Parse an overlap field, returning `Ok(None)` if it's `*`.
+pub(crate) fn parse_overlap(overlap: &[u8]) -> GFAFieldResult<Option<CIGAR>> {
+    if overlap == b"*" {
+        return Ok(None);
+    }
+    match CIGAR::parser_bytestring(overlap) {
+        Ok((b"", cigar)) => Ok(Some(cigar)),
+        _ => Err(ParseFieldError::InvalidField("overlap")),
+    }
+}
+
 impl<N, T: OptFields> Link<N, T> {
+    /// The overlap as a CIGAR, or `None` if the overlap is `*`.
+    /// Returns an error if it's neither.
+    pub fn overlap_cigar(&self) -> GFAFieldResult<Option<CIGAR>> {
+        parse_overlap(&self.overlap)
+    }
+
     pub(crate) fn nameless_clone<M: Default>(&self) -> Link<M, T> {
         Link {
             from_segment: Default::default(),
@@ -312,6 +650,12 @@ impl<N, T: OptFields> Link<N, T> {
 }
 
 impl<N, T: OptFields> Containment<N, T> {
+    /// The overlap as a CIGAR, or `None` if the overlap is `*`.
+    /// Returns an error if it's neither.
+    pub fn overlap_cigar(&self) -> GFAFieldResult<Option<CIGAR>> {
+        parse_overlap(&self.overlap)
+    }
+
     pub(crate) fn nameless_clone<M: Default>(&self) -> Containment<M, T> {
         Containment {
             container_name: Default::default(),
@@ -329,6 +673,7 @@ impl<N, T: OptFields> Containment<N, T> {
 mod tests {
     use super::*;
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn path_iter() {
         use Orientation::*;
@@ -348,6 +693,25 @@ mod tests {
         assert_eq!(None, path_iter.next());
     }
 
+    #[test]
+    fn path_try_iter() {
+        let path: Path<Vec<u8>, _> =
+            Path::new("14".into(), "11+,12,-,13-".into(), vec![], ());
+        let steps: Vec<_> = path.try_iter().collect();
+        assert_eq!(steps.len(), 4);
+        assert!(matches!(steps[1], Err(ParseFieldError::OrientationError)));
+        assert!(matches!(steps[2], Err(ParseFieldError::InvalidField(_))));
+        let names: Vec<_> = path.iter().map(|(seg, _)| seg).collect();
+        assert_eq!(names, vec!["11", "13"]);
+
+        let path: Path<usize, _> =
+            Path::new("14".into(), "11+,x-".into(), vec![], ());
+        let steps: Vec<_> = path.try_iter().collect();
+        assert_eq!(steps[0].as_ref().ok(), Some(&(11, Orientation::Forward)));
+        assert!(matches!(steps[1], Err(ParseFieldError::UintIdError)));
+    }
+
+    #[allow(clippy::needless_borrows_for_generic_args)]
     #[test]
     fn gfa_line_ref_iter() {
         let parser: crate::parser::GFAParser<usize, ()> =
@@ -360,4 +724,59 @@ mod tests {
             println!("{:?}", seg);
         }
     }
+
+    #[test]
+    fn typed_overlaps() {
+        use Orientation::*;
+
+        let link: Link<Vec<u8>, ()> =
+            Link::new(b"1", Forward, b"2", Forward, b"3M1I");
+        let cigar = link.overlap_cigar().unwrap().unwrap();
+        assert_eq!(cigar.to_string(), "3M1I");
+
+        let link: Link<Vec<u8>, ()> =
+            Link::new(b"1", Forward, b"2", Forward, b"*");
+        assert!(link.overlap_cigar().unwrap().is_none());
+
+        let cont: Containment<Vec<u8>, ()> = Containment {
+            overlap: b"4Q".to_vec(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            cont.overlap_cigar(),
+            Err(ParseFieldError::InvalidField("overlap"))
+        ));
+    }
+
+    #[cfg(feature = "serde1")]
+    #[test]
+    fn serde_round_trip() {
+        use crate::{optfields::OptionalFields, parser::GFAParser};
+
+        let parser: GFAParser<Vec<u8>, OptionalFields> = GFAParser::new();
+        let lines = [
+            "H\tVN:Z:1.0",
+            "S\t1\tACGT\tSH:H:0AFF\tAB:B:c,1,-2",
+            "S\t2\t*\tLN:i:12\tXF:f:0.5",
+            "L\t1\t+\t2\t-\t4M\tXJ:J:{\"a\":1}",
+            "C\t1\t-\t2\t+\t3\t*",
+            "P\tp1\t1+,2-\t4M",
+        ];
+        let gfa = parser
+            .parse_lines(lines.iter().map(|l| l.as_bytes()))
+            .unwrap();
+
+        let json = serde_json::to_value(&gfa).unwrap();
+        assert_eq!(json["header"]["version"], "1.0");
+        assert_eq!(json["segments"][0]["sequence"], "ACGT");
+        assert_eq!(json["segments"][0]["optional"][0]["tag"], "SH");
+        assert_eq!(json["links"][0]["to_orient"], "-");
+        assert_eq!(json["links"][0]["overlap"], "4M");
+        assert_eq!(json["paths"][0]["segment_names"], "1+,2-");
+        assert_eq!(json["paths"][0]["overlaps"][0], "4M");
+
+        let back: GFA<Vec<u8>, OptionalFields> =
+            serde_json::from_value(json).unwrap();
+        assert_eq!(back, gfa);
+    }
 }
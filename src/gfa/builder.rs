@@ -0,0 +1,322 @@
+use crate::optfields::*;
+
+use super::{
+    parse_overlap, Containment, Header, Link, Orientation, Path, Segment,
+    SegmentId, GFA,
+};
+
+use bstr::ByteSlice;
+use fnv::FnvHashSet;
+
+use std::hash::Hash;
+
+/// The reasons a record can't be added to a `GfaBuilder`. Names are
+/// included as strings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// A segment with the same name has already been added.
+    DuplicateSegment(String),
+    /// A path with the same name has already been added.
+    DuplicatePath(String),
+    /// A link, containment, or path step refers to a segment that
+    /// hasn't been added yet.
+    MissingSegment(String),
+    /// A link or containment overlap is neither `*` nor a CIGAR.
+    InvalidOverlap(String),
+    /// A path was finished without any steps. Includes the path name.
+    EmptyPath(String),
+    /// There's no integer ID left to name a new segment with.
+    OutOfIds,
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::DuplicateSegment(name) => {
+                write!(f, "Segment `{}` was already added", name)
+            }
+            BuildError::DuplicatePath(name) => {
+                write!(f, "Path `{}` was already added", name)
+            }
+            BuildError::MissingSegment(name) => {
+                write!(f, "Reference to missing segment `{}`", name)
+            }
+            BuildError::InvalidOverlap(overlap) => {
+                write!(f, "Overlap `{}` is not a valid CIGAR", overlap)
+            }
+            BuildError::EmptyPath(name) => {
+                write!(f, "Path `{}` has no steps", name)
+            }
+            BuildError::OutOfIds => {
+                write!(f, "No integer ID is left for a new segment")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// Constructs a GFA record by record, checking that every record only
+/// refers to segments that have already been added, so the result is
+/// always consistent. Segments can be given names explicitly, or be
+/// assigned the next unused integer ID.
+#[derive(Debug, Clone)]
+pub struct GfaBuilder<N, T: OptFields> {
+    gfa: GFA<N, T>,
+    segments: FnvHashSet<N>,
+    paths: FnvHashSet<Vec<u8>>,
+    next_id: usize,
+}
+
+impl<N, T> Default for GfaBuilder<N, T>
+where
+    N: SegmentId + Clone + Eq + Hash,
+    T: OptFields,
+{
+    fn default() -> Self {
+        GfaBuilder {
+            gfa: GFA::new(),
+            segments: FnvHashSet::default(),
+            paths: FnvHashSet::default(),
+            next_id: 1,
+        }
+    }
+}
+
+impl<N, T> GfaBuilder<N, T>
+where
+    N: SegmentId + Clone + Eq + Hash,
+    T: OptFields,
+{
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn check_segment(&self, name: &N) -> Result<(), BuildError> {
+        if self.segments.contains(name) {
+            Ok(())
+        } else {
+            Err(BuildError::MissingSegment(name.display()))
+        }
+    }
+
+    fn check_overlap(overlap: &[u8]) -> Result<(), BuildError> {
+        parse_overlap(overlap).map(|_| ()).map_err(|_| {
+            BuildError::InvalidOverlap(overlap.to_str_lossy().into_owned())
+        })
+    }
+
+    pub fn header(&mut self, header: Header<T>) -> &mut Self {
+        self.gfa.header = header;
+        self
+    }
+
+    /// Add a segment, whose name must not already be in use.
+    pub fn add_segment(
+        &mut self,
+        segment: Segment<N, T>,
+    ) -> Result<&mut Self, BuildError> {
+        if self.segments.contains(&segment.name) {
+            let name = segment.name.display();
+            return Err(BuildError::DuplicateSegment(name));
+        }
+        self.segments.insert(segment.name.clone());
+        self.gfa.segments.push(segment);
+        Ok(self)
+    }
+
+    /// Add a segment with the given sequence, named with the smallest
+    /// integer ID greater than every ID assigned so far that isn't
+    /// already in use. Returns the new name, or an error if the IDs
+    /// have run out, i.e. don't fit in `usize` or in `N`.
+    pub fn new_segment(
+        &mut self,
+        sequence: &[u8],
+        optional: T,
+    ) -> Result<N, BuildError> {
+        let name = loop {
+            let id = self.next_id;
+            self.next_id = id.checked_add(1).ok_or(BuildError::OutOfIds)?;
+            let name = N::parse_id(id.to_string().as_bytes())
+                .ok_or(BuildError::OutOfIds)?;
+            if !self.segments.contains(&name) {
+                break name;
+            }
+        };
+        self.segments.insert(name.clone());
+        self.gfa.segments.push(Segment {
+            name: name.clone(),
+            sequence: sequence.to_vec(),
+            optional,
+        });
+        Ok(name)
+    }
+
+    /// Add a link between two segments that have already been added.
+    pub fn add_link(
+        &mut self,
+        link: Link<N, T>,
+    ) -> Result<&mut Self, BuildError> {
+        self.check_segment(&link.from_segment)?;
+        self.check_segment(&link.to_segment)?;
+        Self::check_overlap(&link.overlap)?;
+        self.gfa.links.push(link);
+        Ok(self)
+    }
+
+    /// Add a containment between two segments that have already been
+    /// added.
+    pub fn add_containment(
+        &mut self,
+        containment: Containment<N, T>,
+    ) -> Result<&mut Self, BuildError> {
+        self.check_segment(&containment.container_name)?;
+        self.check_segment(&containment.contained_name)?;
+        Self::check_overlap(&containment.overlap)?;
+        self.gfa.containments.push(containment);
+        Ok(self)
+    }
+
+    /// Start a new path with the given name, which must not already be
+    /// in use. The path is added once `PathBuilder::finish` is called.
+    pub fn start_path(
+        &mut self,
+        path_name: &[u8],
+    ) -> Result<PathBuilder<'_, N, T>, BuildError> {
+        if self.paths.contains(path_name) {
+            let name = path_name.to_str_lossy().into_owned();
+            return Err(BuildError::DuplicatePath(name));
+        }
+        Ok(PathBuilder {
+            builder: self,
+            path_name: path_name.to_vec(),
+            steps: Vec::new(),
+            optional: T::default(),
+        })
+    }
+
+    /// Finish building, and return the GFA.
+    pub fn build(self) -> GFA<N, T> {
+        self.gfa
+    }
+}
+
+/// Accumulates the steps of a path for a `GfaBuilder`.
+pub struct PathBuilder<'a, N, T: OptFields> {
+    builder: &'a mut GfaBuilder<N, T>,
+    path_name: Vec<u8>,
+    steps: Vec<(N, Orientation)>,
+    optional: T,
+}
+
+impl<'a, N, T> PathBuilder<'a, N, T>
+where
+    N: SegmentId + Clone + Eq + Hash,
+    T: OptFields,
+{
+    /// Append a step on a segment that has already been added.
+    pub fn step(
+        &mut self,
+        segment: &N,
+        orient: Orientation,
+    ) -> Result<&mut Self, BuildError> {
+        self.builder.check_segment(segment)?;
+        self.steps.push((segment.clone(), orient));
+        Ok(self)
+    }
+
+    pub fn optional(&mut self, optional: T) -> &mut Self {
+        self.optional = optional;
+        self
+    }
+
+    /// Add the path to the GFA, with `*` as its overlaps.
+    pub fn finish(self) -> Result<(), BuildError> {
+        if self.steps.is_empty() {
+            let name = self.path_name.to_str_lossy().into_owned();
+            return Err(BuildError::EmptyPath(name));
+        }
+        let path =
+            Path::from_steps(self.path_name.clone(), self.steps, self.optional);
+        self.builder.paths.insert(self.path_name);
+        self.builder.gfa.paths.push(path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::gfa_string;
+    use Orientation::*;
+
+    #[test]
+    fn build_valid_gfa() {
+        let mut builder: GfaBuilder<Vec<u8>, OptionalFields> =
+            GfaBuilder::new();
+        builder.add_segment(Segment::new(b"2", b"GG")).unwrap();
+        let a = builder.new_segment(b"ACGT", Vec::new()).unwrap();
+        let b = builder.new_segment(b"T", Vec::new()).unwrap();
+        assert_eq!(a, b"1");
+        assert_eq!(b, b"3");
+
+        builder
+            .add_link(Link::new(&a, Forward, &b, Backward, b"0M"))
+            .unwrap();
+        let mut path = builder.start_path(b"p").unwrap();
+        path.step(&a, Forward).unwrap().step(&b, Backward).unwrap();
+        path.finish().unwrap();
+
+        let gfa = builder.build();
+        assert_eq!(
+            gfa_string(&gfa),
+            "H\tVN:Z:1.0\n\
+             S\t2\tGG\n\
+             S\t1\tACGT\n\
+             S\t3\tT\n\
+             P\tp\t1+,3-\t*\n\
+             L\t1\t+\t3\t-\t0M\n"
+        );
+    }
+
+    #[test]
+    fn reject_invalid_records() {
+        let mut builder: GfaBuilder<usize, ()> = GfaBuilder::new();
+        let a = builder.new_segment(b"A", ()).unwrap();
+        let dup = Segment {
+            name: a,
+            sequence: b"C".to_vec(),
+            optional: (),
+        };
+        assert_eq!(
+            builder.add_segment(dup).err(),
+            Some(BuildError::DuplicateSegment("1".into()))
+        );
+
+        let link = Link {
+            from_segment: a,
+            from_orient: Forward,
+            to_segment: 5,
+            to_orient: Forward,
+            overlap: b"0M".to_vec(),
+            optional: (),
+        };
+        assert_eq!(
+            builder.add_link(link).err(),
+            Some(BuildError::MissingSegment("5".into()))
+        );
+
+        let mut path = builder.start_path(b"p").unwrap();
+        assert!(path.step(&2, Forward).is_err());
+        assert_eq!(path.finish(), Err(BuildError::EmptyPath("p".into())));
+
+        let mut path = builder.start_path(b"p").unwrap();
+        path.step(&a, Forward).unwrap();
+        path.finish().unwrap();
+        assert!(builder.start_path(b"p").is_err());
+
+        builder.next_id = usize::MAX;
+        assert_eq!(builder.new_segment(b"A", ()), Err(BuildError::OutOfIds));
+        assert_eq!(builder.build().paths.len(), 1);
+    }
+}
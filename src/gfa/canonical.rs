@@ -0,0 +1,188 @@
+use crate::{cigar::CIGAR, optfields::*};
+
+use super::{Link, Orientation, GFA};
+
+use std::collections::BTreeMap;
+
+/// Reverse a link overlap, if it's a valid CIGAR. Anything else,
+/// such as `*`, is returned unchanged.
+fn reverse_overlap(overlap: &[u8]) -> Vec<u8> {
+    match CIGAR::parser_bytestring(overlap) {
+        Ok((b"", cigar)) => cigar.reverse_swap().to_string().into_bytes(),
+        _ => overlap.to_vec(),
+    }
+}
+
+impl<N: Clone, T: OptFields> Link<N, T> {
+    /// Return the reverse of this link, i.e. the link that traverses
+    /// the same edge in the opposite direction. `a+ -> b-` becomes
+    /// `b+ -> a-`, and the overlap CIGAR, if any, is reversed with
+    /// insertions and deletions swapped.
+    pub fn reverse(&self) -> Self {
+        Link {
+            from_segment: self.to_segment.clone(),
+            from_orient: self.to_orient.flip(),
+            to_segment: self.from_segment.clone(),
+            to_orient: self.from_orient.flip(),
+            overlap: reverse_overlap(&self.overlap),
+            optional: self.optional.clone(),
+        }
+    }
+}
+
+impl<N: Ord + Clone, T: OptFields> Link<N, T> {
+    fn edge_key(&self) -> (&N, Orientation, &N, Orientation) {
+        (
+            &self.from_segment,
+            self.from_orient,
+            &self.to_segment,
+            self.to_orient,
+        )
+    }
+
+    /// Returns true if the link is in canonical form, i.e. if its
+    /// endpoints and orientations don't compare greater than those of
+    /// its reverse.
+    pub fn is_canonical(&self) -> bool {
+        let rev_key = (
+            &self.to_segment,
+            self.to_orient.flip(),
+            &self.from_segment,
+            self.from_orient.flip(),
+        );
+        self.edge_key() <= rev_key
+    }
+
+    /// Return the canonical form of this link, which is the same for
+    /// a link and its reverse.
+    pub fn canonical(&self) -> Self {
+        if self.is_canonical() {
+            self.clone()
+        } else {
+            self.reverse()
+        }
+    }
+}
+
+impl<N: Ord + Clone, T: OptFields> GFA<N, T> {
+    /// Replace every link in the GFA with its canonical form.
+    pub fn canonicalize_links(&mut self) {
+        for link in self.links.iter_mut() {
+            if !link.is_canonical() {
+                *link = link.reverse();
+            }
+        }
+    }
+
+    /// Canonicalize all links, then merge the links that describe
+    /// the same edge, keeping the first. The optional fields of the
+    /// removed links are merged into the one that's kept. Returns the
+    /// number of links that were removed.
+    pub fn merge_links(&mut self) -> usize {
+        self.canonicalize_links();
+
+        let links = std::mem::take(&mut self.links);
+        let count = links.len();
+
+        let mut edges: BTreeMap<(N, Orientation, N, Orientation), usize> =
+            BTreeMap::new();
+
+        for link in links {
+            let (from, from_orient, to, to_orient) = link.edge_key();
+            let key = (from.clone(), from_orient, to.clone(), to_orient);
+            if let Some(&ix) = edges.get(&key) {
+                self.links[ix].optional.merge(link.optional);
+            } else {
+                edges.insert(key, self.links.len());
+                self.links.push(link);
+            }
+        }
+
+        count - self.links.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::GFAParser;
+    use crate::test_util::parse;
+    use Orientation::*;
+
+    #[test]
+    fn reverse_link() {
+        let link: Link<Vec<u8>, ()> =
+            Link::new(b"a", Forward, b"b", Backward, b"3M1I");
+        let rev = link.reverse();
+        assert_eq!(rev.from_segment, b"b");
+        assert_eq!(rev.from_orient, Forward);
+        assert_eq!(rev.to_segment, b"a");
+        assert_eq!(rev.to_orient, Backward);
+        assert_eq!(rev.overlap, b"1D3M");
+        assert_eq!(rev.reverse(), link);
+
+        let link: Link<Vec<u8>, ()> =
+            Link::new(b"a", Forward, b"b", Forward, b"*");
+        assert_eq!(link.reverse().overlap, b"*");
+    }
+
+    #[test]
+    fn canonical_link() {
+        let link: Link<Vec<u8>, ()> =
+            Link::new(b"b", Forward, b"a", Backward, b"0M");
+        let canonical = link.canonical();
+        assert!(!link.is_canonical());
+        assert!(canonical.is_canonical());
+        assert_eq!(canonical, link.reverse());
+        assert_eq!(link.reverse().canonical(), canonical);
+
+        // A link from a segment to its own reverse complement is its
+        // own reverse.
+        let link: Link<Vec<u8>, ()> =
+            Link::new(b"a", Forward, b"a", Backward, b"0M");
+        assert_eq!(link.reverse(), link);
+        assert!(link.is_canonical());
+    }
+
+    #[test]
+    fn merge_reverse_links() {
+        let mut gfa: GFA<Vec<u8>, OptionalFields> = parse(&[
+            "S\ta\tACGT",
+            "S\tb\tGG",
+            "L\ta\t+\tb\t-\t2M\tRC:i:3",
+            "L\tb\t+\ta\t-\t2M\tRC:i:5\tKC:i:10",
+            "L\tb\t-\ta\t-\t0M",
+        ]);
+
+        assert_eq!(gfa.merge_links(), 1);
+        assert_eq!(gfa.links.len(), 2);
+
+        let merged = &gfa.links[0];
+        assert_eq!(merged.from_segment, b"a");
+        assert_eq!(merged.to_segment, b"b");
+        assert_eq!(
+            merged.optional.get_field(b"RC").unwrap().value,
+            OptFieldVal::Int(3)
+        );
+        assert_eq!(
+            merged.optional.get_field(b"KC").unwrap().value,
+            OptFieldVal::Int(10)
+        );
+
+        let flipped = &gfa.links[1];
+        assert_eq!(flipped.from_segment, b"a");
+        assert_eq!(flipped.from_orient, Forward);
+        assert_eq!(flipped.to_segment, b"b");
+        assert_eq!(flipped.to_orient, Forward);
+    }
+
+    #[test]
+    fn lil_gfa_links_are_distinct() {
+        let parser: GFAParser<usize, ()> = GFAParser::new();
+        let mut gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+        let count = gfa.links.len();
+        assert_eq!(gfa.merge_links(), 0);
+        assert_eq!(gfa.links.len(), count);
+        assert!(gfa.links.iter().all(|l| l.is_canonical()));
+    }
+}
@@ -0,0 +1,413 @@
+use crate::{
+    graph::{flip, in_side, out_side, segment_indices, Handle, Side},
+    optfields::*,
+    validation::segment_length,
+};
+
+use super::{
+    dedup::remove_indices, Orientation, PathPolicy, PathTraversalError,
+    SegmentId, GFA,
+};
+
+use fnv::FnvHashMap;
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    hash::Hash,
+};
+
+/// Minimum values of the coverage tags of segments, used by
+/// `GFA::filter_by_coverage`. A threshold of `None` isn't checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CoverageThresholds {
+    /// The minimum read count, the RC tag.
+    pub min_rc: Option<i64>,
+    /// The minimum k-mer count, the KC tag.
+    pub min_kc: Option<i64>,
+    /// The minimum fragment count, the FC tag.
+    pub min_fc: Option<i64>,
+}
+
+impl CoverageThresholds {
+    /// Whether a segment with the given optional fields falls below
+    /// any of the thresholds. Missing tags aren't checked.
+    fn below<T: OptFields>(&self, optional: &T) -> bool {
+        let below = |tag: &[u8], min: Option<i64>| match min {
+            Some(min) => {
+                optional.get_typed::<i64>(tag).is_some_and(|v| v < min)
+            }
+            None => false,
+        };
+        below(b"RC", self.min_rc)
+            || below(b"KC", self.min_kc)
+            || below(b"FC", self.min_fc)
+    }
+}
+
+impl<N, T> GFA<N, T>
+where
+    N: SegmentId + Clone + Eq + Hash,
+    T: OptFields,
+{
+    /// Remove the segments whose RC, KC, or FC tags are below the
+    /// thresholds, as when cleaning up an assembly graph, along with
+    /// the links and containments that refer to them. Segments
+    /// without a tag aren't filtered on it. Paths and walks that
+    /// traverse the removed segments are handled according to
+    /// `policy`, as in `remove_segments`. Returns the number of segments removed.
+    pub fn filter_by_coverage(
+        &mut self,
+        thresholds: &CoverageThresholds,
+        policy: PathPolicy,
+    ) -> Result<usize, PathTraversalError> {
+        let removed: HashSet<N> = self
+            .segments
+            .iter()
+            .filter(|seg| thresholds.below(&seg.optional))
+            .map(|seg| seg.name.clone())
+            .collect();
+        self.remove_segments(&removed, policy)
+    }
+}
+
+/// The handles reached by leaving through each side of the segments.
+/// Links that refer to missing segments are left out.
+fn side_links<N, T>(gfa: &GFA<N, T>) -> FnvHashMap<Side, Vec<Handle>>
+where
+    N: Eq + Hash,
+    T: OptFields,
+{
+    let indices = segment_indices(gfa);
+    let mut sides: FnvHashMap<Side, Vec<Handle>> = FnvHashMap::default();
+    for link in gfa.links.iter() {
+        let from = indices.get(&link.from_segment);
+        let to = indices.get(&link.to_segment);
+        if let (Some(&from), Some(&to)) = (from, to) {
+            let from = (from, link.from_orient);
+            let to = (to, link.to_orient);
+            sides.entry(out_side(from)).or_default().push(to);
+            sides.entry(in_side(to)).or_default().push(flip(from));
+        }
+    }
+    sides
+}
+
+/// Walk from a dead end, entering the graph through `start`, and
+/// return the segments of the tip if the walk reaches a side with
+/// more than one link before the total length reaches `max_length`.
+fn tip_from(
+    sides: &FnvHashMap<Side, Vec<Handle>>,
+    lengths: &[Option<usize>],
+    start: Handle,
+    max_length: usize,
+) -> Option<Vec<usize>> {
+    let mut chain = vec![start.0];
+    let mut length = lengths[start.0]?;
+    let mut handle = start;
+    loop {
+        if length >= max_length {
+            return None;
+        }
+        // a chain that ends in another dead end, or branches out, is
+        // not a tip
+        let next = match sides.get(&out_side(handle))?.as_slice() {
+            [next] => *next,
+            _ => return None,
+        };
+        if chain.contains(&next.0) {
+            return None;
+        }
+        if sides.get(&in_side(next)).map_or(0, Vec::len) > 1 {
+            return Some(chain);
+        }
+        chain.push(next.0);
+        length += lengths[next.0]?;
+        handle = next;
+    }
+}
+
+impl<N, T> GFA<N, T>
+where
+    N: SegmentId + Clone + Eq + Hash,
+    T: OptFields,
+{
+    /// Remove the tips of the graph: chains of segments that start at
+    /// a dead end, i.e. a segment side without links, and join the
+    /// rest of the graph at a side with more than one link, with a
+    /// total sequence length below `max_length`. Segments of unknown
+    /// length are never part of a tip, and chains that are dead ends
+    /// on both sides are left for `remove_isolated_segments`.
+    ///
+    /// The tips are found in a single pass, so removing them may
+    /// expose new tips. Paths and walks are handled according to
+    /// `policy`, as in `remove_segments`. Returns the number of segments removed.
+    pub fn remove_tips(
+        &mut self,
+        max_length: usize,
+        policy: PathPolicy,
+    ) -> Result<usize, PathTraversalError> {
+        let sides = side_links(self);
+        let lengths: Vec<_> =
+            self.segments.iter().map(segment_length).collect();
+        let dead_end = |side: Side| !sides.contains_key(&side);
+
+        let mut tips = HashSet::new();
+        for ix in 0..self.segments.len() {
+            let start = match (dead_end((ix, false)), dead_end((ix, true))) {
+                (true, false) => (ix, Orientation::Forward),
+                (false, true) => (ix, Orientation::Backward),
+                _ => continue,
+            };
+            if let Some(chain) = tip_from(&sides, &lengths, start, max_length) {
+                tips.extend(chain);
+            }
+        }
+
+        let names: HashSet<N> = tips
+            .into_iter()
+            .map(|ix| self.segments[ix].name.clone())
+            .collect();
+        self.remove_segments(&names, policy)
+    }
+
+    /// Remove the segments that aren't part of any link or
+    /// containment. Paths and walks are handled according to
+    /// `policy`, as in `remove_segments`. Returns the number of segments removed.
+    pub fn remove_isolated_segments(
+        &mut self,
+        policy: PathPolicy,
+    ) -> Result<usize, PathTraversalError> {
+        let mut connected: HashSet<&N> = HashSet::new();
+        for link in self.links.iter() {
+            connected.insert(&link.from_segment);
+            connected.insert(&link.to_segment);
+        }
+        for containment in self.containments.iter() {
+            connected.insert(&containment.container_name);
+            connected.insert(&containment.contained_name);
+        }
+        let isolated: HashSet<N> = self
+            .segments
+            .iter()
+            .filter(|seg| !connected.contains(&seg.name))
+            .map(|seg| seg.name.clone())
+            .collect();
+        self.remove_segments(&isolated, policy)
+    }
+}
+
+/// Self-links and parallel links in a GFA, as indices into
+/// `GFA::links`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RedundantLinks {
+    /// Links from a segment to itself, in either orientation.
+    pub self_links: Vec<usize>,
+    /// Groups of links between the same oriented segments, where a
+    /// link and its reverse count as the same; the overlaps may
+    /// differ. Each group is sorted, and the groups are sorted by
+    /// their first link.
+    pub parallel: Vec<Vec<usize>>,
+}
+
+impl RedundantLinks {
+    pub fn is_empty(&self) -> bool {
+        self.self_links.is_empty() && self.parallel.is_empty()
+    }
+}
+
+type EdgeKey<'a, N> = (&'a N, Orientation, &'a N, Orientation);
+
+impl<N: Ord + Clone, T: OptFields> GFA<N, T> {
+    /// Find the self-links and groups of parallel links, without
+    /// modifying the GFA.
+    pub fn redundant_links(&self) -> RedundantLinks {
+        let self_links = self
+            .links
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| l.from_segment == l.to_segment)
+            .map(|(ix, _)| ix)
+            .collect();
+
+        let mut edges: BTreeMap<EdgeKey<'_, N>, usize> = BTreeMap::new();
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for (ix, l) in self.links.iter().enumerate() {
+            let key = if l.is_canonical() {
+                (&l.from_segment, l.from_orient, &l.to_segment, l.to_orient)
+            } else {
+                let (from, to) = (l.to_orient.flip(), l.from_orient.flip());
+                (&l.to_segment, from, &l.from_segment, to)
+            };
+            match edges.get(&key) {
+                Some(&group) => groups[group].push(ix),
+                None => {
+                    edges.insert(key, groups.len());
+                    groups.push(vec![ix]);
+                }
+            }
+        }
+        groups.retain(|group| group.len() > 1);
+
+        RedundantLinks {
+            self_links,
+            parallel: groups,
+        }
+    }
+
+    /// Remove all self-links, and return their indices in the
+    /// original list of links.
+    pub fn remove_self_links(&mut self) -> Vec<usize> {
+        let self_links = self.redundant_links().self_links;
+        remove_indices(&mut self.links, &self_links);
+        self_links
+    }
+
+    /// Merge each group of parallel links into the first link of the
+    /// group, which keeps its orientation and overlap and gets the
+    /// optional fields of the others, as in `merge_links`. Returns
+    /// the groups that were merged, as indices in the original list
+    /// of links.
+    pub fn merge_parallel_links(&mut self) -> Vec<Vec<usize>> {
+        let groups = self.redundant_links().parallel;
+        let mut removed = Vec::new();
+        for group in groups.iter() {
+            for &ix in &group[1..] {
+                let optional = std::mem::take(&mut self.links[ix].optional);
+                self.links[group[0]].optional.merge(optional);
+                removed.push(ix);
+            }
+        }
+        removed.sort_unstable();
+        remove_indices(&mut self.links, &removed);
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::GFAParser;
+
+    fn graph() -> GFA<usize, OptionalFields> {
+        let lines = [
+            "S\t1\tA\tRC:i:10\tKC:i:100",
+            "S\t2\tC\tRC:i:1\tKC:i:100",
+            "S\t3\tG\tKC:i:5",
+            "S\t4\tT",
+            "L\t1\t+\t2\t+\t0M",
+            "L\t1\t+\t3\t+\t0M",
+            "L\t1\t+\t4\t+\t0M",
+            "P\tp\t1+,2+,4+\t*",
+            "W\tHG1\t1\tchr1\t0\t3\t>1>3>4",
+        ];
+        let parser = GFAParser::new();
+        parser.parse_lines(lines.iter()).unwrap()
+    }
+
+    #[test]
+    fn filter_segments_by_coverage() {
+        let mut gfa = graph();
+        let thresholds = CoverageThresholds {
+            min_rc: Some(2),
+            min_kc: Some(10),
+            ..Default::default()
+        };
+        let removed = gfa
+            .filter_by_coverage(&thresholds, PathPolicy::Split)
+            .unwrap();
+        assert_eq!(removed, 2);
+
+        let names: Vec<_> = gfa.segments.iter().map(|s| s.name).collect();
+        assert_eq!(names, vec![1, 4]);
+        assert_eq!(gfa.links.len(), 1);
+        let paths: Vec<_> =
+            gfa.paths.iter().map(|p| &p.segment_names).collect();
+        assert_eq!(paths, vec![b"1+", b"4+"]);
+        let walks: Vec<_> = gfa.walks.iter().map(|w| &w.walk).collect();
+        assert_eq!(walks, vec![b">1", b">4"]);
+        assert_eq!(gfa.walks[1].seq_start, Some(2));
+
+        let mut gfa = graph();
+        assert!(gfa
+            .filter_by_coverage(&thresholds, PathPolicy::Error)
+            .is_err());
+        let removed = gfa
+            .filter_by_coverage(&Default::default(), PathPolicy::Error)
+            .unwrap();
+        assert_eq!(removed, 0);
+    }
+
+    #[test]
+    fn remove_tips_and_isolated_segments() {
+        // 1 -> 2 -> 3 is the backbone, 4 -> 5 and 6 (entered on its
+        // reverse strand) are tips into 2, 7 is too long to be a tip,
+        // and 8 is isolated
+        let lines = [
+            "S\t1\tAAAAAAAAAA",
+            "S\t2\tAAAAAAAAAA",
+            "S\t3\tAAAAAAAAAA",
+            "S\t4\tAA",
+            "S\t5\tAA",
+            "S\t6\tA",
+            "S\t7\tAAAAAAAAAA",
+            "S\t8\tA",
+            "L\t1\t+\t2\t+\t0M",
+            "L\t2\t+\t3\t+\t0M",
+            "L\t4\t+\t5\t+\t0M",
+            "L\t5\t+\t2\t+\t0M",
+            "L\t2\t-\t6\t+\t0M",
+            "L\t2\t+\t7\t+\t0M",
+            "P\tp\t4+,5+,2+,3+\t*",
+            "W\tHG1\t1\tchr1\t0\t21\t>6<2>7",
+        ];
+        let parser = GFAParser::new();
+        let mut gfa: GFA<usize, ()> = parser.parse_lines(lines.iter()).unwrap();
+
+        let mut copy = gfa.clone();
+        assert!(copy.remove_tips(5, PathPolicy::Error).is_err());
+
+        assert_eq!(gfa.remove_tips(5, PathPolicy::Split).unwrap(), 3);
+        let names: Vec<_> = gfa.segments.iter().map(|s| s.name).collect();
+        assert_eq!(names, vec![1, 2, 3, 7, 8]);
+        assert_eq!(gfa.links.len(), 3);
+        assert_eq!(gfa.paths[0].segment_names, b"2+,3+");
+        assert_eq!(gfa.walks[0].walk, b"<2>7");
+        assert_eq!(gfa.walks[0].seq_start, Some(1));
+        assert_eq!(gfa.walks[0].seq_end, Some(21));
+
+        assert_eq!(gfa.remove_isolated_segments(PathPolicy::Error).unwrap(), 1);
+        let names: Vec<_> = gfa.segments.iter().map(|s| s.name).collect();
+        assert_eq!(names, vec![1, 2, 3, 7]);
+    }
+
+    #[test]
+    fn self_and_parallel_links() {
+        let lines = [
+            "S\t1\tACGT",
+            "S\t2\tGG",
+            "L\t1\t+\t2\t+\t0M",
+            "L\t1\t+\t1\t-\t0M",
+            "L\t2\t-\t1\t-\t1M\tRC:i:4",
+            "L\t2\t+\t2\t+\t0M",
+            "L\t1\t+\t2\t-\t0M",
+            "L\t1\t+\t2\t+\t2M",
+        ];
+        let parser = GFAParser::new();
+        let gfa: GFA<usize, OptionalFields> =
+            parser.parse_lines(lines.iter()).unwrap();
+
+        let report = gfa.redundant_links();
+        assert_eq!(report.self_links, vec![1, 3]);
+        assert_eq!(report.parallel, vec![vec![0, 2, 5]]);
+
+        let mut merged = gfa.clone();
+        assert_eq!(merged.merge_parallel_links(), report.parallel);
+        assert_eq!(merged.links.len(), 4);
+        assert_eq!(merged.links[0].overlap, b"0M");
+        assert_eq!(merged.links[0].optional.len(), 1);
+
+        assert_eq!(merged.remove_self_links(), vec![1, 2]);
+        assert_eq!(merged.links.len(), 2);
+        assert!(merged.redundant_links().is_empty());
+    }
+}
@@ -0,0 +1,239 @@
+use crate::optfields::*;
+
+use super::{
+    normalize::NaturalKey, walk_steps, Containment, Link, Path, Segment,
+    SegmentId, GFA,
+};
+
+use bstr::ByteSlice;
+use fnv::{FnvHashMap, FnvHashSet};
+
+use std::hash::Hash;
+
+/// The order in which `GFA::compact_ids` hands out the new IDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdOrder {
+    /// The order segments appear in the GFA, followed by any names
+    /// that are only referenced, in the order they're found.
+    Segments,
+    /// The natural order of the segment names, e.g. `s2` before `s10`.
+    Natural,
+}
+
+/// A GFA with compacted IDs, along with the map from the old names to
+/// the new IDs.
+pub type CompactIds<N, T> = (GFA<usize, T>, FnvHashMap<N, usize>);
+
+/// Returned by `GFA::compact_ids` when a record refers to a segment
+/// that wasn't given an ID, as happens with malformed path steps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnassignedIdError {
+    pub segment: String,
+}
+
+impl std::fmt::Display for UnassignedIdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Segment {} wasn't assigned an ID", self.segment)
+    }
+}
+
+impl std::error::Error for UnassignedIdError {}
+
+impl<N: SegmentId + Clone + Eq + Hash, T: OptFields> GFA<N, T> {
+    /// Renumber the segments to the dense range `first..first + n`,
+    /// where `n` is the number of distinct segment names, including
+    /// names that only occur in links, containments, or path and walk
+    /// steps.
+    /// Returns the new GFA along with the map from the old names to
+    /// the new IDs, so data keyed by the old names can be carried over.
+    ///
+    /// Path and walk steps whose names can't be parsed as `N` are kept
+    /// as they are, but a malformed path step that names a segment that
+    /// isn't found elsewhere is an error.
+    pub fn compact_ids(
+        &self,
+        order: IdOrder,
+        first: usize,
+    ) -> Result<CompactIds<N, T>, UnassignedIdError> {
+        let mut names: Vec<N> = Vec::with_capacity(self.segments.len());
+        let mut seen: FnvHashSet<N> = FnvHashSet::default();
+        let mut add = |name: &N| {
+            if seen.insert(name.clone()) {
+                names.push(name.clone());
+            }
+        };
+
+        for seg in self.segments.iter() {
+            add(&seg.name);
+        }
+        for link in self.links.iter() {
+            add(&link.from_segment);
+            add(&link.to_segment);
+        }
+        for cont in self.containments.iter() {
+            add(&cont.container_name);
+            add(&cont.contained_name);
+        }
+        for path in self.paths.iter() {
+            for (name, _) in path.steps() {
+                add(&name);
+            }
+        }
+        for walk in self.walks.iter() {
+            for (name, _) in walk.steps() {
+                add(&name);
+            }
+        }
+
+        if order == IdOrder::Natural {
+            names.sort_by_cached_key(|n| {
+                NaturalKey::new(n.display().as_bytes())
+            });
+        }
+
+        let ids: FnvHashMap<N, usize> = names
+            .into_iter()
+            .enumerate()
+            .map(|(ix, name)| (name, first + ix))
+            .collect();
+        let id = |name: &N| {
+            ids.get(name).copied().ok_or_else(|| UnassignedIdError {
+                segment: name.display(),
+            })
+        };
+
+        let segments = self
+            .segments
+            .iter()
+            .map(|seg| {
+                let mut new_seg: Segment<usize, T> = seg.nameless_clone();
+                new_seg.name = id(&seg.name)?;
+                Ok(new_seg)
+            })
+            .collect::<Result<_, _>>()?;
+
+        let links = self
+            .links
+            .iter()
+            .map(|link| {
+                let mut new_link: Link<usize, T> = link.nameless_clone();
+                new_link.from_segment = id(&link.from_segment)?;
+                new_link.to_segment = id(&link.to_segment)?;
+                Ok(new_link)
+            })
+            .collect::<Result<_, _>>()?;
+
+        let containments = self
+            .containments
+            .iter()
+            .map(|cont| {
+                let mut new_cont: Containment<usize, T> = cont.nameless_clone();
+                new_cont.container_name = id(&cont.container_name)?;
+                new_cont.contained_name = id(&cont.contained_name)?;
+                Ok(new_cont)
+            })
+            .collect::<Result<_, _>>()?;
+
+        let paths = self
+            .paths
+            .iter()
+            .map(|path| {
+                let mut steps = Vec::with_capacity(path.segment_names.len());
+                for (i, step) in path.segment_names.split_str(b",").enumerate()
+                {
+                    if i > 0 {
+                        steps.push(b',');
+                    }
+                    let (name, orient) =
+                        step.split_at(step.len().saturating_sub(1));
+                    match N::parse_id(name) {
+                        Some(name) => {
+                            steps.extend(id(&name)?.to_string().bytes())
+                        }
+                        None => steps.extend_from_slice(name),
+                    }
+                    steps.extend_from_slice(orient);
+                }
+                Ok(Path::new(
+                    path.path_name.clone(),
+                    steps,
+                    path.overlaps.clone(),
+                    path.optional.clone(),
+                ))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let walks = self
+            .walks
+            .iter()
+            .map(|walk| {
+                let mut steps = Vec::with_capacity(walk.walk.len());
+                // the parser rejects walks with malformed steps
+                for (name, orient) in walk_steps(&walk.walk).flatten() {
+                    steps.push(orient.gt_ln_as_byte());
+                    match N::parse_id(name) {
+                        Some(name) => {
+                            steps.extend(id(&name)?.to_string().bytes())
+                        }
+                        None => steps.extend_from_slice(name),
+                    }
+                }
+                Ok(walk.with_walk(steps))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let gfa = GFA {
+            header: self.header.clone(),
+            segments,
+            links,
+            containments,
+            paths,
+            walks,
+        };
+        Ok((gfa, ids))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    #[test]
+    fn compact_in_chosen_order() {
+        let lines = [
+            "S\ts10\tACGT",
+            "S\ts2\tGG",
+            "L\ts10\t+\ts2\t-\t0M",
+            "L\ts2\t+\ts1\t+\t0M",
+            "P\tp\ts10+,s2-\t0M",
+        ];
+        let gfa: GFA<Vec<u8>, ()> = parse(&lines);
+
+        let (compact, ids) = gfa.compact_ids(IdOrder::Segments, 1).unwrap();
+        assert_eq!(ids[&b"s10".to_vec()], 1);
+        assert_eq!(ids[&b"s2".to_vec()], 2);
+        assert_eq!(ids[&b"s1".to_vec()], 3);
+        assert_eq!(compact.segments[0].name, 1);
+        assert_eq!(compact.links[1].to_segment, 3);
+        assert_eq!(compact.paths[0].segment_names, b"1+,2-");
+
+        let (compact, ids) = gfa.compact_ids(IdOrder::Natural, 0).unwrap();
+        assert_eq!(ids.len(), 3);
+        assert_eq!(ids[&b"s1".to_vec()], 0);
+        assert_eq!(compact.segments[0].name, 2);
+        assert_eq!(compact.segments[1].name, 1);
+        assert_eq!(compact.paths[0].segment_names, b"2+,1-");
+
+        // a step without an orientation is skipped when collecting the
+        // names, so it has no ID
+        let mut gfa = gfa;
+        gfa.paths[0].segment_names = b"s10+,s3".to_vec();
+        assert_eq!(
+            gfa.compact_ids(IdOrder::Segments, 0).err(),
+            Some(UnassignedIdError {
+                segment: "s".into()
+            })
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use crate::optfields::*;
+
+use super::{Orientation, GFA};
+
+use bstr::{BStr, ByteSlice};
+
+use std::convert::TryFrom;
+
+/// A segment index and orientation packed into a single integer, with
+/// the orientation in the lowest bit.
+fn pack(ix: usize, orient: Orientation) -> u32 {
+    ((ix as u32) << 1) | (orient == Orientation::Backward) as u32
+}
+
+fn unpack(handle: u32) -> (usize, Orientation) {
+    let orient = Orientation::from(handle & 1 == 0);
+    ((handle >> 1) as usize, orient)
+}
+
+/// A read-only graph built from a `GFA<usize, T>`, using a handful of
+/// flat arrays instead of a separate allocation per record: the
+/// segment sequences are concatenated, the links are stored as
+/// adjacency lists in compressed sparse row form, and the paths as
+/// vectors of packed steps. Walks are stored as paths named by
+/// `Walk::name`, after the paths. Optional fields and overlaps are
+/// not kept.
+///
+/// Segments are referred to by their index, in the order of
+/// `GFA::segments`, and traversed in an orientation, as
+/// `(index, orientation)` pairs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactGFA {
+    names: Vec<usize>,
+    /// (name, index) pairs, sorted by name.
+    sorted_names: Vec<(usize, u32)>,
+    sequences: Vec<u8>,
+    sequence_offsets: Vec<usize>,
+    /// The edges out of each packed handle are the targets in
+    /// `edge_offsets[h]..edge_offsets[h + 1]`.
+    edge_offsets: Vec<usize>,
+    edge_targets: Vec<u32>,
+    path_names: Vec<u8>,
+    path_name_offsets: Vec<usize>,
+    path_steps: Vec<u32>,
+    path_step_offsets: Vec<usize>,
+}
+
+impl CompactGFA {
+    /// Build the compact graph. Links and path steps that refer to
+    /// segments that aren't in the GFA are left out. Returns `None`
+    /// if there are too many segments for the packed handles, i.e.
+    /// 2^31 or more.
+    pub fn new<T: OptFields>(gfa: &GFA<usize, T>) -> Option<Self> {
+        let count = gfa.segments.len();
+        u32::try_from(count.checked_mul(2)?).ok()?;
+
+        let names: Vec<usize> = gfa.segments.iter().map(|s| s.name).collect();
+        let mut sorted_names: Vec<(usize, u32)> = names
+            .iter()
+            .enumerate()
+            .map(|(ix, &name)| (name, ix as u32))
+            .collect();
+        // keep the first segment with each name, as elsewhere
+        sorted_names.sort_unstable();
+        sorted_names.dedup_by_key(|(name, _)| *name);
+
+        let mut graph = CompactGFA {
+            names,
+            sorted_names,
+            sequences: Vec::new(),
+            sequence_offsets: vec![0],
+            edge_offsets: Vec::new(),
+            edge_targets: Vec::new(),
+            path_names: Vec::new(),
+            path_name_offsets: vec![0],
+            path_steps: Vec::new(),
+            path_step_offsets: vec![0],
+        };
+
+        let total = gfa.segments.iter().map(|s| s.sequence.len()).sum();
+        graph.sequences.reserve_exact(total);
+        for seg in gfa.segments.iter() {
+            graph.sequences.extend_from_slice(&seg.sequence);
+            graph.sequence_offsets.push(graph.sequences.len());
+        }
+
+        let mut edges: Vec<(u32, u32)> =
+            Vec::with_capacity(gfa.links.len() * 2);
+        for link in gfa.links.iter() {
+            let from = graph.segment_index(link.from_segment);
+            let to = graph.segment_index(link.to_segment);
+            if let (Some(from), Some(to)) = (from, to) {
+                let from_handle = pack(from, link.from_orient);
+                let to_handle = pack(to, link.to_orient);
+                edges.push((from_handle, to_handle));
+                edges.push((to_handle ^ 1, from_handle ^ 1));
+            }
+        }
+        edges.sort_unstable();
+        graph.edge_offsets = vec![0; 2 * count + 1];
+        for &(from, _) in edges.iter() {
+            graph.edge_offsets[from as usize + 1] += 1;
+        }
+        for ix in 1..graph.edge_offsets.len() {
+            graph.edge_offsets[ix] += graph.edge_offsets[ix - 1];
+        }
+        graph.edge_targets = edges.into_iter().map(|(_, to)| to).collect();
+
+        for path in gfa.path_refs() {
+            graph.path_names.extend_from_slice(&path.name());
+            graph.path_name_offsets.push(graph.path_names.len());
+            for (name, orient) in path.steps() {
+                if let Some(ix) = graph.segment_index(name) {
+                    graph.path_steps.push(pack(ix, orient));
+                }
+            }
+            graph.path_step_offsets.push(graph.path_steps.len());
+        }
+
+        graph.sequences.shrink_to_fit();
+        graph.path_names.shrink_to_fit();
+        graph.path_steps.shrink_to_fit();
+        Some(graph)
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.names.len()
+    }
+
+    /// The index of the first segment with the given name.
+    pub fn segment_index(&self, name: usize) -> Option<usize> {
+        let found = self
+            .sorted_names
+            .binary_search_by_key(&name, |&(name, _)| name)
+            .ok()?;
+        Some(self.sorted_names[found].1 as usize)
+    }
+
+    pub fn segment_name(&self, ix: usize) -> Option<usize> {
+        self.names.get(ix).copied()
+    }
+
+    /// The sequence of the segment with the given index.
+    pub fn sequence(&self, ix: usize) -> Option<&BStr> {
+        let start = *self.sequence_offsets.get(ix)?;
+        let end = *self.sequence_offsets.get(ix + 1)?;
+        Some(self.sequences[start..end].as_bstr())
+    }
+
+    /// The segments reached by following the links out of the
+    /// segment in the given orientation, in the orientation they're
+    /// entered. Every link can be followed in both directions.
+    pub fn neighbors(
+        &self,
+        ix: usize,
+        orient: Orientation,
+    ) -> impl Iterator<Item = (usize, Orientation)> + '_ {
+        let range = if ix < self.segment_count() {
+            let handle = pack(ix, orient) as usize;
+            self.edge_offsets[handle]..self.edge_offsets[handle + 1]
+        } else {
+            0..0
+        };
+        self.edge_targets[range].iter().map(|&h| unpack(h))
+    }
+
+    /// The number of links, counting each link once.
+    pub fn link_count(&self) -> usize {
+        self.edge_targets.len() / 2
+    }
+
+    pub fn path_count(&self) -> usize {
+        self.path_name_offsets.len() - 1
+    }
+
+    pub fn path_name(&self, ix: usize) -> Option<&BStr> {
+        let start = *self.path_name_offsets.get(ix)?;
+        let end = *self.path_name_offsets.get(ix + 1)?;
+        Some(self.path_names[start..end].as_bstr())
+    }
+
+    /// The steps of the path with the given index, as segment
+    /// indices and orientations.
+    pub fn path_steps(
+        &self,
+        ix: usize,
+    ) -> impl Iterator<Item = (usize, Orientation)> + '_ {
+        let range = match (
+            self.path_step_offsets.get(ix),
+            self.path_step_offsets.get(ix + 1),
+        ) {
+            (Some(&start), Some(&end)) => start..end,
+            _ => 0..0,
+        };
+        self.path_steps[range].iter().map(|&h| unpack(h))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::GFAParser;
+
+    #[test]
+    fn compact_lil_gfa() {
+        let parser: GFAParser<usize, ()> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+        let compact = CompactGFA::new(&gfa).unwrap();
+
+        assert_eq!(compact.segment_count(), gfa.segments.len());
+        assert_eq!(compact.link_count(), gfa.links.len());
+        for (ix, seg) in gfa.segments.iter().enumerate() {
+            assert_eq!(compact.segment_index(seg.name), Some(ix));
+            assert_eq!(compact.segment_name(ix), Some(seg.name));
+            assert_eq!(compact.sequence(ix).unwrap(), seg.sequence.as_bstr());
+        }
+        assert_eq!(compact.segment_index(100), None);
+
+        // 1+ -> 2+ and 1+ -> 3+, and 2- -> 1-
+        let ix = |name| compact.segment_index(name).unwrap();
+        let next: Vec<_> =
+            compact.neighbors(ix(1), Orientation::Forward).collect();
+        assert_eq!(
+            next,
+            vec![(ix(2), Orientation::Forward), (ix(3), Orientation::Forward)]
+        );
+        let prev: Vec<_> =
+            compact.neighbors(ix(2), Orientation::Backward).collect();
+        assert_eq!(prev, vec![(ix(1), Orientation::Backward)]);
+
+        assert_eq!(compact.path_count(), 3);
+        for (ix, path) in gfa.paths.iter().enumerate() {
+            assert_eq!(
+                compact.path_name(ix).unwrap(),
+                path.path_name.as_bstr()
+            );
+            let steps: Vec<_> = compact
+                .path_steps(ix)
+                .map(|(seg, orient)| {
+                    (compact.segment_name(seg).unwrap(), orient)
+                })
+                .collect();
+            let expected: Vec<_> = path.iter().collect();
+            assert_eq!(steps, expected);
+        }
+    }
+
+    #[test]
+    fn walks_are_paths() {
+        let parser: GFAParser<usize, ()> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/walks.gfa").unwrap();
+        let compact = CompactGFA::new(&gfa).unwrap();
+
+        assert_eq!(compact.path_count(), 4);
+        assert_eq!(compact.path_name(0).unwrap(), "ref");
+        assert_eq!(compact.path_name(3).unwrap(), "HG003#1#chr1");
+        let steps: Vec<_> = compact
+            .path_steps(3)
+            .map(|(seg, orient)| (compact.segment_name(seg).unwrap(), orient))
+            .collect();
+        let back = Orientation::Backward;
+        assert_eq!(steps, vec![(4, back), (3, back), (1, back)]);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::optfields::*;
+
+use super::{Orientation, GFA};
+
+use fnv::FnvHashSet;
+
+use std::hash::Hash;
+
+/// Indices of the records in a GFA that repeat an earlier record.
+/// Segments are duplicates if they have the same name, links and
+/// containments if they have the same endpoints and orientations
+/// (and position, for containments), paths if they have the same
+/// name, and walks if they have the same sample, haplotype, sequence
+/// ID, and sequence range. The first occurrence of each record is not
+/// included.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Duplicates {
+    pub segments: Vec<usize>,
+    pub links: Vec<usize>,
+    pub containments: Vec<usize>,
+    pub paths: Vec<usize>,
+    pub walks: Vec<usize>,
+}
+
+impl Duplicates {
+    /// The total number of duplicate records.
+    pub fn len(&self) -> usize {
+        self.segments.len()
+            + self.links.len()
+            + self.containments.len()
+            + self.paths.len()
+            + self.walks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Return the indices of the items whose key has already been seen.
+fn repeated<I, K>(items: I) -> Vec<usize>
+where
+    I: Iterator<Item = K>,
+    K: Eq + Hash,
+{
+    let mut seen = FnvHashSet::default();
+    items
+        .enumerate()
+        .filter_map(|(ix, key)| if seen.insert(key) { None } else { Some(ix) })
+        .collect()
+}
+
+/// Remove the elements at the given sorted indices from the vector.
+pub(crate) fn remove_indices<X>(items: &mut Vec<X>, indices: &[usize]) {
+    let mut indices = indices.iter().peekable();
+    let mut ix = 0;
+    items.retain(|_| {
+        let keep = indices.peek() != Some(&&ix);
+        if !keep {
+            indices.next();
+        }
+        ix += 1;
+        keep
+    });
+}
+
+type EdgeKey<'a, N> = (&'a N, Orientation, &'a N, Orientation);
+
+impl<N: Eq + Hash, T: OptFields> GFA<N, T> {
+    /// Find the duplicate segments, links, containments, paths, and
+    /// walks in the GFA, without modifying it.
+    pub fn duplicates(&self) -> Duplicates {
+        let segments = repeated(self.segments.iter().map(|s| &s.name));
+
+        let links = repeated(self.links.iter().map(|l| -> EdgeKey<'_, N> {
+            (&l.from_segment, l.from_orient, &l.to_segment, l.to_orient)
+        }));
+
+        let containments = repeated(self.containments.iter().map(|c| {
+            let key: EdgeKey<'_, N> = (
+                &c.container_name,
+                c.container_orient,
+                &c.contained_name,
+                c.contained_orient,
+            );
+            (key, c.pos)
+        }));
+
+        let paths = repeated(self.paths.iter().map(|p| &p.path_name));
+
+        let walks = repeated(self.walks.iter().map(|w| {
+            let range = (w.seq_start, w.seq_end);
+            (&w.sample_id, w.hap_index, &w.seq_id, range)
+        }));
+
+        Duplicates {
+            segments,
+            links,
+            containments,
+            paths,
+            walks,
+        }
+    }
+
+    /// Remove all duplicate records from the GFA, keeping the first
+    /// occurrence of each, and return the indices of the removed
+    /// records in the original GFA.
+    pub fn dedup(&mut self) -> Duplicates {
+        let dups = self.duplicates();
+        remove_indices(&mut self.segments, &dups.segments);
+        remove_indices(&mut self.links, &dups.links);
+        remove_indices(&mut self.containments, &dups.containments);
+        remove_indices(&mut self.paths, &dups.paths);
+        remove_indices(&mut self.walks, &dups.walks);
+        dups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::GFAParser;
+    use crate::test_util::parse;
+
+    #[test]
+    fn no_duplicates_in_lil_gfa() {
+        let parser: GFAParser<usize, ()> = GFAParser::new();
+        let mut gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+        let orig = gfa.clone();
+        assert!(gfa.duplicates().is_empty());
+        assert!(gfa.dedup().is_empty());
+        assert_eq!(gfa, orig);
+    }
+
+    #[test]
+    fn find_and_remove_duplicates() {
+        let mut gfa: GFA<Vec<u8>, OptionalFields> = parse(&[
+            "S\t1\tACGT",
+            "S\t2\tGG",
+            "S\t1\tACGT\tRC:i:3",
+            "L\t1\t+\t2\t-\t0M",
+            "L\t1\t+\t2\t+\t0M",
+            "L\t1\t+\t2\t-\t2M",
+            "C\t1\t+\t2\t+\t1\t2M",
+            "C\t1\t+\t2\t+\t2\t2M",
+            "C\t1\t+\t2\t+\t1\t2M",
+            "P\tp\t1+,2-\t*",
+            "P\tq\t1+\t*",
+            "P\tp\t1+,2-\t*",
+            "S\t2\tGG",
+            "W\tHG1\t1\tchr1\t0\t6\t>1>2",
+            "W\tHG1\t1\tchr1\t6\t8\t>2",
+            "W\tHG1\t1\tchr1\t0\t6\t>1>2",
+            "W\tHG1\t2\tchr1\t0\t6\t>1>2",
+        ]);
+
+        let expected = Duplicates {
+            segments: vec![2, 3],
+            links: vec![2],
+            containments: vec![2],
+            paths: vec![2],
+            walks: vec![2],
+        };
+
+        assert_eq!(gfa.duplicates(), expected);
+        assert_eq!(gfa.dedup(), expected);
+        assert!(gfa.duplicates().is_empty());
+
+        let names: Vec<_> =
+            gfa.segments.iter().map(|s| s.name.as_slice()).collect();
+        assert_eq!(names, vec![b"1", b"2"]);
+        assert!(gfa.segments[0].optional.is_empty());
+        assert_eq!(gfa.links.len(), 2);
+        assert_eq!(gfa.links[0].overlap, b"0M");
+        assert_eq!(gfa.containments.len(), 2);
+        assert_eq!(gfa.paths.len(), 2);
+        assert_eq!(gfa.walks.len(), 3);
+    }
+}
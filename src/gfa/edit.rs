@@ -0,0 +1,678 @@
+use crate::{
+    cigar::CIGAR, optfields::*, seq::revcomp_in_place,
+    validation::segment_length,
+};
+
+use super::{raw_walk_steps, Orientation, Path, SegmentId, Walk, GFA};
+
+use bstr::ByteSlice;
+
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+/// What `GFA::remove_segments` does with paths and walks that
+/// traverse removed segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathPolicy {
+    /// Remove the paths and walks.
+    Drop,
+    /// Split the paths at the removed segments, into fragments named
+    /// after the path, with `_1`, `_2`, etc. appended. Walks are split
+    /// into walks with the same sample, haplotype, and sequence IDs,
+    /// and the sequence ranges of the fragments. Fragments without
+    /// steps are left out.
+    Split,
+    /// Fail without modifying the graph.
+    Error,
+}
+
+/// Returned by `GFA::remove_segments` with `PathPolicy::Error`, when
+/// a path or walk traverses a removed segment. Walks are named by
+/// `Walk::name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathTraversalError {
+    pub path: String,
+    pub segment: String,
+}
+
+impl std::fmt::Display for PathTraversalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Path {} traverses removed segment {}",
+            self.path, self.segment
+        )
+    }
+}
+
+impl std::error::Error for PathTraversalError {}
+
+/// Split a path at the steps whose segments are in `removed`, or
+/// return None if it doesn't traverse any of them. The overlaps are
+/// split along with the steps if there's one per pair of steps, and
+/// set to `*` otherwise.
+fn split_path<N, T>(
+    path: &Path<N, T>,
+    removed: &HashSet<N>,
+) -> Option<Vec<Path<N, T>>>
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    let steps: Vec<&[u8]> = path.segment_names.split_str(b",").collect();
+    let is_removed = |step: &[u8]| {
+        let id = &step[..step.len().saturating_sub(1)];
+        N::parse_id(id).is_some_and(|id| removed.contains(&id))
+    };
+    if !steps.iter().any(|step| is_removed(step)) {
+        return None;
+    }
+
+    let per_step = path.overlaps.len() + 1 == steps.len();
+    let mut fragments = Vec::new();
+    let mut start = 0;
+    for end in 0..=steps.len() {
+        if end < steps.len() && !is_removed(steps[end]) {
+            continue;
+        }
+        if end > start {
+            let name =
+                format!("{}_{}", path.path_name.as_bstr(), fragments.len() + 1);
+            let overlaps = if per_step {
+                path.overlaps[start..end - 1].to_vec()
+            } else {
+                vec![None]
+            };
+            fragments.push(Path::new(
+                name.into_bytes(),
+                steps[start..end].join(&b","[..]),
+                overlaps,
+                path.optional.clone(),
+            ));
+        }
+        start = end + 1;
+    }
+    Some(fragments)
+}
+
+/// Split a walk at the steps whose segments are in `removed`, or
+/// return None if it doesn't traverse any of them. The sequence range
+/// of each fragment is computed from the segment lengths if the start
+/// of the walk and the lengths of the steps before the end of the
+/// fragment are known, and set to `*` otherwise.
+fn split_walk<N, T>(
+    walk: &Walk<N, T>,
+    removed: &HashSet<N>,
+    lengths: &HashMap<&N, Option<usize>>,
+) -> Option<Vec<Walk<N, T>>>
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    let steps: Vec<(&[u8], Option<N>)> = raw_walk_steps(&walk.walk)
+        .map(|step| (step, N::parse_id(&step[1..])))
+        .collect();
+    let is_removed =
+        |id: &Option<N>| id.as_ref().is_some_and(|id| removed.contains(id));
+    if !steps.iter().any(|(_, id)| is_removed(id)) {
+        return None;
+    }
+
+    let mut offsets = Vec::with_capacity(steps.len() + 1);
+    let mut offset = walk.seq_start;
+    offsets.push(offset);
+    for (_, id) in steps.iter() {
+        let len = id.as_ref().and_then(|id| *lengths.get(id)?);
+        offset = offset.zip(len).map(|(offset, len)| offset + len);
+        offsets.push(offset);
+    }
+
+    let mut fragments = Vec::new();
+    let mut start = 0;
+    for end in 0..=steps.len() {
+        if end < steps.len() && !is_removed(&steps[end].1) {
+            continue;
+        }
+        if end > start {
+            let mut fragment = walk.with_walk(
+                steps[start..end]
+                    .iter()
+                    .flat_map(|(s, _)| *s)
+                    .copied()
+                    .collect(),
+            );
+            let range = offsets[start].zip(offsets[end]);
+            fragment.seq_start = range.map(|(start, _)| start);
+            fragment.seq_end = range.map(|(_, end)| end);
+            fragments.push(fragment);
+        }
+        start = end + 1;
+    }
+    Some(fragments)
+}
+
+impl<N: SegmentId + Eq, T: OptFields> GFA<N, T> {
+    /// Reverse complement the sequence of the segment with the given
+    /// name, and flip the orientation of every reference to it in
+    /// links, containments, path steps, and walks, so that the graph still
+    /// describes the same sequences. Overlaps and containment
+    /// positions are unchanged, as they're relative to the oriented
+    /// segments. Optional fields that depend on the sequence, such as
+    /// SH, aren't updated.
+    ///
+    /// Returns false, leaving the graph unchanged, if there's no such
+    /// segment.
+    pub fn flip_segment(&mut self, name: &N) -> bool {
+        let mut found = false;
+        for seg in self.segments.iter_mut().filter(|s| &s.name == name) {
+            found = true;
+            if seg.sequence != b"*" {
+                revcomp_in_place(&mut seg.sequence);
+            }
+        }
+        if !found {
+            return false;
+        }
+
+        for link in self.links.iter_mut() {
+            if &link.from_segment == name {
+                link.from_orient = link.from_orient.flip();
+            }
+            if &link.to_segment == name {
+                link.to_orient = link.to_orient.flip();
+            }
+        }
+
+        for cont in self.containments.iter_mut() {
+            if &cont.container_name == name {
+                cont.container_orient = cont.container_orient.flip();
+            }
+            if &cont.contained_name == name {
+                cont.contained_orient = cont.contained_orient.flip();
+            }
+        }
+
+        for path in self.paths.iter_mut() {
+            let mut steps = Vec::with_capacity(path.segment_names.len());
+            for (i, step) in path.segment_names.split_str(b",").enumerate() {
+                if i > 0 {
+                    steps.push(b',');
+                }
+                let (id, orient) = step.split_at(step.len().saturating_sub(1));
+                let orient = Orientation::from_bytes_plus_minus(orient);
+                match (N::parse_id(id), orient) {
+                    (Some(parsed), Some(orient)) if &parsed == name => {
+                        steps.extend_from_slice(id);
+                        steps.push(orient.flip().plus_minus_as_byte());
+                    }
+                    _ => steps.extend_from_slice(step),
+                }
+            }
+            path.segment_names = steps;
+        }
+
+        for walk in self.walks.iter_mut() {
+            let mut steps = Vec::with_capacity(walk.walk.len());
+            for step in raw_walk_steps(&walk.walk) {
+                let (orient, id) = step.split_at(1);
+                let orient = Orientation::from_bytes_gt_ln(orient);
+                match (N::parse_id(id), orient) {
+                    (Some(parsed), Some(orient)) if &parsed == name => {
+                        steps.push(orient.flip().gt_ln_as_byte());
+                        steps.extend_from_slice(id);
+                    }
+                    _ => steps.extend_from_slice(step),
+                }
+            }
+            walk.walk = steps;
+        }
+
+        true
+    }
+}
+
+impl<N, T> GFA<N, T>
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    /// Remove the segments with the given names, along with the links
+    /// and containments that refer to them. Paths and walks that
+    /// traverse them are handled according to `policy`. Returns the
+    /// number of segments removed.
+    pub fn remove_segments(
+        &mut self,
+        names: &HashSet<N>,
+        policy: PathPolicy,
+    ) -> Result<usize, PathTraversalError> {
+        let mut paths = Vec::with_capacity(self.paths.len());
+        for path in self.paths.iter() {
+            match split_path(path, names) {
+                None => paths.push(None),
+                Some(fragments) => match policy {
+                    PathPolicy::Drop => paths.push(Some(Vec::new())),
+                    PathPolicy::Split => paths.push(Some(fragments)),
+                    PathPolicy::Error => {
+                        let segment = path
+                            .steps()
+                            .find(|(id, _)| names.contains(id))
+                            .map(|(id, _)| id.display())
+                            .unwrap_or_default();
+                        return Err(PathTraversalError {
+                            path: path.path_name.to_str_lossy().into_owned(),
+                            segment,
+                        });
+                    }
+                },
+            }
+        }
+
+        let lengths: HashMap<&N, Option<usize>> = self
+            .segments
+            .iter()
+            .map(|seg| (&seg.name, segment_length(seg)))
+            .collect();
+        let mut walks = Vec::with_capacity(self.walks.len());
+        for walk in self.walks.iter() {
+            match split_walk(walk, names, &lengths) {
+                None => walks.push(None),
+                Some(fragments) => match policy {
+                    PathPolicy::Drop => walks.push(Some(Vec::new())),
+                    PathPolicy::Split => walks.push(Some(fragments)),
+                    PathPolicy::Error => {
+                        let segment = walk
+                            .steps()
+                            .find(|(id, _)| names.contains(id))
+                            .map(|(id, _)| id.display())
+                            .unwrap_or_default();
+                        return Err(PathTraversalError {
+                            path: walk.name().to_str_lossy().into_owned(),
+                            segment,
+                        });
+                    }
+                },
+            }
+        }
+
+        let count = self.segments.len();
+        self.segments.retain(|s| !names.contains(&s.name));
+        self.links.retain(|l| {
+            !names.contains(&l.from_segment) && !names.contains(&l.to_segment)
+        });
+        self.containments.retain(|c| {
+            !names.contains(&c.container_name)
+                && !names.contains(&c.contained_name)
+        });
+
+        let old_paths = std::mem::take(&mut self.paths);
+        for (path, new) in old_paths.into_iter().zip(paths) {
+            match new {
+                None => self.paths.push(path),
+                Some(fragments) => self.paths.extend(fragments),
+            }
+        }
+        let old_walks = std::mem::take(&mut self.walks);
+        for (walk, new) in old_walks.into_iter().zip(walks) {
+            match new {
+                None => self.walks.push(walk),
+                Some(fragments) => self.walks.extend(fragments),
+            }
+        }
+
+        Ok(count - self.segments.len())
+    }
+}
+
+/// Parse a path step into its segment ID and orientation.
+fn parse_step<N: SegmentId>(step: &[u8]) -> Option<(N, Orientation)> {
+    let (id, orient) = step.split_at(step.len().checked_sub(1)?);
+    Some((
+        N::parse_id(id)?,
+        Orientation::from_bytes_plus_minus(orient)?,
+    ))
+}
+
+fn step_bytes<N: SegmentId>(id: &N, orient: Orientation) -> Vec<u8> {
+    let mut step = id.display().into_bytes();
+    step.push(orient.plus_minus_as_byte());
+    step
+}
+
+impl<N: SegmentId, T: OptFields> Path<N, T> {
+    /// Build a path from its steps, with `*` as the overlaps.
+    pub fn from_steps<I>(path_name: Vec<u8>, steps: I, optional: T) -> Self
+    where
+        I: IntoIterator<Item = (N, Orientation)>,
+    {
+        let steps: Vec<_> = steps
+            .into_iter()
+            .map(|(id, orient)| step_bytes(&id, orient))
+            .collect();
+        Path::new(path_name, steps.join(&b","[..]), vec![None], optional)
+    }
+
+    fn raw_steps(&self) -> Vec<&[u8]> {
+        if self.segment_names.is_empty() {
+            Vec::new()
+        } else {
+            self.segment_names.split_str(b",").collect()
+        }
+    }
+
+    /// Edit the step list, and keep the overlaps consistent with it.
+    /// Overlaps are only edited when there's one for each pair of
+    /// steps, or when there's at most one step and no overlaps; the
+    /// overlaps are `*` if none are left after editing.
+    fn edit_steps<F, G>(&mut self, edit: F, edit_overlaps: G)
+    where
+        F: FnOnce(&mut Vec<Vec<u8>>),
+        G: FnOnce(&mut Vec<Option<CIGAR>>),
+    {
+        let mut steps: Vec<Vec<u8>> =
+            self.raw_steps().into_iter().map(Vec::from).collect();
+        if steps.len() <= 1 && self.overlaps.iter().all(Option::is_none) {
+            self.overlaps.clear();
+        }
+        if self.overlaps.len() + 1 == steps.len() {
+            edit_overlaps(&mut self.overlaps);
+        }
+        if self.overlaps.is_empty() {
+            self.overlaps.push(None);
+        }
+        edit(&mut steps);
+        self.segment_names = steps.join(&b","[..]);
+    }
+
+    /// The number of steps in the path.
+    pub fn step_count(&self) -> usize {
+        self.raw_steps().len()
+    }
+
+    /// The segment ID and orientation of the step at `index`, or
+    /// `None` if it's out of bounds or can't be parsed.
+    pub fn step(&self, index: usize) -> Option<(N, Orientation)> {
+        parse_step(self.raw_steps().get(index)?)
+    }
+
+    /// Insert a step at `index`, shifting the following steps. If the
+    /// path has an overlap for each pair of steps, `overlap` is
+    /// inserted as the overlap between the new step and the one
+    /// before it, or the one after it if the step is inserted first.
+    /// Returns false, leaving the path unchanged, if `index` is
+    /// greater than the number of steps.
+    pub fn insert_step(
+        &mut self,
+        index: usize,
+        id: &N,
+        orient: Orientation,
+        overlap: Option<CIGAR>,
+    ) -> bool {
+        let count = self.step_count();
+        if index > count {
+            return false;
+        }
+        let step = step_bytes(id, orient);
+        self.edit_steps(
+            |steps| steps.insert(index, step),
+            |overlaps| overlaps.insert(index.saturating_sub(1), overlap),
+        );
+        true
+    }
+
+    /// Remove the step at `index`, along with its overlap with the
+    /// step before it, or with the step after it if it's the first
+    /// step. Returns the removed step, or `None` if `index` is out of
+    /// bounds.
+    pub fn remove_step(&mut self, index: usize) -> Option<(N, Orientation)> {
+        let removed = self.raw_steps().get(index).map(|s| s.to_vec())?;
+        self.edit_steps(
+            |steps| {
+                steps.remove(index);
+            },
+            |overlaps| {
+                if !overlaps.is_empty() {
+                    overlaps.remove(index.saturating_sub(1));
+                }
+            },
+        );
+        parse_step(&removed)
+    }
+
+    /// Replace the segment and orientation of the step at `index`,
+    /// keeping its overlaps, and return the old step. Returns `None`,
+    /// leaving the path unchanged, if `index` is out of bounds.
+    pub fn replace_step(
+        &mut self,
+        index: usize,
+        id: &N,
+        orient: Orientation,
+    ) -> Option<(N, Orientation)> {
+        let old = self.raw_steps().get(index).map(|s| s.to_vec())?;
+        let step = step_bytes(id, orient);
+        self.edit_steps(|steps| steps[index] = step, |_| ());
+        parse_step(&old)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    #[test]
+    fn flip_segment_references() {
+        let lines = [
+            "S\t1\tAACG",
+            "S\t2\t*",
+            "L\t1\t+\t2\t-\t0M",
+            "L\t1\t-\t1\t+\t0M",
+            "C\t2\t+\t1\t-\t1\t4M",
+            "P\tp\t2+,1-,11+\t0M,0M",
+            "W\tHG1\t1\tchr1\t*\t*\t>2<1>11>1",
+        ];
+        let mut gfa: GFA<Vec<u8>, ()> = parse(&lines);
+        let orig = gfa.clone();
+
+        assert!(!gfa.flip_segment(&b"3".to_vec()));
+        assert_eq!(gfa, orig);
+
+        assert!(gfa.flip_segment(&b"1".to_vec()));
+        assert_eq!(gfa.segments[0].sequence, b"CGTT");
+        assert_eq!(gfa.links[0].from_orient, Orientation::Backward);
+        assert_eq!(gfa.links[0].to_orient, Orientation::Backward);
+        assert_eq!(gfa.links[1].from_orient, Orientation::Forward);
+        assert_eq!(gfa.links[1].to_orient, Orientation::Backward);
+        assert_eq!(gfa.containments[0].container_orient, Orientation::Forward);
+        assert_eq!(gfa.containments[0].contained_orient, Orientation::Forward);
+        assert_eq!(gfa.paths[0].segment_names, b"2+,1+,11+");
+        assert_eq!(gfa.walks[0].walk, b">2>1>11<1");
+
+        assert!(gfa.flip_segment(&b"1".to_vec()));
+        assert_eq!(gfa, orig);
+    }
+
+    #[test]
+    fn remove_segments_and_paths() {
+        let lines = [
+            "S\t1\tA",
+            "S\t2\tC",
+            "S\t3\tG",
+            "S\t4\tT",
+            "L\t1\t+\t2\t+\t0M",
+            "L\t2\t+\t3\t+\t0M",
+            "L\t3\t+\t4\t+\t0M",
+            "C\t1\t+\t3\t+\t0\t1M",
+            "P\tp\t1+,2+,3+,4+\t1M,2M,3M",
+            "P\tq\t3+,4+\t*",
+            "P\tr\t2+\t*",
+        ];
+        let gfa: GFA<usize, ()> = parse(&lines);
+        let removed: HashSet<usize> = [2].iter().copied().collect();
+
+        let mut error = gfa.clone();
+        let err = error
+            .remove_segments(&removed, PathPolicy::Error)
+            .unwrap_err();
+        assert_eq!(err.path, "p");
+        assert_eq!(err.segment, "2");
+        assert_eq!(error, gfa);
+
+        let mut dropped = gfa.clone();
+        assert_eq!(dropped.remove_segments(&removed, PathPolicy::Drop), Ok(1));
+        assert_eq!(dropped.segments.len(), 3);
+        assert_eq!(dropped.links.len(), 1);
+        assert_eq!(dropped.containments.len(), 1);
+        assert_eq!(dropped.paths.len(), 1);
+        assert_eq!(dropped.paths[0].path_name, b"q");
+
+        let mut split = gfa;
+        split.remove_segments(&removed, PathPolicy::Split).unwrap();
+        let paths: Vec<_> = split
+            .paths
+            .iter()
+            .map(|p| {
+                let overlaps: Vec<_> = p
+                    .overlaps
+                    .iter()
+                    .map(|o| o.as_ref().map(|c| c.to_string()))
+                    .collect();
+                (
+                    p.path_name.as_bstr().to_string(),
+                    p.iter().count(),
+                    overlaps,
+                )
+            })
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                ("p_1".to_string(), 1, vec![]),
+                ("p_2".to_string(), 2, vec![Some("3M".to_string())]),
+                ("q".to_string(), 2, vec![None]),
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_segments_from_walks() {
+        let lines = [
+            "S\t1\tAC",
+            "S\t2\tC",
+            "S\t3\tGGT",
+            "S\t4\t*",
+            "W\tHG1\t1\tchr1\t10\t16\t>1>2<3",
+            "W\tHG1\t2\tchr1\t*\t*\t>1<2>3",
+            "W\tHG2\t1\tchr1\t0\t3\t>3>4>2>1",
+            "W\tHG3\t1\tchr1\t0\t2\t>1",
+        ];
+        let gfa: GFA<usize, ()> = parse(&lines);
+        let removed: HashSet<usize> = [2].iter().copied().collect();
+
+        let mut error = gfa.clone();
+        let err = error
+            .remove_segments(&removed, PathPolicy::Error)
+            .unwrap_err();
+        assert_eq!(err.path, "HG1#1#chr1");
+        assert_eq!(err.segment, "2");
+        assert_eq!(error, gfa);
+
+        let mut dropped = gfa.clone();
+        dropped.remove_segments(&removed, PathPolicy::Drop).unwrap();
+        assert_eq!(dropped.walks.len(), 1);
+        assert_eq!(dropped.walks[0].sample_id, b"HG3");
+
+        let mut split = gfa;
+        split.remove_segments(&removed, PathPolicy::Split).unwrap();
+        let walks: Vec<_> = split
+            .walks
+            .iter()
+            .map(|w| {
+                let name = w.name().to_str_lossy().into_owned();
+                (
+                    name,
+                    w.seq_start,
+                    w.seq_end,
+                    w.walk.to_str_lossy().into_owned(),
+                )
+            })
+            .collect();
+        let walk = |name: &str, start, end, steps: &str| {
+            (name.to_string(), start, end, steps.to_string())
+        };
+        assert_eq!(
+            walks,
+            vec![
+                walk("HG1#1#chr1", Some(10), Some(12), ">1"),
+                walk("HG1#1#chr1", Some(13), Some(16), "<3"),
+                walk("HG1#2#chr1", None, None, ">1"),
+                walk("HG1#2#chr1", None, None, ">3"),
+                walk("HG2#1#chr1", None, None, ">3>4"),
+                walk("HG2#1#chr1", None, None, ">1"),
+                walk("HG3#1#chr1", Some(0), Some(2), ">1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn edit_path_steps() {
+        use Orientation::*;
+
+        let cigar = |s: &str| CIGAR::from_bytestring(s.as_bytes());
+        let overlaps = |path: &Path<usize, ()>| -> Vec<_> {
+            path.overlaps
+                .iter()
+                .map(|o| o.as_ref().map(|c| c.to_string()))
+                .collect()
+        };
+
+        let mut path: Path<usize, ()> = Path::from_steps(
+            b"p".to_vec(),
+            vec![(1, Forward), (2, Backward)],
+            (),
+        );
+        assert_eq!(path.segment_names, b"1+,2-");
+        assert_eq!(path.overlaps, vec![None]);
+        assert_eq!(path.step_count(), 2);
+        assert_eq!(path.step(1), Some((2, Backward)));
+        assert_eq!(path.step(2), None);
+
+        path.overlaps = vec![cigar("1M")];
+        assert!(path.insert_step(1, &3, Forward, cigar("2M")));
+        assert_eq!(path.segment_names, b"1+,3+,2-");
+        assert_eq!(overlaps(&path), vec![Some("2M".into()), Some("1M".into())]);
+
+        assert!(path.insert_step(0, &4, Backward, cigar("3M")));
+        assert!(path.insert_step(4, &5, Forward, None));
+        assert!(!path.insert_step(6, &6, Forward, None));
+        assert_eq!(path.segment_names, b"4-,1+,3+,2-,5+");
+        assert_eq!(
+            overlaps(&path),
+            vec![
+                Some("3M".into()),
+                Some("2M".into()),
+                Some("1M".into()),
+                None
+            ]
+        );
+
+        assert_eq!(path.replace_step(2, &7, Backward), Some((3, Forward)));
+        assert_eq!(path.segment_names, b"4-,1+,7-,2-,5+");
+        assert_eq!(path.replace_step(5, &7, Backward), None);
+
+        assert_eq!(path.remove_step(0), Some((4, Backward)));
+        assert_eq!(path.remove_step(2), Some((2, Backward)));
+        assert_eq!(path.remove_step(3), None);
+        assert_eq!(path.segment_names, b"1+,7-,5+");
+        assert_eq!(overlaps(&path), vec![Some("2M".into()), None]);
+
+        while path.remove_step(0).is_some() {}
+        assert_eq!(path.segment_names, b"");
+        assert_eq!(path.overlaps, vec![None]);
+        assert!(path.insert_step(0, &1, Forward, None));
+        assert!(path.insert_step(1, &2, Forward, cigar("4M")));
+        assert_eq!(path.segment_names, b"1+,2+");
+        assert_eq!(overlaps(&path), vec![Some("4M".into())]);
+    }
+}
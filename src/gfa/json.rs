@@ -0,0 +1,654 @@
+//! Conversion of GFA graphs to and from a plain JSON document, for
+//! consumers that don't have a GFA parser of their own. Unlike the
+//! serde implementations, which mirror the Rust types, the document
+//! uses strings for names, orientations, sequences, and overlaps, and
+//! objects for the optional fields:
+//!
+//! ```json
+//! {
+//!   "header": { "version": "1.0", "tags": {} },
+//!   "segments": [
+//!     { "name": "1", "sequence": "ACGT",
+//!       "tags": { "LN": { "type": "i", "value": 4 } } }
+//!   ],
+//!   "links": [
+//!     { "from": "1", "from_orient": "+", "to": "2", "to_orient": "-",
+//!       "overlap": "4M", "tags": {} }
+//!   ],
+//!   "containments": [
+//!     { "container": "1", "container_orient": "+",
+//!       "contained": "2", "contained_orient": "+",
+//!       "pos": 2, "overlap": "*", "tags": {} }
+//!   ],
+//!   "paths": [
+//!     { "name": "p1",
+//!       "steps": [ { "segment": "1", "orient": "+" },
+//!                  { "segment": "2", "orient": "-" } ],
+//!       "overlaps": [ "4M" ],
+//!       "tags": {} }
+//!   ],
+//!   "walks": [
+//!     { "sample": "HG002", "haplotype": 1, "sequence": "chr1",
+//!       "start": 0, "end": 5,
+//!       "steps": [ { "segment": "1", "orient": "+" } ],
+//!       "tags": {} }
+//!   ]
+//! }
+//! ```
+//!
+//! Each tag maps to an object holding its GFA type and its value.
+//! `i` and `f` values are numbers, `J` values are embedded as JSON,
+//! `B` values are arrays of numbers with an additional `subtype`
+//! field, and the rest are strings, with `H` values in hexadecimal.
+//! `version` and path overlaps are `null` when they're missing.
+//! Converting a graph to a JSON string and back produces the same
+//! graph, as long as its byte strings are valid UTF-8.
+
+use super::*;
+
+use serde::{
+    de::{MapAccess, Visitor},
+    ser::SerializeMap,
+    Deserializer, Serializer,
+};
+use serde_json::Value;
+
+use std::fmt;
+
+/// An error produced when converting a JSON document to a GFA.
+#[derive(Debug)]
+pub enum JsonError {
+    /// The document isn't valid JSON, or doesn't match the schema.
+    Json(serde_json::Error),
+    /// A segment name couldn't be parsed as the name type.
+    InvalidName(String),
+    InvalidOrientation(String),
+    InvalidOverlap(String),
+    /// A tag, its type, or its value, isn't valid.
+    InvalidTag(String),
+    /// A path or walk has a step that isn't a valid segment name and
+    /// orientation. Includes the steps of the path or walk.
+    InvalidStep(String),
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use JsonError::*;
+        match self {
+            Json(err) => write!(f, "{}", err),
+            InvalidName(name) => write!(f, "Invalid segment name: {}", name),
+            InvalidOrientation(orient) => {
+                write!(f, "Invalid orientation: {}", orient)
+            }
+            InvalidOverlap(overlap) => {
+                write!(f, "Invalid overlap: {}", overlap)
+            }
+            InvalidTag(tag) => write!(f, "Invalid tag: {}", tag),
+            InvalidStep(steps) => write!(f, "Invalid step in: {}", steps),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl From<serde_json::Error> for JsonError {
+    fn from(err: serde_json::Error) -> Self {
+        JsonError::Json(err)
+    }
+}
+
+pub type JsonResult<T> = Result<T, JsonError>;
+
+#[derive(Serialize, Deserialize)]
+struct JsonTag {
+    #[serde(rename = "type")]
+    field_type: String,
+    value: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    subtype: Option<String>,
+}
+
+/// The optional fields of a line, serialized as a map to keep their
+/// order without depending on the map type of `serde_json`.
+#[derive(Default)]
+struct JsonTags(Vec<(String, JsonTag)>);
+
+impl Serialize for JsonTags {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (tag, value) in &self.0 {
+            map.serialize_entry(tag, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonTags {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        struct TagsVisitor;
+
+        impl<'de> Visitor<'de> for TagsVisitor {
+            type Value = JsonTags;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a map of tags")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut tags = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    tags.push(entry);
+                }
+                Ok(JsonTags(tags))
+            }
+        }
+
+        deserializer.deserialize_map(TagsVisitor)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonHeader {
+    version: Option<String>,
+    #[serde(default)]
+    tags: JsonTags,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonSegment {
+    name: String,
+    sequence: String,
+    #[serde(default)]
+    tags: JsonTags,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonLink {
+    from: String,
+    from_orient: String,
+    to: String,
+    to_orient: String,
+    overlap: String,
+    #[serde(default)]
+    tags: JsonTags,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonContainment {
+    container: String,
+    container_orient: String,
+    contained: String,
+    contained_orient: String,
+    pos: usize,
+    overlap: String,
+    #[serde(default)]
+    tags: JsonTags,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonStep {
+    segment: String,
+    orient: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonPath {
+    name: String,
+    steps: Vec<JsonStep>,
+    #[serde(default)]
+    overlaps: Vec<Option<String>>,
+    #[serde(default)]
+    tags: JsonTags,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonWalk {
+    sample: String,
+    haplotype: usize,
+    sequence: String,
+    #[serde(default)]
+    start: Option<usize>,
+    #[serde(default)]
+    end: Option<usize>,
+    steps: Vec<JsonStep>,
+    #[serde(default)]
+    tags: JsonTags,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonGFA {
+    header: JsonHeader,
+    #[serde(default)]
+    segments: Vec<JsonSegment>,
+    #[serde(default)]
+    links: Vec<JsonLink>,
+    #[serde(default)]
+    containments: Vec<JsonContainment>,
+    #[serde(default)]
+    paths: Vec<JsonPath>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    walks: Vec<JsonWalk>,
+}
+
+fn string(bytes: &[u8]) -> String {
+    bytes.to_str_lossy().into_owned()
+}
+
+/// Parses a number from the GFA representation of a field value,
+/// falling back to a string for values that aren't valid JSON
+/// numbers, e.g. infinite floats.
+fn number(text: &str) -> Value {
+    serde_json::from_str::<serde_json::Number>(text)
+        .map(Value::Number)
+        .unwrap_or_else(|_| Value::String(text.to_string()))
+}
+
+fn tags_to_json<T: OptFields>(optional: &T) -> JsonTags {
+    let tags = optional
+        .to_fields()
+        .iter()
+        .map(|field| {
+            let text = field.to_string();
+            let (tag, field_type, value) =
+                (&text[0..2], &text[3..4], &text[5..]);
+            let (value, subtype) = match field_type {
+                "i" | "f" => (number(value), None),
+                "J" => (
+                    serde_json::from_str(value)
+                        .unwrap_or_else(|_| Value::String(value.to_string())),
+                    None,
+                ),
+                "B" => {
+                    let mut values = value.split(',');
+                    let subtype = values.next().map(String::from);
+                    (Value::Array(values.map(number).collect()), subtype)
+                }
+                _ => (Value::String(value.to_string()), None),
+            };
+            let json = JsonTag {
+                field_type: field_type.to_string(),
+                value,
+                subtype,
+            };
+            (tag.to_string(), json)
+        })
+        .collect();
+    JsonTags(tags)
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn tags_from_json<T: OptFields>(tags: JsonTags) -> JsonResult<T> {
+    let mut fields = Vec::with_capacity(tags.0.len());
+    for (tag, json) in tags.0 {
+        let value = match (json.field_type.as_str(), &json.value) {
+            ("J", value) => value.to_string(),
+            ("B", Value::Array(values)) => {
+                let mut text = json.subtype.clone().unwrap_or_default();
+                for value in values {
+                    text.push(',');
+                    text.push_str(&value_text(value));
+                }
+                text
+            }
+            (_, value) => value_text(value),
+        };
+        let field = format!("{}:{}:{}", tag, json.field_type, value);
+
+        let bytes = tag.as_bytes();
+        let valid_tag = bytes.len() == 2
+            && bytes[0].is_ascii_alphabetic()
+            && bytes[1].is_ascii_alphanumeric();
+        if !valid_tag || OptField::parse(field.as_bytes()).is_none() {
+            return Err(JsonError::InvalidTag(field));
+        }
+        fields.push(field);
+    }
+    Ok(T::parse(fields))
+}
+
+fn parse_name<N: SegmentId>(name: &str) -> JsonResult<N> {
+    N::parse_id(name.as_bytes())
+        .ok_or_else(|| JsonError::InvalidName(name.to_string()))
+}
+
+fn parse_orient(orient: &str) -> JsonResult<Orientation> {
+    Orientation::from_bytes_plus_minus(orient)
+        .ok_or_else(|| JsonError::InvalidOrientation(orient.to_string()))
+}
+
+fn check_overlap(overlap: String) -> JsonResult<Vec<u8>> {
+    match parse_overlap(overlap.as_bytes()) {
+        Ok(_) => Ok(overlap.into_bytes()),
+        Err(_) => Err(JsonError::InvalidOverlap(overlap)),
+    }
+}
+
+/// Convert the steps of a path or walk, given as the split steps and
+/// the field they're from, failing on the first invalid step.
+fn steps_to_json<'a, N, I, E>(
+    steps: I,
+    field: &[u8],
+) -> JsonResult<Vec<JsonStep>>
+where
+    N: SegmentId,
+    I: Iterator<Item = Result<(&'a [u8], Orientation), E>>,
+{
+    steps
+        .map(|step| {
+            let (segment, orient) = step
+                .ok()
+                .and_then(|(seg, orient)| Some((N::parse_id(seg)?, orient)))
+                .ok_or_else(|| JsonError::InvalidStep(string(field)))?;
+            Ok(JsonStep {
+                segment: segment.display(),
+                orient: orient.to_string(),
+            })
+        })
+        .collect()
+}
+
+impl<N: SegmentId, T: OptFields> GFA<N, T> {
+    fn to_json_gfa(&self) -> JsonResult<JsonGFA> {
+        let header = JsonHeader {
+            version: self.header.version.as_deref().map(string),
+            tags: tags_to_json(&self.header.optional),
+        };
+
+        let segments = self
+            .segments
+            .iter()
+            .map(|seg| JsonSegment {
+                name: seg.name.display(),
+                sequence: string(&seg.sequence),
+                tags: tags_to_json(&seg.optional),
+            })
+            .collect();
+
+        let links = self
+            .links
+            .iter()
+            .map(|link| JsonLink {
+                from: link.from_segment.display(),
+                from_orient: link.from_orient.to_string(),
+                to: link.to_segment.display(),
+                to_orient: link.to_orient.to_string(),
+                overlap: string(&link.overlap),
+                tags: tags_to_json(&link.optional),
+            })
+            .collect();
+
+        let containments = self
+            .containments
+            .iter()
+            .map(|cont| JsonContainment {
+                container: cont.container_name.display(),
+                container_orient: cont.container_orient.to_string(),
+                contained: cont.contained_name.display(),
+                contained_orient: cont.contained_orient.to_string(),
+                pos: cont.pos,
+                overlap: string(&cont.overlap),
+                tags: tags_to_json(&cont.optional),
+            })
+            .collect();
+
+        let paths = self
+            .paths
+            .iter()
+            .map(|path| {
+                let steps = path.segment_names.split_str(b",").map(parse_step);
+                Ok(JsonPath {
+                    name: string(&path.path_name),
+                    steps: steps_to_json::<N, _, _>(
+                        steps,
+                        &path.segment_names,
+                    )?,
+                    overlaps: path
+                        .overlaps
+                        .iter()
+                        .map(|o| o.as_ref().map(|c| c.to_string()))
+                        .collect(),
+                    tags: tags_to_json(&path.optional),
+                })
+            })
+            .collect::<JsonResult<_>>()?;
+
+        let walks = self
+            .walks
+            .iter()
+            .map(|walk| {
+                let steps = walk_steps(&walk.walk);
+                Ok(JsonWalk {
+                    sample: string(&walk.sample_id),
+                    haplotype: walk.hap_index,
+                    sequence: string(&walk.seq_id),
+                    start: walk.seq_start,
+                    end: walk.seq_end,
+                    steps: steps_to_json::<N, _, _>(steps, &walk.walk)?,
+                    tags: tags_to_json(&walk.optional),
+                })
+            })
+            .collect::<JsonResult<_>>()?;
+
+        Ok(JsonGFA {
+            header,
+            segments,
+            links,
+            containments,
+            paths,
+            walks,
+        })
+    }
+
+    fn from_json_gfa(json: JsonGFA) -> JsonResult<Self> {
+        let header = Header {
+            version: json.header.version.map(String::into_bytes),
+            optional: tags_from_json(json.header.tags)?,
+        };
+
+        let mut gfa = GFA {
+            header,
+            ..Default::default()
+        };
+
+        for seg in json.segments {
+            gfa.segments.push(Segment {
+                name: parse_name(&seg.name)?,
+                sequence: seg.sequence.into_bytes(),
+                optional: tags_from_json(seg.tags)?,
+            });
+        }
+
+        for link in json.links {
+            gfa.links.push(Link {
+                from_segment: parse_name(&link.from)?,
+                from_orient: parse_orient(&link.from_orient)?,
+                to_segment: parse_name(&link.to)?,
+                to_orient: parse_orient(&link.to_orient)?,
+                overlap: check_overlap(link.overlap)?,
+                optional: tags_from_json(link.tags)?,
+            });
+        }
+
+        for cont in json.containments {
+            gfa.containments.push(Containment {
+                container_name: parse_name(&cont.container)?,
+                container_orient: parse_orient(&cont.container_orient)?,
+                contained_name: parse_name(&cont.contained)?,
+                contained_orient: parse_orient(&cont.contained_orient)?,
+                pos: cont.pos,
+                overlap: check_overlap(cont.overlap)?,
+                optional: tags_from_json(cont.tags)?,
+            });
+        }
+
+        for path in json.paths {
+            let mut segment_names = Vec::new();
+            for (i, step) in path.steps.iter().enumerate() {
+                parse_name::<N>(&step.segment)?;
+                parse_orient(&step.orient)?;
+                if i > 0 {
+                    segment_names.push(b',');
+                }
+                segment_names.extend(step.segment.bytes());
+                segment_names.extend(step.orient.bytes());
+            }
+            let overlaps = path
+                .overlaps
+                .into_iter()
+                .map(|overlap| match overlap {
+                    None => Ok(None),
+                    Some(overlap) => match parse_overlap(overlap.as_bytes()) {
+                        Ok(Some(cigar)) => Ok(Some(cigar)),
+                        _ => Err(JsonError::InvalidOverlap(overlap)),
+                    },
+                })
+                .collect::<JsonResult<_>>()?;
+            gfa.paths.push(Path::new(
+                path.name.into_bytes(),
+                segment_names,
+                overlaps,
+                tags_from_json(path.tags)?,
+            ));
+        }
+
+        for walk in json.walks {
+            let mut steps = Vec::new();
+            for step in walk.steps.iter() {
+                parse_name::<N>(&step.segment)?;
+                steps.push(parse_orient(&step.orient)?.gt_ln_as_byte());
+                steps.extend(step.segment.bytes());
+            }
+            gfa.walks.push(Walk::new(
+                walk.sample.into_bytes(),
+                walk.haplotype,
+                walk.sequence.into_bytes(),
+                walk.start,
+                walk.end,
+                steps,
+                tags_from_json(walk.tags)?,
+            ));
+        }
+
+        Ok(gfa)
+    }
+
+    /// Convert the graph to a JSON string, using the schema described
+    /// in the module documentation. Fails if a path or walk has a step
+    /// that can't be parsed.
+    pub fn to_json(&self) -> JsonResult<String> {
+        Ok(serde_json::to_string(&self.to_json_gfa()?)?)
+    }
+
+    /// Convert the graph to a JSON value. The tags of each line are
+    /// sorted, as `serde_json` maps don't keep the insertion order.
+    pub fn to_json_value(&self) -> JsonResult<Value> {
+        Ok(serde_json::to_value(self.to_json_gfa()?)?)
+    }
+
+    /// Build a graph from a JSON string that follows the schema
+    /// described in the module documentation.
+    pub fn from_json(json: &str) -> JsonResult<Self> {
+        Self::from_json_gfa(serde_json::from_str(json)?)
+    }
+
+    /// Build a graph from a JSON value that follows the schema
+    /// described in the module documentation.
+    pub fn from_json_value(json: Value) -> JsonResult<Self> {
+        Self::from_json_gfa(serde_json::from_value(json)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    #[test]
+    fn json_round_trip() {
+        let gfa: GFA<Vec<u8>, OptionalFields> = parse(&[
+            "H\tVN:Z:1.0",
+            "S\t1\tACGT\tSH:H:0AFF\tAB:B:c,1,-2\tXA:A:x",
+            "S\t2\t*\tLN:i:12\tXF:f:0.5",
+            "L\t1\t+\t2\t-\t4M\tXJ:J:{\"a\":[1,2]}",
+            "C\t1\t-\t2\t+\t3\t*",
+            "P\tp1\t1+,2-\t4M",
+        ]);
+
+        let json = gfa.to_json_value().unwrap();
+        assert_eq!(json["header"]["version"], "1.0");
+        assert_eq!(json["segments"][0]["name"], "1");
+        assert_eq!(json["segments"][0]["tags"]["SH"]["value"], "0AFF");
+        assert_eq!(json["segments"][0]["tags"]["AB"]["subtype"], "c");
+        assert_eq!(json["segments"][0]["tags"]["AB"]["value"][1], -2);
+        assert_eq!(json["segments"][1]["tags"]["LN"]["value"], 12);
+        assert_eq!(json["segments"][1]["tags"]["XF"]["value"], 0.5);
+        assert_eq!(json["links"][0]["to_orient"], "-");
+        assert_eq!(json["links"][0]["tags"]["XJ"]["value"]["a"][1], 2);
+        assert_eq!(json["containments"][0]["pos"], 3);
+        assert_eq!(json["paths"][0]["steps"][1]["segment"], "2");
+        assert_eq!(json["paths"][0]["steps"][1]["orient"], "-");
+
+        let back = GFA::from_json(&gfa.to_json().unwrap()).unwrap();
+        assert_eq!(back, gfa);
+
+        let usize_gfa: GFA<usize, ()> =
+            GFA::from_json(&gfa.to_json().unwrap()).unwrap();
+        assert_eq!(usize_gfa.links[0].to_segment, 2);
+    }
+
+    #[test]
+    fn invalid_json() {
+        let invalid = |json: &str| {
+            GFA::<usize, OptionalFields>::from_json(json).unwrap_err()
+        };
+
+        let err = invalid(
+            r#"{"header": {"version": null}, "segments": [{"name": "a", "sequence": "*"}]}"#,
+        );
+        assert!(matches!(err, JsonError::InvalidName(_)));
+
+        let err = invalid(
+            r#"{"header": {"version": null}, "links": [{"from": "1", "from_orient": "<", "to": "2", "to_orient": "+", "overlap": "*"}]}"#,
+        );
+        assert!(matches!(err, JsonError::InvalidOrientation(_)));
+
+        let err = invalid(
+            r#"{"header": {"version": null, "tags": {"LN": {"type": "i", "value": "x"}}}}"#,
+        );
+        assert!(matches!(err, JsonError::InvalidTag(_)));
+
+        let err = invalid(r#"{"segments": []}"#);
+        assert!(matches!(err, JsonError::Json(_)));
+    }
+
+    #[test]
+    fn malformed_steps_are_errors() {
+        let mut gfa: GFA<Vec<u8>, OptionalFields> =
+            parse(&["S\t1\tA", "P\tp1\t1+\t*"]);
+        gfa.paths[0].segment_names = b"1+,1".to_vec();
+        let err = gfa.to_json().unwrap_err();
+        assert!(matches!(err, JsonError::InvalidStep(ref s) if s == "1+,1"));
+
+        let mut gfa: GFA<Vec<u8>, OptionalFields> =
+            parse(&["S\t1\tA", "W\ts\t0\tc\t*\t*\t>1"]);
+        gfa.walks[0].walk = b">1<".to_vec();
+        assert!(matches!(
+            gfa.to_json_value(),
+            Err(JsonError::InvalidStep(_))
+        ));
+    }
+}
@@ -0,0 +1,267 @@
+use crate::optfields::*;
+
+use super::{
+    rename::{map_steps, map_walk_steps},
+    Path, SegmentId, GFA,
+};
+
+use bstr::ByteSlice;
+use fnv::FnvHashSet;
+
+use std::hash::Hash;
+
+/// How `GFA::merge` names the segments of the graph that's merged in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenamePolicy {
+    /// Keep the names of the incoming graph as they are.
+    Keep,
+    /// Prepend a prefix to the names of the segments and paths, and
+    /// to the sample IDs of the walks, of the incoming graph.
+    Prefix(String),
+    /// Add an offset to the names of the segments of the incoming
+    /// graph, which must be integers.
+    Offset(usize),
+}
+
+/// Errors produced by `GFA::merge`, which leave the graph unmodified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeError {
+    /// Segments or paths of the incoming graph, after renaming, have
+    /// the same names as ones already in the graph. Walks are
+    /// included in `paths` by their `Walk::name`.
+    NameCollision {
+        segments: Vec<String>,
+        paths: Vec<String>,
+    },
+    /// A name of the incoming graph couldn't be renamed, either
+    /// because it's not an integer when using an offset, or because
+    /// the renamed name isn't valid for the segment name type.
+    InvalidName(String),
+}
+
+impl std::fmt::Display for MergeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeError::NameCollision { segments, paths } => write!(
+                f,
+                "Name collisions between the graphs: segments [{}], paths [{}]",
+                segments.join(", "),
+                paths.join(", ")
+            ),
+            MergeError::InvalidName(name) => {
+                write!(f, "Can't rename segment {}", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+impl RenamePolicy {
+    fn rename_segment<N: SegmentId>(&self, name: &N) -> Result<N, MergeError> {
+        let old = name.display();
+        let new = match self {
+            RenamePolicy::Keep => old.clone(),
+            RenamePolicy::Prefix(prefix) => format!("{}{}", prefix, old),
+            RenamePolicy::Offset(offset) => match old.parse::<usize>() {
+                Ok(id) => (id + offset).to_string(),
+                Err(_) => return Err(MergeError::InvalidName(old)),
+            },
+        };
+        N::parse_id(new.as_bytes()).ok_or(MergeError::InvalidName(old))
+    }
+
+    fn rename_path(&self, name: &[u8]) -> Vec<u8> {
+        match self {
+            RenamePolicy::Prefix(prefix) => {
+                let mut new = prefix.clone().into_bytes();
+                new.extend_from_slice(name);
+                new
+            }
+            _ => name.to_vec(),
+        }
+    }
+}
+
+impl<N, T> GFA<N, T>
+where
+    N: SegmentId + Clone + Eq + Hash,
+    T: OptFields,
+{
+    /// Add the records of `other` to the graph, renaming its segments
+    /// paths, and walks according to `policy`, and updating its links,
+    /// containments, paths, and walks to match. The header optional fields
+    /// are merged with `OptFields::merge`.
+    ///
+    /// If any renamed segment or path has the same name as one that's
+    /// already in the graph, returns `MergeError::NameCollision` with
+    /// the colliding names, and the graph is left as it was.
+    pub fn merge(
+        &mut self,
+        mut other: GFA<N, T>,
+        policy: RenamePolicy,
+    ) -> Result<(), MergeError> {
+        for seg in other.segments.iter_mut() {
+            seg.name = policy.rename_segment(&seg.name)?;
+        }
+        for link in other.links.iter_mut() {
+            link.from_segment = policy.rename_segment(&link.from_segment)?;
+            link.to_segment = policy.rename_segment(&link.to_segment)?;
+        }
+        for cont in other.containments.iter_mut() {
+            cont.container_name =
+                policy.rename_segment(&cont.container_name)?;
+            cont.contained_name =
+                policy.rename_segment(&cont.contained_name)?;
+        }
+        let mut paths = Vec::with_capacity(other.paths.len());
+        for path in other.paths {
+            paths.push(Path::new(
+                policy.rename_path(&path.path_name),
+                map_steps(
+                    &path.segment_names,
+                    |name: &N| policy.rename_segment(name),
+                    |name| {
+                        let name = name.to_str_lossy().into_owned();
+                        Err(MergeError::InvalidName(name))
+                    },
+                )?,
+                path.overlaps,
+                path.optional,
+            ));
+        }
+        other.paths = paths;
+        for walk in other.walks.iter_mut() {
+            walk.sample_id = policy.rename_path(&walk.sample_id);
+            walk.walk = map_walk_steps(
+                &walk.walk,
+                |name: &N| policy.rename_segment(name),
+                |name| {
+                    let name = name.to_str_lossy().into_owned();
+                    Err(MergeError::InvalidName(name))
+                },
+            )?;
+        }
+
+        let names: FnvHashSet<&N> =
+            self.segments.iter().map(|s| &s.name).collect();
+        let segments: Vec<_> = other
+            .segments
+            .iter()
+            .filter(|s| names.contains(&s.name))
+            .map(|s| s.name.display())
+            .collect();
+
+        let path_names: FnvHashSet<_> =
+            self.path_refs().map(|p| p.name()).collect();
+        let paths: Vec<_> = other
+            .path_refs()
+            .map(|p| p.name())
+            .filter(|name| path_names.contains(name))
+            .map(|name| name.to_str_lossy().into_owned())
+            .collect();
+
+        if !segments.is_empty() || !paths.is_empty() {
+            return Err(MergeError::NameCollision { segments, paths });
+        }
+
+        self.header.optional.merge(other.header.optional);
+        self.segments.extend(other.segments);
+        self.links.extend(other.links);
+        self.containments.extend(other.containments);
+        self.paths.extend(other.paths);
+        self.walks.extend(other.walks);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    const LINES: [&str; 5] = [
+        "S\t1\tACGT",
+        "S\t2\tGG",
+        "L\t1\t+\t2\t-\t0M",
+        "C\t1\t+\t2\t+\t1\t2M",
+        "P\tp\t1+,2-\t0M",
+    ];
+
+    #[test]
+    fn merge_with_prefix() {
+        let mut gfa: GFA<Vec<u8>, OptionalFields> = parse(&LINES);
+        let other: GFA<Vec<u8>, OptionalFields> = gfa.clone();
+        let orig = gfa.clone();
+
+        let err = gfa.merge(other.clone(), RenamePolicy::Keep).unwrap_err();
+        assert_eq!(
+            err,
+            MergeError::NameCollision {
+                segments: vec!["1".into(), "2".into()],
+                paths: vec!["p".into()],
+            }
+        );
+        assert_eq!(gfa, orig);
+
+        gfa.merge(other, RenamePolicy::Prefix("b.".into())).unwrap();
+        assert_eq!(gfa.segments.len(), 4);
+        assert_eq!(gfa.segments[2].name, b"b.1");
+        assert_eq!(gfa.links[1].from_segment, b"b.1");
+        assert_eq!(gfa.links[1].to_segment, b"b.2");
+        assert_eq!(gfa.containments[1].contained_name, b"b.2");
+        assert_eq!(gfa.paths[1].path_name, b"b.p");
+        assert_eq!(gfa.paths[1].segment_names, b"b.1+,b.2-");
+        assert_eq!(gfa.duplicates().len(), 0);
+    }
+
+    #[test]
+    fn merge_with_offset() {
+        let mut gfa: GFA<usize, OptionalFields> = parse(&LINES);
+        let other: GFA<usize, OptionalFields> =
+            parse(&["S\t1\tA", "P\tq\t1-\t*"]);
+
+        let err = gfa.merge(other.clone(), RenamePolicy::Offset(1));
+        assert!(err.is_err());
+
+        gfa.merge(other, RenamePolicy::Offset(2)).unwrap();
+        assert_eq!(gfa.segments[2].name, 3);
+        assert_eq!(gfa.paths[1].segment_names, b"3-");
+
+        let mut gfa: GFA<Vec<u8>, OptionalFields> = parse(&LINES);
+        let other: GFA<Vec<u8>, OptionalFields> = parse(&["S\tx\tA"]);
+        assert_eq!(
+            gfa.merge(other, RenamePolicy::Offset(2)),
+            Err(MergeError::InvalidName("x".into()))
+        );
+    }
+
+    #[test]
+    fn merge_walks() {
+        let mut lines = LINES.to_vec();
+        lines.push("W\tHG1\t1\tchr1\t0\t4\t>1<2");
+        let mut gfa: GFA<Vec<u8>, OptionalFields> = parse(&lines);
+        let other: GFA<Vec<u8>, OptionalFields> = gfa.clone();
+
+        let err = gfa.merge(other.clone(), RenamePolicy::Keep).unwrap_err();
+        assert_eq!(
+            err,
+            MergeError::NameCollision {
+                segments: vec!["1".into(), "2".into()],
+                paths: vec!["p".into(), "HG1#1#chr1".into()],
+            }
+        );
+
+        gfa.merge(other, RenamePolicy::Prefix("b.".into())).unwrap();
+        assert_eq!(gfa.walks.len(), 2);
+        assert_eq!(gfa.walks[1].name(), b"b.HG1#1#chr1");
+        assert_eq!(gfa.walks[1].walk, b">b.1<b.2");
+
+        let mut gfa: GFA<usize, OptionalFields> = parse(&lines);
+        let other: GFA<usize, OptionalFields> =
+            parse(&["S\t1\tA", "W\tHG2\t1\tchr1\t*\t*\t<1"]);
+        gfa.merge(other, RenamePolicy::Offset(2)).unwrap();
+        assert_eq!(gfa.walks[1].sample_id, b"HG2");
+        assert_eq!(gfa.walks[1].walk, b"<3");
+    }
+}
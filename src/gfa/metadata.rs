@@ -0,0 +1,150 @@
+//! Typed views of the optional fields defined by the GFA1 spec for
+//! each line type. The views borrow the optional fields of a record
+//! and look up each tag when it's requested, so they work with any
+//! `OptFields` implementation.
+
+use crate::optfields::*;
+
+use super::{Containment, Link, Segment};
+
+use bstr::BStr;
+
+/// The spec-defined optional fields of a segment.
+#[derive(Debug, Clone)]
+pub struct SegmentMetadata<'a, T: OptFields> {
+    optional: &'a T,
+}
+
+impl<'a, T: OptFields> SegmentMetadata<'a, T> {
+    /// Segment length, from the LN tag.
+    pub fn length(&self) -> Option<usize> {
+        self.optional.get_typed(b"LN")
+    }
+
+    /// Read count, from the RC tag.
+    pub fn read_count(&self) -> Option<i64> {
+        self.optional.get_typed(b"RC")
+    }
+
+    /// Fragment count, from the FC tag.
+    pub fn fragment_count(&self) -> Option<i64> {
+        self.optional.get_typed(b"FC")
+    }
+
+    /// k-mer count, from the KC tag.
+    pub fn kmer_count(&self) -> Option<i64> {
+        self.optional.get_typed(b"KC")
+    }
+
+    /// SHA-256 checksum of the sequence, from the SH tag.
+    pub fn checksum(&self) -> Option<&'a [u8]> {
+        match &self.optional.get_field(b"SH")?.value {
+            OptFieldVal::H(checksum) => Some(checksum),
+            _ => None,
+        }
+    }
+
+    /// URI or local path of the sequence, from the UR tag.
+    pub fn uri(&self) -> Option<&'a BStr> {
+        self.optional.get_typed(b"UR")
+    }
+}
+
+/// The spec-defined optional fields of a link or containment.
+#[derive(Debug, Clone)]
+pub struct OverlapMetadata<'a, T: OptFields> {
+    optional: &'a T,
+}
+
+impl<'a, T: OptFields> OverlapMetadata<'a, T> {
+    /// Mapping quality, from the MQ tag.
+    pub fn mapping_quality(&self) -> Option<i64> {
+        self.optional.get_typed(b"MQ")
+    }
+
+    /// Number of mismatches and gaps, from the NM tag.
+    pub fn mismatches(&self) -> Option<i64> {
+        self.optional.get_typed(b"NM")
+    }
+
+    /// Read count, from the RC tag.
+    pub fn read_count(&self) -> Option<i64> {
+        self.optional.get_typed(b"RC")
+    }
+
+    /// Fragment count, from the FC tag.
+    pub fn fragment_count(&self) -> Option<i64> {
+        self.optional.get_typed(b"FC")
+    }
+
+    /// k-mer count, from the KC tag.
+    pub fn kmer_count(&self) -> Option<i64> {
+        self.optional.get_typed(b"KC")
+    }
+
+    /// Edge identifier, from the ID tag.
+    pub fn id(&self) -> Option<&'a BStr> {
+        self.optional.get_typed(b"ID")
+    }
+}
+
+impl<N, T: OptFields> Segment<N, T> {
+    pub fn metadata(&self) -> SegmentMetadata<'_, T> {
+        SegmentMetadata {
+            optional: &self.optional,
+        }
+    }
+}
+
+impl<N, T: OptFields> Link<N, T> {
+    pub fn metadata(&self) -> OverlapMetadata<'_, T> {
+        OverlapMetadata {
+            optional: &self.optional,
+        }
+    }
+}
+
+impl<N, T: OptFields> Containment<N, T> {
+    pub fn metadata(&self) -> OverlapMetadata<'_, T> {
+        OverlapMetadata {
+            optional: &self.optional,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{gfa::GFA, optfields::OptionalFields, test_util::parse};
+    use bstr::ByteSlice;
+
+    #[test]
+    fn record_metadata() {
+        let lines = [
+            "S\t1\t*\tLN:i:12\tRC:i:30\tUR:Z:file://a.fa\tSH:H:0AFF",
+            "S\t2\tACGT\tKC:Z:oops",
+            "L\t1\t+\t2\t-\t4M\tMQ:i:60\tNM:i:1\tID:Z:e1",
+            "C\t1\t+\t2\t+\t3\t4M\tRC:i:2",
+        ];
+        let gfa: GFA<Vec<u8>, OptionalFields> = parse(&lines);
+
+        let meta = gfa.segments[0].metadata();
+        assert_eq!(meta.length(), Some(12));
+        assert_eq!(meta.read_count(), Some(30));
+        assert_eq!(meta.uri(), Some(b"file://a.fa".as_bstr()));
+        assert_eq!(meta.checksum(), Some(&[0x0a, 0xff][..]));
+        assert_eq!(meta.fragment_count(), None);
+
+        // the KC tag has the wrong type
+        let meta = gfa.segments[1].metadata();
+        assert_eq!(meta.kmer_count(), None);
+        assert_eq!(meta.length(), None);
+
+        let meta = gfa.links[0].metadata();
+        assert_eq!(meta.mapping_quality(), Some(60));
+        assert_eq!(meta.mismatches(), Some(1));
+        assert_eq!(meta.id(), Some(b"e1".as_bstr()));
+        assert_eq!(meta.read_count(), None);
+
+        assert_eq!(gfa.containments[0].metadata().read_count(), Some(2));
+    }
+}
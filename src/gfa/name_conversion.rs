@@ -1,5 +1,5 @@
 use crate::{
-    gfa::{Containment, Link, Path, Segment, GFA},
+    gfa::{Containment, Link, Path, Segment, Walk, GFA},
     optfields::*,
 };
 
@@ -42,6 +42,10 @@ fn hash_gfa<T: OptFields>(gfa: &GFA<Vec<u8>, T>) -> u64 {
         path.path_name.hash(&mut hasher);
         path.segment_names.hash(&mut hasher);
     }
+    for walk in gfa.walks.iter() {
+        walk.name().hash(&mut hasher);
+        walk.walk.hash(&mut hasher);
+    }
 
     hasher.finish()
 }
@@ -57,6 +61,7 @@ pub struct NameMapString {
 }
 
 #[cfg(feature = "serde1")]
+#[cfg(feature = "fs")]
 impl NameMapString {
     fn from_name_map(map: &NameMap) -> Self {
         let name_map: FnvHashMap<String, usize> = map
@@ -114,6 +119,7 @@ pub struct NameMap {
 impl NameMap {
     /// Save the NameMap to a JSON file.
     #[cfg(feature = "serde1")]
+    #[cfg(feature = "fs")]
     pub fn save_json<P: AsRef<std::path::Path>>(
         &self,
         path: P,
@@ -128,6 +134,7 @@ impl NameMap {
 
     /// Load a NameMap from a JSON file.
     #[cfg(feature = "serde1")]
+    #[cfg(feature = "fs")]
     pub fn load_json<P: AsRef<std::path::Path>>(
         path: P,
     ) -> std::io::Result<Self> {
@@ -138,6 +145,24 @@ impl NameMap {
         Ok(name_map.into_name_map())
     }
 
+    /// The number of names in the map
+    pub fn len(&self) -> usize {
+        self.inverse_map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inverse_map.is_empty()
+    }
+
+    /// Produces an iterator over the (name, ID) pairs in the map, in
+    /// ID order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'_ [u8], usize)> {
+        self.inverse_map
+            .iter()
+            .enumerate()
+            .map(|(ix, name)| (name.as_slice(), ix))
+    }
+
     pub fn map_name<N: AsRef<[u8]>>(&self, name: N) -> Option<usize> {
         self.name_map.get(name.as_ref()).copied()
     }
@@ -183,6 +208,30 @@ impl NameMap {
         Some(new_path)
     }
 
+    fn map_walk_segments<T: OptFields>(
+        &self,
+        walk: &Walk<Vec<u8>, T>,
+    ) -> Option<Walk<usize, T>> {
+        let mut steps = Vec::with_capacity(walk.walk.len());
+        for (seg, o) in walk.iter() {
+            steps.push(o.gt_ln_as_byte());
+            steps.extend(self.map_name(seg)?.to_string().bytes());
+        }
+        Some(walk.with_walk(steps))
+    }
+
+    fn inverse_map_walk_segments<T: OptFields>(
+        &self,
+        walk: &Walk<usize, T>,
+    ) -> Option<Walk<Vec<u8>, T>> {
+        let mut steps = Vec::with_capacity(walk.walk.len());
+        for (seg, o) in walk.steps() {
+            steps.push(o.gt_ln_as_byte());
+            steps.extend_from_slice(self.inverse_map_name(seg)?);
+        }
+        Some(walk.with_walk(steps))
+    }
+
     fn inverse_map_path_segments<T: OptFields>(
         &self,
         path: &Path<usize, T>,
@@ -268,12 +317,19 @@ impl NameMap {
             paths.push(new_path);
         }
 
+        let walks = gfa
+            .walks
+            .iter()
+            .map(|walk| self.map_walk_segments(walk))
+            .collect::<Option<_>>()?;
+
         Some(GFA {
             header: gfa.header.clone(),
             segments,
             links,
             containments,
             paths,
+            walks,
         })
     }
 
@@ -317,15 +373,26 @@ impl NameMap {
             paths.push(new_path);
         }
 
+        let walks = gfa
+            .walks
+            .iter()
+            .map(|walk| self.inverse_map_walk_segments(walk))
+            .collect::<Option<_>>()?;
+
         Some(GFA {
             header: gfa.header.clone(),
             segments,
             links,
             containments,
             paths,
+            walks,
         })
     }
 
+    /// Build a NameMap containing every segment name in the GFA,
+    /// including names that only occur in links, containments, or
+    /// path and walk steps. IDs are assigned in the order the names
+    /// are found.
     pub fn build_from_gfa<T: OptFields>(gfa: &GFA<Vec<u8>, T>) -> Self {
         let mut name_map = FnvHashMap::default();
         let mut inverse_map = Vec::with_capacity(gfa.segments.len());
@@ -349,12 +416,22 @@ impl NameMap {
         }
         for link in gfa.links.iter() {
             get_ix(link.from_segment.as_ref());
-            get_ix(link.from_segment.as_ref());
+            get_ix(link.to_segment.as_ref());
         }
         for cont in gfa.containments.iter() {
             get_ix(cont.container_name.as_ref());
             get_ix(cont.contained_name.as_ref());
         }
+        for path in gfa.paths.iter() {
+            for (seg, _) in path.iter() {
+                get_ix(seg.as_ref());
+            }
+        }
+        for walk in gfa.walks.iter() {
+            for (seg, _) in walk.iter() {
+                get_ix(seg.as_ref());
+            }
+        }
 
         NameMap {
             name_map,
@@ -364,19 +441,36 @@ impl NameMap {
     }
 }
 
+impl<T: OptFields> GFA<Vec<u8>, T> {
+    /// Convert the GFA to one using usize segment IDs, returning the
+    /// NameMap that maps between the original names and the IDs
+    /// alongside it, so that the conversion can be reversed with
+    /// `NameMap::gfa_usize_to_bytestring`.
+    pub fn usize_names(&self) -> (GFA<usize, T>, NameMap) {
+        let name_map = NameMap::build_from_gfa(self);
+        let gfa = name_map
+            .gfa_bytestring_to_usize(self, false)
+            .expect("NameMap built from a GFA contains all of its names");
+        (gfa, name_map)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parser::GFAParser;
+    use crate::{gfa::Orientation, parser::GFAParser};
 
+    #[cfg(feature = "serde1")]
     fn diatom_name_map_path() -> &'static str {
         "./test/gfas/diatom_map.json"
     }
 
+    #[cfg(feature = "serde1")]
     fn lil_name_map_path() -> &'static str {
         "./test/gfas/lil_map.json"
     }
 
+    #[allow(clippy::needless_borrows_for_generic_args)]
     fn load_diatom_gfa() -> GFA<Vec<u8>, OptionalFields> {
         let parser = GFAParser::new();
         let gfa: GFA<Vec<u8>, OptionalFields> =
@@ -384,6 +478,7 @@ mod tests {
         gfa
     }
 
+    #[allow(clippy::needless_borrows_for_generic_args)]
     fn load_lil_gfa() -> GFA<Vec<u8>, OptionalFields> {
         let parser = GFAParser::new();
         let gfa: GFA<Vec<u8>, OptionalFields> =
@@ -391,6 +486,7 @@ mod tests {
         gfa
     }
 
+    #[allow(clippy::needless_borrow)]
     fn test_isomorphism(original_gfa: &GFA<Vec<u8>, OptionalFields>) {
         let name_map = NameMap::build_from_gfa(original_gfa);
 
@@ -412,6 +508,38 @@ mod tests {
         assert_eq!(original_gfa, &inverted_gfa);
     }
 
+    #[test]
+    fn name_map_includes_all_references() {
+        let parser = GFAParser::new();
+        let lines: Vec<&[u8]> = vec![
+            b"S\ta\tA",
+            b"L\ta\t+\tb\t-\t0M",
+            b"C\tc\t+\ta\t+\t0\t1M",
+            b"P\tp\ta+,d-\t*",
+        ];
+        let gfa: GFA<Vec<u8>, ()> = parser.parse_lines(lines.iter()).unwrap();
+
+        let (usize_gfa, name_map) = gfa.usize_names();
+        assert_eq!(name_map.len(), 4);
+
+        for name in [&b"a"[..], b"b", b"c", b"d"].iter() {
+            let id = name_map.map_name(name).unwrap();
+            assert_eq!(name_map.inverse_map_name(id), Some(*name));
+        }
+
+        let steps: Vec<_> = usize_gfa.paths[0].iter().collect();
+        assert_eq!(
+            steps,
+            vec![
+                (name_map.map_name(b"a").unwrap(), Orientation::Forward),
+                (name_map.map_name(b"d").unwrap(), Orientation::Backward),
+            ]
+        );
+
+        let inverted = name_map.gfa_usize_to_bytestring(&usize_gfa).unwrap();
+        assert_eq!(gfa, inverted);
+    }
+
     #[test]
     fn lil_name_map_isomorphism() {
         let original_gfa = load_lil_gfa();
@@ -0,0 +1,163 @@
+use crate::optfields::*;
+
+use super::{SegmentId, GFA};
+
+/// A piece of a name, for natural ordering. Runs of digits compare by
+/// their numeric value, and come before text, which compares
+/// bytewise; numbers that only differ in leading zeros are ordered
+/// by the number of zeros.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Chunk {
+    Number {
+        len: usize,
+        digits: Vec<u8>,
+        zeros: usize,
+    },
+    Text(Vec<u8>),
+}
+
+/// The sort key of a name in natural order, e.g. `s2` < `s10`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NaturalKey(Vec<Chunk>);
+
+impl NaturalKey {
+    pub fn new(name: &[u8]) -> Self {
+        let mut chunks = Vec::new();
+        let mut rest = name;
+        while let Some(first) = rest.first() {
+            let is_digit = first.is_ascii_digit();
+            let end = rest
+                .iter()
+                .position(|b| b.is_ascii_digit() != is_digit)
+                .unwrap_or(rest.len());
+            let (chunk, tail) = rest.split_at(end);
+            if is_digit {
+                let zeros = chunk.iter().take_while(|&&b| b == b'0').count();
+                let digits = chunk[zeros..].to_vec();
+                chunks.push(Chunk::Number {
+                    len: digits.len(),
+                    digits,
+                    zeros,
+                });
+            } else {
+                chunks.push(Chunk::Text(chunk.to_vec()));
+            }
+            rest = tail;
+        }
+        NaturalKey(chunks)
+    }
+}
+
+impl<N: SegmentId, T: OptFields> GFA<N, T> {
+    /// Put the GFA in a deterministic order, so that graphs with the
+    /// same records produce the same output when written. Segments are
+    /// sorted by name in natural order, links by their endpoints and
+    /// orientations, containments by their endpoints, orientations,
+    /// and position, paths by name, and walks by sample, haplotype,
+    /// sequence ID, and sequence range. Records that compare equal
+    /// keep their relative order, and the optional fields of every
+    /// record are sorted by tag.
+    ///
+    /// Links aren't reversed; use `canonicalize_links` first if links
+    /// and their reverses should be considered the same.
+    pub fn normalize(&mut self) {
+        let name_key = |name: &N| NaturalKey::new(name.display().as_bytes());
+
+        self.segments.sort_by_cached_key(|s| name_key(&s.name));
+        self.links.sort_by_cached_key(|l| {
+            (
+                name_key(&l.from_segment),
+                l.from_orient,
+                name_key(&l.to_segment),
+                l.to_orient,
+                l.overlap.clone(),
+            )
+        });
+        self.containments.sort_by_cached_key(|c| {
+            (
+                name_key(&c.container_name),
+                c.container_orient,
+                name_key(&c.contained_name),
+                c.contained_orient,
+                c.pos,
+                c.overlap.clone(),
+            )
+        });
+        self.paths
+            .sort_by_cached_key(|p| NaturalKey::new(&p.path_name));
+        self.walks.sort_by_cached_key(|w| {
+            (
+                NaturalKey::new(&w.sample_id),
+                w.hap_index,
+                NaturalKey::new(&w.seq_id),
+                w.seq_start,
+                w.seq_end,
+            )
+        });
+
+        self.header.optional.sort_by_tag();
+        self.segments
+            .iter_mut()
+            .for_each(|s| s.optional.sort_by_tag());
+        self.links.iter_mut().for_each(|l| l.optional.sort_by_tag());
+        self.containments
+            .iter_mut()
+            .for_each(|c| c.optional.sort_by_tag());
+        self.paths.iter_mut().for_each(|p| p.optional.sort_by_tag());
+        self.walks.iter_mut().for_each(|w| w.optional.sort_by_tag());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+    use crate::writer::gfa_string;
+
+    #[test]
+    fn natural_order() {
+        let mut names = vec!["s10", "s2", "a", "s02", "10", "9", "s2a"];
+        names.sort_by_key(|n| NaturalKey::new(n.as_bytes()));
+        assert_eq!(names, vec!["9", "10", "a", "s2", "s2a", "s02", "s10"]);
+    }
+
+    #[test]
+    fn normalized_graphs_are_identical() {
+        let mut a = parse(&[
+            "S\ts10\tA\tRC:i:1\tLN:i:1",
+            "S\ts2\tC",
+            "L\ts2\t+\ts10\t-\t0M",
+            "L\ts10\t+\ts2\t+\t0M",
+            "P\tp10\ts2+,s10-\t0M",
+            "P\tp9\ts10+\t*",
+            "W\tHG10\t1\tchr1\t*\t*\t>s2",
+            "W\tHG2\t2\tchr1\t0\t1\t>s10\tXB:i:1\tXA:i:1",
+            "W\tHG2\t1\tchr1\t0\t1\t>s10",
+        ]);
+        let mut b = parse(&[
+            "S\ts2\tC",
+            "S\ts10\tA\tLN:i:1\tRC:i:1",
+            "L\ts10\t+\ts2\t+\t0M",
+            "L\ts2\t+\ts10\t-\t0M",
+            "P\tp9\ts10+\t*",
+            "P\tp10\ts2+,s10-\t0M",
+            "W\tHG2\t1\tchr1\t0\t1\t>s10",
+            "W\tHG10\t1\tchr1\t*\t*\t>s2",
+            "W\tHG2\t2\tchr1\t0\t1\t>s10\tXA:i:1\tXB:i:1",
+        ]);
+        assert_ne!(gfa_string(&a), gfa_string(&b));
+
+        a.normalize();
+        b.normalize();
+        assert_eq!(gfa_string(&a), gfa_string(&b));
+        assert_eq!(a.segments[0].name, b"s2");
+        assert_eq!(a.segments[1].optional[0].tag, *b"LN");
+        assert_eq!(a.links[0].from_segment, b"s2");
+        assert_eq!(a.paths[0].path_name, b"p9");
+        let walks: Vec<_> = a.walks.iter().map(|w| w.name()).collect();
+        assert_eq!(
+            walks,
+            vec![&b"HG2#1#chr1"[..], b"HG2#2#chr1", b"HG10#1#chr1"]
+        );
+    }
+}
@@ -1,11 +1,7 @@
-#[cfg(feature = "serde1")]
-use serde::{Deserialize, Serialize};
-
 use crate::parser::ParseFieldError;
 
 /// Represents segment orientation/strand
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
 pub enum Orientation {
     Forward,
     Backward,
@@ -30,11 +26,7 @@ impl Orientation {
         &self,
         f: &mut std::fmt::Formatter<'_>,
     ) -> std::fmt::Result {
-        let sym = match self {
-            Self::Forward => '+',
-            Self::Backward => '-',
-        };
-        write!(f, "{}", sym)
+        write!(f, "{}", self.plus_minus_as_char())
     }
 
     pub fn plus_minus_as_byte(&self) -> u8 {
@@ -44,6 +36,11 @@ impl Orientation {
         }
     }
 
+    /// The orientation as used in GFA and PAF, `+` or `-`.
+    pub fn plus_minus_as_char(&self) -> char {
+        self.plus_minus_as_byte() as char
+    }
+
     /// Parse an orientation from a single-element bytestring, where >
     /// is Forward, < is Backward
     pub fn from_bytes_gt_ln<T: AsRef<[u8]>>(bs: T) -> Option<Self> {
@@ -58,11 +55,50 @@ impl Orientation {
         &self,
         f: &mut std::fmt::Formatter<'_>,
     ) -> std::fmt::Result {
-        let sym = match self {
-            Self::Forward => '>',
-            Self::Backward => '<',
-        };
-        write!(f, "{}", sym)
+        write!(f, "{}", self.gt_ln_as_char())
+    }
+
+    pub fn gt_ln_as_byte(&self) -> u8 {
+        match self {
+            Self::Forward => b'>',
+            Self::Backward => b'<',
+        }
+    }
+
+    /// The orientation as used in the steps of GAF paths, `>` or `<`.
+    pub fn gt_ln_as_char(&self) -> char {
+        self.gt_ln_as_byte() as char
+    }
+
+    /// Parse an orientation from a single byte in either notation,
+    /// i.e. + or > is Forward, - or < is Backward
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use gfa::gfa::Orientation as O;
+    ///
+    /// assert_eq!(O::from_byte(b'<'), Some(O::Backward));
+    /// assert_eq!(O::from_byte(b'+'), Some(O::Forward));
+    /// assert_eq!(O::from_byte(b'*'), None);
+    /// assert_eq!(O::from_bytes(">"), Some(O::Forward));
+    /// assert_eq!(O::Backward.gt_ln_as_char(), '<');
+    /// ```
+    pub fn from_byte(b: u8) -> Option<Self> {
+        match b {
+            b'+' | b'>' => Some(Orientation::Forward),
+            b'-' | b'<' => Some(Orientation::Backward),
+            _ => None,
+        }
+    }
+
+    /// Parse an orientation from a single-element bytestring in either
+    /// notation, as `from_byte`
+    pub fn from_bytes<T: AsRef<[u8]>>(bs: T) -> Option<Self> {
+        match bs.as_ref() {
+            [b] => Self::from_byte(*b),
+            _ => None,
+        }
     }
 }
 
@@ -83,10 +119,63 @@ impl From<Orientation> for bool {
     }
 }
 
+/// Forward is true, backward is false
+impl From<bool> for Orientation {
+    fn from(forward: bool) -> Orientation {
+        if forward {
+            Orientation::Forward
+        } else {
+            Orientation::Backward
+        }
+    }
+}
+
+/// Parse an orientation from `+` or `-`, as in GFA.
+impl std::convert::TryFrom<char> for Orientation {
+    type Error = ParseFieldError;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            '+' => Ok(Orientation::Forward),
+            '-' => Ok(Orientation::Backward),
+            _ => Err(ParseFieldError::OrientationError),
+        }
+    }
+}
+
+/// Negation flips the orientation.
+///
+/// # Examples
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use gfa::gfa::Orientation as O;
+///
+/// assert_eq!(!O::Forward, O::Backward);
+/// assert_eq!(O::from(false), O::Backward);
+/// assert_eq!(O::try_from('-').ok(), Some(O::Backward));
+/// assert!(O::try_from('>').is_err());
+/// ```
+impl std::ops::Not for Orientation {
+    type Output = Orientation;
+
+    fn not(self) -> Orientation {
+        self.flip()
+    }
+}
+
 impl Orientation {
     pub fn is_reverse(&self) -> bool {
         !bool::from(*self)
     }
+
+    /// Return the opposite orientation.
+    pub fn flip(&self) -> Self {
+        match self {
+            Self::Forward => Self::Backward,
+            Self::Backward => Self::Forward,
+        }
+    }
 }
 
 /// The default parser uses the GFA spec with + as Forward, - as Backward
@@ -124,3 +213,31 @@ impl std::fmt::Display for Orientation {
         }
     }
 }
+
+/// Orientations are serialized as `+` and `-`, as in GFA files.
+#[cfg(feature = "serde1")]
+impl serde::Serialize for Orientation {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let sym = match self {
+            Self::Forward => "+",
+            Self::Backward => "-",
+        };
+        serializer.serialize_str(sym)
+    }
+}
+
+#[cfg(feature = "serde1")]
+impl<'de> serde::Deserialize<'de> for Orientation {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        use serde::de::{Error, Unexpected};
+        let sym = String::deserialize(deserializer)?;
+        Orientation::from_bytes_plus_minus(&sym).ok_or_else(|| {
+            D::Error::invalid_value(Unexpected::Str(&sym), &"+ or -")
+        })
+    }
+}
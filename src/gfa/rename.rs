@@ -0,0 +1,140 @@
+use crate::optfields::*;
+
+use super::{raw_walk_steps, SegmentId, GFA};
+
+use bstr::ByteSlice;
+
+use std::convert::Infallible;
+
+/// Rewrite the segment names in the step list of a path, keeping the
+/// orientations. Steps whose names can't be parsed as `N` are passed
+/// to `invalid`, which can either produce an error or keep them as
+/// they are.
+pub(crate) fn map_steps<N, E, F, G>(
+    steps: &[u8],
+    mut f: F,
+    mut invalid: G,
+) -> Result<Vec<u8>, E>
+where
+    N: SegmentId,
+    F: FnMut(&N) -> Result<N, E>,
+    G: FnMut(&[u8]) -> Result<(), E>,
+{
+    let mut renamed = Vec::with_capacity(steps.len());
+    for (i, step) in steps.split_str(b",").enumerate() {
+        if i > 0 {
+            renamed.push(b',');
+        }
+        let (name, orient) = step.split_at(step.len().saturating_sub(1));
+        match N::parse_id(name) {
+            Some(name) => renamed.extend(f(&name)?.display().bytes()),
+            None => {
+                invalid(name)?;
+                renamed.extend_from_slice(name);
+            }
+        }
+        renamed.extend_from_slice(orient);
+    }
+    Ok(renamed)
+}
+
+/// Rewrite the segment names in the steps of a walk, as `map_steps`
+/// does for a path, keeping the `>` and `<` orientations.
+pub(crate) fn map_walk_steps<N, E, F, G>(
+    walk: &[u8],
+    mut f: F,
+    mut invalid: G,
+) -> Result<Vec<u8>, E>
+where
+    N: SegmentId,
+    F: FnMut(&N) -> Result<N, E>,
+    G: FnMut(&[u8]) -> Result<(), E>,
+{
+    let mut renamed = Vec::with_capacity(walk.len());
+    for step in raw_walk_steps(walk) {
+        let (orient, name) = step.split_at(1);
+        renamed.extend_from_slice(orient);
+        match N::parse_id(name) {
+            Some(name) => renamed.extend(f(&name)?.display().bytes()),
+            None => {
+                invalid(name)?;
+                renamed.extend_from_slice(name);
+            }
+        }
+    }
+    Ok(renamed)
+}
+
+impl<N: SegmentId, T: OptFields> GFA<N, T> {
+    /// Rename every segment with the given function, and update the
+    /// links, containments, path step lists, and walks to use the new
+    /// names. Path and walk steps whose names can't be parsed as `N`
+    /// are kept as they are.
+    pub fn rename_segments<F>(&mut self, f: F)
+    where
+        F: Fn(&N) -> N,
+    {
+        for seg in self.segments.iter_mut() {
+            seg.name = f(&seg.name);
+        }
+        for link in self.links.iter_mut() {
+            link.from_segment = f(&link.from_segment);
+            link.to_segment = f(&link.to_segment);
+        }
+        for cont in self.containments.iter_mut() {
+            cont.container_name = f(&cont.container_name);
+            cont.contained_name = f(&cont.contained_name);
+        }
+        for path in self.paths.iter_mut() {
+            let steps: Result<_, Infallible> = map_steps(
+                &path.segment_names,
+                |name: &N| Ok(f(name)),
+                |_| Ok(()),
+            );
+            path.segment_names = steps.unwrap_or_else(|never| match never {});
+        }
+        for walk in self.walks.iter_mut() {
+            let steps: Result<_, Infallible> =
+                map_walk_steps(&walk.walk, |name: &N| Ok(f(name)), |_| Ok(()));
+            walk.walk = steps.unwrap_or_else(|never| match never {});
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{gfa::GFA, test_util::parse};
+
+    #[test]
+    fn rename_all_references() {
+        let lines = [
+            "S\t1\tACGT",
+            "S\t22\tGG",
+            "L\t1\t+\t22\t-\t0M",
+            "C\t22\t+\t1\t+\t1\t2M",
+            "P\tp\t1+,22-,1+\t0M,0M",
+            "W\tHG1\t1\tchr1\t0\t10\t>1<22>1",
+        ];
+        let mut gfa: GFA<Vec<u8>, ()> = parse(&lines);
+
+        gfa.rename_segments(|name| {
+            let mut new = b"s".to_vec();
+            new.extend_from_slice(name);
+            new
+        });
+
+        assert_eq!(gfa.segments[1].name, b"s22");
+        assert_eq!(gfa.links[0].from_segment, b"s1");
+        assert_eq!(gfa.links[0].to_segment, b"s22");
+        assert_eq!(gfa.containments[0].container_name, b"s22");
+        assert_eq!(gfa.containments[0].contained_name, b"s1");
+        assert_eq!(gfa.paths[0].segment_names, b"s1+,s22-,s1+");
+        assert_eq!(gfa.walks[0].walk, b">s1<s22>s1");
+
+        let mut gfa: GFA<usize, ()> = parse(&lines);
+        gfa.rename_segments(|id| id * 10);
+        assert_eq!(gfa.links[0].to_segment, 220);
+        assert_eq!(gfa.paths[0].segment_names, b"10+,220-,10+");
+        assert_eq!(gfa.walks[0].walk, b">10<220>10");
+    }
+}
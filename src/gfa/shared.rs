@@ -0,0 +1,218 @@
+use crate::optfields::*;
+
+use super::{Segment, GFA};
+
+use bstr::ByteSlice;
+
+use std::{fmt, hash::Hash, ops::Range, sync::Arc};
+
+/// A slice of a reference-counted buffer. Cloning only increments the
+/// reference count, so the bytes are never copied.
+#[derive(Clone)]
+pub struct SharedBytes {
+    buffer: Arc<[u8]>,
+    range: Range<usize>,
+}
+
+impl SharedBytes {
+    /// Take ownership of the bytes, as a slice of the whole buffer.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        let range = 0..bytes.len();
+        SharedBytes {
+            buffer: bytes.into(),
+            range,
+        }
+    }
+
+    /// The given range of this slice, sharing the same buffer.
+    /// Returns `None` if the range is out of bounds.
+    pub fn slice(&self, range: Range<usize>) -> Option<SharedBytes> {
+        if range.start > range.end || range.end > self.len() {
+            return None;
+        }
+        let start = self.range.start + range.start;
+        Some(SharedBytes {
+            buffer: self.buffer.clone(),
+            range: start..start + range.len(),
+        })
+    }
+}
+
+impl std::ops::Deref for SharedBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buffer[self.range.clone()]
+    }
+}
+
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl PartialEq for SharedBytes {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for SharedBytes {}
+
+impl PartialEq<[u8]> for SharedBytes {
+    fn eq(&self, other: &[u8]) -> bool {
+        **self == *other
+    }
+}
+
+impl Hash for SharedBytes {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl fmt::Debug for SharedBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_bstr())
+    }
+}
+
+impl Default for SharedBytes {
+    fn default() -> Self {
+        SharedBytes::new(Vec::new())
+    }
+}
+
+impl From<Vec<u8>> for SharedBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        SharedBytes::new(bytes)
+    }
+}
+
+impl PartialOrd for SharedBytes {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SharedBytes {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+/// A GFA whose segment sequences are slices of a single shared
+/// buffer, so that cloning it or taking subgraphs of it, e.g. with
+/// `graph::neighborhood_subgraph`, never copies a sequence.
+pub type SharedGFA<N, T> = GFA<N, T, SharedBytes>;
+
+impl<N, T: OptFields> GFA<N, T> {
+    /// Move the segment sequences into a single shared buffer.
+    pub fn into_shared(self) -> SharedGFA<N, T> {
+        let total = self.segments.iter().map(|s| s.sequence.len()).sum();
+        let mut buffer = Vec::with_capacity(total);
+        let mut ranges = Vec::with_capacity(self.segments.len());
+        for segment in self.segments.iter() {
+            let start = buffer.len();
+            buffer.extend_from_slice(&segment.sequence);
+            ranges.push(start..buffer.len());
+        }
+        let buffer = SharedBytes::new(buffer);
+
+        let segments = self
+            .segments
+            .into_iter()
+            .zip(ranges)
+            .map(|(segment, range)| Segment {
+                name: segment.name,
+                sequence: SharedBytes {
+                    buffer: buffer.buffer.clone(),
+                    range,
+                },
+                optional: segment.optional,
+            })
+            .collect();
+
+        GFA {
+            header: self.header,
+            segments,
+            links: self.links,
+            containments: self.containments,
+            paths: self.paths,
+            walks: self.walks,
+        }
+    }
+}
+
+impl<N, T: OptFields> SharedGFA<N, T> {
+    /// Copy the segment sequences into owned vectors, e.g. to use
+    /// the functions that only take a `GFA<N, T>`.
+    pub fn into_owned(self) -> GFA<N, T> {
+        let segments = self
+            .segments
+            .into_iter()
+            .map(|segment| Segment {
+                name: segment.name,
+                sequence: segment.sequence.to_vec(),
+                optional: segment.optional,
+            })
+            .collect();
+
+        GFA {
+            header: self.header,
+            segments,
+            links: self.links,
+            containments: self.containments,
+            paths: self.paths,
+            walks: self.walks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graph::{neighborhood_subgraph, Radius},
+        parser::GFAParser,
+        writer::write_gfa,
+    };
+
+    #[test]
+    fn shared_sequences_round_trip() {
+        let parser: GFAParser<Vec<u8>, OptionalFields> = GFAParser::new();
+        let original = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+
+        let shared = original.clone().into_shared();
+        let copy = shared.clone();
+        for (a, b) in shared.segments.iter().zip(copy.segments.iter()) {
+            assert!(std::ptr::eq(&a.sequence[..], &b.sequence[..]));
+        }
+
+        let seeds = [original.segments[0].name.clone()];
+        let sub = neighborhood_subgraph(&shared, &seeds, Radius::Hops(1));
+        let owned_sub =
+            neighborhood_subgraph(&original, &seeds, Radius::Hops(1));
+        assert_eq!(sub.segments.len(), owned_sub.segments.len());
+        let first = &sub.segments[0].sequence;
+        assert!(std::ptr::eq(&first[..], &shared.segments[0].sequence[..]));
+
+        let (mut written, mut expected) = (String::new(), String::new());
+        write_gfa(&shared, &mut written).unwrap();
+        write_gfa(&original, &mut expected).unwrap();
+        assert_eq!(written, expected);
+
+        assert_eq!(shared.into_owned(), original);
+    }
+
+    #[test]
+    fn shared_bytes_slices() {
+        let bytes = SharedBytes::new(b"ACGTACGT".to_vec());
+        let sub = bytes.slice(2..6).unwrap();
+        assert_eq!(&*sub, b"GTAC");
+        assert_eq!(&*sub.slice(1..3).unwrap(), b"TA");
+        assert!(sub.slice(3..5).is_none());
+        assert_eq!(format!("{:?}", sub), "\"GTAC\"");
+    }
+}
@@ -1,14 +1,51 @@
 use crate::parser::ParseFieldError;
 
-use bstr::ByteSlice;
+use bstr::{BString, ByteSlice};
 use lazy_static::lazy_static;
 use regex::bytes::Regex;
 
-/// Trait for the types that can be parsed and used as segment IDs;
-/// will probably only be usize and Vec<u8>.
+use std::{fmt::Write, sync::Arc};
+
+/// Trait for the types that can be parsed and used as segment IDs,
+/// i.e. as the `N` parameter of `GFA<N, T>` and the line types.
+///
+/// Implementations are provided for the unsigned integers, for
+/// `Vec<u8>`, `BString`, `String`, and `Arc<str>`, but the trait can
+/// be implemented for any other name type, such as an interned
+/// handle, to use it with the parser and writer. Only `parse_id` and
+/// `display` are required; `write_id` should be overridden if the ID
+/// can be written without allocating.
+///
+/// # Examples
+///
+/// ```
+/// use gfa::{gfa::SegmentId, parser::{GFAParser, ParseFieldError}};
+///
+/// #[derive(Default, Debug, Clone, PartialEq)]
+/// struct Name(Box<str>);
+///
+/// impl SegmentId for Name {
+///     const ERROR: ParseFieldError = ParseFieldError::Utf8Error;
+///
+///     fn parse_id(input: &[u8]) -> Option<Self> {
+///         let name = std::str::from_utf8(input).ok()?;
+///         Some(Name(name.into()))
+///     }
+///
+///     fn display(&self) -> String {
+///         self.0.to_string()
+///     }
+/// }
+///
+/// let parser: GFAParser<Name, ()> = GFAParser::new();
+/// let gfa = parser.parse_lines([&b"S\tseg1\tACGT"[..]].iter()).unwrap();
+/// assert_eq!(gfa.segments[0].name, Name("seg1".into()));
+/// ```
 pub trait SegmentId: Sized + Default {
+    /// The error to report when `parse_id` fails.
     const ERROR: ParseFieldError;
 
+    /// Parse an ID from the bytes of a GFA field.
     fn parse_id(input: &[u8]) -> Option<Self>;
 
     fn parse_next<I>(mut input: I) -> Result<Self, ParseFieldError>
@@ -20,33 +57,112 @@ pub trait SegmentId: Sized + Default {
         Self::parse_id(next.as_ref()).ok_or(Self::ERROR)
     }
 
+    /// Produce the ID as it should be written in a GFA file.
     fn display(&self) -> String;
+
+    /// Write the ID as it should appear in a GFA file. This is what
+    /// the writer uses; the default implementation uses `display`.
+    fn write_id<W: Write>(&self, stream: &mut W) -> std::fmt::Result {
+        stream.write_str(&self.display())
+    }
+}
+
+macro_rules! impl_uint_segment_id {
+    ($($uint:ty),*) => {
+        $(
+            impl SegmentId for $uint {
+                const ERROR: ParseFieldError = ParseFieldError::UintIdError;
+
+                fn parse_id(input: &[u8]) -> Option<Self> {
+                    input.to_str().ok()?.parse::<$uint>().ok()
+                }
+
+                fn display(&self) -> String {
+                    self.to_string()
+                }
+
+                fn write_id<W: Write>(
+                    &self,
+                    stream: &mut W,
+                ) -> std::fmt::Result {
+                    write!(stream, "{}", self)
+                }
+            }
+        )*
+    };
+}
+
+impl_uint_segment_id!(usize, u32, u64);
+
+/// Find the part of a GFA field that is a valid segment or path
+/// name, as stored by the byte string `SegmentId`s.
+pub(crate) fn find_name(input: &[u8]) -> Option<&[u8]> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"(?-u)[!-)+-<>-~][!-~]*").unwrap();
+    }
+    RE.find(input).map(|s| s.as_bytes())
+}
+
+impl SegmentId for Vec<u8> {
+    const ERROR: ParseFieldError = ParseFieldError::Utf8Error;
+
+    fn parse_id(input: &[u8]) -> Option<Self> {
+        find_name(input).map(Vec::from)
+    }
+
+    fn display(&self) -> String {
+        self.as_bstr().to_string()
+    }
+
+    fn write_id<W: Write>(&self, stream: &mut W) -> std::fmt::Result {
+        write!(stream, "{}", self.as_bstr())
+    }
 }
 
-impl SegmentId for usize {
-    const ERROR: ParseFieldError = ParseFieldError::UintIdError;
+impl SegmentId for BString {
+    const ERROR: ParseFieldError = ParseFieldError::Utf8Error;
 
     fn parse_id(input: &[u8]) -> Option<Self> {
-        input.to_str().ok()?.parse::<usize>().ok()
+        Vec::<u8>::parse_id(input).map(BString::from)
     }
 
     fn display(&self) -> String {
         self.to_string()
     }
+
+    fn write_id<W: Write>(&self, stream: &mut W) -> std::fmt::Result {
+        write!(stream, "{}", self)
+    }
 }
 
-impl SegmentId for Vec<u8> {
+impl SegmentId for String {
     const ERROR: ParseFieldError = ParseFieldError::Utf8Error;
 
     fn parse_id(input: &[u8]) -> Option<Self> {
-        lazy_static! {
-            static ref RE: Regex =
-                Regex::new(r"(?-u)[!-)+-<>-~][!-~]*").unwrap();
-        }
-        RE.find(input).map(|s| Vec::from(s.as_bytes()))
+        String::from_utf8(Vec::<u8>::parse_id(input)?).ok()
     }
 
     fn display(&self) -> String {
-        self.as_bstr().to_string()
+        self.clone()
+    }
+
+    fn write_id<W: Write>(&self, stream: &mut W) -> std::fmt::Result {
+        stream.write_str(self)
+    }
+}
+
+impl SegmentId for Arc<str> {
+    const ERROR: ParseFieldError = ParseFieldError::Utf8Error;
+
+    fn parse_id(input: &[u8]) -> Option<Self> {
+        String::parse_id(input).map(Arc::from)
+    }
+
+    fn display(&self) -> String {
+        self.to_string()
+    }
+
+    fn write_id<W: Write>(&self, stream: &mut W) -> std::fmt::Result {
+        stream.write_str(self)
     }
 }
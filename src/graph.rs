@@ -0,0 +1,288 @@
+//! Algorithms over the graph formed by the segments and links of a
+//! GFA. Segments are identified by their index in `GFA::segments`.
+
+pub mod bluntify;
+pub mod bubbles;
+pub mod subgraph;
+pub mod topo;
+pub mod transitive;
+pub mod unchop;
+pub mod variants;
+
+pub use self::bluntify::*;
+pub use self::bubbles::*;
+pub use self::subgraph::*;
+pub use self::topo::*;
+pub use self::transitive::*;
+pub use self::unchop::*;
+pub use self::variants::*;
+
+use crate::{
+    cigar::CIGAROp,
+    gfa::{parse_overlap, Orientation, SegmentId, GFA},
+    optfields::*,
+};
+
+use fnv::FnvHashMap;
+
+use std::hash::Hash;
+
+/// A segment index and the orientation it's traversed in.
+pub(crate) type Handle = (usize, Orientation);
+
+/// A side of a segment, as the segment index and whether it's the
+/// end, i.e. the side a forward traversal leaves through.
+pub(crate) type Side = (usize, bool);
+
+pub(crate) fn flip((seg, orient): Handle) -> Handle {
+    (seg, orient.flip())
+}
+
+pub(crate) fn out_side((seg, orient): Handle) -> Side {
+    (seg, orient == Orientation::Forward)
+}
+
+pub(crate) fn in_side((seg, orient): Handle) -> Side {
+    (seg, orient == Orientation::Backward)
+}
+
+/// The length of an overlap that consists only of matches, with `*`
+/// treated as a blunt end. `None` for any other overlap.
+pub(crate) fn match_overlap(overlap: &[u8]) -> Option<usize> {
+    match parse_overlap(overlap).ok()? {
+        None => Some(0),
+        Some(cigar) => {
+            let all_matches =
+                cigar.iter().all(|op| matches!(op, CIGAROp::M | CIGAROp::E));
+            if all_matches {
+                Some(cigar.query_len())
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Map each segment name to the index of the first segment with
+/// that name.
+pub(crate) fn segment_indices<N, T, S>(
+    gfa: &GFA<N, T, S>,
+) -> FnvHashMap<&N, usize>
+where
+    N: Eq + Hash,
+    T: OptFields,
+{
+    let mut indices = FnvHashMap::default();
+    for (ix, seg) in gfa.segments.iter().enumerate() {
+        indices.entry(&seg.name).or_insert(ix);
+    }
+    indices
+}
+
+/// Map each segment name, as written in GFA and GAF files, to the
+/// index of the first segment with that name.
+pub(crate) fn segment_name_indices<N, T, S>(
+    gfa: &GFA<N, T, S>,
+) -> FnvHashMap<Vec<u8>, usize>
+where
+    N: SegmentId,
+    T: OptFields,
+{
+    let mut indices = FnvHashMap::default();
+    for (ix, seg) in gfa.segments.iter().enumerate() {
+        indices.entry(seg.name.display().into_bytes()).or_insert(ix);
+    }
+    indices
+}
+
+/// Build the sub-GFA with the header and the segments marked in
+/// `keep`, plus the links and containments between them. Paths and
+/// walks are not included.
+pub(crate) fn induced_subgraph<N, T, S>(
+    gfa: &GFA<N, T, S>,
+    keep: &[bool],
+) -> GFA<N, T, S>
+where
+    N: Eq + Hash + Clone,
+    T: OptFields,
+    S: Clone,
+{
+    let indices = segment_indices(gfa);
+    let kept = |name: &N| indices.get(name).map(|&ix| keep[ix]) == Some(true);
+
+    GFA {
+        header: gfa.header.clone(),
+        segments: gfa
+            .segments
+            .iter()
+            .zip(keep.iter())
+            .filter(|(_, &keep)| keep)
+            .map(|(seg, _)| seg.clone())
+            .collect(),
+        links: gfa
+            .links
+            .iter()
+            .filter(|l| kept(&l.from_segment) && kept(&l.to_segment))
+            .cloned()
+            .collect(),
+        containments: gfa
+            .containments
+            .iter()
+            .filter(|c| kept(&c.container_name) && kept(&c.contained_name))
+            .cloned()
+            .collect(),
+        paths: Vec::new(),
+        walks: Vec::new(),
+    }
+}
+
+/// Choose an orientation for every segment so that each link joins
+/// two segments with the same orientation once the segments are
+/// flipped accordingly, as when sorting a graph with inversions. In
+/// each connected component, as few segments as possible are
+/// reversed, and ties keep the first segment forward. Returns
+/// the index of a link that conflicts with the others if there's no
+/// such choice, e.g. when a segment is traversed in both orientations
+/// between the same two segments. Links that refer to missing
+/// segments are ignored.
+pub(crate) fn orient_segments<N, T, S>(
+    gfa: &GFA<N, T, S>,
+) -> Result<Vec<Orientation>, usize>
+where
+    N: Eq + Hash,
+    T: OptFields,
+{
+    let indices = segment_indices(gfa);
+    let count = gfa.segments.len();
+    // the other segment of each link, the link, and whether the link
+    // has mixed orientations
+    let mut adjacent: Vec<Vec<(usize, usize, bool)>> = vec![Vec::new(); count];
+    for (link_ix, link) in gfa.links.iter().enumerate() {
+        let from = indices.get(&link.from_segment);
+        let to = indices.get(&link.to_segment);
+        if let (Some(&from), Some(&to)) = (from, to) {
+            let mixed = link.from_orient != link.to_orient;
+            adjacent[from].push((to, link_ix, mixed));
+            adjacent[to].push((from, link_ix, mixed));
+        }
+    }
+
+    let mut orients: Vec<Option<Orientation>> = vec![None; count];
+    let mut stack = Vec::new();
+    for root in 0..count {
+        if orients[root].is_some() {
+            continue;
+        }
+        orients[root] = Some(Orientation::Forward);
+        stack.push(root);
+        let mut component = Vec::new();
+        while let Some(v) = stack.pop() {
+            component.push(v);
+            let orient = orients[v].unwrap();
+            for &(u, link, mixed) in adjacent[v].iter() {
+                let wanted = if mixed { orient.flip() } else { orient };
+                match orients[u] {
+                    Some(o) if o != wanted => return Err(link),
+                    Some(_) => (),
+                    None => {
+                        orients[u] = Some(wanted);
+                        stack.push(u);
+                    }
+                }
+            }
+        }
+
+        let reversed = component
+            .iter()
+            .filter(|&&v| orients[v] == Some(Orientation::Backward))
+            .count();
+        if 2 * reversed > component.len() {
+            for v in component {
+                orients[v] = orients[v].map(|o| o.flip());
+            }
+        }
+    }
+
+    Ok(orients.into_iter().map(Option::unwrap).collect())
+}
+
+/// Directed view of the segment graph. Each link becomes an edge
+/// from its `from` segment to its `to` segment, except for links
+/// where both orientations are reverse, which are the reverse of a
+/// forward link and become an edge from `to` to `from`. Links that
+/// refer to missing segments are left out.
+///
+/// A `+`/`-` or `-`/`+` link has no direction in this view, and
+/// becomes an edge from `from` to `to`, which is only meaningful when
+/// the graph is traversed regardless of direction; `from_oriented`
+/// flips the segments first so that there are no such links.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SegmentGraph {
+    /// Outgoing edges of each segment, as (target, link index).
+    pub(crate) succ: Vec<Vec<(usize, usize)>>,
+    /// Incoming edges of each segment, as (source, link index).
+    pub(crate) pred: Vec<Vec<(usize, usize)>>,
+}
+
+impl SegmentGraph {
+    pub(crate) fn from_gfa<N, T, S>(gfa: &GFA<N, T, S>) -> Self
+    where
+        N: Eq + Hash,
+        T: OptFields,
+    {
+        let orients = vec![Orientation::Forward; gfa.segments.len()];
+        Self::from_oriented(gfa, &orients)
+    }
+
+    /// Build the segment graph as if each segment with a reverse
+    /// orientation in `orients` were flipped, e.g. with the
+    /// orientations chosen by `orient_segments`.
+    pub(crate) fn from_oriented<N, T, S>(
+        gfa: &GFA<N, T, S>,
+        orients: &[Orientation],
+    ) -> Self
+    where
+        N: Eq + Hash,
+        T: OptFields,
+    {
+        let indices = segment_indices(gfa);
+        let count = gfa.segments.len();
+        let mut graph = SegmentGraph {
+            succ: vec![Vec::new(); count],
+            pred: vec![Vec::new(); count],
+        };
+
+        let oriented = |seg: usize, orient: Orientation| match orients[seg] {
+            Orientation::Forward => orient,
+            Orientation::Backward => orient.flip(),
+        };
+        for (link_ix, link) in gfa.links.iter().enumerate() {
+            let from = indices.get(&link.from_segment);
+            let to = indices.get(&link.to_segment);
+            if let (Some(&from), Some(&to)) = (from, to) {
+                let both_reverse = oriented(from, link.from_orient)
+                    == Orientation::Backward
+                    && oriented(to, link.to_orient) == Orientation::Backward;
+                let (from, to) =
+                    if both_reverse { (to, from) } else { (from, to) };
+                graph.succ[from].push((to, link_ix));
+                graph.pred[to].push((from, link_ix));
+            }
+        }
+
+        graph
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.succ.len()
+    }
+
+    /// Iterate over all edges as (source, target, link index).
+    pub(crate) fn edges(
+        &self,
+    ) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.succ.iter().enumerate().flat_map(|(from, out)| {
+            out.iter().map(move |&(to, link)| (from, to, link))
+        })
+    }
+}
@@ -0,0 +1,291 @@
+use crate::{
+    cigar::CIGAR,
+    gfa::{SegmentId, GFA},
+    optfields::*,
+};
+
+use super::{in_side, match_overlap, out_side, segment_indices, Side};
+
+use fnv::FnvHashMap;
+
+use std::{collections::VecDeque, hash::Hash};
+
+/// The reasons `bluntify` can fail, with the index of the offending
+/// link. The graph is left unmodified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BluntifyError {
+    /// The overlap of the link isn't `*` or made only of matches.
+    UnsupportedOverlap(usize),
+    /// The overlaps around the link can't be removed by trimming
+    /// segment ends, e.g. because a segment end has links with
+    /// different overlaps on both sides, or because trimming would
+    /// leave a segment empty.
+    Unresolvable(usize),
+}
+
+impl std::fmt::Display for BluntifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BluntifyError::UnsupportedOverlap(link) => {
+                write!(f, "Link {} has an unsupported overlap", link)
+            }
+            BluntifyError::Unresolvable(link) => {
+                write!(f, "Can't remove the overlap of link {}", link)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BluntifyError {}
+
+/// A link with a nonzero overlap, as its index, sides, and overlap.
+struct Overlap {
+    link: usize,
+    sides: (Side, Side),
+    len: usize,
+}
+
+/// Convert a graph whose links overlap into a blunt graph, by
+/// trimming the overlapping sequence from one segment end of every
+/// overlapping link, and setting the link overlaps to `0M`. Path
+/// overlaps are set to `0M` as well, except where they're `*`, so the
+/// sequences spelled by paths stay the same. Walks keep their steps,
+/// and the sequence range of a walk that starts or ends at a trimmed
+/// segment end is narrowed by the trimmed length. Returns the number
+/// of links that were made blunt.
+///
+/// Every link with an overlap must have exactly one of its ends
+/// trimmed, and all links at a trimmed end must have the same
+/// overlap; when there's no such choice of ends, this returns an
+/// error instead of duplicating sequence. Overlaps are assumed to
+/// consist of matching sequence, and links with `*` overlaps are
+/// treated as blunt.
+pub fn bluntify<N, T>(gfa: &mut GFA<N, T>) -> Result<usize, BluntifyError>
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    let indices = segment_indices(gfa);
+
+    let mut overlaps = Vec::new();
+    for (ix, link) in gfa.links.iter().enumerate() {
+        let len = match_overlap(&link.overlap)
+            .ok_or(BluntifyError::UnsupportedOverlap(ix))?;
+        let from = indices.get(&link.from_segment);
+        let to = indices.get(&link.to_segment);
+        if let (Some(&from), Some(&to), true) = (from, to, len > 0) {
+            let from = out_side((from, link.from_orient));
+            let to = in_side((to, link.to_orient));
+            overlaps.push(Overlap {
+                link: ix,
+                sides: (from, to),
+                len,
+            });
+        }
+    }
+
+    let mut side_overlaps: FnvHashMap<Side, Vec<usize>> = FnvHashMap::default();
+    for (ix, overlap) in overlaps.iter().enumerate() {
+        side_overlaps.entry(overlap.sides.0).or_default().push(ix);
+        side_overlaps.entry(overlap.sides.1).or_default().push(ix);
+    }
+
+    let lengths: Vec<Option<usize>> = gfa
+        .segments
+        .iter()
+        .map(|s| {
+            if s.sequence == b"*" {
+                None
+            } else {
+                Some(s.sequence.len())
+            }
+        })
+        .collect();
+
+    // Every overlapping link must have exactly one trimmed side, so
+    // the sides joined by overlapping links are two-colored, and one
+    // color of each connected component is trimmed
+    let mut color: FnvHashMap<Side, bool> = FnvHashMap::default();
+    let mut trim: FnvHashMap<Side, usize> = FnvHashMap::default();
+    for first in overlaps.iter() {
+        let root = first.sides.1;
+        if color.contains_key(&root) {
+            continue;
+        }
+        color.insert(root, true);
+        let mut component = vec![root];
+        let mut queue = VecDeque::new();
+        queue.push_back(root);
+        while let Some(side) = queue.pop_front() {
+            for &ix in side_overlaps[&side].iter() {
+                let overlap = &overlaps[ix];
+                let (a, b) = overlap.sides;
+                if a == b {
+                    return Err(BluntifyError::Unresolvable(overlap.link));
+                }
+                let other = if a == side { b } else { a };
+                let other_color = !color[&side];
+                match color.get(&other) {
+                    Some(&c) if c != other_color => {
+                        return Err(BluntifyError::Unresolvable(overlap.link))
+                    }
+                    Some(_) => (),
+                    None => {
+                        color.insert(other, other_color);
+                        component.push(other);
+                        queue.push_back(other);
+                    }
+                }
+            }
+        }
+
+        // The trim length of each side of a color, if they can all
+        // be trimmed
+        let trimmed = |trimmed_color: bool| -> Option<Vec<(Side, usize)>> {
+            let mut trims = Vec::new();
+            for &side in component.iter() {
+                if color[&side] != trimmed_color {
+                    continue;
+                }
+                let mut lens =
+                    side_overlaps[&side].iter().map(|&ix| overlaps[ix].len);
+                let len = lens.next()?;
+                if lens.any(|l| l != len) || lengths[side.0]? <= len {
+                    return None;
+                }
+                trims.push((side, len));
+            }
+            Some(trims)
+        };
+
+        let trims = trimmed(true)
+            .or_else(|| trimmed(false))
+            .ok_or(BluntifyError::Unresolvable(first.link))?;
+        trim.extend(trims);
+    }
+
+    for (seg, len) in lengths.iter().enumerate() {
+        let start = trim.get(&(seg, false)).copied().unwrap_or(0);
+        let end = trim.get(&(seg, true)).copied().unwrap_or(0);
+        if start + end > 0 && len.is_none_or(|len| start + end >= len) {
+            let link = side_overlaps
+                .get(&(seg, false))
+                .or_else(|| side_overlaps.get(&(seg, true)))
+                .map(|ixs| overlaps[ixs[0]].link)
+                .unwrap_or(0);
+            return Err(BluntifyError::Unresolvable(link));
+        }
+    }
+
+    // The lengths trimmed from the start and end of each walk
+    let trimmed = |side| trim.get(&side).copied().unwrap_or(0);
+    let walk_trims: Vec<_> = gfa
+        .walks
+        .iter()
+        .map(|walk| {
+            let handle = |(name, orient)| Some((*indices.get(&name)?, orient));
+            let first = walk.steps().next().and_then(handle);
+            let last = walk.steps().last().and_then(handle);
+            (
+                first.map_or(0, |first| trimmed(in_side(first))),
+                last.map_or(0, |last| trimmed(out_side(last))),
+            )
+        })
+        .collect();
+
+    for (seg, segment) in gfa.segments.iter_mut().enumerate() {
+        let start = trim.get(&(seg, false)).copied().unwrap_or(0);
+        let end = trim.get(&(seg, true)).copied().unwrap_or(0);
+        if start + end > 0 {
+            let len = segment.sequence.len();
+            segment.sequence = segment.sequence[start..len - end].to_vec();
+        }
+    }
+
+    let blunt = CIGAR::from_bytestring(b"0M");
+    for overlap in overlaps.iter() {
+        gfa.links[overlap.link].overlap = b"0M".to_vec();
+    }
+    for path in gfa.paths.iter_mut() {
+        for overlap in path.overlaps.iter_mut().filter(|o| o.is_some()) {
+            *overlap = blunt.clone();
+        }
+    }
+    for (walk, (start, end)) in gfa.walks.iter_mut().zip(walk_trims) {
+        walk.seq_start = walk.seq_start.map(|s| s + start);
+        walk.seq_end = walk.seq_end.map(|e| e.saturating_sub(end));
+    }
+
+    Ok(overlaps.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fasta::path_sequence;
+    use crate::test_util::parse;
+
+    #[test]
+    fn bluntify_uniform_overlaps() {
+        let mut gfa = parse(&[
+            "S\t1\tACGTA",
+            "S\t2\tTACCG",
+            "S\t3\tTAGGC",
+            "S\t4\tTGCC",
+            "L\t1\t+\t2\t+\t2M",
+            "L\t1\t+\t3\t+\t2M",
+            "L\t3\t+\t4\t-\t3M",
+            "P\tp\t1+,3+,4-\t2M,3M",
+            "W\tHG1\t1\tchr1\t0\t8\t>1>2",
+            "W\tHG1\t1\tchr2\t0\t5\t>2",
+            "W\tHG1\t2\tchr1\t0\t4\t>4",
+        ]);
+
+        let spelled = |gfa: &GFA<Vec<u8>, OptionalFields>| {
+            let seq = path_sequence(gfa, &gfa.paths[0]).unwrap();
+            String::from_utf8(seq).unwrap()
+        };
+        // the path sequence, with overlaps ignored
+        assert_eq!(spelled(&gfa), "ACGTATAGGCGGCA");
+
+        assert_eq!(bluntify(&mut gfa), Ok(3));
+        let seqs: Vec<_> = gfa
+            .segments
+            .iter()
+            .map(|s| String::from_utf8(s.sequence.clone()).unwrap())
+            .collect();
+        assert_eq!(seqs, vec!["ACGTA", "CCG", "GGC", "T"]);
+        assert!(gfa.links.iter().all(|l| l.overlap == b"0M"));
+        assert_eq!(spelled(&gfa), "ACGTAGGCA");
+        // 2 loses its first two bases, and 4 its last three
+        let ranges: Vec<_> =
+            gfa.walks.iter().map(|w| (w.seq_start, w.seq_end)).collect();
+        assert_eq!(
+            ranges,
+            vec![(Some(0), Some(8)), (Some(2), Some(5)), (Some(0), Some(1))]
+        );
+    }
+
+    #[test]
+    fn unresolvable_overlaps() {
+        let lines = [
+            "S\t1\tACGTA",
+            "S\t2\tTACCG",
+            "S\t3\tTAGGC",
+            "L\t1\t+\t2\t+\t2M",
+            "L\t3\t-\t2\t+\t1M",
+            "L\t1\t+\t3\t+\t1M",
+        ];
+        let mut gfa: GFA<Vec<u8>, OptionalFields> = parse(&lines);
+        let orig = gfa.clone();
+        assert_eq!(bluntify(&mut gfa), Err(BluntifyError::Unresolvable(2)));
+        assert_eq!(gfa, orig);
+
+        let mut gfa: GFA<Vec<u8>, OptionalFields> =
+            parse(&["S\t1\tA", "S\t2\tC", "L\t1\t+\t2\t+\t1M1I"]);
+        assert_eq!(
+            bluntify(&mut gfa),
+            Err(BluntifyError::UnsupportedOverlap(0))
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use crate::{
+    gfa::{Orientation, GFA},
+    optfields::*,
+};
+
+use super::{orient_segments, MixedOrientationError, SegmentGraph};
+
+use fnv::FnvHashSet;
+
+use std::hash::Hash;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BubbleKind {
+    /// Every interior segment is connected only to the source and
+    /// the sink, e.g. the alleles of a SNP or a simple indel.
+    Simple,
+    /// Any other superbubble, where the paths between the source and
+    /// sink can branch and merge.
+    Super,
+}
+
+/// A superbubble in the segment graph: a subgraph with a single
+/// entrance (the source) and a single exit (the sink), where every
+/// path from the source reaches the sink, and which contains no
+/// cycles and no other superbubble with the same source.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bubble {
+    pub source: usize,
+    /// The orientation the source is traversed in when entering the
+    /// bubble.
+    pub source_orient: Orientation,
+    pub sink: usize,
+    /// The orientation the sink is traversed in when leaving the
+    /// bubble.
+    pub sink_orient: Orientation,
+    /// The segments between the source and sink, sorted by index.
+    pub interior: Vec<usize>,
+    pub kind: BubbleKind,
+}
+
+/// Distinct targets of the edges leaving `v`.
+fn children(graph: &SegmentGraph, v: usize) -> Vec<usize> {
+    let mut children: Vec<_> = graph.succ[v].iter().map(|&(u, _)| u).collect();
+    children.sort_unstable();
+    children.dedup();
+    children
+}
+
+/// Find the superbubble with the given source, if there is one,
+/// returning the sink and the interior segments. This is the
+/// algorithm by Onodera et al. (2013).
+fn superbubble_from(
+    graph: &SegmentGraph,
+    source: usize,
+) -> Option<(usize, Vec<usize>)> {
+    let mut visited = FnvHashSet::default();
+    let mut seen = FnvHashSet::default();
+    let mut stack = vec![source];
+    seen.insert(source);
+
+    while let Some(v) = stack.pop() {
+        visited.insert(v);
+        seen.remove(&v);
+
+        let children = children(graph, v);
+        if children.is_empty() {
+            return None;
+        }
+
+        for u in children {
+            if u == source {
+                return None;
+            }
+            seen.insert(u);
+            if graph.pred[u].iter().all(|(p, _)| visited.contains(p)) {
+                stack.push(u);
+            }
+        }
+
+        if stack.len() == 1 && seen.len() == 1 && seen.contains(&stack[0]) {
+            let sink = stack[0];
+            if graph.succ[sink].iter().any(|&(u, _)| u == source) {
+                return None;
+            }
+            visited.remove(&source);
+            let mut interior: Vec<_> = visited.into_iter().collect();
+            interior.sort_unstable();
+            return Some((sink, interior));
+        }
+    }
+
+    None
+}
+
+fn is_simple(
+    graph: &SegmentGraph,
+    source: usize,
+    sink: usize,
+    interior: &[usize],
+) -> bool {
+    interior.iter().all(|&v| {
+        graph.pred[v].iter().all(|&(p, _)| p == source)
+            && graph.succ[v].iter().all(|&(u, _)| u == sink)
+    })
+}
+
+/// Find all bubbles and superbubbles in the segment graph, following
+/// the link directions described in `SegmentGraph`. Superbubbles
+/// without any interior segments, i.e. a single link, are not
+/// included. Nested bubbles are reported separately from the ones
+/// containing them. The bubbles are sorted by source.
+///
+/// As in `topological_sort`, `+`/`-` and `-`/`+` links are handled by
+/// first orienting the segments so that every link joins segments in
+/// the same orientation. If there's no such choice, e.g. at an
+/// inversion, where a segment is traversed in both orientations
+/// between the same two segments, this returns an error rather than
+/// reading the links in the wrong direction.
+pub fn find_bubbles<N, T>(
+    gfa: &GFA<N, T>,
+) -> Result<Vec<Bubble>, MixedOrientationError>
+where
+    N: Eq + Hash,
+    T: OptFields,
+{
+    let orients =
+        orient_segments(gfa).map_err(|link| MixedOrientationError { link })?;
+    let graph = SegmentGraph::from_oriented(gfa, &orients);
+
+    let bubbles = (0..graph.len())
+        .filter_map(|source| {
+            let (sink, interior) = superbubble_from(&graph, source)?;
+            if interior.is_empty() {
+                return None;
+            }
+            let kind = if is_simple(&graph, source, sink, &interior) {
+                BubbleKind::Simple
+            } else {
+                BubbleKind::Super
+            };
+            Some(Bubble {
+                source,
+                source_orient: orients[source],
+                sink,
+                sink_orient: orients[sink],
+                interior,
+                kind,
+            })
+        })
+        .collect();
+    Ok(bubbles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+    use Orientation::*;
+
+    fn segments(count: usize) -> Vec<String> {
+        (1..=count).map(|i| format!("S\t{}\tA", i)).collect()
+    }
+
+    fn graph(count: usize, links: &[(usize, usize)]) -> GFA<usize, ()> {
+        let mut lines = segments(count);
+        for (from, to) in links {
+            lines.push(format!("L\t{}\t+\t{}\t+\t0M", from, to));
+        }
+        let lines: Vec<_> = lines.iter().map(|l| l.as_str()).collect();
+        parse(&lines)
+    }
+
+    #[test]
+    fn simple_bubbles() {
+        // SNP
+        let gfa = graph(5, &[(1, 2), (1, 3), (2, 4), (3, 4), (4, 5)]);
+        let bubbles = find_bubbles(&gfa).unwrap();
+        assert_eq!(
+            bubbles,
+            vec![Bubble {
+                source: 0,
+                source_orient: Forward,
+                sink: 3,
+                sink_orient: Forward,
+                interior: vec![1, 2],
+                kind: BubbleKind::Simple,
+            }]
+        );
+
+        // deletion
+        let gfa = graph(4, &[(1, 2), (2, 3), (1, 3), (3, 4)]);
+        let bubbles = find_bubbles(&gfa).unwrap();
+        assert_eq!(
+            bubbles,
+            vec![Bubble {
+                source: 0,
+                source_orient: Forward,
+                sink: 2,
+                sink_orient: Forward,
+                interior: vec![1],
+                kind: BubbleKind::Simple,
+            }]
+        );
+    }
+
+    #[test]
+    fn nested_superbubble() {
+        let gfa = graph(
+            7,
+            &[
+                (1, 2),
+                (1, 3),
+                (2, 4),
+                (2, 5),
+                (4, 6),
+                (5, 6),
+                (6, 7),
+                (3, 7),
+            ],
+        );
+        let bubbles = find_bubbles(&gfa).unwrap();
+        assert_eq!(
+            bubbles,
+            vec![
+                Bubble {
+                    source: 0,
+                    source_orient: Forward,
+                    sink: 6,
+                    sink_orient: Forward,
+                    interior: vec![1, 2, 3, 4, 5],
+                    kind: BubbleKind::Super,
+                },
+                Bubble {
+                    source: 1,
+                    source_orient: Forward,
+                    sink: 5,
+                    sink_orient: Forward,
+                    interior: vec![3, 4],
+                    kind: BubbleKind::Simple,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn no_bubbles_with_tips_or_cycles() {
+        // 3 is a tip
+        let gfa = graph(4, &[(1, 2), (1, 3), (2, 4)]);
+        assert!(find_bubbles(&gfa).unwrap().is_empty());
+
+        // the sink loops back to the source
+        let gfa = graph(4, &[(1, 2), (1, 3), (2, 4), (3, 4), (4, 1)]);
+        assert!(find_bubbles(&gfa).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reverse_links_are_followed() {
+        let gfa: GFA<usize, ()> = parse(&[
+            "S\t1\tA",
+            "S\t2\tA",
+            "S\t3\tA",
+            "S\t4\tA",
+            "L\t2\t-\t1\t-\t0M",
+            "L\t1\t+\t3\t+\t0M",
+            "L\t4\t-\t2\t-\t0M",
+            "L\t3\t+\t4\t+\t0M",
+        ]);
+        let bubbles = find_bubbles(&gfa).unwrap();
+        assert_eq!(bubbles.len(), 1);
+        assert_eq!((bubbles[0].source, bubbles[0].sink), (0, 3));
+    }
+
+    #[test]
+    fn mixed_orientations() {
+        // a bubble where 3 is reversed relative to the others
+        let gfa: GFA<usize, ()> = parse(&[
+            "S\t1\tA",
+            "S\t2\tA",
+            "S\t3\tA",
+            "S\t4\tA",
+            "L\t1\t+\t2\t+\t0M",
+            "L\t1\t+\t3\t-\t0M",
+            "L\t2\t+\t4\t+\t0M",
+            "L\t3\t-\t4\t+\t0M",
+        ]);
+        assert_eq!(
+            find_bubbles(&gfa).unwrap(),
+            vec![Bubble {
+                source: 0,
+                source_orient: Forward,
+                sink: 3,
+                sink_orient: Forward,
+                interior: vec![1, 2],
+                kind: BubbleKind::Simple,
+            }]
+        );
+
+        // an inversion, where 2 is traversed in both orientations
+        // between 1 and 3, has no consistent orientation
+        let gfa: GFA<usize, ()> = parse(&[
+            "S\t1\tA",
+            "S\t2\tA",
+            "S\t3\tA",
+            "L\t1\t+\t2\t+\t0M",
+            "L\t2\t+\t3\t+\t0M",
+            "L\t1\t+\t2\t-\t0M",
+            "L\t2\t-\t3\t+\t0M",
+        ]);
+        assert!(find_bubbles(&gfa).is_err());
+    }
+}
@@ -0,0 +1,363 @@
+use crate::{
+    gfa::{raw_walk_steps, split_step, Path, PathRef, SegmentId, GFA},
+    optfields::*,
+    validation::segment_length,
+};
+
+use super::{induced_subgraph, segment_indices, SegmentGraph};
+
+use bstr::ByteSlice;
+
+use std::{cmp::Reverse, collections::BinaryHeap, hash::Hash};
+
+/// How far from the seed segments a neighborhood extends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Radius {
+    /// Include the segments at most this many links away.
+    Hops(usize),
+    /// Include the segments that can be reached by passing through
+    /// at most this many base pairs of other segments.
+    BasePairs(usize),
+}
+
+/// Extract the part of the GFA covered by the base-pair interval
+/// `start..end` on the path or walk with the given name, where walks
+/// are named by `Walk::name`. The result contains the segments of
+/// every step overlapping the interval, the links and containments
+/// between those segments, and the path or walk itself, trimmed to
+/// those steps. Coordinates are computed from the segment lengths,
+/// ignoring any overlaps between steps; on a walk, they're offset by
+/// the start of its sequence range, if it's known, and the trimmed
+/// walk gets the range of its remaining steps.
+///
+/// Returns `None` if there is no path or walk with the given name.
+pub fn path_range_subgraph<N, T, S>(
+    gfa: &GFA<N, T, S>,
+    path_name: &[u8],
+    start: usize,
+    end: usize,
+) -> Option<GFA<N, T, S>>
+where
+    N: SegmentId + Eq + Hash + Clone,
+    T: OptFields,
+    S: AsRef<[u8]> + Clone,
+{
+    let path = gfa.path_refs().find(|p| p.name().as_ref() == path_name)?;
+    let indices = segment_indices(gfa);
+
+    let mut keep = vec![false; gfa.segments.len()];
+    let mut first_step = None;
+    let mut last_step = 0;
+    let mut range = (0, 0);
+
+    // malformed steps are kept in place, with length zero, so the step
+    // indices match the raw steps
+    let (steps, mut offset): (Vec<&[u8]>, _) = match path {
+        PathRef::Path(path) => {
+            (path.segment_names.split_str(b",").collect(), 0)
+        }
+        PathRef::Walk(walk) => (
+            raw_walk_steps(&walk.walk).collect(),
+            walk.seq_start.unwrap_or(0),
+        ),
+    };
+    for (step_ix, step) in steps.iter().enumerate() {
+        let name = match path {
+            PathRef::Path(_) => split_step(step).map(|(name, _)| name),
+            PathRef::Walk(_) => step.get(1..),
+        };
+        let seg_ix = name
+            .and_then(N::parse_id)
+            .and_then(|name| indices.get(&name).copied());
+        let len = seg_ix
+            .and_then(|ix| segment_length(&gfa.segments[ix]))
+            .unwrap_or(0);
+        let step_end = offset + len;
+
+        if offset < end && step_end > start {
+            if let Some(ix) = seg_ix {
+                keep[ix] = true;
+            }
+            if first_step.is_none() {
+                first_step = Some(step_ix);
+                range.0 = offset;
+            }
+            last_step = step_ix;
+            range.1 = step_end;
+        }
+
+        if step_end >= end {
+            break;
+        }
+        offset = step_end;
+    }
+
+    let mut subgraph = induced_subgraph(gfa, &keep);
+
+    match (first_step, path) {
+        (Some(first_step), PathRef::Path(path)) => {
+            let overlaps = if path.overlaps.len() + 1 == steps.len() {
+                path.overlaps[first_step..last_step].to_vec()
+            } else {
+                vec![None]
+            };
+
+            subgraph.paths.push(Path::new(
+                path.path_name.clone(),
+                bstr::join(",", &steps[first_step..=last_step]),
+                overlaps,
+                path.optional.clone(),
+            ));
+        }
+        (Some(first_step), PathRef::Walk(walk)) => {
+            let mut trimmed =
+                walk.with_walk(steps[first_step..=last_step].concat());
+            if walk.seq_start.is_some() {
+                trimmed.seq_start = Some(range.0);
+                trimmed.seq_end = Some(range.1);
+            } else {
+                trimmed.seq_end = None;
+            }
+            subgraph.walks.push(trimmed);
+        }
+        (None, _) => (),
+    }
+
+    Some(subgraph)
+}
+
+/// Extract the neighborhood around the given seed segments: every
+/// segment within `radius` of a seed, ignoring link directions, plus
+/// the links and containments between them. Paths and walks are not
+/// included, since they may leave and reenter the neighborhood, and
+/// seeds that aren't in the GFA are ignored.
+pub fn neighborhood_subgraph<N, T, S>(
+    gfa: &GFA<N, T, S>,
+    seeds: &[N],
+    radius: Radius,
+) -> GFA<N, T, S>
+where
+    N: Eq + Hash + Clone,
+    T: OptFields,
+    S: AsRef<[u8]> + Clone,
+{
+    let indices = segment_indices(gfa);
+    let graph = SegmentGraph::from_gfa(gfa);
+
+    let (max_dist, hops) = match radius {
+        Radius::Hops(k) => (k, true),
+        Radius::BasePairs(k) => (k, false),
+    };
+
+    let mut dist: Vec<Option<usize>> = vec![None; gfa.segments.len()];
+    let mut queue = BinaryHeap::new();
+    for seed in seeds {
+        if let Some(&ix) = indices.get(seed) {
+            dist[ix] = Some(0);
+            queue.push(Reverse((0, ix)));
+        }
+    }
+
+    while let Some(Reverse((d, v))) = queue.pop() {
+        if matches!(dist[v], Some(best) if best < d) {
+            continue;
+        }
+
+        let step = if hops {
+            1
+        } else if d == 0 && seeds.contains(&gfa.segments[v].name) {
+            0
+        } else {
+            segment_length(&gfa.segments[v]).unwrap_or(0)
+        };
+        let next = d + step;
+        if next > max_dist {
+            continue;
+        }
+
+        let neighbors = graph.succ[v].iter().chain(graph.pred[v].iter());
+        for &(u, _) in neighbors {
+            if !matches!(dist[u], Some(best) if best <= next) {
+                dist[u] = Some(next);
+                queue.push(Reverse((next, u)));
+            }
+        }
+    }
+
+    let keep: Vec<_> = dist.iter().map(Option::is_some).collect();
+    induced_subgraph(gfa, &keep)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::GFAParser;
+    use crate::test_util::parse;
+
+    fn lil_gfa() -> GFA<Vec<u8>, ()> {
+        let parser = GFAParser::new();
+        parser.parse_file("./test/gfas/lil.gfa").unwrap()
+    }
+
+    fn names(gfa: &GFA<Vec<u8>, ()>) -> Vec<&str> {
+        gfa.segments
+            .iter()
+            .map(|s| s.name.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn extract_path_range() {
+        let gfa = lil_gfa();
+        let path = &gfa.paths[0];
+        assert_eq!(path.path_name, b"x");
+
+        let lens: Vec<_> = path
+            .iter()
+            .map(|(name, _)| {
+                let seg = gfa.segments.iter().find(|s| s.name == name);
+                segment_length(seg.unwrap()).unwrap()
+            })
+            .collect();
+
+        // the interval covers the second step and the start of the third
+        let start = lens[0];
+        let end = lens[0] + lens[1] + 1;
+
+        let sub = path_range_subgraph(&gfa, b"x", start, end).unwrap();
+        let steps: Vec<_> = path.iter().skip(1).take(2).collect();
+
+        assert_eq!(sub.paths.len(), 1);
+        assert_eq!(sub.paths[0].iter().collect::<Vec<_>>(), steps);
+
+        let mut expected: Vec<_> = steps
+            .iter()
+            .map(|(name, _)| name.to_str().unwrap())
+            .collect();
+        expected.sort_unstable();
+        expected.dedup();
+        let mut found = names(&sub);
+        found.sort_unstable();
+        assert_eq!(found, expected);
+
+        for link in sub.links.iter() {
+            assert!(found.contains(&link.from_segment.to_str().unwrap()));
+            assert!(found.contains(&link.to_segment.to_str().unwrap()));
+        }
+        assert!(!sub.links.is_empty());
+    }
+
+    #[test]
+    fn path_range_keeps_malformed_steps_in_place() {
+        let lines = ["S\t1\tAA", "S\t2\tCC", "S\t3\tGG", "S\t4\tTT"];
+        let mut gfa: GFA<Vec<u8>, ()> = parse(&lines);
+        gfa.paths.push(Path::new(
+            b"p".to_vec(),
+            b"1+,2,3+,4+".to_vec(),
+            vec![],
+            (),
+        ));
+
+        // the second step has no length, so this covers 3 and 4
+        let sub = path_range_subgraph(&gfa, b"p", 3, 6).unwrap();
+        assert_eq!(sub.paths[0].segment_names, b"3+,4+");
+        assert_eq!(names(&sub), vec!["3", "4"]);
+    }
+
+    #[test]
+    fn extract_walk_range() {
+        let gfa: GFA<Vec<u8>, ()> = parse(&[
+            "S\t1\tAA",
+            "S\t2\tCC",
+            "S\t3\tGG",
+            "S\t4\tTT",
+            "W\tHG1\t1\tchr1\t10\t18\t>1<2>3>4",
+            "W\tHG2\t1\tchr1\t*\t*\t>1>2",
+        ]);
+
+        // the walk coordinates start at 10
+        let sub = path_range_subgraph(&gfa, b"HG1#1#chr1", 12, 15).unwrap();
+        assert_eq!(names(&sub), vec!["2", "3"]);
+        assert!(sub.paths.is_empty());
+        assert_eq!(sub.walks.len(), 1);
+        assert_eq!(sub.walks[0].walk, b"<2>3");
+        assert_eq!(sub.walks[0].seq_start, Some(12));
+        assert_eq!(sub.walks[0].seq_end, Some(16));
+
+        let sub = path_range_subgraph(&gfa, b"HG2#1#chr1", 1, 3).unwrap();
+        assert_eq!(sub.walks[0].walk, b">1>2");
+        assert_eq!(sub.walks[0].seq_start, None);
+        assert_eq!(sub.walks[0].seq_end, None);
+
+        assert!(path_range_subgraph(&gfa, b"HG1#1#chr1", 0, 10)
+            .unwrap()
+            .walks
+            .is_empty());
+    }
+
+    #[test]
+    fn neighborhood_by_hops() {
+        let gfa = lil_gfa();
+        let seeds = vec![b"6".to_vec()];
+
+        let zero = neighborhood_subgraph(&gfa, &seeds, Radius::Hops(0));
+        assert_eq!(names(&zero), vec!["6"]);
+        assert!(zero.links.is_empty());
+
+        let one = neighborhood_subgraph(&gfa, &seeds, Radius::Hops(1));
+        assert_eq!(names(&one), vec!["4", "5", "6", "7", "8"]);
+        assert_eq!(one.links.len(), 4);
+
+        let two = neighborhood_subgraph(&gfa, &seeds, Radius::Hops(2));
+        assert_eq!(names(&two), vec!["2", "3", "4", "5", "6", "7", "8", "9"]);
+
+        let none =
+            neighborhood_subgraph(&gfa, &[b"x".to_vec()], Radius::Hops(3));
+        assert!(none.segments.is_empty());
+
+        let gfa: GFA<Vec<u8>, ()> = parse(&[
+            "S\t1\tA",
+            "S\t2\tC",
+            "L\t1\t+\t2\t+\t0M",
+            "P\tp\t1+,2+\t*",
+            "W\tHG1\t1\tchr1\t0\t2\t>1>2",
+        ]);
+        let sub =
+            neighborhood_subgraph(&gfa, &[b"1".to_vec()], Radius::Hops(1));
+        assert_eq!(names(&sub), vec!["1", "2"]);
+        assert!(sub.paths.is_empty());
+        assert!(sub.walks.is_empty());
+    }
+
+    #[test]
+    fn neighborhood_by_base_pairs() {
+        let gfa = lil_gfa();
+        let seeds = vec![b"1".to_vec()];
+
+        // the seed itself doesn't count toward the distance
+        let sub = neighborhood_subgraph(&gfa, &seeds, Radius::BasePairs(0));
+        assert_eq!(names(&sub), vec!["1", "2", "3"]);
+
+        let sub = neighborhood_subgraph(&gfa, &seeds, Radius::BasePairs(1));
+        assert_eq!(names(&sub), vec!["1", "2", "3", "4", "5"]);
+
+        // 6 is 3bp long, so 7 and 8 need a radius of 5
+        let sub = neighborhood_subgraph(&gfa, &seeds, Radius::BasePairs(4));
+        assert_eq!(names(&sub), vec!["1", "2", "3", "4", "5", "6"]);
+        let sub = neighborhood_subgraph(&gfa, &seeds, Radius::BasePairs(5));
+        assert_eq!(names(&sub), vec!["1", "2", "3", "4", "5", "6", "7", "8"]);
+    }
+
+    #[test]
+    fn path_range_edge_cases() {
+        let gfa = lil_gfa();
+        assert!(path_range_subgraph(&gfa, b"none", 0, 10).is_none());
+
+        let empty = path_range_subgraph(&gfa, b"x", 10, 10).unwrap();
+        assert!(empty.segments.is_empty());
+        assert!(empty.paths.is_empty());
+
+        let whole = path_range_subgraph(&gfa, b"x", 0, usize::MAX).unwrap();
+        assert_eq!(whole.paths[0].segment_names, gfa.paths[0].segment_names);
+    }
+}
@@ -0,0 +1,335 @@
+use crate::{
+    gfa::{Orientation, GFA},
+    optfields::*,
+};
+
+use super::{orient_segments, SegmentGraph};
+
+use std::{cmp::Reverse, collections::BinaryHeap, hash::Hash};
+
+/// The result of topologically sorting the segment graph.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TopoOrder {
+    /// Every segment index, in topological order. Segments in the
+    /// same strongly connected component are placed next to each
+    /// other, sorted by index.
+    pub order: Vec<usize>,
+    /// The orientation of each segment in the order, indexed by
+    /// segment. Segments are only reversed if that's needed for every
+    /// link to join two segments in the same orientation.
+    pub orientations: Vec<Orientation>,
+    /// The strongly connected components that contain cycles,
+    /// including single segments with a self-loop.
+    pub cycles: Vec<Vec<usize>>,
+    /// Indices of the links that go backward in `order`. Removing
+    /// these links would make the graph acyclic.
+    pub feedback_links: Vec<usize>,
+}
+
+impl TopoOrder {
+    /// Returns true if the segment graph has no cycles.
+    pub fn is_dag(&self) -> bool {
+        self.cycles.is_empty()
+    }
+
+    /// The position of each segment in `order`, indexed by segment.
+    pub fn ranks(&self) -> Vec<usize> {
+        let mut ranks = vec![0; self.order.len()];
+        for (rank, &seg) in self.order.iter().enumerate() {
+            ranks[seg] = rank;
+        }
+        ranks
+    }
+}
+
+/// Returned by `topological_sort` when the segments can't be oriented
+/// so that every link joins two segments in the same orientation,
+/// e.g. at an inversion, where a segment is traversed in both
+/// orientations between the same two segments. Includes the index of
+/// a link that conflicts with the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MixedOrientationError {
+    pub link: usize,
+}
+
+impl std::fmt::Display for MixedOrientationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Link {} can't be oriented consistently with the others",
+            self.link
+        )
+    }
+}
+
+impl std::error::Error for MixedOrientationError {}
+
+/// Find the strongly connected components of the graph using an
+/// iterative version of Tarjan's algorithm. Components are returned
+/// in reverse topological order.
+fn strongly_connected(graph: &SegmentGraph) -> Vec<Vec<usize>> {
+    let count = graph.len();
+    let mut next_index = 0;
+    let mut index: Vec<Option<usize>> = vec![None; count];
+    let mut lowlink = vec![0; count];
+    let mut on_stack = vec![false; count];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+
+    for root in 0..count {
+        if index[root].is_some() {
+            continue;
+        }
+
+        let mut calls = vec![(root, 0)];
+        index[root] = Some(next_index);
+        lowlink[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some((v, edge)) = calls.last_mut() {
+            let v = *v;
+            if let Some(&(w, _)) = graph.succ[v].get(*edge) {
+                *edge += 1;
+                match index[w] {
+                    None => {
+                        index[w] = Some(next_index);
+                        lowlink[w] = next_index;
+                        next_index += 1;
+                        stack.push(w);
+                        on_stack[w] = true;
+                        calls.push((w, 0));
+                    }
+                    Some(w_index) if on_stack[w] => {
+                        lowlink[v] = lowlink[v].min(w_index);
+                    }
+                    _ => (),
+                }
+            } else {
+                calls.pop();
+                if let Some(&(parent, _)) = calls.last() {
+                    lowlink[parent] = lowlink[parent].min(lowlink[v]);
+                }
+                if Some(lowlink[v]) == index[v] {
+                    let mut component = Vec::new();
+                    while let Some(w) = stack.pop() {
+                        on_stack[w] = false;
+                        component.push(w);
+                        if w == v {
+                            break;
+                        }
+                    }
+                    component.sort_unstable();
+                    components.push(component);
+                }
+            }
+        }
+    }
+
+    components
+}
+
+/// Topologically sort the segments of the GFA, following the link
+/// directions described in `SegmentGraph`, after orienting the
+/// segments as described below. Cycles are handled by
+/// condensing each strongly connected component into a single node;
+/// the links that then point backward in the order are reported as
+/// feedback links. Ties are broken by segment index, so a graph
+/// whose segments are already sorted keeps its order.
+///
+/// Only links where both segments have the same orientation can be
+/// ordered this way, as `+`/`+` links go from `from` to `to` and
+/// `-`/`-` links the other way. So, as in odgi, the segments are first
+/// oriented so that every `+`/`-` or `-`/`+` link joins segments in
+/// the same orientation, flipping as few of them as possible in each
+/// connected component, and the orientations are returned in
+/// `TopoOrder::orientations`. The GFA is only rejected if there's no
+/// such choice of orientations.
+pub fn topological_sort<N, T>(
+    gfa: &GFA<N, T>,
+) -> Result<TopoOrder, MixedOrientationError>
+where
+    N: Eq + Hash,
+    T: OptFields,
+{
+    let orientations =
+        orient_segments(gfa).map_err(|link| MixedOrientationError { link })?;
+    let graph = SegmentGraph::from_oriented(gfa, &orientations);
+    let components = strongly_connected(&graph);
+
+    let mut component_of = vec![0; graph.len()];
+    for (comp_ix, component) in components.iter().enumerate() {
+        for &seg in component {
+            component_of[seg] = comp_ix;
+        }
+    }
+
+    // Kahn's algorithm over the condensed graph, always choosing the
+    // available component with the smallest segment index
+    let mut in_degree = vec![0usize; components.len()];
+    for (from, to, _) in graph.edges() {
+        if component_of[from] != component_of[to] {
+            in_degree[component_of[to]] += 1;
+        }
+    }
+
+    let mut ready: BinaryHeap<_> = in_degree
+        .iter()
+        .enumerate()
+        .filter(|(_, &deg)| deg == 0)
+        .map(|(comp_ix, _)| Reverse((components[comp_ix][0], comp_ix)))
+        .collect();
+
+    let mut order = Vec::with_capacity(graph.len());
+    while let Some(Reverse((_, comp_ix))) = ready.pop() {
+        for &seg in components[comp_ix].iter() {
+            order.push(seg);
+            for &(to, _) in graph.succ[seg].iter() {
+                let to_comp = component_of[to];
+                if to_comp != comp_ix {
+                    in_degree[to_comp] -= 1;
+                    if in_degree[to_comp] == 0 {
+                        let first = components[to_comp][0];
+                        ready.push(Reverse((first, to_comp)));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut topo = TopoOrder {
+        order,
+        orientations,
+        ..Default::default()
+    };
+
+    let ranks = topo.ranks();
+    topo.feedback_links = graph
+        .edges()
+        .filter(|&(from, to, _)| ranks[to] <= ranks[from])
+        .map(|(_, _, link)| link)
+        .collect();
+    topo.feedback_links.sort_unstable();
+
+    topo.cycles = components
+        .into_iter()
+        .filter(|comp| {
+            comp.len() > 1
+                || graph.succ[comp[0]].iter().any(|&(to, _)| to == comp[0])
+        })
+        .collect();
+    topo.cycles.sort_unstable();
+
+    Ok(topo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::GFAParser;
+    use crate::test_util::parse;
+
+    fn is_topological(gfa: &GFA<Vec<u8>, ()>, topo: &TopoOrder) -> bool {
+        let graph = SegmentGraph::from_oriented(gfa, &topo.orientations);
+        let ranks = topo.ranks();
+        let valid = graph.edges().all(|(from, to, link)| {
+            ranks[from] < ranks[to] || topo.feedback_links.contains(&link)
+        });
+        valid
+    }
+
+    #[test]
+    fn sort_dag() {
+        let gfa = parse(&[
+            "S\tc\tA",
+            "S\ta\tA",
+            "S\tb\tA",
+            "S\td\tA",
+            "L\ta\t+\tb\t+\t0M",
+            "L\tb\t+\tc\t+\t0M",
+            "L\td\t-\tc\t-\t0M",
+        ]);
+        let topo = topological_sort(&gfa).unwrap();
+        assert!(topo.is_dag());
+        assert!(topo.feedback_links.is_empty());
+        // a, b, c, d
+        assert_eq!(topo.order, vec![1, 2, 0, 3]);
+        assert!(is_topological(&gfa, &topo));
+    }
+
+    #[test]
+    fn sort_mixed_orientations() {
+        use Orientation::*;
+
+        // c is reversed relative to a, b, and d
+        let gfa = parse(&[
+            "S\tc\tA",
+            "S\ta\tA",
+            "S\tb\tA",
+            "S\td\tA",
+            "L\ta\t+\tb\t+\t0M",
+            "L\tb\t+\tc\t-\t0M",
+            "L\td\t+\tc\t-\t0M",
+        ]);
+        let topo = topological_sort(&gfa).unwrap();
+        assert!(topo.is_dag());
+        assert_eq!(
+            topo.orientations,
+            vec![Backward, Forward, Forward, Forward]
+        );
+        // a, b, d, c
+        assert_eq!(topo.order, vec![1, 2, 3, 0]);
+        assert!(is_topological(&gfa, &topo));
+    }
+
+    #[test]
+    fn reject_inconsistent_orientations() {
+        // b is traversed in both orientations between a and c
+        let gfa: GFA<Vec<u8>, ()> = parse(&[
+            "S\ta\tA",
+            "S\tb\tA",
+            "S\tc\tA",
+            "L\ta\t+\tb\t+\t0M",
+            "L\tb\t+\tc\t+\t0M",
+            "L\ta\t+\tb\t-\t0M",
+            "L\tb\t-\tc\t+\t0M",
+        ]);
+        assert!(topological_sort(&gfa).is_err());
+    }
+
+    #[test]
+    fn sort_with_cycles() {
+        let gfa = parse(&[
+            "S\t1\tA",
+            "S\t2\tA",
+            "S\t3\tA",
+            "S\t4\tA",
+            "S\t5\tA",
+            "L\t1\t+\t2\t+\t0M",
+            "L\t2\t+\t3\t+\t0M",
+            "L\t3\t+\t2\t+\t0M",
+            "L\t3\t+\t4\t+\t0M",
+            "L\t4\t+\t4\t+\t0M",
+            "L\t4\t+\t5\t+\t0M",
+        ]);
+        let topo = topological_sort(&gfa).unwrap();
+        assert!(!topo.is_dag());
+        assert_eq!(topo.order, vec![0, 1, 2, 3, 4]);
+        assert_eq!(topo.cycles, vec![vec![1, 2], vec![3]]);
+        assert_eq!(topo.feedback_links, vec![2, 4]);
+        assert!(is_topological(&gfa, &topo));
+    }
+
+    #[test]
+    fn sort_lil_gfa() {
+        let parser: GFAParser<Vec<u8>, ()> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+        let topo = topological_sort(&gfa).unwrap();
+
+        let mut sorted = topo.order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..gfa.segments.len()).collect::<Vec<_>>());
+        assert!(is_topological(&gfa, &topo));
+    }
+}
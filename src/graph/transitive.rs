@@ -0,0 +1,191 @@
+use crate::{
+    gfa::{dedup::remove_indices, GFA},
+    optfields::*,
+    validation::segment_length,
+};
+
+use super::{flip, match_overlap, out_side, segment_indices, Handle, Side};
+
+use fnv::FnvHashMap;
+
+use std::hash::Hash;
+
+/// An edge of the string graph, from the handle it leaves, as the
+/// handle it enters, the length of the sequence it adds, and the
+/// index of its link.
+type Edge = (Handle, usize, usize);
+
+/// The outgoing edges of each side, sorted by length. Every link is
+/// an edge in both directions, and links whose overlap or segment
+/// lengths aren't known, as well as self-links, are left out.
+fn string_graph<N, T>(gfa: &GFA<N, T>) -> FnvHashMap<Side, Vec<Edge>>
+where
+    N: Eq + Hash,
+    T: OptFields,
+{
+    let indices = segment_indices(gfa);
+    let lengths: Vec<_> = gfa.segments.iter().map(segment_length).collect();
+    let mut edges: FnvHashMap<Side, Vec<Edge>> = FnvHashMap::default();
+
+    for (link_ix, link) in gfa.links.iter().enumerate() {
+        let from = indices.get(&link.from_segment);
+        let to = indices.get(&link.to_segment);
+        let (from, to) = match (from, to) {
+            (Some(&from), Some(&to)) if from != to => (from, to),
+            _ => continue,
+        };
+        let overlap = match match_overlap(&link.overlap) {
+            Some(overlap) => overlap,
+            None => continue,
+        };
+        let from_len = lengths[from].and_then(|l| l.checked_sub(overlap));
+        let to_len = lengths[to].and_then(|l| l.checked_sub(overlap));
+        if let (Some(from_len), Some(to_len)) = (from_len, to_len) {
+            let from = (from, link.from_orient);
+            let to = (to, link.to_orient);
+            edges
+                .entry(out_side(from))
+                .or_default()
+                .push((to, to_len, link_ix));
+            edges.entry(out_side(flip(to))).or_default().push((
+                flip(from),
+                from_len,
+                link_ix,
+            ));
+        }
+    }
+
+    edges.values_mut().for_each(|out| out.sort_by_key(|e| e.1));
+    edges
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    InPlay,
+    Eliminated,
+}
+
+/// Find the links that are implied by a path of two shorter links,
+/// using the transitive reduction algorithm from Myers' "The fragment
+/// assembly string graph" (2005). An edge from `v` to `x` is
+/// transitive if there's an edge from `v` to `w` and from `w` to `x`
+/// whose lengths, i.e. the sequence they add beyond the overlap, sum
+/// to at most the longest edge out of `v` plus `fuzz`. Edges out of
+/// `w` that are shorter than `fuzz`, or the shortest out of `w`, also
+/// eliminate their targets, to tolerate small errors in the overlap
+/// lengths.
+///
+/// The overlaps must be `*` or consist only of matches, as in the
+/// overlap graphs built by `gafpaf::overlap_graph`, and the segment
+/// lengths must be known from their sequences or LN tags. Other links
+/// are never reduced, nor used to reduce others. Returns the sorted
+/// indices of the transitive links.
+pub fn find_transitive_links<N, T>(gfa: &GFA<N, T>, fuzz: usize) -> Vec<usize>
+where
+    N: Eq + Hash,
+    T: OptFields,
+{
+    let graph = string_graph(gfa);
+    let out_edges = |handle: Handle| -> &[Edge] {
+        graph.get(&out_side(handle)).map_or(&[], Vec::as_slice)
+    };
+
+    let mut reduced = vec![false; gfa.links.len()];
+    let mut marks: FnvHashMap<Handle, Mark> = FnvHashMap::default();
+    for out in graph.values() {
+        let longest = match out.last() {
+            Some(&(_, len, _)) => len + fuzz,
+            None => continue,
+        };
+        marks.clear();
+        for &(w, _, _) in out {
+            marks.insert(w, Mark::InPlay);
+        }
+
+        for &(w, len_vw, _) in out {
+            if marks.get(&w) != Some(&Mark::InPlay) {
+                continue;
+            }
+            for &(x, len_wx, _) in out_edges(w) {
+                if len_vw + len_wx > longest {
+                    break;
+                }
+                if let Some(mark) = marks.get_mut(&x) {
+                    *mark = Mark::Eliminated;
+                }
+            }
+        }
+
+        for &(w, _, _) in out {
+            let edges = out_edges(w);
+            for (ix, &(x, len_wx, _)) in edges.iter().enumerate() {
+                if len_wx >= fuzz && ix > 0 {
+                    break;
+                }
+                if let Some(mark) = marks.get_mut(&x) {
+                    *mark = Mark::Eliminated;
+                }
+            }
+        }
+
+        for &(w, _, link) in out {
+            if marks.get(&w) == Some(&Mark::Eliminated) {
+                reduced[link] = true;
+            }
+        }
+    }
+
+    reduced
+        .iter()
+        .enumerate()
+        .filter_map(|(ix, &reduced)| if reduced { Some(ix) } else { None })
+        .collect()
+}
+
+/// Remove the links found by `find_transitive_links`, turning an
+/// overlap graph into a string graph, and return the number of links
+/// removed. Paths are left unchanged.
+pub fn transitive_reduction<N, T>(gfa: &mut GFA<N, T>, fuzz: usize) -> usize
+where
+    N: Eq + Hash,
+    T: OptFields,
+{
+    let reduced = find_transitive_links(gfa, fuzz);
+    remove_indices(&mut gfa.links, &reduced);
+    reduced.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::GFAParser;
+
+    #[test]
+    fn reduce_transitive_links() {
+        // reads of length 10, each overlapping the next by 7, the one
+        // after that by 4, and the one after that by 1, with 3 on the
+        // reverse strand; only the links between consecutive reads
+        // aren't transitive
+        let lines = [
+            "S\t1\t*\tLN:i:10",
+            "S\t2\t*\tLN:i:10",
+            "S\t3\t*\tLN:i:10",
+            "S\t4\t*\tLN:i:10",
+            "L\t1\t+\t2\t+\t7M",
+            "L\t2\t+\t3\t-\t7M",
+            "L\t3\t-\t4\t+\t7M",
+            "L\t1\t+\t3\t-\t4M",
+            "L\t4\t-\t2\t-\t4M",
+            "L\t1\t+\t4\t+\t1M",
+        ];
+        let parser = GFAParser::new();
+        let mut gfa: GFA<usize, OptionalFields> =
+            parser.parse_lines(lines.iter()).unwrap();
+
+        assert_eq!(find_transitive_links(&gfa, 0), vec![3, 4, 5]);
+
+        assert_eq!(transitive_reduction(&mut gfa, 0), 3);
+        assert_eq!(gfa.links.len(), 3);
+        assert_eq!(find_transitive_links(&gfa, 0), Vec::<usize>::new());
+    }
+}
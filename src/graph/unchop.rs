@@ -0,0 +1,472 @@
+use crate::{
+    gfa::{raw_walk_steps, Orientation, Segment, SegmentId, GFA},
+    optfields::*,
+    seq::revcomp,
+};
+
+use super::{
+    flip, in_side, match_overlap, out_side, segment_indices, Handle, Side,
+};
+
+use bstr::ByteSlice;
+use fnv::{FnvHashMap, FnvHashSet};
+
+use std::{collections::VecDeque, hash::Hash};
+
+fn oriented_sequence(seq: &[u8], orient: Orientation) -> Vec<u8> {
+    match orient {
+        Orientation::Forward => seq.to_vec(),
+        Orientation::Backward => revcomp(seq).into(),
+    }
+}
+
+/// The segment handles of the steps of a path, with `None` for steps
+/// that don't refer to a known segment.
+fn step_handles<N: SegmentId + Eq + Hash>(
+    steps: &[u8],
+    indices: &FnvHashMap<&N, usize>,
+) -> Vec<Option<Handle>> {
+    steps
+        .split_str(b",")
+        .map(|step| {
+            let (name, orient) = step.split_at(step.len().checked_sub(1)?);
+            let orient = Orientation::from_bytes_plus_minus(orient)?;
+            let ix = *indices.get(&N::parse_id(name)?)?;
+            Some((ix, orient))
+        })
+        .collect()
+}
+
+/// The segment handles of the steps of a walk, as `step_handles`
+/// does for a path.
+fn walk_step_handles<N: SegmentId + Eq + Hash>(
+    walk: &[u8],
+    indices: &FnvHashMap<&N, usize>,
+) -> Vec<Option<Handle>> {
+    raw_walk_steps(walk)
+        .map(|step| {
+            let (orient, name) = step.split_at(1);
+            let orient = Orientation::from_bytes_gt_ln(orient)?;
+            let ix = *indices.get(&N::parse_id(name)?)?;
+            Some((ix, orient))
+        })
+        .collect()
+}
+
+struct Adjacency {
+    /// The links incident to each side.
+    sides: FnvHashMap<Side, Vec<usize>>,
+    /// The link endpoints, as handles.
+    links: Vec<Option<(Handle, Handle)>>,
+}
+
+impl Adjacency {
+    /// The handle reached from `handle` through its only link, if it
+    /// has exactly one.
+    fn next(&self, handle: Handle) -> Option<(Handle, usize)> {
+        match self.sides.get(&out_side(handle))?.as_slice() {
+            [link] => {
+                let (from, to) = self.links[*link]?;
+                if from == handle {
+                    Some((to, *link))
+                } else {
+                    Some((flip(from), *link))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn has_single_link(&self, side: Side) -> bool {
+        self.sides.get(&side).map(|links| links.len()) == Some(1)
+    }
+}
+
+/// Collapse every maximal chain of segments joined by non-branching
+/// links into a single segment, and return the number of segments
+/// that were removed.
+///
+/// Two segments are merged if the link between them is the only link
+/// on both of the joined sides, and every path or walk that traverses
+/// one of the sides continues through the other; segments without a
+/// sequence, and segments that are part of a containment, are never
+/// merged. The link overlaps must be `*` or consist only of matches,
+/// whose length is removed from the start of the next sequence.
+///
+/// A merged segment takes the name and position of the first
+/// segment in its chain, and its optional fields, except LN and SH.
+/// Links at the ends of the chain are rewritten to refer to it, and
+/// paths and walks have their steps through the chain replaced by a
+/// single step.
+pub fn unchop<N, T>(gfa: &mut GFA<N, T>) -> usize
+where
+    N: SegmentId + Clone + Eq + Hash,
+    T: OptFields,
+{
+    let count = gfa.segments.len();
+    let indices = segment_indices(gfa);
+
+    let mut adjacency = Adjacency {
+        sides: FnvHashMap::default(),
+        links: Vec::with_capacity(gfa.links.len()),
+    };
+    for (ix, link) in gfa.links.iter().enumerate() {
+        let from = indices.get(&link.from_segment);
+        let to = indices.get(&link.to_segment);
+        let handles = match (from, to) {
+            (Some(&from), Some(&to)) => {
+                let from = (from, link.from_orient);
+                let to = (to, link.to_orient);
+                adjacency.sides.entry(out_side(from)).or_default().push(ix);
+                adjacency.sides.entry(in_side(to)).or_default().push(ix);
+                Some((from, to))
+            }
+            _ => None,
+        };
+        adjacency.links.push(handles);
+    }
+
+    let mut mergeable: Vec<bool> =
+        gfa.segments.iter().map(|s| s.sequence != b"*").collect();
+    for cont in gfa.containments.iter() {
+        for name in [&cont.container_name, &cont.contained_name].iter() {
+            if let Some(&ix) = indices.get(name) {
+                mergeable[ix] = false;
+            }
+        }
+    }
+
+    // Sides that paths and walks start or end at, or leave other than
+    // through their only link, can't be merged
+    let mut boundaries: FnvHashSet<Side> = FnvHashSet::default();
+    let path_handles: Vec<_> = gfa
+        .paths
+        .iter()
+        .map(|path| step_handles(&path.segment_names, &indices))
+        .collect();
+    let walk_handles: Vec<_> = gfa
+        .walks
+        .iter()
+        .map(|walk| walk_step_handles(&walk.walk, &indices))
+        .collect();
+    for handles in path_handles.iter().chain(walk_handles.iter()) {
+        for (i, handle) in handles.iter().enumerate() {
+            let handle = match handle {
+                Some(handle) => *handle,
+                None => continue,
+            };
+            if i == 0 || handles[i - 1].is_none() {
+                boundaries.insert(in_side(handle));
+            }
+            match handles.get(i + 1).copied().flatten() {
+                Some(next) => {
+                    if adjacency.next(handle).map(|(h, _)| h) != Some(next) {
+                        boundaries.insert(out_side(handle));
+                        boundaries.insert(in_side(next));
+                    }
+                }
+                None => {
+                    boundaries.insert(out_side(handle));
+                }
+            }
+        }
+    }
+
+    // The handle that `handle` can be merged with, and the link
+    // between them
+    let merge_next = |handle: Handle| -> Option<(Handle, usize)> {
+        let (next, link) = adjacency.next(handle)?;
+        let joinable = next.0 != handle.0
+            && mergeable[handle.0]
+            && mergeable[next.0]
+            && adjacency.has_single_link(in_side(next))
+            && !boundaries.contains(&out_side(handle))
+            && !boundaries.contains(&in_side(next))
+            && match_overlap(&gfa.links[link].overlap).is_some();
+        if joinable {
+            Some((next, link))
+        } else {
+            None
+        }
+    };
+
+    // Each chain, as its handles and the links between them
+    let mut chains: Vec<(Vec<Handle>, Vec<usize>)> = Vec::new();
+    let mut visited = vec![false; count];
+    for seg in 0..count {
+        if visited[seg] {
+            continue;
+        }
+        visited[seg] = true;
+        let mut handles = VecDeque::new();
+        let mut links = VecDeque::new();
+        handles.push_back((seg, Orientation::Forward));
+
+        let mut current = (seg, Orientation::Forward);
+        while let Some((next, link)) = merge_next(current) {
+            if visited[next.0] {
+                break;
+            }
+            visited[next.0] = true;
+            handles.push_back(next);
+            links.push_back(link);
+            current = next;
+        }
+
+        let mut current = (seg, Orientation::Forward);
+        while let Some((prev, link)) = merge_next(flip(current)) {
+            let prev = flip(prev);
+            if visited[prev.0] {
+                break;
+            }
+            visited[prev.0] = true;
+            handles.push_front(prev);
+            links.push_front(link);
+            current = prev;
+        }
+
+        if handles.len() > 1 {
+            chains.push((handles.into(), links.into()));
+        }
+    }
+
+    if chains.is_empty() {
+        return 0;
+    }
+
+    // The chain, position in the chain, and orientation in the
+    // chain, of each merged segment
+    let mut chain_of: Vec<Option<(usize, usize, Orientation)>> =
+        vec![None; count];
+    let mut merged_links = vec![false; gfa.links.len()];
+    let mut merged_segments = Vec::with_capacity(chains.len());
+    for (chain_ix, (handles, links)) in chains.iter().enumerate() {
+        let mut sequence = Vec::new();
+        for (i, &(seg, orient)) in handles.iter().enumerate() {
+            chain_of[seg] = Some((chain_ix, i, orient));
+            let seq = oriented_sequence(&gfa.segments[seg].sequence, orient);
+            let skip = match i {
+                0 => 0,
+                _ => {
+                    match_overlap(&gfa.links[links[i - 1]].overlap).unwrap_or(0)
+                }
+            };
+            sequence.extend_from_slice(seq.get(skip..).unwrap_or(&[]));
+        }
+        for &link in links.iter() {
+            merged_links[link] = true;
+        }
+
+        let first = &gfa.segments[handles[0].0];
+        let fields: Vec<_> = first
+            .optional
+            .to_fields()
+            .iter()
+            .filter(|f| &f.tag != b"LN" && &f.tag != b"SH")
+            .map(|f| f.to_string())
+            .collect();
+        merged_segments.push((first.name.clone(), sequence, T::parse(fields)));
+    }
+
+    let names: Vec<N> = gfa.segments.iter().map(|s| s.name.clone()).collect();
+    let rename = |name: &N, orient: Orientation| -> (N, Orientation) {
+        let ix = match indices.get(name) {
+            Some(&ix) => ix,
+            None => return (name.clone(), orient),
+        };
+        match chain_of[ix] {
+            Some((chain, _, chain_orient)) => {
+                let first = chains[chain].0[0].0;
+                let orient = Orientation::from(orient == chain_orient);
+                (names[first].clone(), orient)
+            }
+            None => (name.clone(), orient),
+        }
+    };
+
+    let mut new_links = Vec::with_capacity(gfa.links.len());
+    for (ix, link) in gfa.links.iter().enumerate() {
+        if merged_links[ix] {
+            continue;
+        }
+        let mut link = link.clone();
+        let (from, from_orient) = rename(&link.from_segment, link.from_orient);
+        let (to, to_orient) = rename(&link.to_segment, link.to_orient);
+        link.from_segment = from;
+        link.from_orient = from_orient;
+        link.to_segment = to;
+        link.to_orient = to_orient;
+        new_links.push(link);
+    }
+
+    // The indices of the steps that are kept, and the merged segment
+    // and orientation of the ones that are replaced
+    let merge_steps = |handles: &[Option<Handle>]| {
+        let mut kept = Vec::with_capacity(handles.len());
+        for (i, handle) in handles.iter().enumerate() {
+            match handle.map(|h| (h, chain_of[h.0])) {
+                Some(((_, orient), Some((chain, pos, chain_orient)))) => {
+                    let last = chains[chain].0.len() - 1;
+                    let forward = orient == chain_orient;
+                    if (forward && pos != 0) || (!forward && pos != last) {
+                        continue;
+                    }
+                    let first = chains[chain].0[0].0;
+                    kept.push((i, Some((first, Orientation::from(forward)))));
+                }
+                _ => kept.push((i, None)),
+            }
+        }
+        kept
+    };
+    let in_chain = |handles: &[Option<Handle>]| {
+        handles.iter().flatten().any(|h| chain_of[h.0].is_some())
+    };
+
+    for (path, handles) in gfa.paths.iter_mut().zip(path_handles.iter()) {
+        if !in_chain(handles) {
+            continue;
+        }
+        let old_steps: Vec<_> = path.segment_names.split_str(b",").collect();
+        let kept = merge_steps(handles);
+        let mut steps = Vec::with_capacity(path.segment_names.len());
+        for &(i, replacement) in kept.iter() {
+            if !steps.is_empty() {
+                steps.push(b',');
+            }
+            match replacement {
+                Some((first, orient)) => {
+                    let step = format!("{}{}", names[first].display(), orient);
+                    steps.extend_from_slice(step.as_bytes());
+                }
+                None => steps.extend_from_slice(old_steps[i]),
+            }
+        }
+
+        if path.overlaps.len() + 1 == handles.len() {
+            path.overlaps = kept
+                .iter()
+                .skip(1)
+                .map(|&(i, _)| path.overlaps[i - 1].clone())
+                .collect();
+        }
+        path.segment_names = steps;
+    }
+
+    for (walk, handles) in gfa.walks.iter_mut().zip(walk_handles.iter()) {
+        if !in_chain(handles) {
+            continue;
+        }
+        let old_steps: Vec<_> = raw_walk_steps(&walk.walk).collect();
+        let mut steps = Vec::with_capacity(walk.walk.len());
+        for (i, replacement) in merge_steps(handles) {
+            match replacement {
+                Some((first, orient)) => {
+                    steps.push(orient.gt_ln_as_byte());
+                    steps.extend(names[first].display().bytes());
+                }
+                None => steps.extend_from_slice(old_steps[i]),
+            }
+        }
+        walk.walk = steps;
+    }
+
+    let mut merged_segments = merged_segments.into_iter();
+    let segments = std::mem::take(&mut gfa.segments);
+    for (ix, seg) in segments.into_iter().enumerate() {
+        match chain_of[ix] {
+            Some((_, 0, _)) => {
+                let (name, sequence, optional) =
+                    merged_segments.next().unwrap();
+                gfa.segments.push(Segment {
+                    name,
+                    sequence,
+                    optional,
+                });
+            }
+            Some(_) => (),
+            None => gfa.segments.push(seg),
+        }
+    }
+    gfa.links = new_links;
+
+    count - gfa.segments.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    #[test]
+    fn unchop_linear_chain() {
+        let mut gfa: GFA<Vec<u8>, OptionalFields> = parse(&[
+            "S\t1\tAC\tLN:i:2\tRC:i:4",
+            "S\t2\tGTT",
+            "S\t3\tCA",
+            "S\t4\tG",
+            "S\t5\tT",
+            "L\t1\t+\t2\t-\t0M",
+            "L\t3\t-\t2\t+\t1M",
+            "L\t3\t+\t4\t+\t*",
+            "L\t3\t+\t5\t+\t*",
+            "P\tp\t1+,2-,3+,4+\t0M,0M,*",
+            "P\tq\t5-,3-,2+,1-\t*,1M,0M",
+            "W\tHG1\t1\tchr1\t0\t8\t>5<3>2<1",
+        ]);
+
+        let removed = unchop(&mut gfa);
+        assert_eq!(removed, 2);
+        assert_eq!(gfa.segments.len(), 3);
+
+        let merged = &gfa.segments[0];
+        assert_eq!(merged.name, b"1");
+        // AC + rc(GTT) + CA, with a 1 base overlap on the last link
+        assert_eq!(merged.sequence, b"ACAACA");
+        assert_eq!(merged.optional.len(), 1);
+        assert_eq!(merged.optional[0].tag, *b"RC");
+
+        assert_eq!(gfa.links.len(), 2);
+        assert_eq!(gfa.links[0].from_segment, b"1");
+        assert_eq!(gfa.links[0].from_orient, Orientation::Forward);
+        assert_eq!(gfa.links[0].to_segment, b"4");
+
+        assert_eq!(gfa.paths[0].segment_names, b"1+,4+");
+        assert_eq!(gfa.paths[0].overlaps, vec![None]);
+        assert_eq!(gfa.paths[1].segment_names, b"5-,1-");
+        assert_eq!(gfa.walks[0].walk, b">5<1");
+    }
+
+    #[test]
+    fn unchop_respects_paths_and_branches() {
+        let mut gfa: GFA<Vec<u8>, OptionalFields> = parse(&[
+            "S\t1\tA",
+            "S\t2\tC",
+            "S\t3\tG",
+            "S\t4\tT",
+            "L\t1\t+\t2\t+\t*",
+            "L\t2\t+\t3\t+\t*",
+            "L\t2\t+\t4\t+\t*",
+            "L\t4\t+\t4\t-\t*",
+            "P\tp\t1+\t*",
+        ]);
+        let orig = gfa.clone();
+        // 1-2 is the only non-branching link, but p ends at 1
+        assert_eq!(unchop(&mut gfa), 0);
+        assert_eq!(gfa, orig);
+
+        // as does the walk
+        gfa.paths.clear();
+        gfa.walks =
+            parse::<Vec<u8>, OptionalFields>(&["W\tHG1\t1\tchr1\t*\t*\t<1"])
+                .walks;
+        assert_eq!(unchop(&mut gfa), 0);
+
+        gfa.walks.clear();
+        assert_eq!(unchop(&mut gfa), 1);
+        assert_eq!(gfa.segments[0].sequence, b"AC");
+        assert_eq!(gfa.links.len(), 3);
+        assert_eq!(gfa.links[0].from_segment, b"1");
+        assert_eq!(gfa.links[1].from_segment, b"1");
+    }
+}
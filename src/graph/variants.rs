@@ -0,0 +1,323 @@
+use crate::{
+    gfa::{Orientation, PathRef, SegmentId, GFA},
+    optfields::*,
+    seq::revcomp,
+    validation::segment_length,
+};
+
+use super::{
+    find_bubbles, flip, segment_indices, Handle, MixedOrientationError,
+};
+
+use bstr::ByteSlice;
+use fnv::FnvHashMap;
+
+use std::{
+    hash::Hash,
+    io::{self, Write},
+};
+
+/// A variant site: a bubble traversed by the reference path, with
+/// the alleles spelled along the reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    /// The segment where the reference enters the bubble, as an
+    /// index into `GFA::segments`.
+    pub source: usize,
+    /// The segment where the reference leaves the bubble.
+    pub sink: usize,
+    /// The 0-based position on the reference path of the first base
+    /// of the reference allele, i.e. right after the source.
+    pub position: usize,
+    /// The last base of the source, used to pad the alleles in VCF.
+    pub anchor: u8,
+    pub ref_allele: Vec<u8>,
+    /// The distinct alleles of the other traversals of the bubble,
+    /// in the order of the paths or walks that traverse them first.
+    pub alt_alleles: Vec<Vec<u8>>,
+}
+
+/// The reasons `find_variants` can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantError {
+    /// There's no path or walk with the reference name, or it
+    /// traverses a segment that's missing from the GFA.
+    MissingReference,
+    /// The bubbles can't be found, as in `find_bubbles`.
+    MixedOrientation(MixedOrientationError),
+}
+
+impl std::fmt::Display for VariantError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VariantError::MissingReference => {
+                write!(f, "The reference is missing from the GFA")
+            }
+            VariantError::MixedOrientation(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for VariantError {}
+
+impl From<MixedOrientationError> for VariantError {
+    fn from(err: MixedOrientationError) -> Self {
+        VariantError::MixedOrientation(err)
+    }
+}
+
+/// The handles of the steps of a path or walk, or `None` if a step
+/// doesn't refer to a segment in the GFA.
+fn path_handles<N, T>(
+    path: PathRef<'_, N, T>,
+    indices: &FnvHashMap<&N, usize>,
+) -> Option<Vec<Handle>>
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    path.steps()
+        .map(|(name, orient)| Some((*indices.get(&name)?, orient)))
+        .collect()
+}
+
+/// Find the first traversal of the bubble by the steps, as the step
+/// indices of the two endpoints, and whether the source comes first.
+fn traversal(
+    steps: &[Handle],
+    source: usize,
+    sink: usize,
+) -> Option<(usize, usize, bool)> {
+    let entry = steps
+        .iter()
+        .position(|&(seg, _)| seg == source || seg == sink)?;
+    let forward = steps[entry].0 == source;
+    let other = if forward { sink } else { source };
+    let exit =
+        entry + steps[entry..].iter().position(|&(seg, _)| seg == other)?;
+    Some((entry, exit, forward))
+}
+
+/// The sequence of the handles, or `None` if a segment has no
+/// sequence. Overlaps between the steps are ignored.
+fn spell<N, T, I>(gfa: &GFA<N, T>, handles: I) -> Option<Vec<u8>>
+where
+    T: OptFields,
+    I: Iterator<Item = Handle>,
+{
+    let mut seq = Vec::new();
+    for (seg, orient) in handles {
+        let segment = &gfa.segments[seg].sequence;
+        if segment == b"*" {
+            return None;
+        }
+        match orient {
+            Orientation::Forward => seq.extend_from_slice(segment),
+            Orientation::Backward => seq.extend_from_slice(&revcomp(segment)),
+        }
+    }
+    Some(seq)
+}
+
+/// Find the variants in the bubbles of the graph that the reference
+/// path traverses, as in `find_bubbles`. The reference allele is
+/// spelled from the steps of the reference path between the source
+/// and sink, and the alternative alleles from the steps of the other
+/// paths and walks, reverse complemented if they traverse the bubble
+/// in the opposite direction. Only the first traversal of a bubble by each
+/// path is used, and sites without an alternative allele aren't
+/// included; nested bubbles are reported separately, so their
+/// variants can overlap. The variants are sorted by position.
+///
+/// Positions are computed from the segment lengths, ignoring
+/// overlaps. The reference can also be a walk, named as by
+/// `Walk::name`. Returns `VariantError::MissingReference` if there's
+/// no path or walk with the reference name, or if it traverses a
+/// segment that's missing from the GFA.
+pub fn find_variants<N, T>(
+    gfa: &GFA<N, T>,
+    reference: &[u8],
+) -> Result<Vec<Variant>, VariantError>
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    let indices = segment_indices(gfa);
+    let (ref_ix, ref_path) = gfa
+        .path_refs()
+        .enumerate()
+        .find(|(_, path)| path.name() == reference)
+        .ok_or(VariantError::MissingReference)?;
+    let ref_steps = path_handles(ref_path, &indices)
+        .ok_or(VariantError::MissingReference)?;
+    let paths: Vec<_> = gfa
+        .path_refs()
+        .enumerate()
+        .filter(|&(ix, _)| ix != ref_ix)
+        .filter_map(|(_, path)| path_handles(path, &indices))
+        .collect();
+
+    let mut offsets = Vec::with_capacity(ref_steps.len() + 1);
+    offsets.push(0);
+    for &(seg, _) in ref_steps.iter() {
+        let len = segment_length(&gfa.segments[seg]).unwrap_or(0);
+        offsets.push(offsets.last().unwrap() + len);
+    }
+
+    let mut variants = Vec::new();
+    for bubble in find_bubbles(gfa)? {
+        let (entry, exit, ref_forward) =
+            match traversal(&ref_steps, bubble.source, bubble.sink) {
+                Some(found) => found,
+                None => continue,
+            };
+        let ref_allele =
+            match spell(gfa, ref_steps[entry + 1..exit].iter().copied()) {
+                Some(allele) => allele,
+                None => continue,
+            };
+
+        let mut alt_alleles: Vec<Vec<u8>> = Vec::new();
+        for steps in paths.iter() {
+            let (start, end, forward) =
+                match traversal(steps, bubble.source, bubble.sink) {
+                    Some(found) => found,
+                    None => continue,
+                };
+            let interior = steps[start + 1..end].iter().copied();
+            let allele = if forward == ref_forward {
+                spell(gfa, interior)
+            } else {
+                spell(gfa, interior.rev().map(flip))
+            };
+            if let Some(allele) = allele {
+                if allele != ref_allele && !alt_alleles.contains(&allele) {
+                    alt_alleles.push(allele);
+                }
+            }
+        }
+        if alt_alleles.is_empty() {
+            continue;
+        }
+
+        let anchor = spell(gfa, std::iter::once(ref_steps[entry]))
+            .and_then(|seq| seq.last().copied())
+            .unwrap_or(b'N');
+        let (source, sink) = (ref_steps[entry].0, ref_steps[exit].0);
+        variants.push(Variant {
+            source,
+            sink,
+            position: offsets[entry + 1],
+            anchor,
+            ref_allele,
+            alt_alleles,
+        });
+    }
+
+    variants.sort_by_key(|v| (v.position, v.source, v.sink));
+    Ok(variants)
+}
+
+/// Write the variants as a minimal VCF, without samples, on the
+/// given chromosome. The alleles are padded with the anchor base, so
+/// `POS` is the 1-based position of the anchor.
+pub fn write_vcf<W: Write>(
+    variants: &[Variant],
+    chrom: &str,
+    stream: &mut W,
+) -> io::Result<()> {
+    writeln!(stream, "##fileformat=VCFv4.2")?;
+    writeln!(stream, "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO")?;
+    for variant in variants {
+        let padded = |allele: &[u8]| {
+            let mut padded = vec![variant.anchor];
+            padded.extend_from_slice(allele);
+            padded.to_ascii_uppercase()
+        };
+        let alts: Vec<_> = variant
+            .alt_alleles
+            .iter()
+            .map(|alt| padded(alt).to_str_lossy().into_owned())
+            .collect();
+        writeln!(
+            stream,
+            "{}\t{}\t.\t{}\t{}\t.\t.\t.",
+            chrom,
+            variant.position,
+            padded(&variant.ref_allele).as_bstr(),
+            alts.join(",")
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::GFAParser, test_util::parse};
+
+    #[test]
+    fn variants_on_reference() {
+        // a SNP between 2 and 3, and a deletion of 5; path c traverses
+        // the graph in reverse
+        let lines = [
+            "S\t1\tACGT",
+            "S\t2\tA",
+            "S\t3\tG",
+            "S\t4\tTT",
+            "S\t5\tCA",
+            "S\t6\tGG",
+            "L\t1\t+\t2\t+\t0M",
+            "L\t1\t+\t3\t+\t0M",
+            "L\t2\t+\t4\t+\t0M",
+            "L\t3\t+\t4\t+\t0M",
+            "L\t4\t+\t5\t+\t0M",
+            "L\t5\t+\t6\t+\t0M",
+            "L\t4\t+\t6\t+\t0M",
+            "P\tref\t1+,2+,4+,5+,6+\t*",
+            "P\ta\t1+,3+,4+,6+\t*",
+            "P\tb\t1+,2+,4+,5+,6+\t*",
+            "P\tc\t6-,4-,3-,1-\t*",
+        ];
+        let gfa: GFA<usize, ()> = parse(&lines);
+
+        let variants = find_variants(&gfa, b"ref").unwrap();
+        assert_eq!(variants.len(), 2);
+        assert_eq!(variants[0].position, 4);
+        assert_eq!(variants[0].ref_allele, b"A");
+        assert_eq!(variants[0].alt_alleles, vec![b"G".to_vec()]);
+        assert_eq!(variants[1].position, 7);
+        assert_eq!(variants[1].ref_allele, b"CA");
+        assert_eq!(variants[1].alt_alleles, vec![Vec::<u8>::new()]);
+
+        let mut vcf = Vec::new();
+        write_vcf(&variants, "chr1", &mut vcf).unwrap();
+        let lines: Vec<_> = vcf.to_str().unwrap().lines().skip(2).collect();
+        assert_eq!(
+            lines,
+            vec!["chr1\t4\t.\tTA\tTG\t.\t.\t.", "chr1\t7\t.\tTCA\tT\t.\t.\t."]
+        );
+
+        assert_eq!(
+            find_variants(&gfa, b"missing"),
+            Err(VariantError::MissingReference)
+        );
+    }
+
+    #[test]
+    fn variants_of_walks() {
+        let parser = GFAParser::new();
+        let gfa: GFA<usize, ()> =
+            parser.parse_file("./test/gfas/walks.gfa").unwrap();
+
+        let variants = find_variants(&gfa, b"ref").unwrap();
+        assert_eq!(variants.len(), 1);
+        assert_eq!(variants[0].position, 4);
+        assert_eq!(variants[0].ref_allele, b"G");
+        assert_eq!(variants[0].alt_alleles, vec![b"T".to_vec()]);
+
+        let variants = find_variants(&gfa, b"HG002#1#chr1").unwrap();
+        assert_eq!(variants[0].ref_allele, b"T");
+        assert_eq!(variants[0].alt_alleles, vec![b"G".to_vec()]);
+    }
+}
@@ -0,0 +1,210 @@
+//! Read coverage annotation of segments with the `KC` (k-mer count)
+//! and `RC` (read count) tags, computed either by matching the
+//! k-mers of read sequences to the segments, or from the coverage of
+//! GAF alignments.
+
+use fnv::FnvHashMap;
+
+use crate::{gafpaf::coverage::Coverage, gfa::*, optfields::*};
+
+/// The largest supported k-mer size, as k-mers are packed into a u64.
+pub const MAX_K: usize = 32;
+
+fn base_code(base: u8) -> Option<u64> {
+    match base.to_ascii_uppercase() {
+        b'A' => Some(0),
+        b'C' => Some(1),
+        b'G' => Some(2),
+        b'T' => Some(3),
+        _ => None,
+    }
+}
+
+/// Call `f` with the canonical encoding of each k-mer in the
+/// sequence, i.e. the smaller of the encodings of the k-mer and its
+/// reverse complement. K-mers containing anything other than `ACGT`
+/// are skipped.
+fn for_each_kmer<F: FnMut(u64)>(seq: &[u8], k: usize, mut f: F) {
+    let mask = if k == MAX_K {
+        u64::MAX
+    } else {
+        (1 << (2 * k)) - 1
+    };
+    let shift = 2 * (k - 1);
+    let (mut fwd, mut rev, mut valid) = (0u64, 0u64, 0);
+    for &base in seq {
+        match base_code(base) {
+            Some(code) => {
+                fwd = ((fwd << 2) | code) & mask;
+                rev = (rev >> 2) | ((3 - code) << shift);
+                valid += 1;
+            }
+            None => valid = 0,
+        }
+        if valid >= k {
+            f(fwd.min(rev));
+        }
+    }
+}
+
+/// The number of k-mers and reads matched to each segment, indexed
+/// like `GFA::segments`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SegmentCounts {
+    pub kmers: Vec<u64>,
+    pub reads: Vec<u64>,
+}
+
+impl SegmentCounts {
+    /// Counts from the coverage of GAF alignments, with the number of
+    /// aligned bases as the k-mer count, i.e. using k = 1.
+    pub fn from_coverage(coverage: &Coverage) -> Self {
+        SegmentCounts {
+            kmers: coverage.bases.clone(),
+            reads: coverage.reads.clone(),
+        }
+    }
+}
+
+/// Counts the k-mers of reads on the segments of a GFA, one read at a
+/// time. A k-mer of a read is counted on every segment that contains
+/// it on either strand, and a read is counted on every segment it
+/// shares a k-mer with.
+pub struct KmerCounter {
+    k: usize,
+    index: FnvHashMap<u64, Vec<usize>>,
+    counts: SegmentCounts,
+    hits: Vec<usize>,
+}
+
+impl KmerCounter {
+    /// Index the k-mers of the segment sequences. Returns `None` if
+    /// `k` is 0 or larger than `MAX_K`.
+    pub fn new<N, T: OptFields>(gfa: &GFA<N, T>, k: usize) -> Option<Self> {
+        if k == 0 || k > MAX_K {
+            return None;
+        }
+        let mut index: FnvHashMap<u64, Vec<usize>> = FnvHashMap::default();
+        for (ix, seg) in gfa.segments.iter().enumerate() {
+            for_each_kmer(&seg.sequence, k, |kmer| {
+                let segments = index.entry(kmer).or_default();
+                if segments.last() != Some(&ix) {
+                    segments.push(ix);
+                }
+            });
+        }
+        let counts = SegmentCounts {
+            kmers: vec![0; gfa.segments.len()],
+            reads: vec![0; gfa.segments.len()],
+        };
+        Some(KmerCounter {
+            k,
+            index,
+            counts,
+            hits: Vec::new(),
+        })
+    }
+
+    pub fn add_read(&mut self, read: &[u8]) {
+        let KmerCounter {
+            k,
+            index,
+            counts,
+            hits,
+        } = self;
+        hits.clear();
+        for_each_kmer(read, *k, |kmer| {
+            for &ix in index.get(&kmer).into_iter().flatten() {
+                counts.kmers[ix] += 1;
+                hits.push(ix);
+            }
+        });
+        hits.sort_unstable();
+        hits.dedup();
+        hits.iter().for_each(|&ix| counts.reads[ix] += 1);
+    }
+
+    pub fn finish(self) -> SegmentCounts {
+        self.counts
+    }
+}
+
+/// Count the k-mers of the given reads on the segments of the GFA.
+/// Returns `None` if `k` is 0 or larger than `MAX_K`.
+pub fn count_read_kmers<N, T, I>(
+    gfa: &GFA<N, T>,
+    k: usize,
+    reads: I,
+) -> Option<SegmentCounts>
+where
+    T: OptFields,
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    let mut counter = KmerCounter::new(gfa, k)?;
+    for read in reads {
+        counter.add_read(read.as_ref());
+    }
+    Some(counter.finish())
+}
+
+impl<N> GFA<N, OptionalFields> {
+    /// Set the KC and RC tags of each segment from the counts,
+    /// replacing any existing KC and RC tags. Segments without counts
+    /// are left unchanged.
+    pub fn set_count_tags(&mut self, counts: &SegmentCounts) {
+        let counts = counts.kmers.iter().zip(counts.reads.iter());
+        for (seg, (&kmers, &reads)) in self.segments.iter_mut().zip(counts) {
+            seg.optional.retain(|f| &f.tag != b"KC" && &f.tag != b"RC");
+            seg.optional
+                .push(OptField::new(b"KC", OptFieldVal::Int(kmers as i64)));
+            seg.optional
+                .push(OptField::new(b"RC", OptFieldVal::Int(reads as i64)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    #[test]
+    fn count_kmers_on_segments() {
+        let lines = ["S\t1\tACGTAC\tKC:i:9", "S\t2\tTTTTG", "S\t3\t*"];
+        let mut gfa: GFA<usize, OptionalFields> = parse(&lines);
+
+        // GTA is in 1, CAAAA is the reverse complement of the end of 2,
+        // and N breaks up the k-mers of the last read
+        let reads = ["CGTA", "CAAAA", "ACNGT", "GGG"];
+        let counts = count_read_kmers(&gfa, 3, reads.iter()).unwrap();
+        assert_eq!(counts.kmers, vec![2, 3, 0]);
+        assert_eq!(counts.reads, vec![1, 1, 0]);
+
+        gfa.set_count_tags(&counts);
+        let tags: Vec<_> = gfa.segments[0]
+            .optional
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        assert_eq!(tags, vec!["KC:i:2", "RC:i:1"]);
+
+        assert!(count_read_kmers(&gfa, 0, reads.iter()).is_none());
+        assert!(count_read_kmers(&gfa, 33, reads.iter()).is_none());
+    }
+
+    #[test]
+    fn canonical_kmers() {
+        let mut kmers = Vec::new();
+        for_each_kmer(b"ACGTT", 4, |kmer| kmers.push(kmer));
+        let mut rev = Vec::new();
+        for_each_kmer(b"AACGT", 4, |kmer| rev.push(kmer));
+        rev.reverse();
+        assert_eq!(kmers, rev);
+
+        let mut long = Vec::new();
+        let seq = b"ACGTACGTACGTACGTACGTACGTACGTACGTA";
+        for_each_kmer(seq, MAX_K, |kmer| long.push(kmer));
+        assert_eq!(long.len(), 2);
+    }
+}
@@ -1,6 +1,80 @@
+#[cfg(feature = "gzip")]
+pub mod bgzf;
+#[cfg(feature = "checksum")]
+pub mod checksum;
 pub mod cigar;
+pub mod fasta;
 pub mod gafpaf;
 pub mod gfa;
+pub mod graph;
+pub mod kmer;
 pub mod optfields;
+pub mod pangenome;
 pub mod parser;
+pub mod reader;
+pub mod rgfa;
+pub mod seq;
+#[cfg(feature = "serde1")]
+mod serde_impls;
+pub mod stats;
+pub mod validation;
 pub mod writer;
+
+/// Helpers shared by the unit tests.
+#[cfg(test)]
+pub(crate) mod test_util {
+    use std::{
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use crate::{
+        gafpaf::{parse_gaf, GAF},
+        gfa::{SegmentId, GFA},
+        optfields::{OptFields, OptionalFields},
+        parser::GFAParser,
+        rgfa::RGfa,
+    };
+
+    /// Parse a GFA from its lines, panicking if any is invalid.
+    pub(crate) fn parse<N: SegmentId, T: OptFields>(
+        lines: &[&str],
+    ) -> GFA<N, T> {
+        let parser = GFAParser::new();
+        parser
+            .parse_lines(lines.iter().map(|l| l.as_bytes()))
+            .unwrap()
+    }
+
+    /// The rGFA in `test/gfas/rgfa.gfa`.
+    pub(crate) fn rgfa() -> RGfa<Vec<u8>, OptionalFields> {
+        let parser = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/rgfa.gfa").unwrap();
+        RGfa::new(gfa).unwrap()
+    }
+
+    /// A GAF record of a 4 bp read aligned to the range from `start`
+    /// to `end` of the given path, whose length is `len`.
+    pub(crate) fn gaf(
+        path: &str,
+        len: usize,
+        start: usize,
+        end: usize,
+    ) -> GAF<()> {
+        let line = format!(
+            "r\t4\t0\t4\t+\t{}\t{}\t{}\t{}\t4\t4\t60",
+            path, len, start, end
+        );
+        parse_gaf(line.as_bytes().split(|&b| b == b'\t')).unwrap()
+    }
+
+    /// A path in the temporary directory that no other test, or
+    /// concurrent run of the tests, uses. The file name ends with
+    /// `name`, so that it keeps its extension.
+    pub(crate) fn temp_path(name: &str) -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let id = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let name = format!("rs-gfa-{}-{}-{}", std::process::id(), id, name);
+        std::env::temp_dir().join(name)
+    }
+}
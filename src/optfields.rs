@@ -1,4 +1,12 @@
-use bstr::ByteSlice;
+use bstr::{BStr, BString, ByteSlice};
+
+use std::{borrow::Cow, convert::TryFrom};
+
+#[cfg(feature = "serde1")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "derive")]
+pub use gfa_derive::OptFields;
 
 use lazy_static::lazy_static;
 use regex::bytes::Regex;
@@ -12,35 +20,183 @@ pub type NoOptionalFields = ();
 /// An optional field a la SAM. Identified by its tag, which is any
 /// two characters matching [A-Za-z][A-Za-z0-9].
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
 pub struct OptField {
+    #[cfg_attr(feature = "serde1", serde(with = "crate::serde_impls::tag"))]
     pub tag: [u8; 2],
     pub value: OptFieldVal,
 }
 
 /// enum for representing each of the SAM optional field types. The
-/// `B` type, which denotes either an integer or float array, is split
-/// in two variants, and they ignore the size modifiers in the spec,
-/// instead always holding i64 or f32.
+/// `B` type, which denotes a numeric array, holds a `BArray` that
+/// keeps the element type given in the field.
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
 pub enum OptFieldVal {
     A(u8),
     Int(i64),
     Float(f32),
-    Z(Vec<u8>),
-    J(Vec<u8>),
-    H(Vec<u32>),
-    BInt(Vec<i64>),
-    BFloat(Vec<f32>),
+    Z(
+        #[cfg_attr(
+            feature = "serde1",
+            serde(with = "crate::serde_impls::bytes")
+        )]
+        Vec<u8>,
+    ),
+    J(
+        #[cfg_attr(
+            feature = "serde1",
+            serde(with = "crate::serde_impls::bytes")
+        )]
+        Vec<u8>,
+    ),
+    H(Vec<u8>),
+    B(BArray),
+}
+
+#[cfg(feature = "serde1")]
+impl OptFieldVal {
+    /// Parse the contents of a `J` field as JSON. Returns `None` if
+    /// the value isn't a `J` field or isn't valid JSON.
+    pub fn json(&self) -> Option<serde_json::Value> {
+        match self {
+            OptFieldVal::J(json) => serde_json::from_slice(json).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// The contents of a `B` array field, one variant per subtype in the
+/// SAM spec: `c`, `C`, `s`, `S`, `i`, `I`, and `f`.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
+pub enum BArray {
+    Int8(Vec<i8>),
+    UInt8(Vec<u8>),
+    Int16(Vec<i16>),
+    UInt16(Vec<u16>),
+    Int32(Vec<i32>),
+    UInt32(Vec<u32>),
+    Float(Vec<f32>),
+}
+
+impl BArray {
+    /// The subtype character of the array, as used in the GFA and SAM
+    /// formats.
+    pub fn subtype(&self) -> u8 {
+        use BArray::*;
+        match self {
+            Int8(_) => b'c',
+            UInt8(_) => b'C',
+            Int16(_) => b's',
+            UInt16(_) => b'S',
+            Int32(_) => b'i',
+            UInt32(_) => b'I',
+            Float(_) => b'f',
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        use BArray::*;
+        match self {
+            Int8(x) => x.len(),
+            UInt8(x) => x.len(),
+            Int16(x) => x.len(),
+            UInt16(x) => x.len(),
+            Int32(x) => x.len(),
+            UInt32(x) => x.len(),
+            Float(x) => x.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Parse the contents of a `B` field, i.e. the subtype followed
+    /// by a comma-separated list of values. Returns `None` if the
+    /// subtype is unknown or any value doesn't fit the subtype.
+    fn parse(input: &[u8]) -> Option<Self> {
+        fn values<T: std::str::FromStr>(input: &[u8]) -> Option<Vec<T>> {
+            if input.is_empty() {
+                return Some(Vec::new());
+            }
+            input
+                .split_str(b",")
+                .map(|s| s.to_str().ok()?.parse().ok())
+                .collect()
+        }
+
+        use BArray::*;
+        let (&subtype, rest) = input.split_first()?;
+        // the values are preceded by a comma, but older versions of
+        // this crate wrote them without one
+        let rest = rest.strip_prefix(b",").unwrap_or(rest);
+        match subtype {
+            b'c' => values(rest).map(Int8),
+            b'C' => values(rest).map(UInt8),
+            b's' => values(rest).map(Int16),
+            b'S' => values(rest).map(UInt16),
+            b'i' => values(rest).map(Int32),
+            b'I' => values(rest).map(UInt32),
+            b'f' => values(rest).map(Float),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for BArray {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn write_values<T: std::fmt::Display>(
+            f: &mut std::fmt::Formatter<'_>,
+            values: &[T],
+        ) -> std::fmt::Result {
+            for x in values {
+                write!(f, ",{}", x)?
+            }
+            Ok(())
+        }
+
+        use BArray::*;
+        write!(f, "{}", char::from(self.subtype()))?;
+        match self {
+            Int8(x) => write_values(f, x),
+            UInt8(x) => write_values(f, x),
+            Int16(x) => write_values(f, x),
+            UInt16(x) => write_values(f, x),
+            Int32(x) => write_values(f, x),
+            UInt32(x) => write_values(f, x),
+            Float(x) => write_values(f, x),
+        }
+    }
+}
+
+/// The value of an uppercase hex digit, which must have been matched
+/// by the `H` field regex.
+fn hex_digit(digit: u8) -> u8 {
+    match digit {
+        b'0'..=b'9' => digit - b'0',
+        _ => digit - b'A' + 10,
+    }
 }
 
 impl OptField {
     /// Panics if the provided tag doesn't match the regex
     /// [A-Za-z][A-Za-z0-9].
     pub fn tag(t: &[u8]) -> [u8; 2] {
-        assert_eq!(t.len(), 2);
-        assert!(t[0].is_ascii_alphabetic());
-        assert!(t[1].is_ascii_alphanumeric());
-        [t[0], t[1]]
+        Self::checked_tag(t)
+            .expect("Optional field tag must match [A-Za-z][A-Za-z0-9]")
+    }
+
+    /// Returns `None` if the provided tag doesn't match the regex
+    /// [A-Za-z][A-Za-z0-9].
+    pub fn checked_tag(t: &[u8]) -> Option<[u8; 2]> {
+        match *t {
+            [a, b] if a.is_ascii_alphabetic() && b.is_ascii_alphanumeric() => {
+                Some([a, b])
+            }
+            _ => None,
+        }
     }
 
     /// Create a new OptField from a tag name and a value, panicking
@@ -55,8 +211,6 @@ impl OptField {
     /// <TAG>:<TYPE>:<VALUE>
     pub fn parse(input: &[u8]) -> Option<Self> {
         lazy_static! {
-            static ref RE_TAG: Regex =
-                Regex::new(r"(?-u)[A-Za-z][A-Za-z0-9]").unwrap();
             static ref RE_CHAR: Regex = Regex::new(r"(?-u)[!-~]").unwrap();
             static ref RE_INT: Regex = Regex::new(r"(?-u)[-+]?[0-9]+").unwrap();
             static ref RE_FLOAT: Regex =
@@ -68,7 +222,11 @@ impl OptField {
 
         use OptFieldVal::*;
 
-        let o_tag = input.get(0..=1)?;
+        let tag = Self::checked_tag(input.get(0..=1)?)?;
+
+        if input.get(2) != Some(&b':') || input.get(4) != Some(&b':') {
+            return None;
+        }
 
         let o_type = input.get(3)?;
 
@@ -99,28 +257,25 @@ impl OptField {
                 .find(o_contents)
                 .map(|s| s.as_bytes().into())
                 .map(J),
-            // bytearray
+            // bytearray, as pairs of hex digits
             b'H' => RE_BYTES
                 .find(o_contents)
-                .and_then(|s| s.as_bytes().to_str().ok())
-                .map(|s| s.chars().filter_map(|c| c.to_digit(16)))
-                .map(|s| H(s.collect())),
-            // float or int array
-            b'B' => {
-                let first = o_contents[0];
-                let rest = o_contents[1..]
-                    .split_str(b",")
-                    .filter_map(|s| s.as_bytes().to_str().ok());
-                if first == b'f' {
-                    Some(BFloat(rest.filter_map(|s| s.parse().ok()).collect()))
-                } else {
-                    Some(BInt(rest.filter_map(|s| s.parse().ok()).collect()))
-                }
-            }
+                .filter(|s| s.as_bytes().len() % 2 == 0)
+                .map(|s| {
+                    s.as_bytes()
+                        .chunks(2)
+                        .map(|pair| {
+                            (hex_digit(pair[0]) << 4) | hex_digit(pair[1])
+                        })
+                        .collect()
+                })
+                .map(H),
+            // numeric array
+            b'B' => BArray::parse(o_contents).map(B),
             _ => None,
         }?;
 
-        Some(Self::new(o_tag, o_val))
+        Some(OptField { tag, value: o_val })
     }
 }
 
@@ -142,28 +297,275 @@ impl std::fmt::Display for OptField {
             H(x) => {
                 write!(f, "H:")?;
                 for a in x {
-                    write!(f, "{:x}", a)?
+                    write!(f, "{:02X}", a)?
                 }
                 Ok(())
             }
-            BInt(x) => {
-                write!(f, "B:I{}", x[0])?;
-                for a in x[1..].iter() {
-                    write!(f, ",{}", a)?
+            B(x) => write!(f, "B:{}", x),
+        }
+    }
+}
+
+/// Types that the value of an optional field can be converted to by
+/// `OptFields::get_typed`. Each conversion checks the type of the
+/// stored field, and fails if it doesn't match:
+///
+/// * `i64` from `i` fields, and `usize` from non-negative `i` fields
+/// * `f32` and `f64` from `f` fields
+/// * `char` from `A` fields
+/// * `&BStr` and `&[u8]` from `Z` and `J` fields
+/// * `&BArray` from `B` fields
+pub trait FromOptFieldVal<'a>: Sized {
+    fn from_opt_field_val(value: &'a OptFieldVal) -> Option<Self>;
+
+    /// Convert a value that can't be borrowed for `'a`, such as one
+    /// built from a typed field of a derived `OptFields`. Only the
+    /// types that don't borrow from the value support this.
+    fn from_owned_opt_field_val(_value: OptFieldVal) -> Option<Self> {
+        None
+    }
+
+    /// Convert the contents of a `Z` field stored in a typed field.
+    fn from_string_val(_value: &'a [u8]) -> Option<Self> {
+        None
+    }
+
+    /// Convert the contents of a `B` field stored in a typed field.
+    fn from_array_val(_value: &'a BArray) -> Option<Self> {
+        None
+    }
+}
+
+macro_rules! owned_from_opt_field_val {
+    () => {
+        fn from_owned_opt_field_val(value: OptFieldVal) -> Option<Self> {
+            FromOptFieldVal::from_opt_field_val(&value)
+        }
+    };
+}
+
+impl<'a> FromOptFieldVal<'a> for i64 {
+    owned_from_opt_field_val!();
+
+    fn from_opt_field_val(value: &'a OptFieldVal) -> Option<Self> {
+        match value {
+            OptFieldVal::Int(x) => Some(*x),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromOptFieldVal<'a> for usize {
+    owned_from_opt_field_val!();
+
+    fn from_opt_field_val(value: &'a OptFieldVal) -> Option<Self> {
+        match value {
+            OptFieldVal::Int(x) if *x >= 0 => Some(*x as usize),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromOptFieldVal<'a> for f32 {
+    owned_from_opt_field_val!();
+
+    fn from_opt_field_val(value: &'a OptFieldVal) -> Option<Self> {
+        match value {
+            OptFieldVal::Float(x) => Some(*x),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromOptFieldVal<'a> for f64 {
+    owned_from_opt_field_val!();
+
+    fn from_opt_field_val(value: &'a OptFieldVal) -> Option<Self> {
+        f32::from_opt_field_val(value).map(f64::from)
+    }
+}
+
+impl<'a> FromOptFieldVal<'a> for char {
+    owned_from_opt_field_val!();
+
+    fn from_opt_field_val(value: &'a OptFieldVal) -> Option<Self> {
+        match value {
+            OptFieldVal::A(x) => Some(char::from(*x)),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromOptFieldVal<'a> for &'a [u8] {
+    fn from_opt_field_val(value: &'a OptFieldVal) -> Option<Self> {
+        match value {
+            OptFieldVal::Z(x) | OptFieldVal::J(x) => Some(x.as_slice()),
+            _ => None,
+        }
+    }
+
+    fn from_string_val(value: &'a [u8]) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl<'a> FromOptFieldVal<'a> for &'a BStr {
+    fn from_opt_field_val(value: &'a OptFieldVal) -> Option<Self> {
+        <&[u8]>::from_opt_field_val(value).map(|x| x.as_bstr())
+    }
+
+    fn from_string_val(value: &'a [u8]) -> Option<Self> {
+        Some(value.as_bstr())
+    }
+}
+
+impl<'a> FromOptFieldVal<'a> for &'a BArray {
+    fn from_opt_field_val(value: &'a OptFieldVal) -> Option<Self> {
+        match value {
+            OptFieldVal::B(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn from_array_val(value: &'a BArray) -> Option<Self> {
+        Some(value)
+    }
+}
+
+/// Types that can be stored in, and taken out of, an optional field
+/// value. Used by the code generated by `#[derive(OptFields)]` to
+/// parse and write tags stored in typed struct fields.
+pub trait TagValue: Sized {
+    fn from_tag_value(value: OptFieldVal) -> Option<Self>;
+
+    /// The value as an optional field value, or `None` if it can't be
+    /// stored in one, e.g. a `char` that isn't ASCII.
+    fn to_tag_value(&self) -> Option<OptFieldVal>;
+
+    /// Convert the value as `OptFields::get_typed` would convert the
+    /// field written by `to_tag_value`.
+    fn get_as<'a, V: FromOptFieldVal<'a>>(&'a self) -> Option<V> {
+        V::from_owned_opt_field_val(self.to_tag_value()?)
+    }
+}
+
+macro_rules! impl_tag_value {
+    ($($ty:ty => $var:ident),* $(,)?) => {
+        $(
+            impl TagValue for $ty {
+                fn from_tag_value(value: OptFieldVal) -> Option<Self> {
+                    match value {
+                        OptFieldVal::$var(x) => Some(x.into()),
+                        _ => None,
+                    }
                 }
-                Ok(())
-            }
-            BFloat(x) => {
-                write!(f, "B:F{}", x[0])?;
-                for a in x[1..].iter() {
-                    write!(f, ",{}", a)?
+
+                fn to_tag_value(&self) -> Option<OptFieldVal> {
+                    Some(OptFieldVal::$var(self.clone().into()))
                 }
-                Ok(())
             }
+        )*
+    };
+}
+
+impl_tag_value!(i64 => Int, f32 => Float);
+
+impl TagValue for Vec<u8> {
+    fn from_tag_value(value: OptFieldVal) -> Option<Self> {
+        match value {
+            OptFieldVal::Z(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn to_tag_value(&self) -> Option<OptFieldVal> {
+        Some(OptFieldVal::Z(self.clone()))
+    }
+
+    fn get_as<'a, V: FromOptFieldVal<'a>>(&'a self) -> Option<V> {
+        V::from_string_val(self)
+    }
+}
+
+impl TagValue for BString {
+    fn from_tag_value(value: OptFieldVal) -> Option<Self> {
+        Vec::from_tag_value(value).map(BString::from)
+    }
+
+    fn to_tag_value(&self) -> Option<OptFieldVal> {
+        Some(OptFieldVal::Z(self.to_vec()))
+    }
+
+    fn get_as<'a, V: FromOptFieldVal<'a>>(&'a self) -> Option<V> {
+        V::from_string_val(self.as_slice())
+    }
+}
+
+impl TagValue for BArray {
+    fn from_tag_value(value: OptFieldVal) -> Option<Self> {
+        match value {
+            OptFieldVal::B(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn to_tag_value(&self) -> Option<OptFieldVal> {
+        Some(OptFieldVal::B(self.clone()))
+    }
+
+    fn get_as<'a, V: FromOptFieldVal<'a>>(&'a self) -> Option<V> {
+        V::from_array_val(self)
+    }
+}
+
+impl TagValue for char {
+    fn from_tag_value(value: OptFieldVal) -> Option<Self> {
+        match value {
+            OptFieldVal::A(x) => Some(char::from(x)),
+            _ => None,
+        }
+    }
+
+    fn to_tag_value(&self) -> Option<OptFieldVal> {
+        u8::try_from(*self)
+            .ok()
+            .filter(u8::is_ascii)
+            .map(OptFieldVal::A)
+    }
+}
+
+impl TagValue for usize {
+    fn from_tag_value(value: OptFieldVal) -> Option<Self> {
+        match value {
+            OptFieldVal::Int(x) if x >= 0 => Some(x as usize),
+            _ => None,
         }
     }
+
+    fn to_tag_value(&self) -> Option<OptFieldVal> {
+        i64::try_from(*self).ok().map(OptFieldVal::Int)
+    }
+}
+
+/// A typed optional field whose value can't be written as an optional
+/// field, e.g. a `char` that isn't ASCII.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidTagValue {
+    pub tag: [u8; 2],
+}
+
+impl std::fmt::Display for InvalidTagValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The value of the {} field can't be written as an optional field",
+            self.tag.as_bstr()
+        )
+    }
 }
 
+impl std::error::Error for InvalidTagValue {}
+
 /// The OptFields trait describes how to parse, store, and query
 /// optional fields. Each of the GFA line types and the GFA struct
 /// itself are generic over the optional fields, so the choice of
@@ -171,12 +573,60 @@ impl std::fmt::Display for OptField {
 /// fields are parsed, and possibly more in the future
 pub trait OptFields: Sized + Default + Clone {
     /// Return the optional field with the given tag, if it exists.
+    /// Only sees the fields that are stored as `OptField`s; use
+    /// `get_value` or `get_typed` to include the others.
     fn get_field(&self, tag: &[u8]) -> Option<&OptField>;
 
-    /// Return a slice over all optional fields. NB: This may be
-    /// replaced by an iterator or something else in the future
+    /// Return a slice over all optional fields that are stored as
+    /// `OptField`s; use `to_fields` to include the others. NB: This
+    /// may be replaced by an iterator or something else in the future
     fn fields(&self) -> &[OptField];
 
+    /// Return all optional fields as `OptField`s, including any that
+    /// the implementor stores in some other form, leaving out those
+    /// that can't be written as one. The default implementation
+    /// borrows `fields`.
+    fn to_fields(&self) -> Cow<'_, [OptField]> {
+        Cow::Borrowed(self.fields())
+    }
+
+    /// Like `to_fields`, but fails if a field stored in some other
+    /// form can't be written as an `OptField`, where `to_fields`
+    /// leaves it out.
+    fn try_to_fields(&self) -> Result<Cow<'_, [OptField]>, InvalidTagValue> {
+        Ok(self.to_fields())
+    }
+
+    /// Return the value of the field with the given tag, including
+    /// fields that the implementor stores in some other form, in which
+    /// case the value is built when it's requested. The default
+    /// implementation borrows the value from `get_field`.
+    fn get_value(&self, tag: &[u8]) -> Option<Cow<'_, OptFieldVal>> {
+        self.get_field(tag).map(|field| Cow::Borrowed(&field.value))
+    }
+
+    /// Return the value of the field with the given tag, converted to
+    /// `V`, e.g. `get_typed::<i64>(b"LN")`. Returns `None` if there's
+    /// no such field, or if its type doesn't match `V`; see
+    /// `FromOptFieldVal` for the supported types.
+    fn get_typed<'a, V>(&'a self, tag: &[u8]) -> Option<V>
+    where
+        V: FromOptFieldVal<'a>,
+    {
+        match self.get_value(tag)? {
+            Cow::Borrowed(value) => V::from_opt_field_val(value),
+            Cow::Owned(value) => V::from_owned_opt_field_val(value),
+        }
+    }
+
+    /// Return the value of the `J` field with the given tag, parsed
+    /// as JSON. Returns `None` if there's no such field, if it's not
+    /// a `J` field, or if it's not valid JSON.
+    #[cfg(feature = "serde1")]
+    fn get_json(&self, tag: &[u8]) -> Option<serde_json::Value> {
+        self.get_value(tag)?.json()
+    }
+
     /// Given an iterator over bytestrings, each expected to hold one
     /// optional field (in the <TAG>:<TYPE>:<VALUE> format), parse
     /// them as optional fields to create a collection. Returns `Self`
@@ -186,6 +636,18 @@ pub trait OptFields: Sized + Default + Clone {
     where
         T: IntoIterator,
         T::Item: AsRef<[u8]>;
+
+    /// Add the fields of `other` to `self`, used when merging two
+    /// records into one. Fields whose tags are already present in
+    /// `self` are kept as they are. The default implementation
+    /// discards `other`.
+    fn merge(&mut self, _other: Self) {}
+
+    /// Sort the fields by tag, keeping the relative order of fields
+    /// with the same tag. Implementors that store some fields in a
+    /// fixed order need only sort the others. The default
+    /// implementation does nothing.
+    fn sort_by_tag(&mut self) {}
 }
 
 /// This implementation is useful for performance if we don't actually
@@ -231,4 +693,121 @@ impl OptFields for Vec<OptField> {
             .filter_map(|f| OptField::parse(f.as_ref()))
             .collect()
     }
+
+    fn merge(&mut self, other: Self) {
+        for field in other {
+            if self.get_field(&field.tag).is_none() {
+                self.push(field);
+            }
+        }
+    }
+
+    fn sort_by_tag(&mut self) {
+        self.sort_by_key(|f| f.tag);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_b(input: &str) -> Option<BArray> {
+        match OptField::parse(input.as_bytes())?.value {
+            OptFieldVal::B(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn b_array_subtypes() {
+        use BArray::*;
+        assert_eq!(parse_b("XB:B:c,-128,127"), Some(Int8(vec![-128, 127])));
+        assert_eq!(parse_b("XB:B:C,0,255"), Some(UInt8(vec![0, 255])));
+        assert_eq!(parse_b("XB:B:s,-300,3"), Some(Int16(vec![-300, 3])));
+        assert_eq!(parse_b("XB:B:S,65535"), Some(UInt16(vec![65535])));
+        assert_eq!(parse_b("XB:B:i,-70000"), Some(Int32(vec![-70000])));
+        assert_eq!(parse_b("XB:B:I,4294967295"), Some(UInt32(vec![u32::MAX])));
+        assert_eq!(parse_b("XB:B:f,1.5,-2"), Some(Float(vec![1.5, -2.0])));
+        assert_eq!(parse_b("XB:B:I"), Some(UInt32(Vec::new())));
+        // without the leading comma
+        assert_eq!(parse_b("XB:B:I1,2"), Some(UInt32(vec![1, 2])));
+
+        // values that don't fit the subtype
+        assert_eq!(parse_b("XB:B:c,128"), None);
+        assert_eq!(parse_b("XB:B:C,-1"), None);
+        assert_eq!(parse_b("XB:B:S,1.5"), None);
+        assert_eq!(parse_b("XB:B:x,1"), None);
+        assert_eq!(parse_b("XB:B:"), None);
+    }
+
+    #[test]
+    fn hex_byte_arrays() {
+        let field = OptField::parse(b"SH:H:1AE301").unwrap();
+        assert_eq!(field.value, OptFieldVal::H(vec![0x1a, 0xe3, 0x01]));
+        assert_eq!(field.to_string(), "SH:H:1AE301");
+
+        let field = OptField::new(b"XH", OptFieldVal::H(vec![0, 0xf, 0xff]));
+        assert_eq!(field.to_string(), "XH:H:000FFF");
+
+        assert!(OptField::parse(b"SH:H:1AE").is_none());
+    }
+
+    #[cfg(feature = "serde1")]
+    #[test]
+    fn json_fields() {
+        let fields: OptionalFields = OptFields::parse(vec![
+            &b"XJ:J:{\"name\":\"chr1\",\"pos\":[1,2]}"[..],
+            b"BJ:J:{not json",
+            b"LN:i:4",
+        ]);
+        let json = fields.get_json(b"XJ").unwrap();
+        assert_eq!(json["name"], "chr1");
+        assert_eq!(json["pos"][1], 2);
+        assert_eq!(fields.get_json(b"BJ"), None);
+        assert_eq!(fields.get_json(b"LN"), None);
+        assert_eq!(fields.get_json(b"XX"), None);
+    }
+
+    #[test]
+    fn typed_fields() {
+        let fields: OptionalFields = OptFields::parse(vec![
+            "LN:i:42",
+            "NG:i:-3",
+            "XF:f:0.5",
+            "XA:A:c",
+            "UR:Z:file://x.fa",
+            "XB:B:C,1,2",
+        ]);
+
+        assert_eq!(fields.get_typed::<i64>(b"LN"), Some(42));
+        assert_eq!(fields.get_typed::<usize>(b"LN"), Some(42));
+        assert_eq!(fields.get_typed::<usize>(b"NG"), None);
+        assert_eq!(fields.get_typed::<f32>(b"XF"), Some(0.5));
+        assert_eq!(fields.get_typed::<f64>(b"XF"), Some(0.5));
+        assert_eq!(fields.get_typed::<char>(b"XA"), Some('c'));
+        assert_eq!(
+            fields.get_typed::<&BStr>(b"UR"),
+            Some(b"file://x.fa".as_bstr())
+        );
+        assert_eq!(
+            fields.get_typed::<&BArray>(b"XB"),
+            Some(&BArray::UInt8(vec![1, 2]))
+        );
+
+        // wrong types and missing tags
+        assert_eq!(fields.get_typed::<f32>(b"LN"), None);
+        assert_eq!(fields.get_typed::<&[u8]>(b"LN"), None);
+        assert_eq!(fields.get_typed::<i64>(b"XF"), None);
+        assert_eq!(fields.get_typed::<i64>(b"ZZ"), None);
+    }
+
+    #[test]
+    fn b_array_round_trip() {
+        for field in
+            ["XB:B:c,-1,2", "XB:B:S,1,65535", "XB:B:f,0.5,-1", "XB:B:i"].iter()
+        {
+            let parsed = OptField::parse(field.as_bytes()).unwrap();
+            assert_eq!(parsed.to_string(), *field);
+        }
+    }
 }
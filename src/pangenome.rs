@@ -0,0 +1,17 @@
+//! Pangenome analyses of the paths and walks of a GFA, where each
+//! path or walk is a haplotype or assembly traversing the shared
+//! segments of the graph.
+
+pub mod classify;
+pub mod coverage;
+pub mod matrix;
+pub mod pansn;
+pub mod samples;
+pub mod similarity;
+
+pub use self::classify::*;
+pub use self::coverage::*;
+pub use self::matrix::*;
+pub use self::pansn::*;
+pub use self::samples::*;
+pub use self::similarity::*;
@@ -0,0 +1,225 @@
+use crate::{
+    gfa::{PathRef, SegmentId, GFA},
+    optfields::*,
+    validation::segment_length,
+};
+
+use super::{PanSN, PathCoverage};
+
+use fnv::FnvHashMap;
+
+use std::{borrow::Cow, hash::Hash};
+
+/// How paths and walks are grouped when counting the genomes a
+/// segment is present in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    /// Every path and walk counts on its own.
+    Paths,
+    /// Paths are grouped by the sample of their PanSN name, and walks
+    /// by their sample ID. Paths whose name isn't a PanSN name are
+    /// samples of their own.
+    Samples { delimiter: u8 },
+}
+
+impl Grouping {
+    fn key<'a, N, T: OptFields>(
+        &self,
+        path: PathRef<'a, N, T>,
+    ) -> Cow<'a, [u8]> {
+        match (self, path) {
+            (Grouping::Paths, _) => path.name(),
+            (Grouping::Samples { .. }, PathRef::Walk(walk)) => {
+                Cow::Borrowed(&walk.sample_id)
+            }
+            (Grouping::Samples { delimiter }, PathRef::Path(path)) => {
+                let name = &path.path_name;
+                match PanSN::parse_with(name, *delimiter) {
+                    Some(pansn) => Cow::Borrowed(pansn.sample),
+                    None => Cow::Borrowed(name),
+                }
+            }
+        }
+    }
+}
+
+/// The pangenome class of a segment, by the fraction of path groups
+/// it's present in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SegmentClass {
+    Core,
+    Shell,
+    Cloud,
+}
+
+/// The thresholds and grouping used by `classify_segments`. Segments
+/// present in at least `core` of the groups are core, those present
+/// in at least `shell` are shell, and the rest that are present in
+/// any group are cloud.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassifyOptions {
+    pub core: f64,
+    pub shell: f64,
+    pub grouping: Grouping,
+}
+
+impl Default for ClassifyOptions {
+    fn default() -> Self {
+        ClassifyOptions {
+            core: 0.95,
+            shell: 0.15,
+            grouping: Grouping::Samples { delimiter: b'#' },
+        }
+    }
+}
+
+/// The number of segments in a class, and their total length. The
+/// length only includes segments whose length is known.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ClassTotals {
+    pub segments: usize,
+    pub length: usize,
+}
+
+/// The class of each segment, indexed like `GFA::segments`, with
+/// `None` for segments not traversed by any path or walk.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Classification {
+    pub classes: Vec<Option<SegmentClass>>,
+    /// The number of path and walk groups, e.g. samples.
+    pub groups: usize,
+    pub core: ClassTotals,
+    pub shell: ClassTotals,
+    pub cloud: ClassTotals,
+}
+
+/// The number of distinct groups whose paths and walks traverse each
+/// segment, indexed like `GFA::segments`, along with the number of
+/// groups.
+fn group_coverage<N, T>(
+    gfa: &GFA<N, T>,
+    grouping: Grouping,
+) -> (Vec<usize>, usize)
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    let mut group_ids: FnvHashMap<Cow<'_, [u8]>, usize> = FnvHashMap::default();
+    let groups: Vec<usize> = gfa
+        .path_refs()
+        .map(|path| {
+            let next = group_ids.len();
+            *group_ids.entry(grouping.key(path)).or_insert(next)
+        })
+        .collect();
+
+    let coverage = PathCoverage::by_group(gfa, &groups);
+    let counts = coverage.segments.iter().map(|cov| cov.paths).collect();
+    (counts, group_ids.len())
+}
+
+/// Classify the segments as core, shell, or cloud by the fraction of
+/// path and walk groups, e.g. samples, that traverse them, and sum up
+/// the lengths of each class.
+pub fn classify_segments<N, T>(
+    gfa: &GFA<N, T>,
+    options: &ClassifyOptions,
+) -> Classification
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    let (counts, groups) = group_coverage(gfa, options.grouping);
+    let mut result = Classification {
+        groups,
+        ..Default::default()
+    };
+
+    for (seg, count) in gfa.segments.iter().zip(counts) {
+        let fraction = count as f64 / groups.max(1) as f64;
+        let class = if count == 0 {
+            None
+        } else if fraction >= options.core {
+            Some(SegmentClass::Core)
+        } else if fraction >= options.shell {
+            Some(SegmentClass::Shell)
+        } else {
+            Some(SegmentClass::Cloud)
+        };
+        let totals = match class {
+            Some(SegmentClass::Core) => &mut result.core,
+            Some(SegmentClass::Shell) => &mut result.shell,
+            Some(SegmentClass::Cloud) => &mut result.cloud,
+            None => {
+                result.classes.push(None);
+                continue;
+            }
+        };
+        totals.segments += 1;
+        totals.length += segment_length(seg).unwrap_or(0);
+        result.classes.push(class);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    #[test]
+    fn core_shell_and_cloud() {
+        let lines = [
+            "S\t1\tAAAA",
+            "S\t2\tCC",
+            "S\t3\tG",
+            "S\t4\tTTT",
+            "S\t5\tA",
+            "P\ta#1#chr1\t1+,2+,4+\t*",
+            "P\ta#2#chr1\t1+,3+,4+\t*",
+            "P\tb#1#chr1\t1+,2+\t*",
+            "P\tc#1#chr1\t1+,2+,4+\t*",
+            "W\tb\t1\tchr2\t*\t*\t>4",
+            "W\td\t1\tchr1\t*\t*\t>1>5",
+        ];
+        let gfa: GFA<usize, ()> = parse(&lines);
+        use SegmentClass::*;
+
+        let options = ClassifyOptions {
+            core: 1.0,
+            shell: 0.5,
+            ..Default::default()
+        };
+        let by_sample = classify_segments(&gfa, &options);
+        // the walk of b adds 4 to b, and d only has a walk
+        assert_eq!(by_sample.groups, 4);
+        assert_eq!(
+            by_sample.classes,
+            vec![
+                Some(Core),
+                Some(Shell),
+                Some(Cloud),
+                Some(Shell),
+                Some(Cloud)
+            ]
+        );
+        assert_eq!(
+            by_sample.core,
+            ClassTotals {
+                segments: 1,
+                length: 4
+            }
+        );
+        assert_eq!(by_sample.cloud.length, 2);
+
+        let options = ClassifyOptions {
+            grouping: Grouping::Paths,
+            ..options
+        };
+        let by_path = classify_segments(&gfa, &options);
+        assert_eq!(by_path.groups, 6);
+        assert_eq!(by_path.classes[0], Some(Shell));
+        assert_eq!(by_path.classes[1], Some(Shell));
+        assert_eq!(by_path.core.segments, 0);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{
+    gfa::{Orientation, SegmentId, GFA},
+    graph::segment_indices,
+    optfields::*,
+};
+
+use fnv::FnvHashMap;
+
+use std::hash::Hash;
+
+/// The number of paths and path steps that traverse a segment, in
+/// each orientation. Walks count as paths.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentPathCoverage {
+    /// The number of paths with at least one forward step on the
+    /// segment.
+    pub forward_paths: usize,
+    /// The number of paths with at least one reverse step on the
+    /// segment.
+    pub reverse_paths: usize,
+    /// The number of paths that traverse the segment in either
+    /// orientation.
+    pub paths: usize,
+    pub forward_steps: usize,
+    pub reverse_steps: usize,
+}
+
+impl SegmentPathCoverage {
+    /// The total number of steps on the segment.
+    pub fn steps(&self) -> usize {
+        self.forward_steps + self.reverse_steps
+    }
+}
+
+/// Path coverage of the segments of a GFA, indexed like
+/// `GFA::segments`, and also accessible by segment name.
+#[derive(Debug, Clone)]
+pub struct PathCoverage<'a, N> {
+    names: FnvHashMap<&'a N, usize>,
+    pub segments: Vec<SegmentPathCoverage>,
+}
+
+impl<'a, N: Eq + Hash> PathCoverage<'a, N> {
+    /// Count the paths and walks, and their steps, on each segment, in
+    /// a single pass over them. Steps that don't refer to a segment in
+    /// the GFA are skipped.
+    pub fn new<T: OptFields>(gfa: &'a GFA<N, T>) -> Self
+    where
+        N: SegmentId,
+    {
+        let groups: Vec<usize> = (0..gfa.path_refs().count()).collect();
+        Self::by_group(gfa, &groups)
+    }
+
+    /// Like `new`, but counting groups of paths, e.g. the paths of
+    /// each sample, rather than single paths, so that the path counts
+    /// are the number of groups. `groups` holds the group of each
+    /// path and walk, in the order of `GFA::path_refs`: the paths,
+    /// then the walks.
+    pub fn by_group<T: OptFields>(gfa: &'a GFA<N, T>, groups: &[usize]) -> Self
+    where
+        N: SegmentId,
+    {
+        let names = segment_indices(gfa);
+        let mut segments =
+            vec![SegmentPathCoverage::default(); gfa.segments.len()];
+        // the group, plus one, of the last path counted on each
+        // segment in each orientation
+        let mut last_forward = vec![0; segments.len()];
+        let mut last_reverse = vec![0; segments.len()];
+
+        // visit the paths of each group together, so a segment is
+        // counted once per group
+        let paths: Vec<_> = gfa.path_refs().collect();
+        let mut order: Vec<usize> = (0..paths.len()).collect();
+        order.sort_by_key(|&ix| groups[ix]);
+
+        for path_ix in order {
+            let path = &paths[path_ix];
+            let mark = groups[path_ix] + 1;
+            for (name, orient) in path.steps() {
+                let ix = match names.get(&name) {
+                    Some(&ix) => ix,
+                    None => continue,
+                };
+                let cov = &mut segments[ix];
+                if last_forward[ix] != mark && last_reverse[ix] != mark {
+                    cov.paths += 1;
+                }
+                match orient {
+                    Orientation::Forward => {
+                        cov.forward_steps += 1;
+                        if last_forward[ix] != mark {
+                            cov.forward_paths += 1;
+                            last_forward[ix] = mark;
+                        }
+                    }
+                    Orientation::Backward => {
+                        cov.reverse_steps += 1;
+                        if last_reverse[ix] != mark {
+                            cov.reverse_paths += 1;
+                            last_reverse[ix] = mark;
+                        }
+                    }
+                }
+            }
+        }
+
+        PathCoverage { names, segments }
+    }
+
+    /// The coverage of the segment with the given name.
+    pub fn get(&self, name: &N) -> Option<&SegmentPathCoverage> {
+        let ix = *self.names.get(name)?;
+        self.segments.get(ix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    #[test]
+    fn count_path_coverage() {
+        let lines = [
+            "S\t1\tA",
+            "S\t2\tC",
+            "S\t3\tG",
+            "S\t4\tT",
+            "P\tp\t1+,2+,3+,2-\t*",
+            "P\tq\t3-,2-,1-\t*",
+            "P\tr\t1+,5+,1+\t*",
+            "W\tHG1\t1\tchr1\t*\t*\t>4<4>1",
+        ];
+        let gfa: GFA<usize, ()> = parse(&lines);
+        let coverage = PathCoverage::new(&gfa);
+
+        let two = coverage.get(&2).unwrap();
+        assert_eq!(two.paths, 2);
+        assert_eq!(two.forward_paths, 1);
+        assert_eq!(two.reverse_paths, 2);
+        assert_eq!((two.forward_steps, two.reverse_steps), (1, 2));
+
+        let one = coverage.segments[0];
+        assert_eq!(
+            (one.paths, one.forward_paths, one.reverse_paths),
+            (4, 3, 1)
+        );
+        assert_eq!(one.steps(), 5);
+
+        let four = coverage.segments[3];
+        assert_eq!(
+            (four.paths, four.forward_paths, four.reverse_paths),
+            (1, 1, 1)
+        );
+        assert_eq!((four.forward_steps, four.reverse_steps), (1, 1));
+        assert!(coverage.get(&5).is_none());
+
+        // p, r, and the walk in one group, q in the other
+        let coverage = PathCoverage::by_group(&gfa, &[0, 1, 0, 0]);
+        let one = coverage.segments[0];
+        assert_eq!(
+            (one.paths, one.forward_paths, one.reverse_paths),
+            (2, 1, 1)
+        );
+        assert_eq!(one.steps(), 5);
+        assert_eq!(coverage.get(&2).unwrap().reverse_paths, 2);
+    }
+}
@@ -0,0 +1,170 @@
+//! Export of the segments × paths matrix of a pangenome graph, where
+//! walks count as paths, in
+//! sparse formats that can be loaded by PCA and clustering tools:
+//! Matrix Market coordinate files, or delimited tables with one row
+//! per non-zero entry.
+
+use crate::{
+    gfa::{SegmentId, GFA},
+    graph::segment_indices,
+    optfields::*,
+    writer::table::write_row,
+};
+
+use bstr::ByteSlice;
+use fnv::FnvHashMap;
+
+use std::{
+    hash::Hash,
+    io::{self, Write},
+};
+
+/// The values stored in a `PathMatrix`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatrixValues {
+    /// 1 if the path or walk traverses the segment.
+    Presence,
+    /// The number of times the path or walk traverses the segment.
+    Count,
+}
+
+/// A sparse matrix with a row for each segment and a column for each
+/// path and walk, in the order of `GFA::segments` and
+/// `GFA::path_refs`: the paths, then the walks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathMatrix {
+    pub rows: usize,
+    pub columns: usize,
+    /// The non-zero entries, as (row, column, value), sorted by
+    /// column and then row.
+    pub entries: Vec<(usize, usize, usize)>,
+}
+
+impl PathMatrix {
+    /// Build the matrix in a single pass over the paths and walks.
+    /// Steps that don't refer to a segment in the GFA are skipped.
+    pub fn new<N, T>(gfa: &GFA<N, T>, values: MatrixValues) -> Self
+    where
+        N: SegmentId + Eq + Hash,
+        T: OptFields,
+    {
+        let indices = segment_indices(gfa);
+        let mut entries = Vec::new();
+        let mut counts: FnvHashMap<usize, usize> = FnvHashMap::default();
+
+        for (column, path) in gfa.path_refs().enumerate() {
+            counts.clear();
+            for (name, _) in path.steps() {
+                if let Some(&row) = indices.get(&name) {
+                    *counts.entry(row).or_default() += 1;
+                }
+            }
+            let start = entries.len();
+            entries.extend(counts.iter().map(|(&row, &count)| {
+                let value = match values {
+                    MatrixValues::Presence => 1,
+                    MatrixValues::Count => count,
+                };
+                (row, column, value)
+            }));
+            entries[start..].sort_unstable();
+        }
+
+        PathMatrix {
+            rows: gfa.segments.len(),
+            columns: gfa.paths.len() + gfa.walks.len(),
+            entries,
+        }
+    }
+
+    /// Write the matrix in the Matrix Market coordinate format, with
+    /// 1-based indices.
+    pub fn write_matrix_market<W: Write>(
+        &self,
+        stream: &mut W,
+    ) -> io::Result<()> {
+        writeln!(stream, "%%MatrixMarket matrix coordinate integer general")?;
+        writeln!(
+            stream,
+            "{} {} {}",
+            self.rows,
+            self.columns,
+            self.entries.len()
+        )?;
+        for (row, column, value) in self.entries.iter() {
+            writeln!(stream, "{} {} {}", row + 1, column + 1, value)?;
+        }
+        Ok(())
+    }
+
+    /// Write the non-zero entries as a delimited table, with a header
+    /// row and the columns `segment`, `path`, and `value`, using the
+    /// names of the segments, paths, and walks of `gfa`, which must be
+    /// the GFA the matrix was built from. Walks are named by
+    /// `Walk::name`.
+    pub fn write_table<N, T, W>(
+        &self,
+        gfa: &GFA<N, T>,
+        delimiter: u8,
+        stream: &mut W,
+    ) -> io::Result<()>
+    where
+        N: SegmentId,
+        T: OptFields,
+        W: Write,
+    {
+        let header = ["segment", "path", "value"].map(String::from);
+        write_row(stream, delimiter, &header)?;
+        let paths: Vec<_> = gfa.path_refs().map(|p| p.name()).collect();
+        for &(row, column, value) in self.entries.iter() {
+            let cells = [
+                gfa.segments[row].name.display(),
+                paths[column].to_str_lossy().into_owned(),
+                value.to_string(),
+            ];
+            write_row(stream, delimiter, &cells)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    #[test]
+    fn export_path_matrix() {
+        let lines = [
+            "S\t1\tA",
+            "S\t2\tC",
+            "S\t3\tG",
+            "P\ta#1#chr1\t1+,2+,1+\t*",
+            "P\tb#1#chr1\t3-,2-\t*",
+            "W\tc\t1\tchr1\t*\t*\t>3>3",
+        ];
+        let gfa: GFA<Vec<u8>, ()> = parse(&lines);
+
+        let counts = PathMatrix::new(&gfa, MatrixValues::Count);
+        assert_eq!(
+            counts.entries,
+            vec![(0, 0, 2), (1, 0, 1), (1, 1, 1), (2, 1, 1), (2, 2, 2)]
+        );
+
+        let presence = PathMatrix::new(&gfa, MatrixValues::Presence);
+        let mut mtx = Vec::new();
+        presence.write_matrix_market(&mut mtx).unwrap();
+        assert_eq!(
+            mtx.to_str().unwrap(),
+            "%%MatrixMarket matrix coordinate integer general\n\
+             3 3 5\n1 1 1\n2 1 1\n2 2 1\n3 2 1\n3 3 1\n"
+        );
+
+        let mut csv = Vec::new();
+        counts.write_table(&gfa, b',', &mut csv).unwrap();
+        let csv = csv.to_str().unwrap();
+        assert!(csv.starts_with("segment,path,value\n1,a#1#chr1,2\n"));
+        assert!(csv.ends_with("3,c#1#chr1,2\n"));
+        assert_eq!(csv.lines().count(), 6);
+    }
+}
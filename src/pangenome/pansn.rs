@@ -0,0 +1,131 @@
+//! Path names following the Pangenome Sequence Naming convention,
+//! `sample#haplotype#contig`, as used by most pangenome graph
+//! builders. Walks store the same parts in fields of their own.
+
+use crate::{
+    gfa::{Path, Walk},
+    optfields::*,
+};
+
+use bstr::{BStr, ByteSlice};
+
+/// The default PanSN delimiter.
+pub const PANSN_DELIMITER: u8 = b'#';
+
+/// The parts of a PanSN name, borrowed from the name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PanSN<'a> {
+    pub sample: &'a BStr,
+    /// The haplotype, missing in names of the form `sample#contig`.
+    pub haplotype: Option<&'a BStr>,
+    /// The contig, which may itself contain the delimiter.
+    pub contig: &'a BStr,
+}
+
+impl<'a> PanSN<'a> {
+    /// Parse a name with the default `#` delimiter.
+    pub fn parse(name: &'a [u8]) -> Option<Self> {
+        Self::parse_with(name, PANSN_DELIMITER)
+    }
+
+    /// Parse a name with the given delimiter. Returns `None` if the
+    /// name doesn't contain the delimiter, or any of the parts is
+    /// empty.
+    pub fn parse_with(name: &'a [u8], delimiter: u8) -> Option<Self> {
+        let mut parts = name.splitn(3, |&b| b == delimiter);
+        let first = parts.next()?;
+        let second = parts.next()?;
+        let (haplotype, contig) = match parts.next() {
+            Some(contig) => (Some(second), contig),
+            None => (None, second),
+        };
+        let empty = haplotype.is_some_and(<[u8]>::is_empty);
+        if first.is_empty() || contig.is_empty() || empty {
+            return None;
+        }
+        Some(PanSN {
+            sample: first.as_bstr(),
+            haplotype: haplotype.map(ByteSlice::as_bstr),
+            contig: contig.as_bstr(),
+        })
+    }
+
+    /// The haplotype as a number, if it is one.
+    pub fn haplotype_number(&self) -> Option<u32> {
+        self.haplotype?.to_str().ok()?.parse().ok()
+    }
+}
+
+impl<N, T: OptFields> Path<N, T> {
+    /// The PanSN parts of the path name, with the default `#`
+    /// delimiter.
+    pub fn pansn(&self) -> Option<PanSN<'_>> {
+        PanSN::parse(&self.path_name)
+    }
+
+    /// The PanSN parts of the path name, with the given delimiter.
+    pub fn pansn_with(&self, delimiter: u8) -> Option<PanSN<'_>> {
+        PanSN::parse_with(&self.path_name, delimiter)
+    }
+}
+
+impl<N, T: OptFields> Walk<N, T> {
+    pub fn sample(&self) -> &BStr {
+        self.sample_id.as_bstr()
+    }
+
+    pub fn haplotype(&self) -> usize {
+        self.hap_index
+    }
+
+    /// The sequence the walk is on, the contig of the PanSN name.
+    pub fn contig(&self) -> &BStr {
+        self.seq_id.as_bstr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pansn_names() {
+        let name = PanSN::parse(b"HG002#1#chr1#alt").unwrap();
+        assert_eq!(name.sample, "HG002");
+        assert_eq!(name.haplotype, Some(b"1".as_bstr()));
+        assert_eq!(name.haplotype_number(), Some(1));
+        assert_eq!(name.contig, "chr1#alt");
+
+        let name = PanSN::parse(b"grch38#chrX").unwrap();
+        assert_eq!(name.sample, "grch38");
+        assert_eq!(name.haplotype, None);
+        assert_eq!(name.contig, "chrX");
+
+        let name = PanSN::parse_with(b"a.h2.ctg", b'.').unwrap();
+        assert_eq!(name.haplotype, Some(b"h2".as_bstr()));
+        assert_eq!(name.haplotype_number(), None);
+
+        assert_eq!(PanSN::parse(b"chr1"), None);
+        assert_eq!(PanSN::parse(b"a##chr1"), None);
+        assert_eq!(PanSN::parse(b"#1#chr1"), None);
+
+        let path: Path<usize, ()> =
+            Path::new(b"HG002#2#chr2".to_vec(), b"1+".to_vec(), vec![], ());
+        assert_eq!(path.pansn().unwrap().haplotype_number(), Some(2));
+        assert_eq!(path.pansn_with(b'_'), None);
+
+        let walk: Walk<usize, ()> = Walk::new(
+            b"HG002".to_vec(),
+            2,
+            b"chr2".to_vec(),
+            None,
+            None,
+            b">1".to_vec(),
+            (),
+        );
+        assert_eq!(walk.sample(), "HG002");
+        assert_eq!(walk.haplotype(), 2);
+        assert_eq!(walk.contig(), "chr2");
+        assert_eq!(PanSN::parse(&walk.name()).unwrap().contig, "chr2");
+    }
+}
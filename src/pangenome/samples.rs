@@ -0,0 +1,183 @@
+use crate::{
+    gfa::{PathRef, SegmentId, GFA},
+    graph::segment_indices,
+    optfields::*,
+    validation::segment_length,
+};
+
+use super::PANSN_DELIMITER;
+
+use bstr::{BStr, ByteSlice};
+
+use std::{borrow::Cow, collections::BTreeMap, hash::Hash};
+
+/// The paths and walks of each sample, grouped by haplotype, as
+/// returned by `GFA::paths_by_sample`.
+pub type SamplePaths<'a, N, T> =
+    BTreeMap<&'a BStr, BTreeMap<Option<Cow<'a, BStr>>, Vec<PathRef<'a, N, T>>>>;
+
+impl<N, T: OptFields> GFA<N, T> {
+    /// Group the paths by the sample and haplotype of their PanSN
+    /// names, with the default `#` delimiter, and the walks by their
+    /// sample IDs and haplotype indices. Paths whose name isn't a
+    /// PanSN name are a sample of their own, without a haplotype.
+    pub fn paths_by_sample(&self) -> SamplePaths<'_, N, T> {
+        self.paths_by_sample_with(PANSN_DELIMITER)
+    }
+
+    /// Group the paths by sample and haplotype, as in
+    /// `paths_by_sample`, with the given delimiter.
+    pub fn paths_by_sample_with(&self, delimiter: u8) -> SamplePaths<'_, N, T> {
+        let mut samples: SamplePaths<'_, N, T> = BTreeMap::new();
+        for path in self.path_refs() {
+            let (sample, haplotype) = match path {
+                PathRef::Path(p) => match p.pansn_with(delimiter) {
+                    Some(pansn) => {
+                        (pansn.sample, pansn.haplotype.map(Cow::Borrowed))
+                    }
+                    None => (p.path_name.as_bstr(), None),
+                },
+                PathRef::Walk(w) => {
+                    let haplotype = w.hap_index.to_string().into_bytes();
+                    (w.sample_id.as_bstr(), Some(Cow::Owned(haplotype.into())))
+                }
+            };
+            samples
+                .entry(sample)
+                .or_default()
+                .entry(haplotype)
+                .or_default()
+                .push(path);
+        }
+        samples
+    }
+}
+
+/// The distinct segments traversed by the paths of each sample, as
+/// sorted segment indices.
+fn sample_segments<'a, N, T>(
+    gfa: &GFA<N, T>,
+    samples: &SamplePaths<'a, N, T>,
+) -> Vec<(&'a BStr, Vec<usize>)>
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    let indices = segment_indices(gfa);
+    samples
+        .iter()
+        .map(|(&sample, haplotypes)| {
+            let mut segments: Vec<usize> = haplotypes
+                .values()
+                .flatten()
+                .flat_map(|path| path.steps())
+                .filter_map(|(name, _)| indices.get(&name).copied())
+                .collect();
+            segments.sort_unstable();
+            segments.dedup();
+            (sample, segments)
+        })
+        .collect()
+}
+
+/// The total length of the paths of each sample, as the sum of the
+/// lengths of the segments of every step, ignoring overlaps. Steps
+/// on segments of unknown length, or that aren't in the GFA, don't
+/// count.
+pub fn sample_lengths<'a, N, T>(
+    gfa: &GFA<N, T>,
+    samples: &SamplePaths<'a, N, T>,
+) -> BTreeMap<&'a BStr, usize>
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    let indices = segment_indices(gfa);
+    let lengths: Vec<_> = gfa
+        .segments
+        .iter()
+        .map(|seg| segment_length(seg).unwrap_or(0))
+        .collect();
+    samples
+        .iter()
+        .map(|(&sample, haplotypes)| {
+            let length = haplotypes
+                .values()
+                .flatten()
+                .flat_map(|path| path.steps())
+                .filter_map(|(name, _)| indices.get(&name))
+                .map(|&ix| lengths[ix])
+                .sum();
+            (sample, length)
+        })
+        .collect()
+}
+
+/// The number of distinct segments shared by each pair of samples,
+/// keyed by the pair in sorted order.
+pub fn shared_segments<'a, N, T>(
+    gfa: &GFA<N, T>,
+    samples: &SamplePaths<'a, N, T>,
+) -> BTreeMap<(&'a BStr, &'a BStr), usize>
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    let segments = sample_segments(gfa, samples);
+    let mut shared = BTreeMap::new();
+    for (i, (a, a_segs)) in segments.iter().enumerate() {
+        for (b, b_segs) in segments[i + 1..].iter() {
+            let count = a_segs
+                .iter()
+                .filter(|seg| b_segs.binary_search(seg).is_ok())
+                .count();
+            shared.insert((*a, *b), count);
+        }
+    }
+    shared
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    #[test]
+    fn group_and_aggregate_samples() {
+        let lines = [
+            "S\t1\tAAAA",
+            "S\t2\tCC",
+            "S\t3\tG",
+            "P\tb#1#chr1\t1+,2+\t*",
+            "P\ta#2#chr1\t1+,3+\t*",
+            "P\ta#1#chr1\t1+,2+\t*",
+            "P\ta#1#chr2\t2-\t*",
+            "P\tref\t1+,3+,1+\t*",
+            "W\tb\t2\tchr1\t*\t*\t>1>3",
+            "W\tc\t1\tchr1\t*\t*\t>2",
+            "W\ta\t1\tchr3\t*\t*\t>2",
+        ];
+        let gfa: GFA<usize, ()> = parse(&lines);
+
+        let samples = gfa.paths_by_sample();
+        let names: Vec<_> = samples.keys().map(|s| s.to_string()).collect();
+        assert_eq!(names, vec!["a", "b", "c", "ref"]);
+        let hap = |h: &'static str| Some(Cow::Borrowed(h.as_bytes().as_bstr()));
+        let a = &samples[b"a".as_bstr()];
+        assert_eq!(a.len(), 2);
+        assert_eq!(a[&hap("1")].len(), 3);
+        assert_eq!(samples[b"b".as_bstr()][&hap("2")].len(), 1);
+        assert_eq!(samples[b"ref".as_bstr()][&None].len(), 1);
+
+        let lengths = sample_lengths(&gfa, &samples);
+        assert_eq!(lengths[b"a".as_bstr()], 6 + 5 + 2 + 2);
+        assert_eq!(lengths[b"b".as_bstr()], 6 + 5);
+        assert_eq!(lengths[b"ref".as_bstr()], 9);
+
+        let shared = shared_segments(&gfa, &samples);
+        assert_eq!(shared.len(), 6);
+        assert_eq!(shared[&(b"a".as_bstr(), b"b".as_bstr())], 3);
+        assert_eq!(shared[&(b"b".as_bstr(), b"c".as_bstr())], 1);
+        assert_eq!(shared[&(b"b".as_bstr(), b"ref".as_bstr())], 2);
+    }
+}
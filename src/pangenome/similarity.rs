@@ -0,0 +1,154 @@
+use crate::{
+    gfa::{SegmentId, GFA},
+    graph::segment_indices,
+    optfields::*,
+    validation::segment_length,
+};
+
+use std::hash::Hash;
+
+/// A measure of the similarity of two paths or walks, from the sets
+/// of segments they traverse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimilarityMetric {
+    /// The size of the intersection divided by the size of the union.
+    Jaccard,
+    /// The fraction of the segments of the first path that are also
+    /// in the second; not symmetric.
+    Containment,
+}
+
+/// A square matrix of the similarities between each pair of paths
+/// and walks, in the order of `GFA::path_refs`: the paths, then the
+/// walks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarityMatrix {
+    pub size: usize,
+    /// The similarities in row-major order.
+    pub values: Vec<f64>,
+}
+
+impl SimilarityMatrix {
+    /// The similarity of path `row` to path `column`.
+    pub fn get(&self, row: usize, column: usize) -> Option<f64> {
+        if row >= self.size || column >= self.size {
+            return None;
+        }
+        self.values.get(row * self.size + column).copied()
+    }
+}
+
+/// The sum of the weights of the segments in both sorted sets.
+fn shared_weight(a: &[usize], b: &[usize], weights: &[f64]) -> f64 {
+    let (mut i, mut j) = (0, 0);
+    let mut shared = 0.0;
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                shared += weights[a[i]];
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    shared
+}
+
+/// Compute the similarity between every pair of paths and walks, from
+/// the distinct segments they traverse regardless of orientation. If
+/// `length_weighted` is true, each segment counts with its length
+/// instead of once, and segments of unknown length don't count.
+/// Similarities involving a path or walk without any segments are 0.
+pub fn path_similarity<N, T>(
+    gfa: &GFA<N, T>,
+    metric: SimilarityMetric,
+    length_weighted: bool,
+) -> SimilarityMatrix
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    let indices = segment_indices(gfa);
+    let weights: Vec<f64> = gfa
+        .segments
+        .iter()
+        .map(|seg| {
+            if length_weighted {
+                segment_length(seg).unwrap_or(0) as f64
+            } else {
+                1.0
+            }
+        })
+        .collect();
+
+    let sets: Vec<Vec<usize>> = gfa
+        .path_refs()
+        .map(|path| {
+            let mut set: Vec<usize> = path
+                .steps()
+                .filter_map(|(name, _)| indices.get(&name).copied())
+                .collect();
+            set.sort_unstable();
+            set.dedup();
+            set
+        })
+        .collect();
+    let totals: Vec<f64> = sets
+        .iter()
+        .map(|set| set.iter().map(|&ix| weights[ix]).sum())
+        .collect();
+
+    let size = sets.len();
+    let mut values = vec![0.0; size * size];
+    for i in 0..size {
+        for j in 0..size {
+            let shared = shared_weight(&sets[i], &sets[j], &weights);
+            let total = match metric {
+                SimilarityMetric::Jaccard => totals[i] + totals[j] - shared,
+                SimilarityMetric::Containment => totals[i],
+            };
+            if total > 0.0 {
+                values[i * size + j] = shared / total;
+            }
+        }
+    }
+
+    SimilarityMatrix { size, values }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    #[test]
+    fn jaccard_and_containment() {
+        let lines = [
+            "S\t1\tAAAA",
+            "S\t2\tC",
+            "S\t3\tGG",
+            "P\tp\t1+,2+,1+\t*",
+            "P\tq\t1-,2-,3-\t*",
+            "P\tr\t4+\t*",
+            "W\tHG1\t1\tchr1\t*\t*\t>3<2",
+        ];
+        let gfa: GFA<usize, ()> = parse(&lines);
+
+        let jaccard = path_similarity(&gfa, SimilarityMetric::Jaccard, false);
+        assert_eq!(jaccard.get(0, 1), Some(2.0 / 3.0));
+        assert_eq!(jaccard.get(1, 0), Some(2.0 / 3.0));
+        assert_eq!(jaccard.get(0, 0), Some(1.0));
+        assert_eq!(jaccard.get(2, 2), Some(0.0));
+        assert_eq!(jaccard.get(3, 1), Some(2.0 / 3.0));
+        assert_eq!(jaccard.get(4, 0), None);
+
+        let contained =
+            path_similarity(&gfa, SimilarityMetric::Containment, true);
+        assert_eq!(contained.get(0, 1), Some(1.0));
+        assert_eq!(contained.get(1, 0), Some(5.0 / 7.0));
+        assert_eq!(contained.get(3, 1), Some(1.0));
+        assert_eq!(contained.get(1, 3), Some(3.0 / 7.0));
+    }
+}
@@ -1,14 +1,42 @@
+#[cfg(feature = "arena")]
+pub mod arena;
 pub mod error;
+pub mod index;
+pub mod intern;
+pub mod location;
+pub mod peek;
+pub mod records;
+pub mod sink;
+#[cfg(feature = "async")]
+pub mod stream;
+pub mod warning;
 
 pub use self::error::{GFAFieldResult, GFAResult, ParseError, ParseFieldError};
-
-use bstr::{BStr, ByteSlice};
+pub use self::index::{FileStamp, GfaIndex, IndexedGfa};
+pub use self::intern::NameTable;
+pub use self::location::{LineNumbers, WithLocation};
+#[cfg(feature = "fs")]
+pub use self::peek::{detect_version, peek_header};
+pub use self::peek::{detect_version_reader, peek_header_reader, GfaVersion};
+pub use self::records::RecordIter;
+#[cfg(feature = "fs")]
+pub use self::records::{
+    containments_iter, links_iter, paths_iter, segments_iter, walks_iter,
+};
+#[cfg(feature = "fs")]
+pub use self::sink::parse_gfa_into;
+pub use self::sink::GfaSink;
+#[cfg(feature = "async")]
+pub use self::stream::parse_gfa_stream_async;
+pub use self::warning::{ParseWarning, WarningKind, WarningSink};
+
+use bstr::ByteSlice;
 use lazy_static::lazy_static;
 use regex::bytes::Regex;
 
 use crate::{cigar::CIGAR, gfa::*, optfields::*};
 
-use crate::parser::error::ParserTolerance;
+use crate::parser::error::{ParserProfile, ParserTolerance};
 
 /// Builder struct for GFAParsers
 pub struct GFAParserBuilder {
@@ -17,6 +45,12 @@ pub struct GFAParserBuilder {
     pub containments: bool,
     pub paths: bool,
     pub tolerance: ParserTolerance,
+    /// Read a missing last required field, i.e. the sequence of a
+    /// segment or the overlap(s) of a link, containment, or path, as
+    /// `*`. Such lines are usually written by tools that leave the
+    /// field empty, which leaves a trailing tab that is stripped.
+    pub empty_trailing_fields: bool,
+    pub profile: ParserProfile,
 }
 
 impl GFAParserBuilder {
@@ -28,6 +62,8 @@ impl GFAParserBuilder {
             containments: false,
             paths: false,
             tolerance: Default::default(),
+            empty_trailing_fields: false,
+            profile: Default::default(),
         }
     }
 
@@ -39,6 +75,8 @@ impl GFAParserBuilder {
             containments: true,
             paths: true,
             tolerance: Default::default(),
+            empty_trailing_fields: false,
+            profile: Default::default(),
         }
     }
 
@@ -72,6 +110,19 @@ impl GFAParserBuilder {
         self
     }
 
+    /// Accept lines whose last required field is empty or missing,
+    /// reading it as `*`.
+    pub fn empty_trailing_fields(&mut self, tolerate: bool) -> &mut Self {
+        self.empty_trailing_fields = tolerate;
+        self
+    }
+
+    /// Choose how closely lines must follow the GFA1 spec.
+    pub fn profile(&mut self, profile: ParserProfile) -> &mut Self {
+        self.profile = profile;
+        self
+    }
+
     pub fn build<N: SegmentId, T: OptFields>(self) -> GFAParser<N, T> {
         GFAParser {
             segments: self.segments,
@@ -79,6 +130,8 @@ impl GFAParserBuilder {
             containments: self.containments,
             paths: self.paths,
             tolerance: self.tolerance,
+            empty_trailing_fields: self.empty_trailing_fields,
+            profile: self.profile,
             _optional_fields: std::marker::PhantomData,
             _segment_names: std::marker::PhantomData,
         }
@@ -88,6 +141,10 @@ impl GFAParserBuilder {
         self.build()
     }
 
+    pub fn build_u64_id<T: OptFields>(self) -> GFAParser<u64, T> {
+        self.build()
+    }
+
     pub fn build_bstr_id<T: OptFields>(self) -> GFAParser<Vec<u8>, T> {
         self.build()
     }
@@ -100,6 +157,8 @@ pub struct GFAParser<N: SegmentId, T: OptFields> {
     containments: bool,
     paths: bool,
     tolerance: ParserTolerance,
+    empty_trailing_fields: bool,
+    profile: ParserProfile,
     _optional_fields: std::marker::PhantomData<T>,
     _segment_names: std::marker::PhantomData<N>,
 }
@@ -118,28 +177,93 @@ impl<N: SegmentId, T: OptFields> GFAParser<N, T> {
         Default::default()
     }
 
+    /// Parse a single GFA line. Surrounding whitespace, including the
+    /// `\r` of CRLF line endings and any trailing tabs, is ignored,
+    /// except by the strict profile.
     pub fn parse_gfa_line(&self, bytes: &[u8]) -> GFAResult<Line<N, T>> {
-        let line: &BStr = bytes.trim().as_ref();
+        self.parse_gfa_line_with(bytes, parse_segment_id)
+    }
 
+    /// Parse a single GFA line as `parse_gfa_line` does, but with
+    /// `parse_id` parsing the segment names in segments, links, and
+    /// containments, so that they can be stored in something other
+    /// than a `SegmentId`.
+    pub(crate) fn parse_gfa_line_with<M, F>(
+        &self,
+        bytes: &[u8],
+        parse_id: F,
+    ) -> GFAResult<Line<M, T>>
+    where
+        F: FnMut(&[u8]) -> GFAFieldResult<M>,
+    {
+        let line = bytes.trim();
+        let (hdr, fields) = self.split_line(line)?;
+
+        if self.profile == ParserProfile::Strict {
+            check_strict(hdr, bytes, line)
+                .map_err(|e| ParseError::invalid_line(e, bytes))?;
+        }
+        self.parse_fields(hdr, fields, bytes, parse_id)
+    }
+
+    /// Split a trimmed line into its type and the rest of its fields,
+    /// as the parser's profile reads them, i.e. with the last required
+    /// field padded with `*` if it's missing and that's allowed, and
+    /// with every field trimmed by the permissive profile.
+    fn split_line<'a>(
+        &self,
+        line: &'a [u8],
+    ) -> GFAResult<(&'a [u8], LineFields<'a>)> {
         let mut fields = line.split_str(b"\t");
         let hdr = fields.next().ok_or(ParseError::EmptyLine)?;
 
-        let invalid_line =
-            |e: ParseFieldError| ParseError::invalid_line(e, bytes);
+        let fields = match self.profile {
+            ParserProfile::Standard => {
+                let padding = if self.empty_trailing_fields
+                    && line.split_str(b"\t").count() + 1 == required_fields(hdr)
+                {
+                    Some(&b"*"[..])
+                } else {
+                    None
+                };
+                LineFields::Split(fields.chain(padding))
+            }
+            ParserProfile::Strict => LineFields::Split(fields.chain(None)),
+            ParserProfile::Permissive => {
+                let fields = permissive_fields(hdr, fields);
+                LineFields::Normalized(fields.into_iter())
+            }
+        };
+        Ok((hdr, fields))
+    }
 
+    fn parse_fields<'a, I, M, F>(
+        &self,
+        hdr: &[u8],
+        fields: I,
+        bytes: &[u8],
+        parse_id: F,
+    ) -> GFAResult<Line<M, T>>
+    where
+        I: Iterator<Item = &'a [u8]>,
+        F: FnMut(&[u8]) -> GFAFieldResult<M>,
+    {
         let line = match hdr {
             b"H" => Header::parse_line(fields).map(Header::wrap),
             b"S" if self.segments => {
-                Segment::parse_line(fields).map(Segment::wrap)
+                Segment::parse_line(fields, parse_id).map(Segment::wrap)
+            }
+            b"L" if self.links => {
+                Link::parse_line(fields, parse_id).map(Link::wrap)
             }
-            b"L" if self.links => Link::parse_line(fields).map(Link::wrap),
             b"C" if self.containments => {
-                Containment::parse_line(fields).map(Containment::wrap)
+                Containment::parse_line(fields, parse_id).map(Containment::wrap)
             }
             b"P" if self.paths => Path::parse_line(fields).map(Path::wrap),
+            b"W" if self.paths => Walk::parse_line(fields).map(Walk::wrap),
             _ => return Err(ParseError::UnknownLineType),
         }
-        .map_err(invalid_line)?;
+        .map_err(|e| ParseError::invalid_line(e, bytes))?;
         Ok(line)
     }
 
@@ -148,43 +272,84 @@ impl<N: SegmentId, T: OptFields> GFAParser<N, T> {
         I: Iterator,
         I::Item: AsRef<[u8]>,
     {
-        let mut gfa = GFA::new();
-
-        for line in lines {
-            match self.parse_gfa_line(line.as_ref()) {
-                Ok(parsed) => gfa.insert_line(parsed),
-                Err(err) if err.can_safely_continue(&self.tolerance) => (),
-                Err(err) => return Err(err),
-            };
-        }
+        let (gfa, ()) = parse_new_gfa(|gfa| self.parse_lines_into(lines, gfa))?;
+        Ok(gfa)
+    }
 
+    /// Parse a GFA from a reader, such as an in-memory buffer. The
+    /// input may be gzip or BGZF compressed if the `gzip` feature is
+    /// enabled.
+    pub fn parse_reader<R: std::io::BufRead>(
+        &self,
+        reader: R,
+    ) -> GFAResult<GFA<N, T>> {
+        let (gfa, ()) =
+            parse_new_gfa(|gfa| self.parse_reader_into(reader, gfa))?;
         Ok(gfa)
     }
 
+    /// Parse a GFA file, which may be gzip or BGZF compressed if the
+    /// `gzip` feature is enabled.
+    #[cfg(feature = "fs")]
     pub fn parse_file<P: AsRef<std::path::Path>>(
         &self,
         path: P,
     ) -> Result<GFA<N, T>, ParseError> {
-        use {
-            bstr::io::BufReadExt,
-            std::{fs::File, io::BufReader},
-        };
+        let (gfa, ()) = parse_new_gfa(|gfa| self.parse_file_into(path, gfa))?;
+        Ok(gfa)
+    }
+}
 
-        let file = File::open(path)?;
-        let lines = BufReader::new(file).byte_lines();
+/// Parse a new GFA with `parse`, which inserts the parsed lines into
+/// the GFA it's given, and returns whatever else it produces. The GFA
+/// starts without a version, so it gets the first version in its `H`
+/// lines, and only falls back to the default if none has one.
+pub(crate) fn parse_new_gfa<N, T, R, F>(parse: F) -> GFAResult<(GFA<N, T>, R)>
+where
+    N: Default,
+    T: OptFields,
+    F: FnOnce(&mut GFA<N, T>) -> GFAResult<R>,
+{
+    let mut gfa = GFA::default();
+    gfa.header.version = None;
+    let result = parse(&mut gfa)?;
+    if gfa.header.version.is_none() {
+        gfa.header.version = Header::<T>::default().version;
+    }
+    Ok((gfa, result))
+}
 
-        let mut gfa = GFA::new();
+/// Call `f` with each line of a reader, which may be compressed, and
+/// its 1-based line number, stopping at the first error.
+pub(crate) fn for_each_line<R, F>(reader: R, mut f: F) -> GFAResult<()>
+where
+    R: std::io::BufRead,
+    F: FnMut(usize, &[u8]) -> GFAResult<()>,
+{
+    use bstr::io::BufReadExt;
 
-        for line in lines {
-            let line = line?;
-            match self.parse_gfa_line(line.as_ref()) {
-                Ok(parsed) => gfa.insert_line(parsed),
-                Err(err) if err.can_safely_continue(&self.tolerance) => (),
-                Err(err) => return Err(err),
-            };
-        }
+    let reader = crate::reader::decompress_reader(reader)?;
+    for (ix, line) in reader.byte_lines().enumerate() {
+        f(ix + 1, &line?)?;
+    }
+    Ok(())
+}
 
-        Ok(gfa)
+/// The fields of a line after its type, as split by
+/// `GFAParser::split_line`.
+enum LineFields<'a> {
+    Split(std::iter::Chain<bstr::Split<'a>, std::option::IntoIter<&'a [u8]>>),
+    Normalized(std::vec::IntoIter<&'a [u8]>),
+}
+
+impl<'a> Iterator for LineFields<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        match self {
+            LineFields::Split(fields) => fields.next(),
+            LineFields::Normalized(fields) => fields.next(),
+        }
     }
 }
 
@@ -236,6 +401,63 @@ where
 {
 }
 
+/// Parse the lines of a GFA from a reader, producing an iterator over
+/// the parsed lines. Unlike `GFAParser::parse_reader`, the input isn't
+/// decompressed, and the whole GFA is never held in memory.
+///
+/// Errors that the parser's tolerance allows it to skip are filtered
+/// out; all other errors, including IO errors, are yielded as items so
+/// the consumer can decide whether to stop. Use `filter_ok` to skip
+/// them instead.
+pub fn parse_gfa_stream<R, N, T>(
+    parser: GFAParser<N, T>,
+    reader: R,
+) -> impl Iterator<Item = GFAResult<Line<N, T>>>
+where
+    R: std::io::BufRead,
+    N: SegmentId,
+    T: OptFields,
+{
+    use bstr::io::BufReadExt;
+
+    reader.byte_lines().filter_map(move |line| {
+        let result = line
+            .map_err(ParseError::from)
+            .and_then(|line| parser.parse_gfa_line(&line));
+        match result {
+            Err(err) if err.can_safely_continue(&parser.tolerance) => None,
+            result => Some(result),
+        }
+    })
+}
+
+/// Iterator adapter that yields the `Ok` values of an iterator over
+/// results, skipping the errors. Created by `FilterOkExt::filter_ok`.
+pub struct FilterOk<I> {
+    iter: I,
+}
+
+impl<I, T, E> Iterator for FilterOk<I>
+where
+    I: Iterator<Item = Result<T, E>>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.iter.by_ref().find_map(Result::ok)
+    }
+}
+
+pub trait FilterOkExt<T, E>: Iterator<Item = Result<T, E>> + Sized {
+    /// Skip the errors, e.g. of `parse_gfa_stream`, keeping only the
+    /// successfully parsed items.
+    fn filter_ok(self) -> FilterOk<Self> {
+        FilterOk { iter: self }
+    }
+}
+
+impl<I, T, E> FilterOkExt<T, E> for I where I: Iterator<Item = Result<T, E>> {}
+
 fn next_field<I, P>(mut input: I) -> GFAFieldResult<P>
 where
     I: Iterator<Item = P>,
@@ -244,6 +466,11 @@ where
     input.next().ok_or(ParseFieldError::MissingFields)
 }
 
+/// Parse a segment name as a `SegmentId`.
+fn parse_segment_id<N: SegmentId>(input: &[u8]) -> GFAFieldResult<N> {
+    N::parse_id(input).ok_or(N::ERROR)
+}
+
 fn parse_orientation<I>(mut input: I) -> GFAFieldResult<Orientation>
 where
     I: Iterator,
@@ -254,28 +481,126 @@ where
     Orientation::parse_error(parsed)
 }
 
+/// The number of fields on each line type, including the type.
+fn required_fields(hdr: &[u8]) -> usize {
+    match hdr {
+        b"S" => 3,
+        b"L" => 6,
+        b"C" => 7,
+        b"P" => 4,
+        b"W" => 7,
+        _ => 0,
+    }
+}
+
+/// Check that a line follows the GFA1 spec to the letter: no empty
+/// fields or trailing whitespace, segment and path names made of the
+/// allowed characters, and only valid sequences, overlaps, and
+/// optional fields.
+fn check_strict(hdr: &[u8], bytes: &[u8], line: &[u8]) -> GFAFieldResult<()> {
+    use ParseFieldError::SpecViolation;
+
+    let is_name = |name: &[u8]| {
+        Vec::<u8>::parse_id(name).is_some_and(|n| n.len() == name.len())
+    };
+    let is_overlap = |overlap: &[u8]| parse_overlap(overlap).is_ok();
+
+    if !matches!(hdr, b"H" | b"S" | b"L" | b"C" | b"P" | b"W") {
+        return Ok(());
+    }
+    if bytes.trim_end_with(|c| c == '\r' || c == '\n') != line {
+        return Err(SpecViolation("whitespace"));
+    }
+
+    let fields: Vec<&[u8]> = line.split_str(b"\t").skip(1).collect();
+    if fields.iter().any(|f| f.is_empty()) {
+        return Err(SpecViolation("empty field"));
+    }
+    let required = required_fields(hdr).saturating_sub(1);
+    if fields.len() < required {
+        return Err(ParseFieldError::MissingFields);
+    }
+
+    let (names, overlaps): (&[usize], &[usize]) = match hdr {
+        b"S" => (&[0], &[]),
+        b"L" => (&[0, 2], &[4]),
+        b"C" => (&[0, 2], &[5]),
+        b"P" => (&[0], &[]),
+        b"W" => (&[0, 2], &[]),
+        _ => (&[], &[]),
+    };
+    if !names.iter().all(|&ix| is_name(fields[ix])) {
+        return Err(SpecViolation("name"));
+    }
+    if !overlaps.iter().all(|&ix| is_overlap(fields[ix])) {
+        return Err(SpecViolation("overlap"));
+    }
+
+    if hdr == b"S" {
+        let seq = fields[1];
+        let is_base = |c: &u8| c.is_ascii_alphabetic() || b"=.".contains(c);
+        if seq != b"*" && !seq.iter().all(is_base) {
+            return Err(SpecViolation("sequence"));
+        }
+    }
+    if hdr == b"P" && !fields[2].split_str(b",").all(is_overlap) {
+        return Err(SpecViolation("overlaps"));
+    }
+
+    if !fields[required..]
+        .iter()
+        .all(|f| OptField::parse(f).is_some())
+    {
+        return Err(SpecViolation("optional field"));
+    }
+    Ok(())
+}
+
+/// Normalize the fields of a line for the permissive profile: fields
+/// are trimmed of surrounding spaces, and an empty or missing last
+/// required field is read as `*`.
+fn permissive_fields<'a, I>(hdr: &[u8], fields: I) -> Vec<&'a [u8]>
+where
+    I: Iterator<Item = &'a [u8]>,
+{
+    let required = required_fields(hdr).saturating_sub(1);
+    let mut fields: Vec<&[u8]> = fields.map(|f| f.trim()).collect();
+    if required > 0 {
+        if fields.len() + 1 == required {
+            fields.push(b"*");
+        } else if fields.get(required - 1).is_some_and(|f| f.is_empty()) {
+            fields[required - 1] = b"*";
+        }
+    }
+    fields
+}
+
 impl<T: OptFields> Header<T> {
     #[inline]
-    fn wrap<N: SegmentId>(self) -> Line<N, T> {
+    fn wrap<N>(self) -> Line<N, T> {
         Line::Header(self)
     }
 
     #[inline]
-    fn parse_line<I>(mut input: I) -> GFAFieldResult<Self>
+    fn parse_line<I>(input: I) -> GFAFieldResult<Self>
     where
         I: Iterator,
         I::Item: AsRef<[u8]>,
     {
-        let next = next_field(&mut input)?;
-        let version = OptField::parse(next.as_ref());
-        let version =
-            if let Some(OptFieldVal::Z(version)) = version.map(|v| v.value) {
-                Some(version)
-            } else {
-                None
-            };
-
-        let optional = T::parse(input);
+        // the version is the first VN tag, which need not be the
+        // first field; every other tag is an optional field
+        let mut version = None;
+        let mut fields = Vec::new();
+        for field in input {
+            match OptField::parse(field.as_ref()) {
+                Some(OptField {
+                    tag: [b'V', b'N'],
+                    value: OptFieldVal::Z(vn),
+                }) if version.is_none() => version = Some(vn),
+                _ => fields.push(field),
+            }
+        }
+        let optional = T::parse(fields);
 
         Ok(Header { version, optional })
     }
@@ -296,19 +621,20 @@ where
         .ok_or(ParseFieldError::InvalidField("Sequence"))
 }
 
-impl<N: SegmentId, T: OptFields> Segment<N, T> {
+impl<N, T: OptFields> Segment<N, T> {
     #[inline]
     fn wrap(self) -> Line<N, T> {
         Line::Segment(self)
     }
 
     #[inline]
-    fn parse_line<I>(mut input: I) -> GFAFieldResult<Self>
+    fn parse_line<I, F>(mut input: I, mut parse_id: F) -> GFAFieldResult<Self>
     where
         I: Iterator,
         I::Item: AsRef<[u8]>,
+        F: FnMut(&[u8]) -> GFAFieldResult<N>,
     {
-        let name = N::parse_next(&mut input)?;
+        let name = parse_id(next_field(&mut input)?.as_ref())?;
         let sequence = parse_sequence(&mut input)?;
         let optional = T::parse(input);
         Ok(Segment {
@@ -319,21 +645,22 @@ impl<N: SegmentId, T: OptFields> Segment<N, T> {
     }
 }
 
-impl<N: SegmentId, T: OptFields> Link<N, T> {
+impl<N, T: OptFields> Link<N, T> {
     #[inline]
     fn wrap(self) -> Line<N, T> {
         Line::Link(self)
     }
 
     #[inline]
-    fn parse_line<I>(mut input: I) -> GFAFieldResult<Self>
+    fn parse_line<I, F>(mut input: I, mut parse_id: F) -> GFAFieldResult<Self>
     where
         I: Iterator,
         I::Item: AsRef<[u8]>,
+        F: FnMut(&[u8]) -> GFAFieldResult<N>,
     {
-        let from_segment = N::parse_next(&mut input)?;
+        let from_segment = parse_id(next_field(&mut input)?.as_ref())?;
         let from_orient = parse_orientation(&mut input)?;
-        let to_segment = N::parse_next(&mut input)?;
+        let to_segment = parse_id(next_field(&mut input)?.as_ref())?;
         let to_orient = parse_orientation(&mut input)?;
 
         let overlap = next_field(&mut input)?.as_ref().into();
@@ -350,26 +677,32 @@ impl<N: SegmentId, T: OptFields> Link<N, T> {
     }
 }
 
-impl<N: SegmentId, T: OptFields> Containment<N, T> {
+impl<N, T: OptFields> Containment<N, T> {
     #[inline]
     fn wrap(self) -> Line<N, T> {
         Line::Containment(self)
     }
 
     #[inline]
-    fn parse_line<I>(mut input: I) -> GFAFieldResult<Self>
+    fn parse_line<I, F>(mut input: I, mut parse_id: F) -> GFAFieldResult<Self>
     where
         I: Iterator,
         I::Item: AsRef<[u8]>,
+        F: FnMut(&[u8]) -> GFAFieldResult<N>,
     {
-        let container_name = N::parse_next(&mut input)?;
+        let container_name = parse_id(next_field(&mut input)?.as_ref())?;
         let container_orient = parse_orientation(&mut input)?;
 
-        let contained_name = N::parse_next(&mut input)?;
+        let contained_name = parse_id(next_field(&mut input)?.as_ref())?;
         let contained_orient = parse_orientation(&mut input)?;
 
         let pos = next_field(&mut input)?;
-        let pos = pos.as_ref().to_str()?.parse()?;
+        let pos = pos
+            .as_ref()
+            .to_str()
+            .ok()
+            .and_then(|pos| pos.parse().ok())
+            .ok_or(ParseFieldError::InvalidField("pos"))?;
 
         let overlap = next_field(&mut input)?.as_ref().into();
 
@@ -387,7 +720,7 @@ impl<N: SegmentId, T: OptFields> Containment<N, T> {
     }
 }
 
-impl<N: SegmentId, T: OptFields> Path<N, T> {
+impl<N, T: OptFields> Path<N, T> {
     #[inline]
     fn wrap(self) -> Line<N, T> {
         Line::Path(self)
@@ -406,6 +739,15 @@ impl<N: SegmentId, T: OptFields> Path<N, T> {
         let segment_names =
             next_field(&mut input).map(|bs| Vec::<u8>::from(bs.as_ref()))?;
 
+        // reject malformed steps here, so iterating over the steps
+        // of a parsed path can't fail
+        if !segment_names
+            .split_str(b",")
+            .all(|step| crate::gfa::split_step(step).is_some())
+        {
+            return Err(ParseFieldError::InvalidField("Path steps"));
+        }
+
         let overlaps = next_field(&mut input)?
             .as_ref()
             .split_str(b",")
@@ -448,6 +790,59 @@ impl<N: SegmentId, T: OptFields> Path<N, T> {
     }
 }
 
+/// Parse a number field, where `*` means that the value is unknown.
+fn parse_opt_usize(
+    field: &[u8],
+    name: &'static str,
+) -> GFAFieldResult<Option<usize>> {
+    if field == b"*" {
+        return Ok(None);
+    }
+    let value = field.to_str().ok().and_then(|f| f.parse().ok());
+    value.map(Some).ok_or(ParseFieldError::InvalidField(name))
+}
+
+impl<N, T: OptFields> Walk<N, T> {
+    #[inline]
+    fn wrap(self) -> Line<N, T> {
+        Line::Walk(self)
+    }
+
+    #[inline]
+    fn parse_line<I>(mut input: I) -> GFAFieldResult<Self>
+    where
+        I: Iterator,
+        I::Item: AsRef<[u8]>,
+    {
+        let sample_id = Vec::<u8>::parse_next(&mut input)?;
+        let hap_index = next_field(&mut input)?
+            .as_ref()
+            .to_str()
+            .ok()
+            .and_then(|hap| hap.parse().ok())
+            .ok_or(ParseFieldError::InvalidField("HapIndex"))?;
+        let seq_id = Vec::<u8>::parse_next(&mut input)?;
+        let seq_start =
+            parse_opt_usize(next_field(&mut input)?.as_ref(), "SeqStart")?;
+        let seq_end =
+            parse_opt_usize(next_field(&mut input)?.as_ref(), "SeqEnd")?;
+
+        let walk = next_field(&mut input)?.as_ref().to_vec();
+        // reject malformed steps here, as for paths
+        if walk.is_empty()
+            || !crate::gfa::walk_steps(&walk).all(|step| step.is_ok())
+        {
+            return Err(ParseFieldError::InvalidField("Walk steps"));
+        }
+
+        let optional = T::parse(input);
+
+        Ok(Walk::new(
+            sample_id, hap_index, seq_id, seq_start, seq_end, walk, optional,
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -484,7 +879,7 @@ mod tests {
         };
 
         let fields = link.split_terminator('\t');
-        let result = Link::parse_line(fields);
+        let result = Link::parse_line(fields, parse_segment_id);
 
         match result {
             Err(_) => {
@@ -509,7 +904,7 @@ mod tests {
         };
 
         let fields = cont.split_terminator('\t');
-        let result = Containment::parse_line(fields);
+        let result = Containment::parse_line(fields, parse_segment_id);
         match result {
             Err(_) => {
                 panic!("Error parsing containment");
@@ -518,6 +913,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn containment_invalid_pos() {
+        for pos in ["x10", "-1", "", "1.5"].iter() {
+            let cont = format!("1\t-\t2\t+\t{}\t100M", pos);
+            let result: GFAFieldResult<Containment<Vec<u8>, ()>> =
+                Containment::parse_line(
+                    cont.split_terminator('\t'),
+                    parse_segment_id,
+                );
+            assert!(matches!(
+                result,
+                Err(ParseFieldError::InvalidField("pos"))
+            ));
+        }
+
+        let result: GFAFieldResult<Containment<Vec<u8>, ()>> =
+            Containment::parse_line(
+                "1\t-\t2\t+".split_terminator('\t'),
+                parse_segment_id,
+            );
+        assert!(matches!(result, Err(ParseFieldError::MissingFields)));
+
+        let parser: GFAParser<Vec<u8>, ()> = GFAParser::new();
+        let lines = ["S\t1\tA", "S\t2\tC", "C\t1\t+\t2\t+\tpos\t1M"];
+        let result = parser.parse_lines(lines.iter().map(|l| l.as_bytes()));
+        assert!(result.is_err());
+    }
+
+    #[allow(clippy::useless_vec)]
     #[test]
     fn can_parse_path() {
         let path = "14\t11+,12-,13+\t4M,5M";
@@ -542,6 +966,7 @@ mod tests {
         }
     }
 
+    #[allow(clippy::needless_borrows_for_generic_args)]
     #[test]
     fn can_parse_gfa_lines() {
         let parser = GFAParser::new();
@@ -559,6 +984,16 @@ mod tests {
         assert_eq!(num_paths, 3);
     }
 
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn can_parse_gzipped_gfa() {
+        let parser: GFAParser<Vec<u8>, ()> = GFAParser::new();
+        let plain = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+        let gzipped = parser.parse_file("./test/gfas/lil.gfa.gz").unwrap();
+        assert_eq!(plain, gzipped);
+    }
+
+    #[allow(clippy::needless_borrows_for_generic_args)]
     #[test]
     fn gfa_usize_parser_can_fail() {
         let usize_parser: GFAParser<usize, OptionalFields> = GFAParser::new();
@@ -574,6 +1009,59 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn gfa_uint_parsers() {
+        let usize_parser: GFAParser<usize, ()> = GFAParser::new();
+        let u64_parser: GFAParser<u64, ()> =
+            GFAParserBuilder::all().build_u64_id();
+
+        let usize_gfa = usize_parser.parse_file("./test/gfas/lil.gfa").unwrap();
+        let u64_gfa = u64_parser.parse_file("./test/gfas/lil.gfa").unwrap();
+
+        let usize_names: Vec<_> =
+            usize_gfa.segments.iter().map(|s| s.name as u64).collect();
+        let u64_names: Vec<_> =
+            u64_gfa.segments.iter().map(|s| s.name).collect();
+        assert_eq!(usize_names, u64_names);
+
+        let usize_steps: Vec<_> = usize_gfa.paths[0]
+            .iter()
+            .map(|(s, o)| (s as u64, o))
+            .collect();
+        let u64_steps: Vec<_> = u64_gfa.paths[0].iter().collect();
+        assert_eq!(usize_steps, u64_steps);
+
+        let u64_diatom = u64_parser.parse_file("./test/gfas/diatom.gfa");
+        assert!(matches!(
+            u64_diatom,
+            Err(ParseError::InvalidLine(ParseFieldError::UintIdError, _))
+        ));
+    }
+
+    #[test]
+    fn parse_from_buffer() {
+        let parser: GFAParser<usize, ()> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+
+        let bytes = std::fs::read("./test/gfas/lil.gfa").unwrap();
+        let from_buffer = parser.parse_reader(bytes.as_slice()).unwrap();
+        assert_eq!(gfa, from_buffer);
+
+        #[cfg(feature = "gzip")]
+        {
+            use std::io::Write;
+            let mut writer = crate::bgzf::BgzfWriter::new(Vec::new());
+            writer.write_all(&bytes).unwrap();
+            let compressed = writer.finish().unwrap();
+            let from_buffer = parser.parse_reader(compressed.as_slice());
+            assert_eq!(gfa, from_buffer.unwrap());
+        }
+    }
+
+    #[allow(
+        clippy::needless_borrows_for_generic_args,
+        clippy::useless_conversion
+    )]
     #[test]
     fn gfa_parser_line_iter() {
         use {
@@ -608,20 +1096,17 @@ mod tests {
 
         let optional_fields: Vec<_> = vec![
             OptField::new(b"LN", Int(123)),
-            OptField::new(
-                b"SH",
-                H(vec![0xA, 0xA, 0xC, 0xC, 0xF, 0xF, 0x0, 0x5]),
-            ),
+            OptField::new(b"SH", H(vec![0xAA, 0xCC, 0xFF, 0x05])),
             OptField::new(b"RC", Int(123)),
             OptField::new(b"UR", Z(Vec::<u8>::from("http://test.com/"))),
             OptField::new(b"IJ", A(b'x')),
-            OptField::new(b"AB", BInt(vec![1, 2, 3, 52124])),
+            OptField::new(b"AB", B(BArray::UInt32(vec![1, 2, 3, 52124]))),
         ]
         .into_iter()
         .collect();
 
         let segment_1: GFAFieldResult<Segment<Vec<u8>, ()>> =
-            Segment::parse_line(fields.clone());
+            Segment::parse_line(fields.clone(), parse_segment_id);
 
         assert!(segment_1.is_ok());
         assert_eq!(
@@ -634,10 +1119,190 @@ mod tests {
         );
 
         let segment_2: Segment<Vec<u8>, OptionalFields> =
-            Segment::parse_line(fields).unwrap();
+            Segment::parse_line(fields, parse_segment_id).unwrap();
 
         assert_eq!(segment_2.name.as_bstr(), name);
         assert_eq!(segment_2.sequence.as_bstr(), seq);
         assert_eq!(segment_2.optional, optional_fields);
     }
+
+    #[test]
+    fn malformed_lines_are_errors() {
+        let parser: GFAParser<Vec<u8>, OptionalFields> = GFAParser::new();
+        let malformed = [
+            "P\tp\t1+,,2-\t*",
+            "P\tp\t1+,2\t*",
+            "P\tp\t+\t*",
+            "P\tp\t\t*",
+            "C\t1\t+\t2\t-\t99999999999999999999999\t*",
+            "W\ts\tx\tc\t0\t1\t>1",
+            "W\ts\t1\tc\t0\t1\t>",
+            "W\ts\t1\tc\t0\t1\t1+",
+            "W\ts\t1\tc\t-1\t1\t>1",
+        ];
+        for line in malformed.iter() {
+            assert!(
+                parser.parse_gfa_line(line.as_bytes()).is_err(),
+                "{}",
+                line
+            );
+        }
+
+        // invalid optional fields and overlaps are skipped
+        let line = "L\t1\t+\t2\t-\t4294967296M\t1X:i:1\tAB:i\tCD:i:2";
+        let link = parser.parse_gfa_line(line.as_bytes()).unwrap();
+        let link = link.some_link().unwrap();
+        assert_eq!(
+            link.optional,
+            vec![OptField::new(b"CD", OptFieldVal::Int(2))]
+        );
+        assert!(link.overlap_cigar().is_err());
+
+        // paths that weren't parsed may still have malformed steps,
+        // which are skipped rather than panicking
+        let path: Path<Vec<u8>, ()> =
+            Path::new(b"p".to_vec(), b"1+,2,,3-".to_vec(), vec![], ());
+        assert_eq!(path.iter().count(), 2);
+        let path: Path<usize, ()> =
+            Path::new(b"p".to_vec(), b"1+,2".to_vec(), vec![], ());
+        assert_eq!(path.iter().count(), 1);
+
+        assert_eq!(CIGAR::from_bytestring(b"300000000M"), None);
+        assert_eq!(OptField::parse(b"1X:i:1"), None);
+        assert_eq!(OptField::parse(b"XY-i-1"), None);
+        assert_eq!(OptField::parse(b"X"), None);
+    }
+
+    #[test]
+    fn crlf_and_empty_trailing_fields() {
+        let gfa = "H\tVN:Z:1.0\r\nS\t1\tACGT\t\r\nS\t2\t\t\r\n\
+                   L\t1\t+\t2\t-\t\r\nP\tp\t1+,2-\t\r\n";
+
+        let parser: GFAParser<usize, ()> = GFAParser::new();
+        let strict = parser.parse_reader(gfa.as_bytes());
+        assert!(matches!(
+            strict,
+            Err(ParseError::InvalidLine(ParseFieldError::MissingFields, _))
+        ));
+
+        let mut builder = GFAParserBuilder::all();
+        builder.empty_trailing_fields(true);
+        let parser: GFAParser<usize, ()> = builder.build();
+        let gfa = parser.parse_reader(gfa.as_bytes()).unwrap();
+
+        assert_eq!(gfa.header.version, Some(b"1.0".to_vec()));
+        assert_eq!(gfa.segments[0].sequence, b"ACGT");
+        assert_eq!(gfa.segments[1].sequence, b"*");
+        assert_eq!(gfa.links[0].overlap, b"*");
+        assert_eq!(gfa.paths[0].overlaps, vec![None]);
+    }
+
+    #[test]
+    fn parser_profiles() {
+        let parser_with = |profile| {
+            let mut builder = GFAParserBuilder::all();
+            builder.profile(profile);
+            builder.build::<Vec<u8>, OptionalFields>()
+        };
+        let strict = parser_with(ParserProfile::Strict);
+        let standard = parser_with(ParserProfile::Standard);
+        let permissive = parser_with(ParserProfile::Permissive);
+
+        let spec_violation = |line: &str| {
+            matches!(
+                strict.parse_gfa_line(line.as_bytes()),
+                Err(ParseError::InvalidLine(
+                    ParseFieldError::SpecViolation(_),
+                    _
+                ))
+            )
+        };
+
+        let deviations = [
+            "S\t1\tACGT\t",
+            "S\t1 2\tACGT",
+            "S\t1\tAC GT",
+            "S\t1\tACGT\tLN:i:x",
+            "L\t1\t+\t2\t-\t4Q",
+            "P\tp\t1+,2-\t*,**",
+        ];
+        for line in deviations.iter() {
+            assert!(spec_violation(line), "{}", line);
+            assert!(standard.parse_gfa_line(line.as_bytes()).is_ok());
+        }
+
+        let valid = "S\t1\tACGT\tLN:i:4\r\n";
+        assert!(strict.parse_gfa_line(valid.as_bytes()).is_ok());
+        assert!(matches!(
+            strict.parse_gfa_line(b"# comment"),
+            Err(ParseError::UnknownLineType)
+        ));
+
+        let line = "L\t 1\t+ \t2\t-\t\tXY:i:1";
+        assert!(standard.parse_gfa_line(line.as_bytes()).is_err());
+        let link = permissive.parse_gfa_line(line.as_bytes()).unwrap();
+        let link = link.some_link().unwrap();
+        assert_eq!(link.from_segment, b"1");
+        assert_eq!(link.overlap, b"*");
+        assert_eq!(link.optional.len(), 1);
+
+        let path = permissive.parse_gfa_line(b"P\tp\t1+").unwrap();
+        assert_eq!(path.some_path().unwrap().overlaps, vec![None]);
+    }
+
+    #[test]
+    fn multiple_header_lines() {
+        let lines = [
+            "H\tPG:Z:tool\tVN:Z:1.1",
+            "S\t1\tA",
+            "H\tXY:i:2\tPG:Z:other",
+            "H\tVN:Z:1.0",
+        ];
+        let parser: GFAParser<Vec<u8>, OptionalFields> = GFAParser::new();
+        let gfa = parser.parse_lines(lines.iter()).unwrap();
+
+        // the first version wins, as does the first field of each tag
+        assert_eq!(gfa.header.version, Some(b"1.1".to_vec()));
+        let tags: Vec<_> =
+            gfa.header.optional.iter().map(|f| f.to_string()).collect();
+        assert_eq!(tags, vec!["PG:Z:tool", "XY:i:2"]);
+
+        assert_eq!(
+            crate::writer::gfa_string(&gfa).lines().next(),
+            Some("H\tVN:Z:1.1\tPG:Z:tool\tXY:i:2")
+        );
+
+        // including within a line
+        let lines = ["H\tXY:i:1\tVN:Z:1.1\tVN:Z:1.0", "H\tVN:Z:2.0"];
+        let gfa = parser.parse_lines(lines.iter()).unwrap();
+        assert_eq!(gfa.header.version, Some(b"1.1".to_vec()));
+        assert_eq!(gfa.header.optional.len(), 2);
+
+        // and the default is only used if there's no version at all
+        let gfa = parser.parse_lines(["H\tXY:i:1"].iter()).unwrap();
+        assert_eq!(gfa.header.version, Some(b"1.0".to_vec()));
+        let (gfa, _) =
+            parser.parse_lines_interned(["H\tVN:Z:1.1"].iter()).unwrap();
+        assert_eq!(gfa.header.version, Some(b"1.1".to_vec()));
+    }
+
+    #[test]
+    fn gfa_stream_yields_results() {
+        let input: &[u8] =
+            b"S\t1\tA\n\nX\tskipped\nS\tnot_a_number\tC\nS\t2\tG\n";
+        let parser: GFAParser<usize, ()> = GFAParser::new();
+
+        let results: Vec<_> = parse_gfa_stream(parser.clone(), input).collect();
+        assert_eq!(results.len(), 3);
+        assert!(matches!(
+            results[1],
+            Err(ParseError::InvalidLine(ParseFieldError::UintIdError, _))
+        ));
+
+        let names: Vec<_> = parse_gfa_stream(parser, input)
+            .filter_ok()
+            .filter_map(|line| Some(line.some_segment()?.name))
+            .collect();
+        assert_eq!(names, vec![1, 2]);
+    }
 }
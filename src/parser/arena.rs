@@ -0,0 +1,140 @@
+#[cfg(feature = "fs")]
+use bstr::io::BufReadExt;
+use bstr::{BStr, ByteSlice};
+use bumpalo::Bump;
+use fnv::FnvHashSet;
+
+use crate::{
+    gfa::{traits::find_name, *},
+    optfields::*,
+};
+
+use super::{parse_new_gfa, GFAFieldResult, GFAParser, GFAResult};
+
+/// Allocates segment names in a bump arena, storing each distinct
+/// name only once.
+struct ArenaNames<'b> {
+    bump: &'b Bump,
+    names: FnvHashSet<&'b [u8]>,
+}
+
+impl<'b> ArenaNames<'b> {
+    fn new(bump: &'b Bump) -> Self {
+        ArenaNames {
+            bump,
+            names: Default::default(),
+        }
+    }
+
+    /// Parse a segment name from a field, as for `Vec<u8>` names,
+    /// copying it straight from the line into the arena.
+    fn parse(&mut self, input: &[u8]) -> GFAFieldResult<&'b BStr> {
+        let name = find_name(input).ok_or(Vec::<u8>::ERROR)?;
+        if let Some(name) = self.names.get(name) {
+            return Ok(name.as_bstr());
+        }
+        let name: &'b [u8] = self.bump.alloc_slice_copy(name);
+        self.names.insert(name);
+        Ok(name.as_bstr())
+    }
+}
+
+impl<T: OptFields> GFAParser<Vec<u8>, T> {
+    /// Parse the lines of a GFA, allocating the segment names in the
+    /// provided bump arena rather than as separate heap allocations.
+    /// Each distinct name is allocated once, and all references to
+    /// it (in segments, links, and containments) point to the same
+    /// slice, which is freed when the arena is dropped or reset.
+    pub fn parse_lines_in<'b, I>(
+        &self,
+        bump: &'b Bump,
+        lines: I,
+    ) -> GFAResult<GFA<&'b BStr, T>>
+    where
+        I: Iterator,
+        I::Item: AsRef<[u8]>,
+    {
+        let mut names = ArenaNames::new(bump);
+        let (gfa, ()) = parse_new_gfa(|gfa| {
+            for line in lines {
+                let parsed = self
+                    .parse_gfa_line_with(line.as_ref(), |id| names.parse(id));
+                match parsed {
+                    Ok(parsed) => gfa.insert_line(parsed),
+                    Err(err) if err.can_safely_continue(&self.tolerance) => (),
+                    Err(err) => return Err(err),
+                };
+            }
+            Ok(())
+        })?;
+        Ok(gfa)
+    }
+
+    /// Parse a GFA file, allocating the segment names in the provided
+    /// bump arena as in `parse_lines_in`.
+    #[cfg(feature = "fs")]
+    pub fn parse_file_in<'b, P: AsRef<std::path::Path>>(
+        &self,
+        bump: &'b Bump,
+        path: P,
+    ) -> GFAResult<GFA<&'b BStr, T>> {
+        let mut names = ArenaNames::new(bump);
+        let (gfa, ()) = parse_new_gfa(|gfa| {
+            for line in crate::reader::open_file(path)?.byte_lines() {
+                let line = line?;
+                let parsed =
+                    self.parse_gfa_line_with(&line, |id| names.parse(id));
+                match parsed {
+                    Ok(parsed) => gfa.insert_line(parsed),
+                    Err(err) if err.can_safely_continue(&self.tolerance) => (),
+                    Err(err) => return Err(err),
+                };
+            }
+            Ok(())
+        })?;
+        Ok(gfa)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arena_parse_matches_owned_parse() {
+        let parser: GFAParser<Vec<u8>, OptionalFields> = GFAParser::new();
+        let owned = parser.parse_file("./test/gfas/diatom.gfa").unwrap();
+
+        let bump = Bump::new();
+        let arena = parser
+            .parse_file_in(&bump, "./test/gfas/diatom.gfa")
+            .unwrap();
+
+        assert_eq!(owned.segments.len(), arena.segments.len());
+        for (o, a) in owned.segments.iter().zip(arena.segments.iter()) {
+            assert_eq!(o.name.as_bstr(), a.name);
+            assert_eq!(o.sequence, a.sequence);
+            assert_eq!(o.optional, a.optional);
+        }
+        for (o, a) in owned.links.iter().zip(arena.links.iter()) {
+            assert_eq!(o.from_segment.as_bstr(), a.from_segment);
+            assert_eq!(o.to_segment.as_bstr(), a.to_segment);
+        }
+        assert_eq!(owned.paths.len(), arena.paths.len());
+    }
+
+    #[test]
+    fn arena_names_are_shared() {
+        let parser: GFAParser<Vec<u8>, ()> = GFAParser::new();
+        let lines: Vec<&[u8]> =
+            vec![b"S\tfoo\tA", b"S\tbar\tC", b"L\tfoo\t+\tbar\t-\t0M"];
+
+        let bump = Bump::new();
+        let gfa = parser.parse_lines_in(&bump, lines.iter()).unwrap();
+
+        let foo = gfa.segments[0].name;
+        let bar = gfa.segments[1].name;
+        assert!(std::ptr::eq(foo, gfa.links[0].from_segment));
+        assert!(std::ptr::eq(bar, gfa.links[0].to_segment));
+    }
+}
@@ -12,16 +12,35 @@ pub enum ParserTolerance {
     Pedantic,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for ParserTolerance {
     fn default() -> Self {
         Self::Safe
     }
 }
 
+/// How closely the parser expects lines to follow the GFA1 spec.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParserProfile {
+    /// Reject any deviation from the spec, such as names with
+    /// disallowed characters, empty fields, trailing whitespace, or
+    /// optional fields that can't be parsed, with
+    /// `ParseFieldError::SpecViolation`.
+    Strict,
+    /// Parse the required fields, skipping optional fields that
+    /// can't be parsed.
+    #[default]
+    Standard,
+    /// Like `Standard`, but also trim spaces around fields and read
+    /// an empty or missing last required field as `*`.
+    Permissive,
+}
+
 #[derive(Debug, Clone)]
 pub enum ParseFieldError {
-    /// A segment ID couldn't be parsed as a u64. Can only happen
-    /// when parsing into a GFA<u64, T>.
+    /// A segment ID couldn't be parsed as an unsigned integer. Can
+    /// only happen when parsing into a GFA with integer segment IDs,
+    /// e.g. GFA<usize, T> or GFA<u64, T>.
     UintIdError,
     /// A bytestring couldn't be parsed as a bytestring, can happen
     /// when the contents aren't UTF8.
@@ -34,6 +53,10 @@ pub enum ParseFieldError {
     /// name as defined by the GFA1 spec.
     InvalidField(&'static str),
     MissingFields,
+    /// A line deviates from the GFA1 spec in a way that's only
+    /// rejected by the strict parser profile. Includes a description
+    /// of what was wrong.
+    SpecViolation(&'static str),
     Unknown,
 }
 
@@ -83,6 +106,9 @@ impl fmt::Display for ParseFieldError {
                 write!(f, "Failed to parse field `{}`", field)
             }
             PFE::MissingFields => write!(f, "Line is missing required fields"),
+            PFE::SpecViolation(what) => {
+                write!(f, "Line has an invalid {} under the GFA1 spec", what)
+            }
             PFE::Unknown => write!(f, "Unknown error when parsing a field"),
         }
     }
@@ -93,8 +119,8 @@ impl error::Error for ParseFieldError {}
 /// Type encapsulating different kinds of GFA parsing errors
 #[derive(Debug)]
 pub enum ParseError {
-    /// The line type was something other than 'H', 'S', 'L', 'C', or
-    /// 'P'. This is ignored by the file parser rather than a fail
+    /// The line type was something other than 'H', 'S', 'L', 'C', 'P',
+    /// or 'W'. This is ignored by the file parser rather than a fail
     /// condition.
     UnknownLineType,
     /// Tried to parse an empty line. Can be ignored.
@@ -106,6 +132,8 @@ pub enum ParseError {
     InvalidField(ParseFieldError),
     /// Wrapper for an IO error.
     IOError(std::io::Error),
+    /// More segment names were interned than there are `u32` IDs.
+    TooManyNames,
     Unknown,
 }
 
@@ -114,7 +142,10 @@ impl fmt::Display for ParseError {
         use ParseError as PE;
         match self {
             PE::UnknownLineType => {
-                write!(f, "Line type was not one of 'H', 'S', 'L', 'C', 'P'")
+                write!(
+                    f,
+                    "Line type was not one of 'H', 'S', 'L', 'C', 'P', 'W'"
+                )
             }
             PE::EmptyLine => write!(f, "Line was empty"),
             PE::InvalidLine(field_err, line) => {
@@ -124,6 +155,9 @@ impl fmt::Display for ParseError {
                 write!(f, "Failed to parse field: {}", field_err)
             }
             PE::IOError(err) => write!(f, "IO error: {}", err),
+            PE::TooManyNames => {
+                write!(f, "Interned more than u32::MAX segment names")
+            }
             PE::Unknown => write!(f, "Unknown error when parsing a line"),
         }
     }
@@ -150,6 +184,7 @@ impl ParseError {
         Self::InvalidLine(error, dest)
     }
 
+    #[allow(clippy::match_like_matches_macro)]
     pub(crate) fn can_safely_continue(&self, tol: &ParserTolerance) -> bool {
         use ParserTolerance as Tol;
         match tol {
@@ -0,0 +1,627 @@
+//! An index of the byte offsets of the segment, path, and walk lines
+//! in an uncompressed GFA file, and of every 1024th line, so that
+//! single records or ranges of lines can be fetched from huge files
+//! by seeking to them, without parsing the rest of the file. The
+//! index can be saved to a sidecar file next to the GFA and loaded
+//! again later, as long as the GFA hasn't changed since.
+//!
+//! With the `gzip` feature, BGZF-compressed GFAs can be indexed too,
+//! in which case the offsets are BGZF virtual offsets.
+
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    io::{self, BufRead, Seek, SeekFrom, Write},
+    ops::Range,
+    path::{Path as FsPath, PathBuf},
+};
+#[cfg(feature = "fs")]
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+};
+
+use bstr::{io::BufReadExt, ByteSlice};
+use fnv::FnvHashMap;
+
+#[cfg(feature = "gzip")]
+use crate::bgzf::BgzfReader;
+use crate::{gfa::*, optfields::*};
+
+use super::{
+    records::{parse_record, GFARecord},
+    GFAResult,
+};
+
+/// The number of lines between the lines whose offsets are indexed.
+const LINE_INTERVAL: usize = 1024;
+
+/// The byte offsets of the S, P, and W lines of a GFA, keyed by
+/// segment, path, and walk name, and of every 1024th line, keyed by
+/// its 0-based line number. The name of a walk is its sample,
+/// haplotype, and sequence fields joined by `#`, as in PanSN.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GfaIndex {
+    segments: FnvHashMap<Vec<u8>, u64>,
+    paths: FnvHashMap<Vec<u8>, u64>,
+    walks: FnvHashMap<Vec<u8>, u64>,
+    lines: BTreeMap<usize, u64>,
+    source: Option<FileStamp>,
+}
+
+/// The size and modification time of the file an index was built
+/// from, used to tell whether a saved index is out of date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    pub len: u64,
+    /// The modification time in nanoseconds since the Unix epoch.
+    pub modified: u128,
+}
+
+impl FileStamp {
+    #[cfg(feature = "fs")]
+    pub fn of_file<P: AsRef<FsPath>>(path: P) -> io::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos());
+        Ok(FileStamp {
+            len: metadata.len(),
+            modified,
+        })
+    }
+}
+
+fn invalid_index(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn parse_number<F: std::str::FromStr>(field: Option<&[u8]>) -> Option<F> {
+    field?.to_str().ok()?.parse().ok()
+}
+
+impl GfaIndex {
+    /// Build the index by reading lines from the reader, using
+    /// `offset` to find the offset of each line from the reader and
+    /// the number of bytes read before it, and indexing the offset of
+    /// every `line_interval`th line.
+    fn build_with<R, F>(
+        mut reader: R,
+        line_interval: usize,
+        offset_of: F,
+    ) -> io::Result<Self>
+    where
+        R: BufRead,
+        F: Fn(&R, u64) -> u64,
+    {
+        let mut index = GfaIndex::default();
+        let mut bytes_read = 0;
+        let mut buf = Vec::new();
+        for line_no in 0.. {
+            buf.clear();
+            let offset = offset_of(&reader, bytes_read);
+            let len = reader.read_until(b'\n', &mut buf)?;
+            if len == 0 {
+                break;
+            }
+            if line_no % line_interval == 0 {
+                index.lines.insert(line_no, offset);
+            }
+            let mut fields = buf.trim_end().split_str(b"\t");
+            // walks are named by their sample, haplotype, and
+            // sequence fields
+            let (map, name_fields) = match fields.next() {
+                Some(b"S") => (Some(&mut index.segments), 1),
+                Some(b"P") => (Some(&mut index.paths), 1),
+                Some(b"W") => (Some(&mut index.walks), 3),
+                _ => (None, 0),
+            };
+            let name: Vec<_> = fields.take(name_fields).collect();
+            if let Some(map) = map.filter(|_| name.len() == name_fields) {
+                map.entry(name.join(&b"#"[..])).or_insert(offset);
+            }
+            bytes_read += len as u64;
+        }
+        Ok(index)
+    }
+
+    /// Build the index by scanning an uncompressed GFA from its start.
+    /// If a name occurs more than once, the first line is indexed.
+    pub fn build<R: BufRead>(reader: R) -> io::Result<Self> {
+        Self::build_with(reader, LINE_INTERVAL, |_, bytes_read| bytes_read)
+    }
+
+    /// Build the index of a BGZF-compressed GFA, read from its start,
+    /// with virtual offsets.
+    #[cfg(feature = "gzip")]
+    pub fn build_bgzf<R: io::Read>(reader: BgzfReader<R>) -> io::Result<Self> {
+        let offset_of = |reader: &BgzfReader<R>, _| reader.virtual_offset();
+        Self::build_with(reader, LINE_INTERVAL, offset_of)
+    }
+
+    /// Build the index of the GFA file at the given path, recording
+    /// the file's size and modification time.
+    #[cfg(feature = "fs")]
+    pub fn build_file<P: AsRef<FsPath>>(path: P) -> io::Result<Self> {
+        let source = FileStamp::of_file(&path)?;
+        let mut index = Self::build(BufReader::new(File::open(path)?))?;
+        index.source = Some(source);
+        Ok(index)
+    }
+
+    /// The size and modification time of the file the index was
+    /// built from, if known.
+    pub fn source(&self) -> Option<FileStamp> {
+        self.source
+    }
+
+    pub fn set_source(&mut self, source: Option<FileStamp>) {
+        self.source = source;
+    }
+
+    /// The path of the sidecar index file for the GFA at the given
+    /// path, which is the GFA path with `.idx` appended.
+    pub fn sidecar_path<P: AsRef<FsPath>>(gfa_path: P) -> PathBuf {
+        let mut path = gfa_path.as_ref().as_os_str().to_owned();
+        path.push(".idx");
+        PathBuf::from(path)
+    }
+
+    pub fn segment_offset(&self, name: &[u8]) -> Option<u64> {
+        self.segments.get(name).copied()
+    }
+
+    pub fn path_offset(&self, name: &[u8]) -> Option<u64> {
+        self.paths.get(name).copied()
+    }
+
+    /// The offset of the walk with the given `sample#haplotype#sequence`
+    /// name.
+    pub fn walk_offset(&self, name: &[u8]) -> Option<u64> {
+        self.walks.get(name).copied()
+    }
+
+    /// The indexed line closest before the given 0-based line number,
+    /// as its line number and offset.
+    pub fn line_offset_before(&self, line_no: usize) -> Option<(usize, u64)> {
+        let mut lines = self.lines.range(..=line_no);
+        lines
+            .next_back()
+            .map(|(&line_no, &offset)| (line_no, offset))
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn path_count(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn walk_count(&self) -> usize {
+        self.walks.len()
+    }
+
+    /// Write the index as tab-separated lines of the line type, the
+    /// name, and the offset, sorted by offset, where the indexed line
+    /// numbers have the type `N`. If the source file is known, its
+    /// size and modification time are written first, on a line
+    /// starting with `#`.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        if let Some(source) = self.source {
+            writeln!(writer, "#\t{}\t{}", source.len, source.modified)?;
+        }
+        let maps = [
+            (b'S', &self.segments),
+            (b'P', &self.paths),
+            (b'W', &self.walks),
+        ];
+        let mut entries: Vec<(u64, u8, Cow<[u8]>)> = Vec::new();
+        for &(line_type, map) in maps.iter() {
+            let lines = map
+                .iter()
+                .map(|(name, &off)| (off, line_type, name.as_slice().into()));
+            entries.extend(lines);
+        }
+        let lines = self.lines.iter().map(|(line_no, &off)| {
+            (off, b'N', line_no.to_string().into_bytes().into())
+        });
+        entries.extend(lines);
+        entries.sort();
+        for (offset, line_type, name) in entries {
+            writer.write_all(&[line_type, b'\t'])?;
+            writer.write_all(&name)?;
+            writeln!(writer, "\t{}", offset)?;
+        }
+        Ok(())
+    }
+
+    /// Read an index in the format produced by `write`.
+    pub fn read<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut index = GfaIndex::default();
+        for line in reader.byte_lines() {
+            let line = line?;
+            let mut fields = line.split_str(b"\t");
+            let map = match fields.next() {
+                Some(b"S") => &mut index.segments,
+                Some(b"P") => &mut index.paths,
+                Some(b"W") => &mut index.walks,
+                Some(b"N") => {
+                    let line_no = parse_number(fields.next());
+                    let offset = parse_number(fields.next());
+                    let (line_no, offset) = line_no
+                        .zip(offset)
+                        .ok_or_else(|| invalid_index("invalid index line"))?;
+                    index.lines.insert(line_no, offset);
+                    continue;
+                }
+                Some(b"#") => {
+                    let len = parse_number(fields.next());
+                    let modified = parse_number(fields.next());
+                    let source = len
+                        .zip(modified)
+                        .map(|(len, modified)| FileStamp { len, modified })
+                        .ok_or_else(|| invalid_index("invalid index source"))?;
+                    index.source = Some(source);
+                    continue;
+                }
+                _ => return Err(invalid_index("unknown index line type")),
+            };
+            let name = fields
+                .next()
+                .ok_or_else(|| invalid_index("missing index name"))?;
+            let offset = parse_number(fields.next())
+                .ok_or_else(|| invalid_index("invalid index offset"))?;
+            map.insert(name.to_vec(), offset);
+        }
+        Ok(index)
+    }
+
+    /// Save the index to the given file.
+    #[cfg(feature = "fs")]
+    pub fn save<P: AsRef<FsPath>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Load an index from the given file.
+    #[cfg(feature = "fs")]
+    pub fn load<P: AsRef<FsPath>>(path: P) -> io::Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Load the sidecar index of the GFA at the given path, if it
+    /// exists and was built from the GFA as it is now, i.e. if the
+    /// size and modification time it records match those of the GFA.
+    #[cfg(feature = "fs")]
+    pub fn load_sidecar<P: AsRef<FsPath>>(
+        gfa_path: P,
+    ) -> io::Result<Option<Self>> {
+        let sidecar = Self::sidecar_path(&gfa_path);
+        if !sidecar.exists() {
+            return Ok(None);
+        }
+        let index = Self::load(&sidecar)?;
+        let current = FileStamp::of_file(&gfa_path)?;
+        Ok(Some(index).filter(|index| index.source == Some(current)))
+    }
+}
+
+/// Use the up-to-date sidecar index of the GFA at the given path if
+/// there is one, and otherwise build the index, saving it as the
+/// sidecar if `save` is true.
+#[cfg(feature = "fs")]
+fn sidecar_or_build<P, F>(path: P, save: bool, build: F) -> io::Result<GfaIndex>
+where
+    P: AsRef<FsPath>,
+    F: FnOnce() -> io::Result<GfaIndex>,
+{
+    if let Some(index) = GfaIndex::load_sidecar(&path)? {
+        return Ok(index);
+    }
+    let source = FileStamp::of_file(&path)?;
+    let mut index = build()?;
+    index.source = Some(source);
+    if save {
+        index.save(GfaIndex::sidecar_path(&path))?;
+    }
+    Ok(index)
+}
+
+/// A reader that can seek to the offsets stored in a `GfaIndex`.
+pub trait SeekOffset: BufRead {
+    fn seek_offset(&mut self, offset: u64) -> io::Result<()>;
+}
+
+impl<R: BufRead + Seek> SeekOffset for R {
+    fn seek_offset(&mut self, offset: u64) -> io::Result<()> {
+        self.seek(SeekFrom::Start(offset)).map(|_| ())
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl<R: io::Read + Seek> SeekOffset for BgzfReader<R> {
+    fn seek_offset(&mut self, offset: u64) -> io::Result<()> {
+        self.seek_virtual(offset)
+    }
+}
+
+/// A seekable GFA along with its index, for fetching single records
+/// by name, or ranges of lines by line number.
+pub struct IndexedGfa<R: SeekOffset> {
+    reader: R,
+    index: GfaIndex,
+    buf: Vec<u8>,
+}
+
+impl<R: SeekOffset> IndexedGfa<R> {
+    pub fn new(reader: R, index: GfaIndex) -> Self {
+        IndexedGfa {
+            reader,
+            index,
+            buf: Vec::new(),
+        }
+    }
+
+    pub fn index(&self) -> &GfaIndex {
+        &self.index
+    }
+
+    fn fetch<L: GFARecord>(
+        &mut self,
+        offset: Option<u64>,
+    ) -> GFAResult<Option<L>> {
+        let offset = match offset {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+        self.reader.seek_offset(offset)?;
+        self.buf.clear();
+        self.reader.read_until(b'\n', &mut self.buf)?;
+        match parse_record(&self.buf) {
+            Some(record) => record.map(Some),
+            None => Err(invalid_index("index doesn't match the GFA").into()),
+        }
+    }
+
+    /// The lines in the given range of 0-based line numbers, as they
+    /// are in the file but without line endings, read from the
+    /// closest indexed line before the range. Lines past the end of
+    /// the file are left out. The lines can be parsed with
+    /// `GFAParser::parse_gfa_line`.
+    pub fn fetch_lines(
+        &mut self,
+        range: Range<usize>,
+    ) -> GFAResult<Vec<Vec<u8>>> {
+        // indexes without line offsets can still be read from the
+        // start, which is at offset 0 in both plain and BGZF files
+        let (mut line_no, offset) =
+            self.index.line_offset_before(range.start).unwrap_or((0, 0));
+        self.reader.seek_offset(offset)?;
+        let mut lines = Vec::new();
+        while line_no < range.end {
+            self.buf.clear();
+            if self.reader.read_until(b'\n', &mut self.buf)? == 0 {
+                break;
+            }
+            if line_no >= range.start {
+                let line = self.buf.trim_end_with(|c| c == '\n' || c == '\r');
+                lines.push(line.to_vec());
+            }
+            line_no += 1;
+        }
+        Ok(lines)
+    }
+
+    /// Parse the segment with the given name, if it's in the index.
+    pub fn fetch_segment<N: SegmentId, T: OptFields>(
+        &mut self,
+        name: &[u8],
+    ) -> GFAResult<Option<Segment<N, T>>> {
+        let offset = self.index.segment_offset(name);
+        self.fetch(offset)
+    }
+
+    /// Parse the path with the given name, if it's in the index.
+    pub fn fetch_path<N: SegmentId, T: OptFields>(
+        &mut self,
+        name: &[u8],
+    ) -> GFAResult<Option<Path<N, T>>> {
+        let offset = self.index.path_offset(name);
+        self.fetch(offset)
+    }
+
+    /// Parse the walk with the given `sample#haplotype#sequence` name,
+    /// if it's in the index.
+    pub fn fetch_walk<N: SegmentId, T: OptFields>(
+        &mut self,
+        name: &[u8],
+    ) -> GFAResult<Option<Walk<N, T>>> {
+        let offset = self.index.walk_offset(name);
+        self.fetch(offset)
+    }
+}
+
+#[cfg(feature = "fs")]
+impl IndexedGfa<BufReader<File>> {
+    /// Open an uncompressed GFA file, using its sidecar index if it
+    /// exists and is up to date, and otherwise building the index in
+    /// memory.
+    pub fn open<P: AsRef<FsPath>>(path: P) -> GFAResult<Self> {
+        Self::open_with(path, false)
+    }
+
+    /// Like `open`, but saves the index as the sidecar if it had to
+    /// be built, so later calls can load it instead.
+    pub fn open_and_save_index<P: AsRef<FsPath>>(path: P) -> GFAResult<Self> {
+        Self::open_with(path, true)
+    }
+
+    fn open_with<P: AsRef<FsPath>>(path: P, save: bool) -> GFAResult<Self> {
+        let index = sidecar_or_build(&path, save, || {
+            GfaIndex::build(BufReader::new(File::open(&path)?))
+        })?;
+        let reader = BufReader::new(File::open(path)?);
+        Ok(IndexedGfa::new(reader, index))
+    }
+}
+
+#[cfg(feature = "gzip")]
+#[cfg(feature = "fs")]
+impl IndexedGfa<BgzfReader<BufReader<File>>> {
+    /// Open a BGZF-compressed GFA file, using its sidecar index if it
+    /// exists and is up to date, and otherwise building the index in
+    /// memory.
+    pub fn open_bgzf<P: AsRef<FsPath>>(path: P) -> GFAResult<Self> {
+        Self::open_bgzf_with(path, false)
+    }
+
+    /// Like `open_bgzf`, but saves the index as the sidecar if it had
+    /// to be built, so later calls can load it instead.
+    pub fn open_bgzf_and_save_index<P: AsRef<FsPath>>(
+        path: P,
+    ) -> GFAResult<Self> {
+        Self::open_bgzf_with(path, true)
+    }
+
+    fn open_bgzf_with<P: AsRef<FsPath>>(
+        path: P,
+        save: bool,
+    ) -> GFAResult<Self> {
+        let open = || -> io::Result<_> {
+            Ok(BgzfReader::new(BufReader::new(File::open(&path)?)))
+        };
+        let index =
+            sidecar_or_build(&path, save, || GfaIndex::build_bgzf(open()?))?;
+        Ok(IndexedGfa::new(open()?, index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::GFAParser;
+
+    #[test]
+    fn fetch_records_by_name() {
+        let parser: GFAParser<Vec<u8>, OptionalFields> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+
+        let index = GfaIndex::build_file("./test/gfas/lil.gfa").unwrap();
+        assert_eq!(index.segment_count(), gfa.segments.len());
+        assert_eq!(index.path_count(), gfa.paths.len());
+
+        let mut saved = Vec::new();
+        index.write(&mut saved).unwrap();
+        assert_eq!(GfaIndex::read(&saved[..]).unwrap(), index);
+
+        let file = File::open("./test/gfas/lil.gfa").unwrap();
+        let mut indexed = IndexedGfa::new(BufReader::new(file), index);
+        for seg in gfa.segments.iter().rev() {
+            let fetched = indexed.fetch_segment(&seg.name).unwrap();
+            assert_eq!(fetched.as_ref(), Some(seg));
+        }
+        for path in gfa.paths.iter() {
+            let fetched = indexed.fetch_path(&path.path_name).unwrap();
+            assert_eq!(fetched.as_ref(), Some(path));
+        }
+        let missing: Option<Segment<Vec<u8>, ()>> =
+            indexed.fetch_segment(b"missing").unwrap();
+        assert!(missing.is_none());
+    }
+
+    #[test]
+    fn fetch_line_ranges() {
+        let gfa = std::fs::read("./test/gfas/lil.gfa").unwrap();
+        let all: Vec<_> = ByteSlice::lines(gfa.as_slice())
+            .map(|l| l.to_vec())
+            .collect();
+
+        // index every 4th line, so ranges start between indexed lines
+        let index = GfaIndex::build_with(&gfa[..], 4, |_, read| read).unwrap();
+        assert_eq!(index.line_offset_before(6), Some((4, index.lines[&4])));
+        let mut saved = Vec::new();
+        index.write(&mut saved).unwrap();
+        assert_eq!(GfaIndex::read(&saved[..]).unwrap(), index);
+
+        let mut indexed = IndexedGfa::new(io::Cursor::new(&gfa), index);
+        assert_eq!(indexed.fetch_lines(6..9).unwrap(), &all[6..9]);
+        assert_eq!(indexed.fetch_lines(0..2).unwrap(), &all[0..2]);
+        let end = all.len();
+        assert_eq!(
+            indexed.fetch_lines(end - 1..end + 5).unwrap(),
+            &all[end - 1..]
+        );
+        assert!(indexed.fetch_lines(end..end + 1).unwrap().is_empty());
+
+        // an index without line offsets reads from the start
+        let index = GfaIndex::read(&b"S\t1\t0\n"[..]).unwrap();
+        let mut indexed = IndexedGfa::new(io::Cursor::new(&gfa), index);
+        assert_eq!(indexed.fetch_lines(3..5).unwrap(), &all[3..5]);
+    }
+
+    #[test]
+    fn sidecar_is_opt_in_and_checked() {
+        let path = crate::test_util::temp_path("indexed.gfa");
+        let sidecar = GfaIndex::sidecar_path(&path);
+        let mut gfa = std::fs::read("./test/gfas/lil.gfa").unwrap();
+        gfa.extend_from_slice(b"W\tHG002\t1\tchr1\t0\t2\t>1<2\n");
+        std::fs::write(&path, &gfa).unwrap();
+
+        let mut indexed = IndexedGfa::open(&path).unwrap();
+        assert!(!sidecar.exists());
+        let walk: Walk<Vec<u8>, ()> =
+            indexed.fetch_walk(b"HG002#1#chr1").unwrap().unwrap();
+        assert_eq!(walk.name(), b"HG002#1#chr1");
+        assert_eq!(walk.walk, b">1<2");
+
+        let indexed = IndexedGfa::open_and_save_index(&path).unwrap();
+        let saved = GfaIndex::load_sidecar(&path).unwrap();
+        assert_eq!(saved.as_ref(), Some(indexed.index()));
+
+        // the sidecar no longer matches once the GFA changes
+        gfa.extend_from_slice(b"S\tnew\tA\n");
+        std::fs::write(&path, &gfa).unwrap();
+        assert_eq!(GfaIndex::load_sidecar(&path).unwrap(), None);
+        let indexed = IndexedGfa::open(&path).unwrap();
+        assert!(indexed.index().segment_offset(b"new").is_some());
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&sidecar).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn fetch_records_from_bgzf() {
+        use crate::bgzf::BgzfWriter;
+        use std::io::Cursor;
+
+        let parser: GFAParser<usize, ()> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+
+        let mut writer = BgzfWriter::new(Vec::new());
+        let mut file = File::open("./test/gfas/lil.gfa").unwrap();
+        io::copy(&mut file, &mut writer).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let reader = BgzfReader::new(Cursor::new(&compressed));
+        let index = GfaIndex::build_bgzf(reader).unwrap();
+        let reader = BgzfReader::new(Cursor::new(&compressed));
+        let mut indexed = IndexedGfa::new(reader, index);
+        for path in gfa.paths.iter().rev() {
+            let fetched = indexed.fetch_path(&path.path_name).unwrap();
+            assert_eq!(fetched.as_ref(), Some(path));
+        }
+        let seg: Option<Segment<usize, ()>> =
+            indexed.fetch_segment(b"12").unwrap();
+        assert_eq!(seg.as_ref(), gfa.segments.iter().find(|s| s.name == 12));
+
+        let lines = std::fs::read("./test/gfas/lil.gfa").unwrap();
+        let lines: Vec<_> = ByteSlice::lines(lines.as_slice())
+            .map(|l| l.to_vec())
+            .collect();
+        assert_eq!(indexed.fetch_lines(2..5).unwrap(), &lines[2..5]);
+    }
+}
@@ -0,0 +1,230 @@
+use std::convert::TryFrom;
+
+#[cfg(feature = "fs")]
+use bstr::io::BufReadExt;
+use fnv::FnvHashMap;
+
+use crate::{gfa::*, optfields::*};
+
+use super::{parse_new_gfa, GFAParser, GFAResult, ParseError};
+
+/// String table for segment names that have been interned to dense
+/// `u32` IDs. IDs are assigned in the order the names are first
+/// seen, starting from zero, so they can be used directly as indices
+/// into `names()`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NameTable {
+    ids: FnvHashMap<Vec<u8>, u32>,
+    names: Vec<Vec<u8>>,
+}
+
+impl NameTable {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Return the ID of the given name, assigning it the next
+    /// available ID if it hasn't been seen before. Fails if every
+    /// `u32` ID is already taken.
+    pub fn intern(&mut self, name: &[u8]) -> GFAResult<u32> {
+        if let Some(id) = self.ids.get(name) {
+            return Ok(*id);
+        }
+        let id = u32::try_from(self.names.len())
+            .map_err(|_| ParseError::TooManyNames)?;
+        self.ids.insert(name.to_vec(), id);
+        self.names.push(name.to_vec());
+        Ok(id)
+    }
+
+    pub fn get_id(&self, name: &[u8]) -> Option<u32> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn get_name(&self, id: u32) -> Option<&[u8]> {
+        self.names.get(id as usize).map(|n| n.as_slice())
+    }
+
+    /// All interned names, indexed by ID.
+    pub fn names(&self) -> &[Vec<u8>] {
+        &self.names
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    fn intern_path<T: OptFields>(
+        &mut self,
+        path: Path<Vec<u8>, T>,
+    ) -> GFAResult<Path<u32, T>> {
+        let mut segment_names = Vec::with_capacity(path.segment_names.len());
+        for (i, (name, orient)) in path.iter().enumerate() {
+            if i != 0 {
+                segment_names.push(b',');
+            }
+            let id = self.intern(name)?;
+            segment_names.extend(id.to_string().as_bytes());
+            segment_names.push(orient.plus_minus_as_byte());
+        }
+        let overlaps = path.overlaps;
+        Ok(Path::new(
+            path.path_name,
+            segment_names,
+            overlaps,
+            path.optional,
+        ))
+    }
+
+    fn intern_walk<T: OptFields>(
+        &mut self,
+        walk: Walk<Vec<u8>, T>,
+    ) -> GFAResult<Walk<u32, T>> {
+        let mut steps = Vec::with_capacity(walk.walk.len());
+        for (name, orient) in walk.iter() {
+            steps.push(orient.gt_ln_as_byte());
+            let id = self.intern(name)?;
+            steps.extend(id.to_string().as_bytes());
+        }
+        Ok(walk.with_walk(steps))
+    }
+
+    /// Replace every segment name in the line with its interned ID.
+    pub fn intern_line<T: OptFields>(
+        &mut self,
+        line: Line<Vec<u8>, T>,
+    ) -> GFAResult<Line<u32, T>> {
+        let line = match line {
+            Line::Header(h) => Line::Header(h),
+            Line::Segment(s) => Line::Segment(Segment {
+                name: self.intern(&s.name)?,
+                sequence: s.sequence,
+                optional: s.optional,
+            }),
+            Line::Link(l) => Line::Link(Link {
+                from_segment: self.intern(&l.from_segment)?,
+                from_orient: l.from_orient,
+                to_segment: self.intern(&l.to_segment)?,
+                to_orient: l.to_orient,
+                overlap: l.overlap,
+                optional: l.optional,
+            }),
+            Line::Containment(c) => Line::Containment(Containment {
+                container_name: self.intern(&c.container_name)?,
+                container_orient: c.container_orient,
+                contained_name: self.intern(&c.contained_name)?,
+                contained_orient: c.contained_orient,
+                pos: c.pos,
+                overlap: c.overlap,
+                optional: c.optional,
+            }),
+            Line::Path(p) => Line::Path(self.intern_path(p)?),
+            Line::Walk(w) => Line::Walk(self.intern_walk(w)?),
+        };
+        Ok(line)
+    }
+}
+
+impl<T: OptFields> GFAParser<Vec<u8>, T> {
+    /// Parse the lines of a GFA while interning all segment names,
+    /// in segments, links, containments, and path steps, to dense
+    /// `u32` IDs. The names are stored only once, in the returned
+    /// `NameTable`.
+    pub fn parse_lines_interned<I>(
+        &self,
+        lines: I,
+    ) -> GFAResult<(GFA<u32, T>, NameTable)>
+    where
+        I: Iterator,
+        I::Item: AsRef<[u8]>,
+    {
+        parse_new_gfa(|gfa| {
+            let mut table = NameTable::new();
+            for line in lines {
+                match self.parse_gfa_line(line.as_ref()) {
+                    Ok(parsed) => gfa.insert_line(table.intern_line(parsed)?),
+                    Err(err) if err.can_safely_continue(&self.tolerance) => (),
+                    Err(err) => return Err(err),
+                };
+            }
+            Ok(table)
+        })
+    }
+
+    /// Parse a GFA file, interning segment names as in
+    /// `parse_lines_interned`.
+    #[cfg(feature = "fs")]
+    pub fn parse_file_interned<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> GFAResult<(GFA<u32, T>, NameTable)> {
+        parse_new_gfa(|gfa| {
+            let mut table = NameTable::new();
+            for line in crate::reader::open_file(path)?.byte_lines() {
+                let line = line?;
+                match self.parse_gfa_line(line.as_ref()) {
+                    Ok(parsed) => gfa.insert_line(table.intern_line(parsed)?),
+                    Err(err) if err.can_safely_continue(&self.tolerance) => (),
+                    Err(err) => return Err(err),
+                };
+            }
+            Ok(table)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bstr::ByteSlice;
+
+    #[test]
+    fn interned_names_are_dense() {
+        let parser: GFAParser<Vec<u8>, ()> = GFAParser::new();
+        let (gfa, table) = parser
+            .parse_file_interned("./test/gfas/diatom.gfa")
+            .unwrap();
+        let original = parser.parse_file("./test/gfas/diatom.gfa").unwrap();
+
+        assert_eq!(table.len(), original.segments.len());
+        for (seg, orig) in gfa.segments.iter().zip(original.segments.iter()) {
+            assert_eq!(table.get_name(seg.name), Some(orig.name.as_slice()));
+            assert_eq!(table.get_id(&orig.name), Some(seg.name));
+        }
+
+        for (link, orig) in gfa.links.iter().zip(original.links.iter()) {
+            let from = table.get_name(link.from_segment).unwrap();
+            let to = table.get_name(link.to_segment).unwrap();
+            assert_eq!(from, orig.from_segment.as_slice());
+            assert_eq!(to, orig.to_segment.as_slice());
+        }
+    }
+
+    #[test]
+    fn interned_path_steps() {
+        let parser: GFAParser<Vec<u8>, ()> = GFAParser::new();
+        let lines: Vec<&[u8]> =
+            vec![b"S\tfoo\tA", b"S\tbar\tC", b"P\tp1\tbar+,foo-,baz+\t*"];
+        let (gfa, table) = parser.parse_lines_interned(lines.iter()).unwrap();
+
+        let steps: Vec<_> = gfa.paths[0]
+            .iter()
+            .map(|(id, o)| (table.get_name(id).unwrap().as_bstr(), o))
+            .collect();
+
+        use Orientation::*;
+        assert_eq!(
+            steps,
+            vec![
+                ("bar".into(), Forward),
+                ("foo".into(), Backward),
+                ("baz".into(), Forward),
+            ]
+        );
+        assert_eq!(table.len(), 3);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{gfa::*, optfields::*, validation::Record};
+
+use super::{for_each_line, parse_new_gfa, GFAParser, GFAResult};
+
+/// A parsed record along with the 1-based number of the line it was
+/// parsed from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithLocation<L> {
+    pub line_number: usize,
+    pub record: L,
+}
+
+/// The 1-based line numbers that the records of a GFA were parsed
+/// from, in vectors parallel to the ones in the GFA, so that problems
+/// found after parsing can be traced back to the input file.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct LineNumbers {
+    pub headers: Vec<usize>,
+    pub segments: Vec<usize>,
+    pub links: Vec<usize>,
+    pub containments: Vec<usize>,
+    pub paths: Vec<usize>,
+    pub walks: Vec<usize>,
+}
+
+impl LineNumbers {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn push<N, T: OptFields>(&mut self, line: &Line<N, T>, number: usize) {
+        let numbers = match line {
+            Line::Header(_) => &mut self.headers,
+            Line::Segment(_) => &mut self.segments,
+            Line::Link(_) => &mut self.links,
+            Line::Containment(_) => &mut self.containments,
+            Line::Path(_) => &mut self.paths,
+            Line::Walk(_) => &mut self.walks,
+        };
+        numbers.push(number);
+    }
+
+    /// The line number of the given record, such as the record of a
+    /// `ValidationIssue`. The header is reported at its first line.
+    pub fn get(&self, record: Record) -> Option<usize> {
+        match record {
+            Record::Header => self.headers.first(),
+            Record::Segment(ix) => self.segments.get(ix),
+            Record::Link(ix) => self.links.get(ix),
+            Record::Containment(ix) => self.containments.get(ix),
+            Record::Path(ix) => self.paths.get(ix),
+            Record::Walk(ix) => self.walks.get(ix),
+        }
+        .copied()
+    }
+}
+
+impl<N: SegmentId, T: OptFields> GFAParser<N, T> {
+    /// Parse the given lines, producing each parsed line or error
+    /// along with its line number.
+    pub fn located_lines<'a, I>(
+        &'a self,
+        lines: I,
+    ) -> impl Iterator<Item = WithLocation<GFAResult<Line<N, T>>>> + 'a
+    where
+        I: Iterator + 'a,
+        I::Item: AsRef<[u8]>,
+    {
+        lines.enumerate().map(move |(ix, line)| WithLocation {
+            line_number: ix + 1,
+            record: self.parse_gfa_line(line.as_ref()),
+        })
+    }
+
+    fn insert_located(
+        &self,
+        gfa: &mut GFA<N, T>,
+        numbers: &mut LineNumbers,
+        line: WithLocation<GFAResult<Line<N, T>>>,
+    ) -> GFAResult<()> {
+        match line.record {
+            Ok(parsed) => {
+                numbers.push(&parsed, line.line_number);
+                gfa.insert_line(parsed);
+            }
+            Err(err) if err.can_safely_continue(&self.tolerance) => (),
+            Err(err) => return Err(err),
+        }
+        Ok(())
+    }
+
+    /// Parse the given lines into a GFA, along with the line number
+    /// of every record.
+    pub fn parse_lines_with_locations<I>(
+        &self,
+        lines: I,
+    ) -> GFAResult<(GFA<N, T>, LineNumbers)>
+    where
+        I: Iterator,
+        I::Item: AsRef<[u8]>,
+    {
+        parse_new_gfa(|gfa| {
+            let mut numbers = LineNumbers::new();
+            for line in self.located_lines(lines) {
+                self.insert_located(gfa, &mut numbers, line)?;
+            }
+            Ok(numbers)
+        })
+    }
+
+    /// Parse a GFA from a reader, which may be compressed, along with
+    /// the line number of every record.
+    pub fn parse_reader_with_locations<R: std::io::BufRead>(
+        &self,
+        reader: R,
+    ) -> GFAResult<(GFA<N, T>, LineNumbers)> {
+        parse_new_gfa(|gfa| {
+            let mut numbers = LineNumbers::new();
+            for_each_line(reader, |line_number, line| {
+                let line = WithLocation {
+                    line_number,
+                    record: self.parse_gfa_line(line),
+                };
+                self.insert_located(gfa, &mut numbers, line)
+            })?;
+            Ok(numbers)
+        })
+    }
+
+    /// Parse a GFA file, which may be compressed, along with the line
+    /// number of every record.
+    #[cfg(feature = "fs")]
+    pub fn parse_file_with_locations<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> GFAResult<(GFA<N, T>, LineNumbers)> {
+        let file = std::fs::File::open(path)?;
+        self.parse_reader_with_locations(std::io::BufReader::new(file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::validate_references;
+
+    #[test]
+    fn validation_issues_have_line_numbers() {
+        let lines = [
+            "H\tVN:Z:1.0",
+            "S\t1\tA",
+            "# comment",
+            "S\t2\tC",
+            "L\t1\t+\t2\t+\t0M",
+            "L\t1\t+\t3\t+\t0M",
+        ];
+        let parser: GFAParser<Vec<u8>, ()> = GFAParser::new();
+        let (gfa, numbers) =
+            parser.parse_lines_with_locations(lines.iter()).unwrap();
+
+        assert_eq!(numbers.segments, vec![2, 4]);
+        assert_eq!(numbers.links, vec![5, 6]);
+
+        let issues = validate_references(&gfa);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(numbers.get(issues[0].record), Some(6));
+        assert_eq!(numbers.get(Record::Header), Some(1));
+
+        let input = lines.join("\n");
+        let from_reader = parser
+            .parse_reader_with_locations(input.as_bytes())
+            .unwrap();
+        assert_eq!(from_reader, (gfa, numbers));
+    }
+}
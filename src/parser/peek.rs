@@ -0,0 +1,138 @@
+use bstr::{io::BufReadExt, ByteSlice};
+
+use crate::{gfa::Header, optfields::OptFields};
+
+use super::GFAResult;
+
+/// The GFA version of a file, as detected by `detect_version`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GfaVersion {
+    Gfa1,
+    Gfa2,
+    /// The header has a `VN` tag that's neither 1.x nor 2.x.
+    Unknown,
+}
+
+impl GfaVersion {
+    /// The version described by the value of a `VN` header tag.
+    pub fn from_tag(version: &[u8]) -> Self {
+        if version == b"1" || version.starts_with(b"1.") {
+            GfaVersion::Gfa1
+        } else if version == b"2" || version.starts_with(b"2.") {
+            GfaVersion::Gfa2
+        } else {
+            GfaVersion::Unknown
+        }
+    }
+}
+
+/// Read the header lines at the start of a GFA, merging them as the
+/// parser does, and stopping at the first line of any other type.
+/// Also returns the type of that line, if there is one. Empty lines
+/// and `#` comments are skipped.
+fn peek<T, R>(reader: R) -> GFAResult<(Header<T>, Option<u8>)>
+where
+    T: OptFields,
+    R: std::io::BufRead,
+{
+    let reader = crate::reader::decompress_reader(reader)?;
+    let mut header = Header {
+        version: None,
+        optional: T::default(),
+    };
+    for line in reader.byte_lines() {
+        let line = line?;
+        let line = line.trim();
+        match line.first() {
+            None | Some(b'#') => continue,
+            Some(b'H') => {
+                let fields = line.split_str(b"\t").skip(1);
+                header.merge(Header::parse_line(fields)?);
+            }
+            Some(&line_type) => return Ok((header, Some(line_type))),
+        }
+    }
+    Ok((header, None))
+}
+
+/// Read only the header lines at the start of a GFA from a reader,
+/// which may be compressed, without parsing the rest of the file.
+/// The version is `None` if there's no `VN` tag.
+pub fn peek_header_reader<T, R>(reader: R) -> GFAResult<Header<T>>
+where
+    T: OptFields,
+    R: std::io::BufRead,
+{
+    peek(reader).map(|(header, _)| header)
+}
+
+/// Detect the GFA version of a reader from the `VN` header tag or,
+/// without one, from the type of the first line after the header.
+pub fn detect_version_reader<R: std::io::BufRead>(
+    reader: R,
+) -> GFAResult<GfaVersion> {
+    let (header, line_type) = peek::<(), _>(reader)?;
+    if let Some(version) = header.version {
+        return Ok(GfaVersion::from_tag(&version));
+    }
+    match line_type {
+        Some(b'E' | b'G' | b'F' | b'O' | b'U') => Ok(GfaVersion::Gfa2),
+        _ => Ok(GfaVersion::Gfa1),
+    }
+}
+
+/// Read only the header lines at the start of a GFA file, so tools
+/// can check the header before committing to a full parse.
+#[cfg(feature = "fs")]
+pub fn peek_header<T, P>(path: P) -> GFAResult<Header<T>>
+where
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+{
+    let file = std::fs::File::open(path)?;
+    peek_header_reader(std::io::BufReader::new(file))
+}
+
+/// Detect the GFA version of a file, as in `detect_version_reader`.
+#[cfg(feature = "fs")]
+pub fn detect_version<P: AsRef<std::path::Path>>(
+    path: P,
+) -> GFAResult<GfaVersion> {
+    let file = std::fs::File::open(path)?;
+    detect_version_reader(std::io::BufReader::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optfields::OptionalFields;
+
+    #[test]
+    fn peek_header_lines() {
+        let gfa = "# comment\nH\tVN:Z:1.0\nH\tPG:Z:tool\nS\t1\tA\nH\tXY:i:1\n";
+        let header: Header<OptionalFields> =
+            peek_header_reader(gfa.as_bytes()).unwrap();
+        assert_eq!(header.version, Some(b"1.0".to_vec()));
+        assert_eq!(header.optional.len(), 1);
+
+        let header: Header<()> = peek_header("./test/gfas/lil.gfa").unwrap();
+        assert_eq!(header.version, Some(b"1.0".to_vec()));
+        assert_eq!(
+            detect_version("./test/gfas/lil.gfa").unwrap(),
+            GfaVersion::Gfa1
+        );
+    }
+
+    #[test]
+    fn detect_gfa_versions() {
+        let detect = |gfa: &str| detect_version_reader(gfa.as_bytes());
+        assert_eq!(detect("H\tVN:Z:2.0\n").unwrap(), GfaVersion::Gfa2);
+        assert_eq!(detect("H\tVN:Z:1.1\n").unwrap(), GfaVersion::Gfa1);
+        assert_eq!(detect("H\tVN:Z:abc\n").unwrap(), GfaVersion::Unknown);
+        assert_eq!(
+            detect("E\t*\t1+\t2+\t0\t1\t0\t1\t*\n").unwrap(),
+            GfaVersion::Gfa2
+        );
+        assert_eq!(detect("").unwrap(), GfaVersion::Gfa1);
+    }
+}
@@ -0,0 +1,227 @@
+use std::{io::BufRead, marker::PhantomData};
+
+use bstr::ByteSlice;
+
+use crate::{gfa::*, optfields::*};
+
+use super::{parse_segment_id, GFAFieldResult, GFAResult, ParseError};
+
+/// A GFA line type that can be parsed on its own, used to stream
+/// only one kind of record from a file.
+pub trait GFARecord: Sized {
+    /// The byte at the start of lines of this type, e.g. `b'S'`.
+    const LINE_TYPE: u8;
+
+    /// Parse the record from the tab-separated fields following the
+    /// line type.
+    fn parse_fields<'a, I>(fields: I) -> GFAFieldResult<Self>
+    where
+        I: Iterator<Item = &'a [u8]>;
+}
+
+macro_rules! impl_gfa_record {
+    ($record:ident, $line_type:literal $(, $parse_id:ident)?) => {
+        impl<N: SegmentId, T: OptFields> GFARecord for $record<N, T> {
+            const LINE_TYPE: u8 = $line_type;
+
+            fn parse_fields<'a, I>(fields: I) -> GFAFieldResult<Self>
+            where
+                I: Iterator<Item = &'a [u8]>,
+            {
+                $record::parse_line(fields $(, $parse_id)?)
+            }
+        }
+    };
+}
+
+impl_gfa_record!(Segment, b'S', parse_segment_id);
+impl_gfa_record!(Link, b'L', parse_segment_id);
+impl_gfa_record!(Containment, b'C', parse_segment_id);
+impl_gfa_record!(Path, b'P');
+impl_gfa_record!(Walk, b'W');
+
+/// Parse a line as a record of type `L`, or return `None` if it's a
+/// line of another type.
+pub(crate) fn parse_record<L: GFARecord>(line: &[u8]) -> Option<GFAResult<L>> {
+    let line = line.trim();
+    if line.first() != Some(&L::LINE_TYPE) || line.get(1) != Some(&b'\t') {
+        return None;
+    }
+
+    let fields = line[2..].split_str(b"\t");
+    let record = L::parse_fields(fields)
+        .map_err(|err| ParseError::invalid_line(err, line));
+    Some(record)
+}
+
+/// Iterator over the records of a single line type in a GFA. Lines
+/// of other types are skipped without being parsed, and the line
+/// buffer is reused, so the only allocations are those of the
+/// records themselves.
+pub struct RecordIter<R: BufRead, L: GFARecord> {
+    reader: R,
+    buf: Vec<u8>,
+    _record: PhantomData<L>,
+}
+
+impl<R: BufRead, L: GFARecord> RecordIter<R, L> {
+    pub fn new(reader: R) -> Self {
+        RecordIter {
+            reader,
+            buf: Vec::new(),
+            _record: PhantomData,
+        }
+    }
+}
+
+impl<R: BufRead, L: GFARecord> Iterator for RecordIter<R, L> {
+    type Item = GFAResult<L>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buf.clear();
+            match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(0) => return None,
+                Ok(_) => (),
+                Err(err) => return Some(Err(err.into())),
+            }
+
+            if let Some(record) = parse_record(&self.buf) {
+                return Some(record);
+            }
+        }
+    }
+}
+
+impl<R: BufRead, L: GFARecord> std::iter::FusedIterator for RecordIter<R, L> {}
+
+#[cfg(feature = "fs")]
+fn open_records<P, L>(path: P) -> GFAResult<RecordIter<Box<dyn BufRead>, L>>
+where
+    P: AsRef<std::path::Path>,
+    L: GFARecord,
+{
+    let reader = crate::reader::open_file(path)?;
+    Ok(RecordIter::new(reader))
+}
+
+/// Stream the segments of a GFA file.
+#[cfg(feature = "fs")]
+pub fn segments_iter<N, T, P>(
+    path: P,
+) -> GFAResult<RecordIter<Box<dyn BufRead>, Segment<N, T>>>
+where
+    N: SegmentId,
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+{
+    open_records(path)
+}
+
+/// Stream the links of a GFA file.
+#[cfg(feature = "fs")]
+pub fn links_iter<N, T, P>(
+    path: P,
+) -> GFAResult<RecordIter<Box<dyn BufRead>, Link<N, T>>>
+where
+    N: SegmentId,
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+{
+    open_records(path)
+}
+
+/// Stream the containments of a GFA file.
+#[cfg(feature = "fs")]
+pub fn containments_iter<N, T, P>(
+    path: P,
+) -> GFAResult<RecordIter<Box<dyn BufRead>, Containment<N, T>>>
+where
+    N: SegmentId,
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+{
+    open_records(path)
+}
+
+/// Stream the paths of a GFA file.
+#[cfg(feature = "fs")]
+pub fn paths_iter<N, T, P>(
+    path: P,
+) -> GFAResult<RecordIter<Box<dyn BufRead>, Path<N, T>>>
+where
+    N: SegmentId,
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+{
+    open_records(path)
+}
+
+/// Stream the walks of a GFA file.
+#[cfg(feature = "fs")]
+pub fn walks_iter<N, T, P>(
+    path: P,
+) -> GFAResult<RecordIter<Box<dyn BufRead>, Walk<N, T>>>
+where
+    N: SegmentId,
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+{
+    open_records(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::GFAParser;
+
+    #[test]
+    fn typed_iters_match_full_parse() {
+        let parser: GFAParser<Vec<u8>, OptionalFields> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+
+        let segments = segments_iter("./test/gfas/lil.gfa")
+            .unwrap()
+            .collect::<GFAResult<Vec<_>>>()
+            .unwrap();
+        let links = links_iter("./test/gfas/lil.gfa")
+            .unwrap()
+            .collect::<GFAResult<Vec<_>>>()
+            .unwrap();
+        let containments = containments_iter("./test/gfas/lil.gfa")
+            .unwrap()
+            .collect::<GFAResult<Vec<_>>>()
+            .unwrap();
+        let paths = paths_iter("./test/gfas/lil.gfa")
+            .unwrap()
+            .collect::<GFAResult<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(gfa.segments, segments);
+        assert_eq!(gfa.links, links);
+        assert_eq!(gfa.containments, containments);
+        assert_eq!(gfa.paths, paths);
+
+        let gfa = parser.parse_file("./test/gfas/walks.gfa").unwrap();
+        let walks = walks_iter("./test/gfas/walks.gfa")
+            .unwrap()
+            .collect::<GFAResult<Vec<_>>>()
+            .unwrap();
+        assert_eq!(walks.len(), 3);
+        assert_eq!(gfa.walks, walks);
+    }
+
+    #[test]
+    fn record_iter_reports_invalid_lines() {
+        let input: &[u8] = b"S\t1\tA\nL\t1\t+\t2\t+\t0M\nS\tx\tC\n";
+        let mut segments: RecordIter<_, Segment<usize, ()>> =
+            RecordIter::new(input);
+
+        assert_eq!(segments.next().unwrap().unwrap().name, 1);
+        assert!(matches!(
+            segments.next(),
+            Some(Err(ParseError::InvalidLine(_, _)))
+        ));
+        assert!(segments.next().is_none());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{gfa::*, optfields::*};
+
+use super::{for_each_line, GFAParser, GFAResult};
+
+/// A consumer of parsed GFA records, for building custom structures
+/// from a GFA in a single pass without constructing a `GFA` first.
+/// Every callback does nothing by default, so sinks only need to
+/// implement the line types they're interested in.
+pub trait GfaSink<N, T: OptFields> {
+    fn header(&mut self, _header: Header<T>) {}
+
+    fn segment(&mut self, _segment: Segment<N, T>) {}
+
+    fn link(&mut self, _link: Link<N, T>) {}
+
+    fn containment(&mut self, _containment: Containment<N, T>) {}
+
+    fn path(&mut self, _path: Path<N, T>) {}
+
+    fn walk(&mut self, _walk: Walk<N, T>) {}
+
+    /// Pass a parsed line to the callback for its line type.
+    fn line(&mut self, line: Line<N, T>) {
+        match line {
+            Line::Header(h) => self.header(h),
+            Line::Segment(s) => self.segment(s),
+            Line::Link(l) => self.link(l),
+            Line::Containment(c) => self.containment(c),
+            Line::Path(p) => self.path(p),
+            Line::Walk(w) => self.walk(w),
+        }
+    }
+}
+
+/// Inserts each line with `GFA::insert_line`. Header lines are merged
+/// with `Header::merge`, so a GFA that already has a version, as the
+/// default one does, keeps it rather than taking the parsed one.
+impl<N, T: OptFields> GfaSink<N, T> for GFA<N, T> {
+    fn line(&mut self, line: Line<N, T>) {
+        self.insert_line(line);
+    }
+}
+
+impl<N: SegmentId, T: OptFields> GFAParser<N, T> {
+    /// Parse the given lines, passing each record to the sink in the
+    /// order they appear.
+    pub fn parse_lines_into<I, S>(
+        &self,
+        lines: I,
+        sink: &mut S,
+    ) -> GFAResult<()>
+    where
+        I: Iterator,
+        I::Item: AsRef<[u8]>,
+        S: GfaSink<N, T> + ?Sized,
+    {
+        for line in lines {
+            match self.parse_gfa_line(line.as_ref()) {
+                Ok(parsed) => sink.line(parsed),
+                Err(err) if err.can_safely_continue(&self.tolerance) => (),
+                Err(err) => return Err(err),
+            };
+        }
+        Ok(())
+    }
+
+    /// Parse a GFA from a reader, which may be compressed, passing each
+    /// record to the sink in the order they appear.
+    pub fn parse_reader_into<R, S>(
+        &self,
+        reader: R,
+        sink: &mut S,
+    ) -> GFAResult<()>
+    where
+        R: std::io::BufRead,
+        S: GfaSink<N, T> + ?Sized,
+    {
+        for_each_line(reader, |_, line| {
+            match self.parse_gfa_line(line) {
+                Ok(parsed) => sink.line(parsed),
+                Err(err) if err.can_safely_continue(&self.tolerance) => (),
+                Err(err) => return Err(err),
+            };
+            Ok(())
+        })
+    }
+
+    /// Parse a GFA file, which may be compressed, passing each record
+    /// to the sink in the order they appear.
+    #[cfg(feature = "fs")]
+    pub fn parse_file_into<P, S>(&self, path: P, sink: &mut S) -> GFAResult<()>
+    where
+        P: AsRef<std::path::Path>,
+        S: GfaSink<N, T> + ?Sized,
+    {
+        let file = std::fs::File::open(path)?;
+        self.parse_reader_into(std::io::BufReader::new(file), sink)
+    }
+}
+
+/// Parse a GFA file with the default parser, passing each record to
+/// the sink.
+#[cfg(feature = "fs")]
+pub fn parse_gfa_into<N, T, P, S>(path: P, sink: &mut S) -> GFAResult<()>
+where
+    N: SegmentId,
+    T: OptFields,
+    P: AsRef<std::path::Path>,
+    S: GfaSink<N, T> + ?Sized,
+{
+    GFAParser::new().parse_file_into(path, sink)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Lengths {
+        total: usize,
+        edges: Vec<(usize, usize)>,
+    }
+
+    impl GfaSink<usize, ()> for Lengths {
+        fn segment(&mut self, segment: Segment<usize, ()>) {
+            self.total += segment.sequence.len();
+        }
+
+        fn link(&mut self, link: Link<usize, ()>) {
+            self.edges.push((link.from_segment, link.to_segment));
+        }
+    }
+
+    #[test]
+    fn sink_sees_every_record() {
+        let parser: GFAParser<usize, ()> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+
+        let mut sink = Lengths::default();
+        parse_gfa_into("./test/gfas/lil.gfa", &mut sink).unwrap();
+
+        let total: usize = gfa.segments.iter().map(|s| s.sequence.len()).sum();
+        let edges: Vec<_> = gfa
+            .links
+            .iter()
+            .map(|l| (l.from_segment, l.to_segment))
+            .collect();
+        assert_eq!(sink.total, total);
+        assert_eq!(sink.edges, edges);
+
+        let mut copy = GFA::new();
+        parser
+            .parse_file_into("./test/gfas/lil.gfa", &mut copy)
+            .unwrap();
+        assert_eq!(copy, gfa);
+    }
+}
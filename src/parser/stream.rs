@@ -0,0 +1,78 @@
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tokio_stream::{wrappers::SplitStream, Stream, StreamExt};
+
+use crate::{gfa::*, optfields::*};
+
+use super::{GFAParser, GFAResult, ParseError};
+
+/// Parse the lines of a GFA from any `AsyncBufRead`, producing a
+/// stream of parsed lines.
+///
+/// Errors that the parser's tolerance allows it to skip (e.g. empty
+/// lines and unknown line types with the default tolerance) are
+/// filtered out; all other errors, including IO errors, are yielded
+/// as items so the consumer can decide whether to stop.
+pub fn parse_gfa_stream_async<R, N, T>(
+    parser: GFAParser<N, T>,
+    reader: R,
+) -> impl Stream<Item = GFAResult<Line<N, T>>>
+where
+    R: AsyncBufRead + Unpin,
+    N: SegmentId,
+    T: OptFields,
+{
+    let lines = SplitStream::new(reader.split(b'\n'));
+    lines.filter_map(move |line| {
+        let result = line
+            .map_err(ParseError::from)
+            .and_then(|line| parser.parse_gfa_line(&line));
+        match result {
+            Err(err) if err.can_safely_continue(&parser.tolerance) => None,
+            result => Some(result),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stream_lil_gfa() {
+        let file = tokio::fs::File::open("./test/gfas/lil.gfa").await.unwrap();
+        let reader = tokio::io::BufReader::new(file);
+        let parser: GFAParser<usize, ()> = GFAParser::new();
+
+        let lines: Vec<_> = parse_gfa_stream_async(parser.clone(), reader)
+            .collect::<GFAResult<Vec<_>>>()
+            .await
+            .unwrap();
+
+        let mut gfa = GFA::new();
+        for line in lines {
+            gfa.insert_line(line);
+        }
+
+        let expected = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+        assert_eq!(expected, gfa);
+    }
+
+    #[tokio::test]
+    async fn stream_yields_errors() {
+        let input: &[u8] = b"S\t1\tA\n\nX\tskipped\nS\tnot_a_number\tC\n";
+        let parser: GFAParser<usize, ()> = GFAParser::new();
+
+        let results: Vec<_> =
+            parse_gfa_stream_async(parser, input).collect().await;
+
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(matches!(
+            results[1],
+            Err(ParseError::InvalidLine(
+                crate::parser::ParseFieldError::UintIdError,
+                _
+            ))
+        ));
+    }
+}
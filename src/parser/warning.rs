@@ -0,0 +1,267 @@
+use std::fmt;
+
+use bstr::ByteSlice;
+
+use crate::{gfa::*, optfields::*};
+
+use super::{
+    for_each_line, parse_new_gfa, required_fields, GFAParser, GFAResult,
+    ParseError,
+};
+
+/// The kinds of recoverable problems the parser can report while
+/// continuing to parse, rather than skipping them silently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A line type other than 'H', 'S', 'L', 'C', 'P', or 'W'
+    /// was skipped. Includes the line type.
+    UnknownLineType(String),
+    /// An empty line was skipped.
+    EmptyLine,
+    /// An optional field with a type other than those in the spec
+    /// was skipped. Includes the field.
+    UnknownTagType(String),
+    /// An optional field whose value doesn't match its type was
+    /// skipped. Includes the field.
+    InvalidOptionalField(String),
+    /// A line that couldn't be parsed was skipped, as allowed by the
+    /// parser's error tolerance. Includes the error message.
+    SkippedLine(String),
+}
+
+/// A recoverable problem found on the given 1-based line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    pub line_number: usize,
+    pub kind: WarningKind,
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line_number)?;
+        match &self.kind {
+            WarningKind::UnknownLineType(line_type) => {
+                write!(f, "Skipped line of unknown type `{}`", line_type)
+            }
+            WarningKind::EmptyLine => write!(f, "Skipped empty line"),
+            WarningKind::UnknownTagType(field) => {
+                write!(f, "Skipped optional field of unknown type `{}`", field)
+            }
+            WarningKind::InvalidOptionalField(field) => {
+                write!(f, "Skipped invalid optional field `{}`", field)
+            }
+            WarningKind::SkippedLine(err) => {
+                write!(f, "Skipped invalid line: {}", err)
+            }
+        }
+    }
+}
+
+/// A consumer of the warnings produced while parsing.
+pub trait WarningSink {
+    fn warn(&mut self, warning: ParseWarning);
+}
+
+impl WarningSink for Vec<ParseWarning> {
+    fn warn(&mut self, warning: ParseWarning) {
+        self.push(warning);
+    }
+}
+
+/// Discards all warnings.
+impl WarningSink for () {
+    fn warn(&mut self, _warning: ParseWarning) {}
+}
+
+/// The warning about an optional field that the optional field parser
+/// skips, or `None` if it's valid.
+fn optional_field_warning(field: &[u8]) -> Option<WarningKind> {
+    if OptField::parse(field).is_some() {
+        return None;
+    }
+    let known = b"AifZJHB".contains(field.get(3).unwrap_or(&0));
+    let field = field.to_str_lossy().into_owned();
+    if known {
+        Some(WarningKind::InvalidOptionalField(field))
+    } else {
+        Some(WarningKind::UnknownTagType(field))
+    }
+}
+
+impl<N: SegmentId, T: OptFields> GFAParser<N, T> {
+    /// The warnings about the optional fields of a line that was
+    /// parsed successfully, i.e. those the optional field parser
+    /// skips, found in the fields as the parser split them.
+    fn optional_field_warnings(&self, line: &[u8]) -> Vec<WarningKind> {
+        let (hdr, fields) = match self.split_line(line) {
+            Ok(split) => split,
+            Err(_) => return Vec::new(),
+        };
+        let required = required_fields(hdr).saturating_sub(1);
+        fields
+            .skip(required)
+            .filter_map(optional_field_warning)
+            .collect()
+    }
+
+    /// Parse a line, reporting recoverable problems to `warnings`.
+    /// Returns `Ok(None)` if the line was skipped.
+    fn parse_line_warn<W: WarningSink + ?Sized>(
+        &self,
+        bytes: &[u8],
+        line_number: usize,
+        warnings: &mut W,
+    ) -> GFAResult<Option<Line<N, T>>> {
+        let mut warn = |kind| warnings.warn(ParseWarning { line_number, kind });
+        let line = bytes.trim();
+        match self.parse_gfa_line(bytes) {
+            Ok(parsed) => {
+                let found = self.optional_field_warnings(line);
+                found.into_iter().for_each(warn);
+                Ok(Some(parsed))
+            }
+            Err(err) if err.can_safely_continue(&self.tolerance) => {
+                let kind = match err {
+                    _ if line.is_empty() => WarningKind::EmptyLine,
+                    ParseError::EmptyLine => WarningKind::EmptyLine,
+                    ParseError::UnknownLineType => {
+                        let hdr = line.split_str(b"\t").next();
+                        let hdr = hdr.unwrap_or_default().to_str_lossy();
+                        WarningKind::UnknownLineType(hdr.into_owned())
+                    }
+                    err => WarningKind::SkippedLine(err.to_string()),
+                };
+                warn(kind);
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Parse the given lines, reporting recoverable problems, such as
+    /// unknown line types and skipped optional fields, to `warnings`.
+    pub fn parse_lines_with_warnings<I, W>(
+        &self,
+        lines: I,
+        warnings: &mut W,
+    ) -> GFAResult<GFA<N, T>>
+    where
+        I: Iterator,
+        I::Item: AsRef<[u8]>,
+        W: WarningSink + ?Sized,
+    {
+        let (gfa, ()) = parse_new_gfa(|gfa| {
+            for (ix, line) in lines.enumerate() {
+                let parsed =
+                    self.parse_line_warn(line.as_ref(), ix + 1, warnings)?;
+                if let Some(parsed) = parsed {
+                    gfa.insert_line(parsed);
+                }
+            }
+            Ok(())
+        })?;
+        Ok(gfa)
+    }
+
+    /// Parse a GFA from a reader, which may be compressed, reporting
+    /// recoverable problems to `warnings`.
+    pub fn parse_reader_with_warnings<R, W>(
+        &self,
+        reader: R,
+        warnings: &mut W,
+    ) -> GFAResult<GFA<N, T>>
+    where
+        R: std::io::BufRead,
+        W: WarningSink + ?Sized,
+    {
+        let (gfa, ()) = parse_new_gfa(|gfa| {
+            for_each_line(reader, |number, line| {
+                let parsed = self.parse_line_warn(line, number, warnings)?;
+                if let Some(parsed) = parsed {
+                    gfa.insert_line(parsed);
+                }
+                Ok(())
+            })
+        })?;
+        Ok(gfa)
+    }
+
+    /// Parse a GFA file, which may be compressed, reporting
+    /// recoverable problems to `warnings`.
+    #[cfg(feature = "fs")]
+    pub fn parse_file_with_warnings<P, W>(
+        &self,
+        path: P,
+        warnings: &mut W,
+    ) -> GFAResult<GFA<N, T>>
+    where
+        P: AsRef<std::path::Path>,
+        W: WarningSink + ?Sized,
+    {
+        let file = std::fs::File::open(path)?;
+        self.parse_reader_with_warnings(std::io::BufReader::new(file), warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{error::ParserProfile, GFAParserBuilder};
+
+    #[test]
+    fn warnings_are_reported() {
+        let lines = [
+            "H\tVN:Z:1.0",
+            "S\t1\tA\tLN:i:1\tXX:Q:1\tYY:i:abc",
+            "",
+            "U\tgroup\t1 2",
+            "L\t1\t+\t1\t?\t0M",
+        ];
+        let parser: GFAParser<Vec<u8>, OptionalFields> = GFAParser::new();
+        let mut warnings = Vec::new();
+        let result =
+            parser.parse_lines_with_warnings(lines.iter(), &mut warnings);
+        assert!(result.is_err());
+
+        let kinds: Vec<_> =
+            warnings.iter().map(|w| (w.line_number, &w.kind)).collect();
+        use WarningKind::*;
+        assert_eq!(
+            kinds,
+            vec![
+                (2, &UnknownTagType("XX:Q:1".into())),
+                (2, &InvalidOptionalField("YY:i:abc".into())),
+                (3, &EmptyLine),
+                (4, &UnknownLineType("U".into())),
+            ]
+        );
+
+        let mut builder = GFAParserBuilder::all();
+        builder.ignore_errors();
+        let parser: GFAParser<Vec<u8>, OptionalFields> = builder.build();
+        let mut warnings = Vec::new();
+        let gfa = parser
+            .parse_lines_with_warnings(lines.iter(), &mut warnings)
+            .unwrap();
+        assert_eq!(gfa.segments.len(), 1);
+        assert_eq!(gfa.segments[0].optional.len(), 1);
+        assert_eq!(warnings.len(), 5);
+        assert!(matches!(warnings[4].kind, SkippedLine(_)));
+        assert!(warnings[4]
+            .to_string()
+            .starts_with("line 5: Skipped invalid line"));
+
+        // the optional fields are the ones the profile reads
+        let mut builder = GFAParserBuilder::all();
+        builder.profile(ParserProfile::Permissive);
+        let parser: GFAParser<Vec<u8>, OptionalFields> = builder.build();
+        let lines = ["S\t1 \t A\t LN:i:1 \tXX:Q:1"];
+        let mut warnings = Vec::new();
+        let gfa = parser
+            .parse_lines_with_warnings(lines.iter(), &mut warnings)
+            .unwrap();
+        assert_eq!(gfa.segments[0].optional.len(), 1);
+        let kinds: Vec<_> = warnings.into_iter().map(|w| w.kind).collect();
+        assert_eq!(kinds, vec![UnknownTagType("XX:Q:1".into())]);
+    }
+}
@@ -0,0 +1,308 @@
+//! Functions for opening GFA, GAF, and PAF input, transparently
+//! decompressing gzip and BGZF files when the `gzip` feature is
+//! enabled, and for creating output files that are compressed based
+//! on their extension.
+
+use std::io::{self, BufRead, Write};
+#[cfg(feature = "fs")]
+use std::{fs::File, io::BufWriter};
+
+/// The first two bytes of any gzip member. BGZF files are valid
+/// multi-member gzip files, so they start with the same bytes.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Returns true if the bytes start with a BGZF block header, i.e. a
+/// gzip header with a `BC` extra subfield.
+fn is_bgzf(bytes: &[u8]) -> bool {
+    bytes.len() >= 16
+        && bytes[..4] == [0x1f, 0x8b, 0x08, 0x04]
+        && bytes[12..14] == *b"BC"
+}
+
+/// The compression formats that can be detected on input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// Plain gzip.
+    Gzip,
+    /// BGZF, which is gzip made of independently compressed blocks,
+    /// and can be indexed with virtual offsets. It can be read by any
+    /// gzip decoder.
+    Bgzf,
+}
+
+impl Compression {
+    /// Detect the compression format of a reader by peeking at its
+    /// buffer, without consuming any bytes.
+    pub fn detect<R: BufRead>(reader: &mut R) -> io::Result<Self> {
+        let buf = reader.fill_buf()?;
+        if is_bgzf(buf) {
+            Ok(Compression::Bgzf)
+        } else if buf.starts_with(&GZIP_MAGIC) {
+            Ok(Compression::Gzip)
+        } else {
+            Ok(Compression::None)
+        }
+    }
+
+    /// Pick the compression format for an output file from its
+    /// extension. `.gz`, `.bgz`, and `.bgzf` files are all compressed
+    /// with BGZF, as by `bgzip`, so that they can be indexed while
+    /// still being readable by any gzip decoder. Plain gzip output is
+    /// only available by passing `Gzip` to `compress_writer`.
+    pub fn from_extension<P: AsRef<std::path::Path>>(path: P) -> Self {
+        let ext = path.as_ref().extension().and_then(|ext| ext.to_str());
+        match ext {
+            Some("gz") | Some("bgz") | Some("bgzf") => Compression::Bgzf,
+            _ => Compression::None,
+        }
+    }
+}
+
+/// Wrap a buffered reader so that its contents are decompressed if
+/// they start with the gzip magic bytes, and passed through
+/// unchanged otherwise.
+///
+/// Without the `gzip` feature, compressed input produces an
+/// `InvalidData` error rather than a stream of garbage lines.
+pub fn decompress_reader<'a, R>(
+    mut reader: R,
+) -> io::Result<Box<dyn BufRead + 'a>>
+where
+    R: BufRead + 'a,
+{
+    match Compression::detect(&mut reader)? {
+        Compression::None => Ok(Box::new(reader)),
+        Compression::Gzip | Compression::Bgzf => gzip_reader(reader),
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn gzip_reader<'a, R>(reader: R) -> io::Result<Box<dyn BufRead + 'a>>
+where
+    R: BufRead + 'a,
+{
+    // MultiGzDecoder keeps reading past the end of the first member,
+    // which is required for BGZF
+    let decoder = flate2::bufread::MultiGzDecoder::new(reader);
+    Ok(Box::new(io::BufReader::new(decoder)))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip_reader<'a, R>(_reader: R) -> io::Result<Box<dyn BufRead + 'a>>
+where
+    R: BufRead + 'a,
+{
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "input is gzip-compressed, but the `gzip` feature is not enabled",
+    ))
+}
+
+/// Open a file for buffered reading, decompressing it if it's a
+/// gzip or BGZF file. The format is detected from the file contents,
+/// not the extension, so this works equally well for GFA, GAF, and
+/// PAF files.
+#[cfg(feature = "fs")]
+pub fn open_file<P: AsRef<std::path::Path>>(
+    path: P,
+) -> io::Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+    decompress_reader(io::BufReader::new(file))
+}
+
+/// A writer that compresses everything written to it, as returned by
+/// `compress_writer` and `create_file`. The compressed stream must be
+/// completed with `finish`, which reports any error from writing the
+/// final block or trailer; dropping the writer still completes the
+/// stream, but any such error is lost.
+pub struct CompressedWriter<W: Write> {
+    inner: CompressedInner<W>,
+}
+
+enum CompressedInner<W: Write> {
+    Plain(W),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<W>),
+    #[cfg(feature = "gzip")]
+    Bgzf(crate::bgzf::BgzfWriter<W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    /// Write the end of the compressed stream, if any, and flush the
+    /// underlying writer.
+    pub fn finish(self) -> io::Result<()> {
+        let finished: io::Result<W> = match self.inner {
+            CompressedInner::Plain(writer) => Ok(writer),
+            #[cfg(feature = "gzip")]
+            CompressedInner::Gzip(encoder) => encoder.finish(),
+            #[cfg(feature = "gzip")]
+            CompressedInner::Bgzf(writer) => writer.finish(),
+        };
+        finished?.flush()
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            CompressedInner::Plain(writer) => writer.write(buf),
+            #[cfg(feature = "gzip")]
+            CompressedInner::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "gzip")]
+            CompressedInner::Bgzf(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            CompressedInner::Plain(writer) => writer.flush(),
+            #[cfg(feature = "gzip")]
+            CompressedInner::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "gzip")]
+            CompressedInner::Bgzf(writer) => writer.flush(),
+        }
+    }
+}
+
+/// Wrap a writer so that everything written to it is compressed
+/// with the given format. The returned writer must be completed with
+/// `CompressedWriter::finish`.
+///
+/// Without the `gzip` feature, requesting compression produces an
+/// `InvalidInput` error.
+pub fn compress_writer<W: Write>(
+    writer: W,
+    compression: Compression,
+) -> io::Result<CompressedWriter<W>> {
+    let inner = match compression {
+        Compression::None => CompressedInner::Plain(writer),
+        Compression::Gzip => gzip_writer(writer)?,
+        Compression::Bgzf => bgzf_writer(writer)?,
+    };
+    Ok(CompressedWriter { inner })
+}
+
+#[cfg(feature = "gzip")]
+fn gzip_writer<W: Write>(writer: W) -> io::Result<CompressedInner<W>> {
+    let level = flate2::Compression::default();
+    let encoder = flate2::write::GzEncoder::new(writer, level);
+    Ok(CompressedInner::Gzip(encoder))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip_writer<W: Write>(_writer: W) -> io::Result<CompressedInner<W>> {
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "gzip output requires the `gzip` feature",
+    ))
+}
+
+#[cfg(feature = "gzip")]
+fn bgzf_writer<W: Write>(writer: W) -> io::Result<CompressedInner<W>> {
+    Ok(CompressedInner::Bgzf(crate::bgzf::BgzfWriter::new(writer)))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn bgzf_writer<W: Write>(_writer: W) -> io::Result<CompressedInner<W>> {
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "BGZF output requires the `gzip` feature",
+    ))
+}
+
+/// Create a file for buffered writing, compressing it with BGZF if
+/// the path ends with `.gz`, `.bgz`, or `.bgzf`. The returned writer must be completed with
+/// `CompressedWriter::finish`.
+#[cfg(feature = "fs")]
+pub fn create_file<P: AsRef<std::path::Path>>(
+    path: P,
+) -> io::Result<CompressedWriter<BufWriter<File>>> {
+    let compression = Compression::from_extension(&path);
+    let file = BufWriter::new(File::create(path)?);
+    compress_writer(file, compression)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn plain_input_is_passed_through() {
+        let input: &[u8] = b"H\tVN:Z:1.0\nS\t1\tA\n";
+        let mut reader = decompress_reader(input).unwrap();
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).unwrap();
+        assert_eq!(input, output.as_slice());
+    }
+
+    #[cfg(feature = "gzip")]
+    fn gzip_bytes(input: &[u8]) -> Vec<u8> {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(input).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn gzip_input_is_decompressed() {
+        let input = b"H\tVN:Z:1.0\nS\t1\tA\n";
+        let compressed = gzip_bytes(input);
+
+        let mut reader = decompress_reader(compressed.as_slice()).unwrap();
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).unwrap();
+        assert_eq!(&input[..], output.as_slice());
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn multi_member_input_is_decompressed() {
+        // BGZF files consist of many concatenated gzip members
+        let mut compressed = gzip_bytes(b"S\t1\tA\n");
+        compressed.extend(gzip_bytes(b"S\t2\tC\n"));
+
+        let mut reader = decompress_reader(compressed.as_slice()).unwrap();
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).unwrap();
+        assert_eq!(b"S\t1\tA\nS\t2\tC\n", output.as_slice());
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn compressed_output_round_trips() {
+        let input = b"H\tVN:Z:1.0\nS\t1\tA\n";
+        let mut compressed = Vec::new();
+        {
+            let mut writer =
+                compress_writer(&mut compressed, Compression::Gzip).unwrap();
+            writer.write_all(input).unwrap();
+            writer.finish().unwrap();
+        }
+        assert!(compressed.starts_with(&GZIP_MAGIC));
+
+        let mut reader = decompress_reader(compressed.as_slice()).unwrap();
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).unwrap();
+        assert_eq!(&input[..], output.as_slice());
+    }
+
+    #[test]
+    fn compression_from_extension() {
+        assert_eq!(Compression::from_extension("a.gaf.gz"), Compression::Bgzf);
+        assert_eq!(Compression::from_extension("a.bgz"), Compression::Bgzf);
+        assert_eq!(Compression::from_extension("a.paf"), Compression::None);
+    }
+
+    #[test]
+    #[cfg(not(feature = "gzip"))]
+    fn gzip_input_without_feature_is_an_error() {
+        let compressed: &[u8] = &[0x1f, 0x8b, 0x08, 0x00];
+        let result = decompress_reader(compressed);
+        assert!(result.is_err());
+    }
+}
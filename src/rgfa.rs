@@ -0,0 +1,244 @@
+//! Support for rGFA, the reference GFA variant produced by minigraph,
+//! where each segment is tagged with the stable sequence it comes
+//! from (SN), its offset on that sequence (SO), and its rank (SR).
+
+pub mod bed;
+pub mod index;
+pub mod liftover;
+pub mod paf;
+pub mod resolve;
+
+pub use self::bed::*;
+pub use self::index::*;
+pub use self::liftover::*;
+pub use self::resolve::*;
+
+use crate::{
+    gfa::{Segment, GFA},
+    optfields::*,
+    validation::segment_length,
+};
+
+use fnv::FnvHashMap;
+
+use std::{error, fmt};
+
+impl<N, T: OptFields> Segment<N, T> {
+    /// The name of the stable sequence the segment is on, from the
+    /// SN tag.
+    pub fn stable_name(&self) -> Option<&[u8]> {
+        self.optional.get_typed(b"SN")
+    }
+
+    /// The 0-based offset of the segment on its stable sequence, from
+    /// the SO tag.
+    pub fn stable_offset(&self) -> Option<usize> {
+        self.optional.get_typed(b"SO")
+    }
+
+    /// The rank of the segment, from the SR tag. Rank 0 segments are
+    /// on the reference.
+    pub fn stable_rank(&self) -> Option<usize> {
+        self.optional.get_typed(b"SR")
+    }
+}
+
+/// The interval of a stable sequence covered by a segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StableInterval {
+    pub start: usize,
+    pub end: usize,
+    /// Index of the segment in `GFA::segments`.
+    pub segment: usize,
+    pub rank: usize,
+}
+
+impl StableInterval {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn contains(&self, pos: usize) -> bool {
+        self.start <= pos && pos < self.end
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RGfaError {
+    /// A segment is missing one of the SN, SO, or SR tags, or it has
+    /// the wrong type. Includes the segment index and the tag.
+    MissingTag { segment: usize, tag: &'static str },
+    /// The length of a segment is unknown, as it has neither a
+    /// sequence nor an LN tag.
+    UnknownLength { segment: usize },
+}
+
+impl fmt::Display for RGfaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RGfaError::MissingTag { segment, tag } => {
+                write!(f, "Segment {} is missing the {} tag", segment, tag)
+            }
+            RGfaError::UnknownLength { segment } => {
+                write!(f, "Segment {} has no sequence or LN tag", segment)
+            }
+        }
+    }
+}
+
+impl error::Error for RGfaError {}
+
+/// A GFA where every segment has the rGFA tags, together with the
+/// mapping from stable sequence names to the segments covering them.
+#[derive(Debug, Clone)]
+pub struct RGfa<N, T: OptFields> {
+    gfa: GFA<N, T>,
+    intervals: FnvHashMap<Vec<u8>, Vec<StableInterval>>,
+    /// The length of each stable sequence, as the largest end of the
+    /// intervals on it.
+    lengths: FnvHashMap<Vec<u8>, usize>,
+}
+
+impl<N, T: OptFields> RGfa<N, T> {
+    /// Check that every segment has the SN, SO, and SR tags and a
+    /// known length, and build the stable sequence mapping.
+    pub fn new(gfa: GFA<N, T>) -> Result<Self, RGfaError> {
+        let mut intervals: FnvHashMap<Vec<u8>, Vec<StableInterval>> =
+            FnvHashMap::default();
+
+        for (ix, seg) in gfa.segments.iter().enumerate() {
+            let missing = |tag| RGfaError::MissingTag { segment: ix, tag };
+            let name = seg.stable_name().ok_or_else(|| missing("SN"))?;
+            let start = seg.stable_offset().ok_or_else(|| missing("SO"))?;
+            let rank = seg.stable_rank().ok_or_else(|| missing("SR"))?;
+            let len = segment_length(seg)
+                .ok_or(RGfaError::UnknownLength { segment: ix })?;
+
+            intervals
+                .entry(name.to_vec())
+                .or_default()
+                .push(StableInterval {
+                    start,
+                    end: start + len,
+                    segment: ix,
+                    rank,
+                });
+        }
+
+        let mut lengths = FnvHashMap::default();
+        for (name, ivs) in intervals.iter_mut() {
+            ivs.sort_unstable();
+            let len = ivs.iter().map(|iv| iv.end).max().unwrap_or(0);
+            lengths.insert(name.clone(), len);
+        }
+
+        Ok(RGfa {
+            gfa,
+            intervals,
+            lengths,
+        })
+    }
+
+    pub fn gfa(&self) -> &GFA<N, T> {
+        &self.gfa
+    }
+
+    pub fn into_inner(self) -> GFA<N, T> {
+        self.gfa
+    }
+
+    /// Iterate over the names of all stable sequences.
+    pub fn stable_names(&self) -> impl Iterator<Item = &[u8]> {
+        self.intervals.keys().map(|name| name.as_slice())
+    }
+
+    /// The intervals of the segments on the given stable sequence,
+    /// sorted by start position.
+    pub fn intervals(&self, stable_name: &[u8]) -> Option<&[StableInterval]> {
+        self.intervals.get(stable_name).map(|ivs| ivs.as_slice())
+    }
+
+    /// The length of the stable sequence with the given name, as the
+    /// end of the last segment on it.
+    pub fn stable_len(&self, stable_name: &[u8]) -> Option<usize> {
+        self.lengths.get(stable_name).copied()
+    }
+
+    /// The stable interval of the segment with the given index.
+    pub fn segment_interval(
+        &self,
+        segment: usize,
+    ) -> Option<(&[u8], StableInterval)> {
+        let seg = self.gfa.segments.get(segment)?;
+        let name = seg.stable_name()?;
+        let interval = self
+            .intervals(name)?
+            .iter()
+            .find(|iv| iv.segment == segment)?;
+        Some((name, *interval))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{parse, rgfa};
+
+    #[test]
+    fn segment_tags() {
+        let rgfa = rgfa();
+        let seg = &rgfa.gfa().segments[2];
+        assert_eq!(seg.stable_name(), Some(&b"alt1"[..]));
+        assert_eq!(seg.stable_offset(), Some(0));
+        assert_eq!(seg.stable_rank(), Some(1));
+    }
+
+    #[test]
+    fn stable_intervals() {
+        let rgfa = rgfa();
+
+        let mut names: Vec<_> = rgfa.stable_names().collect();
+        names.sort_unstable();
+        assert_eq!(names, vec![&b"alt1"[..], b"chr1", b"chr2"]);
+
+        let chr1: Vec<_> = rgfa
+            .intervals(b"chr1")
+            .unwrap()
+            .iter()
+            .map(|iv| (iv.start, iv.end, iv.segment))
+            .collect();
+        assert_eq!(chr1, vec![(0, 5, 0), (5, 7, 1), (7, 12, 3)]);
+
+        let (name, interval) = rgfa.segment_interval(4).unwrap();
+        assert_eq!(name, b"chr2");
+        assert_eq!((interval.start, interval.end), (100, 107));
+        assert!(rgfa.intervals(b"chr3").is_none());
+    }
+
+    #[test]
+    fn missing_rgfa_tags() {
+        let lines = [
+            "S\ts1\tACGT\tSN:Z:chr1\tSO:i:0\tSR:i:0",
+            "S\ts2\tA\tSN:Z:chr1\tSR:i:0",
+        ];
+        let gfa: GFA<Vec<u8>, OptionalFields> = parse(&lines);
+        assert_eq!(
+            RGfa::new(gfa).unwrap_err(),
+            RGfaError::MissingTag {
+                segment: 1,
+                tag: "SO"
+            }
+        );
+
+        let lines = ["S\ts1\t*\tSN:Z:chr1\tSO:i:0\tSR:i:0"];
+        let gfa: GFA<Vec<u8>, OptionalFields> = parse(&lines);
+        assert_eq!(
+            RGfa::new(gfa).unwrap_err(),
+            RGfaError::UnknownLength { segment: 0 }
+        );
+    }
+}
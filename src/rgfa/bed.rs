@@ -0,0 +1,342 @@
+use crate::{
+    gafpaf::{GAFPath, GAFStep, GAF},
+    gfa::{Orientation, SegmentId, GFA},
+    graph::segment_name_indices,
+    optfields::*,
+    validation::segment_length,
+};
+
+use super::StableSource;
+
+use bstr::BString;
+use fnv::FnvHashMap;
+
+use std::io::{self, Write};
+
+/// A BED6 interval on a stable sequence.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BedRecord {
+    pub chrom: BString,
+    pub start: usize,
+    pub end: usize,
+    pub name: BString,
+    pub score: u8,
+    pub strand: Orientation,
+}
+
+impl std::fmt::Display for BedRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.chrom,
+            self.start,
+            self.end,
+            self.name,
+            self.score,
+            self.strand
+        )
+    }
+}
+
+/// The orientation of `b` relative to `a`.
+pub(super) fn relative(a: Orientation, b: Orientation) -> Orientation {
+    Orientation::from(a == b)
+}
+
+/// A stretch of an alignment on a stable sequence, with the range of
+/// the GAF path it comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Piece<'a> {
+    pub chrom: &'a [u8],
+    pub start: usize,
+    pub end: usize,
+    /// The orientation of the path on the stable sequence.
+    pub orient: Orientation,
+    pub path_range: (usize, usize),
+}
+
+/// Projects GAF alignments onto stable sequences, using the rGFA tags
+/// or reference paths of a GFA.
+pub struct GAFProjector<'a, N, T: OptFields, S: ?Sized> {
+    gfa: &'a GFA<N, T>,
+    source: &'a S,
+    indices: FnvHashMap<Vec<u8>, usize>,
+}
+
+impl<'a, N, T, S> GAFProjector<'a, N, T, S>
+where
+    N: SegmentId,
+    T: OptFields,
+    S: StableSource + ?Sized,
+{
+    pub fn new(gfa: &'a GFA<N, T>, source: &'a S) -> Self {
+        let indices = segment_name_indices(gfa);
+        GAFProjector {
+            gfa,
+            source,
+            indices,
+        }
+    }
+
+    /// The length of a step of a GAF path.
+    fn step_len(&self, step: &GAFStep) -> Option<usize> {
+        match step {
+            GAFStep::SegId(_, name) => {
+                let ix = *self.indices.get(name.as_slice())?;
+                segment_length(&self.gfa.segments[ix])
+            }
+            GAFStep::StableIntv(_, _, start, end) => end.checked_sub(*start),
+        }
+    }
+
+    /// The stable sequence, interval, and orientation of a step of a
+    /// GAF path.
+    fn step_location<'s>(
+        &'s self,
+        step: &'s GAFStep,
+    ) -> Option<(&'s [u8], usize, usize, Orientation)> {
+        match step {
+            GAFStep::SegId(orient, name) => {
+                let ix = *self.indices.get(name.as_slice())?;
+                let (chrom, iv) = self.source.stable_location(ix)?;
+                Some((chrom, iv.start, iv.end, relative(iv.orient, *orient)))
+            }
+            GAFStep::StableIntv(orient, name, start, end) => {
+                Some((name.as_slice(), *start, *end, *orient))
+            }
+        }
+    }
+
+    pub(super) fn source(&self) -> &S {
+        self.source
+    }
+
+    /// The pieces of an alignment on stable sequences, in the order of
+    /// the path, with pieces on consecutive steps merged if they are
+    /// adjacent both on the path and on the same stable sequence and
+    /// strand. Steps that can't be placed on a stable sequence are
+    /// left out, and the projection stops at the first step whose
+    /// length is unknown.
+    pub(super) fn pieces<'s, U: OptFields>(
+        &'s self,
+        gaf: &'s GAF<U>,
+    ) -> Vec<Piece<'s>> {
+        let (start, end) = gaf.path_range;
+        let steps = match &gaf.path {
+            GAFPath::StableId(id) => {
+                return vec![Piece {
+                    chrom: id,
+                    start,
+                    end,
+                    orient: Orientation::Forward,
+                    path_range: (start, end),
+                }];
+            }
+            GAFPath::OrientIntv(steps) => steps,
+        };
+
+        let mut pieces: Vec<Piece<'s>> = Vec::new();
+        let mut offset = 0;
+        for step in steps {
+            let len = match self.step_len(step) {
+                Some(len) => len,
+                None => break,
+            };
+            let step_end = offset + len;
+            let (from, to) = (start.max(offset), end.min(step_end));
+
+            let location = self.step_location(step);
+            if let (Some((chrom, s, e, orient)), true) = (location, from < to) {
+                let (a, b) = (from - offset, to - offset);
+                let (a, b) = match orient {
+                    Orientation::Forward => (s + a, s + b),
+                    Orientation::Backward => (e - b, e - a),
+                };
+                match pieces.last_mut() {
+                    Some(last)
+                        if last.chrom == chrom
+                            && last.orient == orient
+                            && last.path_range.1 == from
+                            && (last.end == a || b == last.start) =>
+                    {
+                        last.start = last.start.min(a);
+                        last.end = last.end.max(b);
+                        last.path_range.1 = to;
+                    }
+                    _ => pieces.push(Piece {
+                        chrom,
+                        start: a,
+                        end: b,
+                        orient,
+                        path_range: (from, to),
+                    }),
+                }
+            }
+            offset = step_end;
+        }
+        pieces
+    }
+
+    /// The footprint of an alignment on stable sequences, as one BED
+    /// interval per stretch of the alignment on a stable sequence.
+    /// Pieces of the alignment on consecutive steps are merged if they
+    /// are adjacent on the same stable sequence and strand. Steps that
+    /// can't be placed on a stable sequence are left out, and the
+    /// projection stops at the first step whose length is unknown.
+    ///
+    /// The name of each interval is the read name, the score is the
+    /// mapping quality, and the strand combines the read strand with
+    /// the orientation of the steps on the stable sequences.
+    pub fn project<U: OptFields>(&self, gaf: &GAF<U>) -> Vec<BedRecord> {
+        let mut records: Vec<BedRecord> = Vec::new();
+        for piece in self.pieces(gaf) {
+            let next = BedRecord {
+                chrom: piece.chrom.into(),
+                start: piece.start,
+                end: piece.end,
+                name: gaf.seq_name.clone(),
+                score: gaf.quality,
+                strand: relative(gaf.strand, piece.orient),
+            };
+            match records.last_mut() {
+                Some(last)
+                    if last.chrom == next.chrom
+                        && last.strand == next.strand
+                        && (last.end == next.start
+                            || next.end == last.start) =>
+                {
+                    last.start = last.start.min(next.start);
+                    last.end = last.end.max(next.end);
+                }
+                _ => records.push(next),
+            }
+        }
+        records
+    }
+
+    /// Write the footprints of GAF records on stable sequences as BED,
+    /// returning the number of intervals written.
+    pub fn write_bed<'b, U, I, W>(
+        &self,
+        records: I,
+        stream: &mut W,
+    ) -> io::Result<usize>
+    where
+        U: OptFields + 'b,
+        I: IntoIterator<Item = &'b GAF<U>>,
+        W: Write,
+    {
+        let mut written = 0;
+        for gaf in records {
+            for bed in self.project(gaf) {
+                writeln!(stream, "{}", bed)?;
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+    use crate::{
+        gafpaf::parse_gaf,
+        parser::GFAParser,
+        rgfa::{RGfa, ReferencePath},
+    };
+    use bstr::ByteSlice;
+
+    fn gaf(strand: char, path: &str, start: usize, end: usize) -> GAF<()> {
+        let line = format!(
+            "read\t10\t0\t4\t{}\t{}\t0\t{}\t{}\t4\t4\t60",
+            strand, path, start, end
+        );
+        parse_gaf(line.as_bytes().split_str("\t")).unwrap()
+    }
+
+    fn beds<S: StableSource + ?Sized, N: SegmentId, T: OptFields>(
+        projector: &GAFProjector<'_, N, T, S>,
+        gaf: &GAF<()>,
+    ) -> Vec<String> {
+        projector
+            .project(gaf)
+            .iter()
+            .map(|b| b.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn project_on_rgfa() {
+        let parser: GFAParser<Vec<u8>, OptionalFields> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/rgfa.gfa").unwrap();
+        let rgfa = RGfa::new(gfa).unwrap();
+        let projector = GAFProjector::new(rgfa.gfa(), &rgfa);
+
+        // s1 is chr1:0-5, s2 is chr1:5-7, and s4 is chr1:7-12
+        let record = gaf('+', ">s1>s2>s4", 3, 9);
+        assert_eq!(beds(&projector, &record), vec!["chr1\t3\t9\tread\t60\t+"]);
+
+        // on the alt allele, the alignment is split
+        let record = gaf('+', ">s1>s3>s4", 4, 7);
+        assert_eq!(
+            beds(&projector, &record),
+            vec![
+                "chr1\t4\t5\tread\t60\t+",
+                "alt1\t0\t1\tread\t60\t+",
+                "chr1\t7\t8\tread\t60\t+",
+            ]
+        );
+
+        let record = gaf('-', "<s4<s2", 1, 6);
+        assert_eq!(beds(&projector, &record), vec!["chr1\t6\t11\tread\t60\t+"]);
+
+        let record = gaf('+', "<chr2:100-107", 1, 3);
+        assert_eq!(
+            beds(&projector, &record),
+            vec!["chr2\t104\t106\tread\t60\t-"]
+        );
+
+        let record = gaf('+', "chr2", 100, 104);
+        assert_eq!(
+            beds(&projector, &record),
+            vec!["chr2\t100\t104\tread\t60\t+"]
+        );
+
+        let mut output = Vec::new();
+        let records = vec![gaf('+', ">s1>s2>s4", 3, 9), gaf('+', ">s5", 0, 2)];
+        let written = projector.write_bed(&records, &mut output).unwrap();
+        assert_eq!(written, 2);
+        assert_eq!(
+            output.to_str().unwrap(),
+            "chr1\t3\t9\tread\t60\t+\nchr2\t100\t102\tread\t60\t+\n"
+        );
+    }
+
+    #[test]
+    fn project_on_reference_path() {
+        let lines = [
+            "S\ta\tACGT",
+            "S\tb\tGG",
+            "S\tc\tTTA",
+            "S\td\tC",
+            "P\tref\ta+,b-,c+\t*",
+        ];
+        let gfa: GFA<Vec<u8>, ()> = parse(&lines);
+        let reference = ReferencePath::new(&gfa, b"ref").unwrap();
+        let projector = GAFProjector::new(&gfa, &reference);
+
+        let record = gaf('+', ">a<b>c", 2, 8);
+        assert_eq!(beds(&projector, &record), vec!["ref\t2\t8\tread\t60\t+"]);
+
+        // the path is the reverse of ref, so 1..7 on it is 2..8 on ref
+        let record = gaf('+', "<c>b<a", 1, 7);
+        assert_eq!(beds(&projector, &record), vec!["ref\t2\t8\tread\t60\t-"]);
+
+        // d isn't on the reference, so only the part on a is kept
+        let record = gaf('+', ">a>d", 2, 5);
+        assert_eq!(beds(&projector, &record), vec!["ref\t2\t4\tread\t60\t+"]);
+    }
+}
@@ -0,0 +1,342 @@
+use crate::{gfa::Orientation, optfields::*};
+
+use super::{PathInterval, RGfa, ReferencePath, SegmentPosition, StableSource};
+
+use fnv::FnvHashMap;
+
+/// The intervals on one stable sequence, sorted by start, laid out
+/// as an implicit interval tree: the interval at index `x` is a node
+/// whose level is the number of trailing ones of `x`, with children
+/// at `x - 2^(level - 1)` and `x + 2^(level - 1)`. Each node stores
+/// the largest end in its subtree, so overlapping intervals can be
+/// found in O(log n + k) time even if they overlap each other.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Track {
+    intervals: Vec<PathInterval>,
+    max_end: Vec<usize>,
+    /// The level of the root, at index `2^root_level - 1`.
+    root_level: u32,
+}
+
+impl Track {
+    fn new(mut intervals: Vec<PathInterval>) -> Self {
+        intervals.sort_unstable();
+        let mut root_level = 0;
+        while (1 << (root_level + 1)) - 1 < intervals.len() {
+            root_level += 1;
+        }
+        let mut track = Track {
+            max_end: intervals.iter().map(|iv| iv.end).collect(),
+            intervals,
+            root_level,
+        };
+        track.fill_max_end((1 << root_level) - 1, root_level);
+        track
+    }
+
+    /// Set the largest end of each subtree of the node at `x`, and
+    /// return the largest end of the whole subtree.
+    fn fill_max_end(&mut self, x: usize, level: u32) -> usize {
+        if level == 0 {
+            return self.max_end.get(x).copied().unwrap_or(0);
+        }
+        let half = 1 << (level - 1);
+        let left = self.fill_max_end(x - half, level - 1);
+        if x >= self.intervals.len() {
+            // the right subtree is past the end as well
+            return left;
+        }
+        let right = self.fill_max_end(x + half, level - 1);
+        let end = self.max_end[x].max(left).max(right);
+        self.max_end[x] = end;
+        end
+    }
+
+    /// Push the indices of the intervals overlapping `start..end` in
+    /// the subtree of the node at `x`, in order.
+    fn find_overlaps(
+        &self,
+        (x, level): (usize, u32),
+        (start, end): (usize, usize),
+        found: &mut Vec<usize>,
+    ) {
+        let half = if level > 0 { 1 << (level - 1) } else { 0 };
+        if x >= self.intervals.len() {
+            if level > 0 {
+                let left = (x - half, level - 1);
+                self.find_overlaps(left, (start, end), found);
+            }
+            return;
+        }
+        if self.max_end[x] <= start {
+            return;
+        }
+        if level > 0 {
+            self.find_overlaps((x - half, level - 1), (start, end), found);
+        }
+        let iv = &self.intervals[x];
+        if iv.start >= end {
+            return;
+        }
+        if iv.end > start {
+            found.push(x);
+        }
+        if level > 0 {
+            self.find_overlaps((x + half, level - 1), (start, end), found);
+        }
+    }
+
+    fn overlapping(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> impl Iterator<Item = &PathInterval> {
+        let mut found = Vec::new();
+        if !self.intervals.is_empty() {
+            let root = ((1 << self.root_level) - 1, self.root_level);
+            self.find_overlaps(root, (start, end), &mut found);
+        }
+        found.into_iter().map(move |ix| &self.intervals[ix])
+    }
+
+    /// The largest end of the intervals. If the root is past the end
+    /// of the intervals, they are all in the subtree of its first
+    /// left descendant that isn't.
+    fn len(&self) -> Option<usize> {
+        if self.intervals.is_empty() {
+            return None;
+        }
+        let mut x = (1 << self.root_level) - 1;
+        let mut level = self.root_level;
+        while x >= self.intervals.len() {
+            level -= 1;
+            x -= 1 << level;
+        }
+        Some(self.max_end[x])
+    }
+}
+
+/// An index from intervals of stable sequences to the segments that
+/// cover them, built from the rGFA tags of a graph or from reference
+/// paths. Point and range queries take O(log n) time, plus the number
+/// of intervals found, which makes it suitable for projecting large
+/// annotation files onto the graph.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StableIndex {
+    names: Vec<Vec<u8>>,
+    tracks: Vec<Track>,
+    by_name: FnvHashMap<Vec<u8>, usize>,
+    /// The first interval of each segment, with its stable sequence.
+    locations: FnvHashMap<usize, (usize, PathInterval)>,
+}
+
+impl StableIndex {
+    /// Build the index from the intervals on each stable sequence.
+    /// If a segment covers more than one interval, the first one
+    /// added is the segment's location.
+    pub fn new<'a, I>(stable: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a [u8], Vec<PathInterval>)>,
+    {
+        let mut index = StableIndex::default();
+        for (name, intervals) in stable {
+            let track = match index.by_name.get(name) {
+                Some(&track) => track,
+                None => {
+                    index.names.push(name.to_vec());
+                    index.tracks.push(Track::default());
+                    index.by_name.insert(name.to_vec(), index.names.len() - 1);
+                    index.names.len() - 1
+                }
+            };
+            for iv in intervals.iter() {
+                index.locations.entry(iv.segment).or_insert((track, *iv));
+            }
+            let mut all = std::mem::take(&mut index.tracks[track].intervals);
+            all.extend(intervals);
+            index.tracks[track] = Track::new(all);
+        }
+        index
+    }
+
+    /// Index the stable sequences of an rGFA. Segments are always on
+    /// the forward strand of their stable sequence.
+    pub fn from_rgfa<N, T: OptFields>(rgfa: &RGfa<N, T>) -> Self {
+        Self::new(rgfa.stable_names().map(|name| {
+            let intervals = rgfa
+                .intervals(name)
+                .unwrap_or_default()
+                .iter()
+                .map(|iv| PathInterval {
+                    start: iv.start,
+                    end: iv.end,
+                    segment: iv.segment,
+                    orient: Orientation::Forward,
+                })
+                .collect();
+            (name, intervals)
+        }))
+    }
+
+    /// Index the coordinates of reference paths, with each path as a
+    /// stable sequence.
+    pub fn from_paths(paths: &[ReferencePath]) -> Self {
+        Self::new(paths.iter().map(|p| (p.name(), p.steps().to_vec())))
+    }
+
+    /// Iterate over the names of all stable sequences.
+    pub fn stable_names(&self) -> impl Iterator<Item = &[u8]> {
+        self.names.iter().map(|name| name.as_slice())
+    }
+
+    /// The intervals on the stable sequence with the given name that
+    /// overlap `start..end`, sorted by start. Returns `None` if there
+    /// is no such stable sequence.
+    pub fn overlapping(
+        &self,
+        name: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<impl Iterator<Item = &PathInterval>> {
+        let &track = self.by_name.get(name)?;
+        Some(self.tracks[track].overlapping(start, end))
+    }
+
+    /// Translate a position on a stable sequence to the position on
+    /// the segment that covers it, in the orientation of the segment
+    /// on the stable sequence, as in `ReferencePath::from_stable`. If
+    /// more than one segment covers the position, the one that starts
+    /// first is used.
+    pub fn find(&self, name: &[u8], pos: usize) -> Option<SegmentPosition> {
+        let iv = self.overlapping(name, pos, pos + 1)?.next()?;
+        Some(SegmentPosition {
+            segment: iv.segment,
+            offset: pos - iv.start,
+            orient: iv.orient,
+        })
+    }
+}
+
+impl StableSource for StableIndex {
+    fn stable_segments(
+        &self,
+        name: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<Vec<PathInterval>> {
+        Some(self.overlapping(name, start, end)?.copied().collect())
+    }
+
+    fn stable_location(&self, segment: usize) -> Option<(&[u8], PathInterval)> {
+        let &(track, interval) = self.locations.get(&segment)?;
+        Some((&self.names[track], interval))
+    }
+
+    fn stable_len(&self, name: &[u8]) -> Option<usize> {
+        let &track = self.by_name.get(name)?;
+        self.tracks[track].len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gfa::GFA, parser::GFAParser, test_util::parse};
+
+    #[test]
+    fn stable_interval_queries() {
+        let parser = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/rgfa.gfa").unwrap();
+        let rgfa: RGfa<Vec<u8>, OptionalFields> = RGfa::new(gfa).unwrap();
+        let index = StableIndex::from_rgfa(&rgfa);
+
+        let segments = |name, start, end| -> Vec<usize> {
+            index
+                .overlapping(name, start, end)
+                .unwrap()
+                .map(|iv| iv.segment)
+                .collect()
+        };
+        assert_eq!(segments(b"chr1", 0, 12), vec![0, 1, 3]);
+        assert_eq!(segments(b"chr1", 5, 7), vec![1]);
+        assert_eq!(segments(b"chr1", 6, 8), vec![1, 3]);
+        assert!(segments(b"chr1", 12, 20).is_empty());
+        assert_eq!(segments(b"alt1", 0, 1), vec![2]);
+        assert!(segments(b"chr2", 0, 100).is_empty());
+        assert!(index.overlapping(b"chr3", 0, 1).is_none());
+
+        let pos = index.find(b"chr1", 8).unwrap();
+        assert_eq!((pos.segment, pos.offset), (3, 1));
+        assert_eq!(index.find(b"chr2", 106).unwrap().offset, 6);
+        assert!(index.find(b"chr2", 107).is_none());
+        assert_eq!(index.stable_location(2).unwrap().0, b"alt1");
+        for name in rgfa.stable_names() {
+            let ends = index.overlapping(name, 0, usize::MAX).unwrap();
+            let len = ends.map(|iv| iv.end).max();
+            assert_eq!(StableSource::stable_len(&index, name), len);
+            assert_eq!(StableSource::stable_len(&rgfa, name), len);
+        }
+        assert_eq!(StableSource::stable_len(&index, b"chr3"), None);
+
+        // a reversed step, and an interval that contains the others
+        let lines = ["S\t1\tACGT", "S\t2\tGG", "P\tref\t1+,2-\t*"];
+        let gfa: GFA<usize, ()> = parse(&lines);
+        let path = ReferencePath::new(&gfa, b"ref").unwrap();
+        let wide = PathInterval {
+            start: 0,
+            end: 10,
+            segment: 7,
+            orient: Orientation::Forward,
+        };
+        let index = StableIndex::new(vec![
+            (path.name(), path.steps().to_vec()),
+            (&b"ref"[..], vec![wide]),
+        ]);
+        let pos = index.find(b"ref", 4).unwrap();
+        assert_eq!((pos.segment, pos.offset), (7, 4));
+        assert_eq!(index.overlapping(b"ref", 5, 6).unwrap().count(), 2);
+        assert_eq!(index.overlapping(b"ref", 6, 7).unwrap().count(), 1);
+
+        let index = StableIndex::from_paths(&[path]);
+        let pos = index.find(b"ref", 5).unwrap();
+        assert_eq!(pos.segment, 1);
+        assert_eq!((pos.offset, pos.orient), (1, Orientation::Backward));
+    }
+
+    #[test]
+    fn track_matches_linear_scan() {
+        // pseudorandom intervals, with some long ones early on
+        let mut seed = 17usize;
+        let mut next = |max: usize| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 33) % max
+        };
+        for n in 0..70 {
+            let intervals: Vec<_> = (0..n)
+                .map(|segment| {
+                    let start = next(100);
+                    let len = if segment % 7 == 0 { 80 } else { next(10) };
+                    PathInterval {
+                        start,
+                        end: start + len + 1,
+                        segment,
+                        orient: Orientation::Forward,
+                    }
+                })
+                .collect();
+            let track = Track::new(intervals);
+            for start in (0..200).step_by(3) {
+                let end = start + next(20) + 1;
+                let expected: Vec<_> = track
+                    .intervals
+                    .iter()
+                    .filter(|iv| iv.start < end && iv.end > start)
+                    .collect();
+                let found: Vec<_> = track.overlapping(start, end).collect();
+                assert_eq!(found, expected);
+            }
+            let len = track.intervals.iter().map(|iv| iv.end).max();
+            assert_eq!(track.len(), len);
+        }
+    }
+}
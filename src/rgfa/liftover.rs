@@ -0,0 +1,317 @@
+use crate::{
+    gfa::{Orientation, SegmentId, GFA},
+    graph::segment_indices,
+    optfields::*,
+    validation::segment_length,
+};
+
+use super::{RGfa, StableInterval};
+
+use fnv::FnvHashMap;
+
+use std::hash::Hash;
+
+/// A position on a segment. The offset is 0-based and relative to
+/// the given orientation, so offset 0 on a reverse segment is the
+/// last base of its sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SegmentPosition {
+    /// Index of the segment in `GFA::segments`.
+    pub segment: usize,
+    pub offset: usize,
+    pub orient: Orientation,
+}
+
+/// A 0-based position on a stable sequence, or reference path, and
+/// the strand of the stable sequence it's on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StablePosition<'a> {
+    pub name: &'a [u8],
+    pub pos: usize,
+    pub orient: Orientation,
+}
+
+/// Flip an offset on a sequence of length `len` to the other strand.
+fn flip_offset(offset: usize, len: usize) -> usize {
+    len - 1 - offset
+}
+
+/// The offset on the forward strand of the segment.
+fn forward_offset(pos: &SegmentPosition, len: usize) -> Option<usize> {
+    if pos.offset >= len {
+        return None;
+    }
+    match pos.orient {
+        Orientation::Forward => Some(pos.offset),
+        Orientation::Backward => Some(flip_offset(pos.offset, len)),
+    }
+}
+
+/// Find the interval containing `pos` in a list sorted by start.
+fn find_interval<I>(
+    intervals: &[I],
+    pos: usize,
+    start_end: impl Fn(&I) -> (usize, usize),
+) -> Option<&I> {
+    let ix = intervals.partition_point(|iv| start_end(iv).0 <= pos);
+    let iv = intervals.get(ix.checked_sub(1)?)?;
+    let (start, end) = start_end(iv);
+    if start <= pos && pos < end {
+        Some(iv)
+    } else {
+        None
+    }
+}
+
+impl<N, T: OptFields> RGfa<N, T> {
+    /// Translate a position on a segment to its position on the
+    /// segment's stable sequence. Stable sequences are always on the
+    /// forward strand of their segments, so the orientation of the
+    /// result is that of the segment position.
+    pub fn to_stable(
+        &self,
+        pos: SegmentPosition,
+    ) -> Option<StablePosition<'_>> {
+        let (name, interval) = self.segment_interval(pos.segment)?;
+        let offset = forward_offset(&pos, interval.len())?;
+        Some(StablePosition {
+            name,
+            pos: interval.start + offset,
+            orient: pos.orient,
+        })
+    }
+
+    /// Translate a position on a stable sequence to the forward
+    /// strand position on the segment that covers it.
+    pub fn from_stable(
+        &self,
+        stable_name: &[u8],
+        pos: usize,
+    ) -> Option<SegmentPosition> {
+        let intervals = self.intervals(stable_name)?;
+        let iv = find_interval(intervals, pos, |iv: &StableInterval| {
+            (iv.start, iv.end)
+        })?;
+        Some(SegmentPosition {
+            segment: iv.segment,
+            offset: pos - iv.start,
+            orient: Orientation::Forward,
+        })
+    }
+}
+
+/// A step of a reference path and the interval of the path it
+/// covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PathInterval {
+    pub start: usize,
+    pub end: usize,
+    /// Index of the segment in `GFA::segments`.
+    pub segment: usize,
+    pub orient: Orientation,
+}
+
+/// Coordinates along a path, used as the reference for liftover
+/// when the graph doesn't have rGFA tags. Overlaps between steps are
+/// ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferencePath {
+    name: Vec<u8>,
+    steps: Vec<PathInterval>,
+    /// The first step of each segment on the path.
+    first_step: FnvHashMap<usize, usize>,
+}
+
+impl ReferencePath {
+    /// Build the coordinates of the path with the given name, which
+    /// can also be a walk named as by `Walk::name`. Returns `None` if
+    /// there's no such path, or if one of its steps refers to a
+    /// missing segment or one with unknown length.
+    pub fn new<N, T>(gfa: &GFA<N, T>, path_name: &[u8]) -> Option<Self>
+    where
+        N: SegmentId + Eq + Hash,
+        T: OptFields,
+    {
+        let path = gfa.path_refs().find(|p| p.name() == path_name)?;
+        let indices = segment_indices(gfa);
+
+        let mut steps = Vec::new();
+        let mut first_step = FnvHashMap::default();
+        let mut offset = 0;
+        for (step_ix, (name, orient)) in path.steps().enumerate() {
+            let segment = *indices.get(&name)?;
+            let len = segment_length(&gfa.segments[segment])?;
+            steps.push(PathInterval {
+                start: offset,
+                end: offset + len,
+                segment,
+                orient,
+            });
+            first_step.entry(segment).or_insert(step_ix);
+            offset += len;
+        }
+
+        Some(ReferencePath {
+            name: path_name.to_vec(),
+            steps,
+            first_step,
+        })
+    }
+
+    pub fn name(&self) -> &[u8] {
+        &self.name
+    }
+
+    pub fn steps(&self) -> &[PathInterval] {
+        &self.steps
+    }
+
+    /// The length of the path in base pairs.
+    pub fn len(&self) -> usize {
+        self.steps.last().map(|s| s.end).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The first step of the path that visits the given segment.
+    pub fn segment_step(&self, segment: usize) -> Option<&PathInterval> {
+        let step_ix = *self.first_step.get(&segment)?;
+        Some(&self.steps[step_ix])
+    }
+
+    /// Translate a position on a segment to its position on the
+    /// path. If the segment is visited more than once, the first
+    /// visit is used. The orientation of the result is relative to
+    /// the path, i.e. it's reverse if the position and the step have
+    /// opposite orientations.
+    pub fn to_stable(
+        &self,
+        pos: SegmentPosition,
+    ) -> Option<StablePosition<'_>> {
+        let step = *self.segment_step(pos.segment)?;
+        let len = step.end - step.start;
+        let offset = forward_offset(&pos, len)?;
+        let (offset, orient) = match step.orient {
+            Orientation::Forward => (offset, pos.orient),
+            Orientation::Backward => {
+                (flip_offset(offset, len), pos.orient.flip())
+            }
+        };
+        Some(StablePosition {
+            name: &self.name,
+            pos: step.start + offset,
+            orient,
+        })
+    }
+
+    /// Translate a position on the path to the position on the
+    /// segment of the step that covers it, in the orientation of the
+    /// step.
+    pub fn from_stable(&self, pos: usize) -> Option<SegmentPosition> {
+        let step = find_interval(&self.steps, pos, |s: &PathInterval| {
+            (s.start, s.end)
+        })?;
+        Some(SegmentPosition {
+            segment: step.segment,
+            offset: pos - step.start,
+            orient: step.orient,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::GFAParser;
+    use Orientation::*;
+
+    fn seg_pos(
+        segment: usize,
+        offset: usize,
+        orient: Orientation,
+    ) -> SegmentPosition {
+        SegmentPosition {
+            segment,
+            offset,
+            orient,
+        }
+    }
+
+    #[test]
+    fn rgfa_liftover() {
+        let parser: GFAParser<Vec<u8>, OptionalFields> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/rgfa.gfa").unwrap();
+        let rgfa = RGfa::new(gfa).unwrap();
+
+        // s2 is chr1:5-7
+        let stable = rgfa.to_stable(seg_pos(1, 1, Forward)).unwrap();
+        assert_eq!(
+            (stable.name, stable.pos, stable.orient),
+            (&b"chr1"[..], 6, Forward)
+        );
+        let stable = rgfa.to_stable(seg_pos(1, 1, Backward)).unwrap();
+        assert_eq!(
+            (stable.name, stable.pos, stable.orient),
+            (&b"chr1"[..], 5, Backward)
+        );
+        assert!(rgfa.to_stable(seg_pos(1, 2, Forward)).is_none());
+
+        assert_eq!(rgfa.from_stable(b"chr1", 6), Some(seg_pos(1, 1, Forward)));
+        assert_eq!(rgfa.from_stable(b"chr1", 7), Some(seg_pos(3, 0, Forward)));
+        assert_eq!(
+            rgfa.from_stable(b"chr2", 106),
+            Some(seg_pos(4, 6, Forward))
+        );
+        assert!(rgfa.from_stable(b"chr1", 12).is_none());
+        assert!(rgfa.from_stable(b"chr2", 99).is_none());
+        assert!(rgfa.from_stable(b"chr3", 0).is_none());
+
+        for pos in 0..12 {
+            let seg = rgfa.from_stable(b"chr1", pos).unwrap();
+            assert_eq!(rgfa.to_stable(seg).unwrap().pos, pos);
+        }
+    }
+
+    #[test]
+    fn path_liftover() {
+        let parser: GFAParser<Vec<u8>, ()> = GFAParser::new();
+        let lines = [
+            "S\ta\tACGT",
+            "S\tb\tGG",
+            "S\tc\tTTA",
+            "P\tref\ta+,b-,c+\t*",
+            "W\tHG1\t1\tchr1\t*\t*\t<c>a",
+        ];
+        let gfa = parser
+            .parse_lines(lines.iter().map(|l| l.as_bytes()))
+            .unwrap();
+        let path = ReferencePath::new(&gfa, b"ref").unwrap();
+        assert_eq!(path.len(), 9);
+        assert!(ReferencePath::new(&gfa, b"alt").is_none());
+        let walk = ReferencePath::new(&gfa, b"HG1#1#chr1").unwrap();
+        assert_eq!(walk.len(), 7);
+        assert_eq!(walk.steps()[0].orient, Backward);
+
+        // b is reversed on the path, so its forward offset 0 is path
+        // position 5, on the reverse strand of the path
+        let stable = path.to_stable(seg_pos(1, 0, Forward)).unwrap();
+        assert_eq!(
+            (stable.name, stable.pos, stable.orient),
+            (&b"ref"[..], 5, Backward)
+        );
+        let stable = path.to_stable(seg_pos(1, 0, Backward)).unwrap();
+        assert_eq!((stable.pos, stable.orient), (4, Forward));
+
+        assert_eq!(path.from_stable(4), Some(seg_pos(1, 0, Backward)));
+        assert_eq!(path.from_stable(8), Some(seg_pos(2, 2, Forward)));
+        assert!(path.from_stable(9).is_none());
+
+        for pos in 0..9 {
+            let seg = path.from_stable(pos).unwrap();
+            let stable = path.to_stable(seg).unwrap();
+            assert_eq!((stable.pos, stable.orient), (pos, Forward));
+        }
+    }
+}
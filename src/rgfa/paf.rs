@@ -0,0 +1,243 @@
+use crate::{
+    cigar::CIGAR,
+    gafpaf::{GAFPath, GAF, PAF},
+    gfa::{Orientation, SegmentId},
+    optfields::*,
+};
+
+use super::{bed::relative, GAFProjector, StableSource};
+
+use std::io::{self, Write};
+
+/// The CIGAR of an alignment to a stable sequence, given the CIGAR of
+/// the alignment to a piece of the path, which is reversed if the
+/// path runs along the reverse strand of the stable sequence.
+fn stable_cigar(cigar: &CIGAR, orient: Orientation) -> CIGAR {
+    match orient {
+        Orientation::Forward => cigar.clone(),
+        Orientation::Backward => CIGAR(cigar.0.iter().rev().copied().collect()),
+    }
+}
+
+impl<'a, N, T, S> GAFProjector<'a, N, T, S>
+where
+    N: SegmentId,
+    T: OptFields,
+    S: StableSource + ?Sized,
+{
+    /// Project an alignment onto stable sequences as PAF records, one
+    /// for each stretch of the alignment on a stable sequence, with
+    /// the stable sequence as the target.
+    ///
+    /// If the alignment projects to a single record covering the
+    /// entire path range, the record keeps the query range, residue
+    /// matches, and block length of the alignment, as well as its cg
+    /// and NM tags. Otherwise, the cg tag is split across the records
+    /// and the query ranges and counts are taken from the pieces of
+    /// the CIGAR, or scaled by the length of each record if there is
+    /// no CIGAR, and the NM tag is dropped.
+    pub fn project_paf<U: OptFields>(
+        &self,
+        gaf: &GAF<U>,
+    ) -> Vec<PAF<OptionalFields>> {
+        let pieces = self.pieces(gaf);
+        let (start, end) = gaf.path_range;
+        let (qs, qe) = gaf.seq_range;
+        let aligned = end.saturating_sub(start);
+        let query_len = qe.saturating_sub(qs);
+        let scale = |count: usize, len: usize| {
+            count.checked_mul(len).map_or(0, |c| c / aligned.max(1))
+        };
+
+        let cigar = gaf.cigar().filter(|cg| {
+            cg.target_len() == aligned && cg.query_len() == query_len
+        });
+        let exact = pieces.len() == 1 && pieces[0].path_range == (start, end);
+
+        let mut rest = cigar.clone();
+        // the target and query bases of the CIGAR already used
+        let mut consumed = (0, 0);
+        let mut records = Vec::with_capacity(pieces.len());
+        for piece in pieces {
+            let t0 = piece.path_range.0 - start;
+            let t1 = piece.path_range.1 - start;
+
+            let (q0, q1, piece_cigar) = match rest.take() {
+                Some(cg) => {
+                    let (skipped, cg) =
+                        cg.split_with_index(cg.ref_index(t0 - consumed.0));
+                    let q0 = consumed.1 + skipped.query_len();
+                    let (piece_cg, remaining) =
+                        cg.split_with_index(cg.ref_index(t1 - t0));
+                    let q1 = q0 + piece_cg.query_len();
+                    consumed = (t1, q1);
+                    rest = Some(remaining);
+                    (q0, q1, Some(piece_cg))
+                }
+                None => (scale(query_len, t0), scale(query_len, t1), None),
+            };
+
+            let (q0, q1, piece_cigar) = if exact {
+                (0, query_len, cigar.clone())
+            } else {
+                (q0, q1, piece_cigar)
+            };
+            let query_seq_range = match gaf.strand {
+                Orientation::Forward => (qs + q0, qs + q1),
+                Orientation::Backward => (qe - q1, qe - q0),
+            };
+
+            let (residue_matches, block_length) = match &piece_cigar {
+                _ if exact => (gaf.residue_matches, gaf.block_length),
+                Some(cg) if cg.matches() + cg.mismatches() > 0 => {
+                    (cg.matches(), cg.len())
+                }
+                Some(cg) => (scale(gaf.residue_matches, t1 - t0), cg.len()),
+                None => (
+                    scale(gaf.residue_matches, t1 - t0),
+                    scale(gaf.block_length, t1 - t0),
+                ),
+            };
+
+            let mut optional = OptionalFields::new();
+            if let Some(cg) = &piece_cigar {
+                let cg = stable_cigar(cg, piece.orient).to_string();
+                optional.push(OptField::new(b"cg", OptFieldVal::Z(cg.into())));
+            }
+            if exact {
+                if let Some(nm) = gaf.optional.get_value(b"NM") {
+                    optional.push(OptField::new(b"NM", nm.into_owned()));
+                }
+            }
+
+            let target_seq_len = match &gaf.path {
+                GAFPath::StableId(_) => gaf.path_len,
+                GAFPath::OrientIntv(_) => {
+                    self.source().stable_len(piece.chrom).unwrap_or(piece.end)
+                }
+            };
+
+            records.push(PAF {
+                query_seq_name: gaf.seq_name.clone(),
+                query_seq_len: gaf.seq_len,
+                query_seq_range,
+                strand: relative(gaf.strand, piece.orient),
+                target_seq_name: piece.chrom.into(),
+                target_seq_len,
+                target_seq_range: (piece.start, piece.end),
+                residue_matches,
+                block_length,
+                quality: gaf.quality,
+                optional,
+            });
+        }
+        records
+    }
+
+    /// Write the projections of GAF records onto stable sequences as
+    /// PAF, returning the number of records written.
+    pub fn write_paf<'b, U, I, W>(
+        &self,
+        records: I,
+        stream: &mut W,
+    ) -> io::Result<usize>
+    where
+        U: OptFields + 'b,
+        I: IntoIterator<Item = &'b GAF<U>>,
+        W: Write,
+    {
+        let mut written = 0;
+        for gaf in records {
+            for paf in self.project_paf(gaf) {
+                writeln!(stream, "{}", paf)?;
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gafpaf::parse_gaf, parser::GFAParser, rgfa::RGfa};
+    use bstr::ByteSlice;
+
+    fn gaf(
+        strand: char,
+        path: &str,
+        (start, end): (usize, usize),
+        tags: &str,
+    ) -> GAF<OptionalFields> {
+        let mut line = format!(
+            "read\t10\t2\t8\t{}\t{}\t200\t{}\t{}\t5\t6\t60",
+            strand, path, start, end
+        );
+        if !tags.is_empty() {
+            line.push('\t');
+            line.push_str(tags);
+        }
+        parse_gaf(line.as_bytes().split_str("\t")).unwrap()
+    }
+
+    #[test]
+    fn project_gaf_to_paf() {
+        let parser: GFAParser<Vec<u8>, OptionalFields> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/rgfa.gfa").unwrap();
+        let rgfa = RGfa::new(gfa).unwrap();
+        let projector = GAFProjector::new(rgfa.gfa(), &rgfa);
+        let pafs = |gaf: &GAF<OptionalFields>| -> Vec<String> {
+            projector
+                .project_paf(gaf)
+                .iter()
+                .map(|p| p.to_string())
+                .collect()
+        };
+
+        // s1 is chr1:0-5, s2 is chr1:5-7, and s4 is chr1:7-12
+        let record = gaf('+', ">s1>s2>s4", (3, 9), "NM:i:1\tcg:Z:6M");
+        assert_eq!(
+            pafs(&record),
+            vec![
+                "read\t10\t2\t8\t+\tchr1\t12\t3\t9\t5\t6\t60\tcg:Z:6M\tNM:i:1"
+            ]
+        );
+
+        // through the alt allele, the alignment and its CIGAR are split
+        let record = gaf('+', ">s1>s3>s4", (3, 9), "NM:i:1\tcg:Z:1=1X1I1=1D2=");
+        assert_eq!(
+            pafs(&record),
+            vec![
+                "read\t10\t2\t5\t+\tchr1\t12\t3\t5\t1\t3\t60\tcg:Z:1=1X1I",
+                "read\t10\t5\t6\t+\talt1\t1\t0\t1\t1\t1\t60\tcg:Z:1=",
+                "read\t10\t6\t8\t+\tchr1\t12\t7\t10\t2\t3\t60\tcg:Z:1D2=",
+            ]
+        );
+
+        // without a CIGAR, the query range is split proportionally
+        let record = gaf('-', ">s1>s3>s4", (3, 9), "NM:i:1");
+        assert_eq!(
+            pafs(&record),
+            vec![
+                "read\t10\t6\t8\t-\tchr1\t12\t3\t5\t1\t2\t60",
+                "read\t10\t5\t6\t-\talt1\t1\t0\t1\t0\t1\t60",
+                "read\t10\t2\t5\t-\tchr1\t12\t7\t10\t2\t3\t60",
+            ]
+        );
+
+        // the path runs along the reverse strand of chr1
+        let record = gaf('+', "<s4<s2", (1, 6), "cg:Z:2=1I3X");
+        assert_eq!(
+            pafs(&record),
+            vec!["read\t10\t2\t8\t-\tchr1\t12\t6\t11\t5\t6\t60\tcg:Z:3X1I2="]
+        );
+
+        let mut output = Vec::new();
+        let records = vec![gaf('+', "chr2", (100, 106), "")];
+        assert_eq!(projector.write_paf(&records, &mut output).unwrap(), 1);
+        assert_eq!(
+            output.to_str().unwrap(),
+            "read\t10\t2\t8\t+\tchr2\t200\t100\t106\t5\t6\t60\n"
+        );
+    }
+}
@@ -0,0 +1,383 @@
+use crate::{
+    gafpaf::{GAFPath, GAFStep, GAF},
+    gfa::{Orientation, SegmentId, GFA},
+    optfields::*,
+};
+
+use super::{PathInterval, RGfa, ReferencePath};
+
+/// A source of stable coordinates that GAF paths on stable sequences
+/// can be resolved against, either the rGFA tags of a graph or its
+/// P-lines.
+pub trait StableSource {
+    /// The segments overlapping `start..end` on the stable sequence
+    /// with the given name, in order along it, with the intervals
+    /// they cover and their orientation on the stable sequence.
+    /// Returns `None` if there is no such stable sequence.
+    fn stable_segments(
+        &self,
+        name: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<Vec<PathInterval>>;
+
+    /// The stable sequence a segment is on, the interval it covers,
+    /// and its orientation on the stable sequence.
+    fn stable_location(&self, segment: usize) -> Option<(&[u8], PathInterval)>;
+
+    /// The length of the stable sequence with the given name, as the
+    /// end of the last segment on it.
+    fn stable_len(&self, name: &[u8]) -> Option<usize> {
+        let segments = self.stable_segments(name, 0, usize::MAX)?;
+        segments.iter().map(|iv| iv.end).max()
+    }
+}
+
+impl<N, T: OptFields> StableSource for RGfa<N, T> {
+    fn stable_segments(
+        &self,
+        name: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<Vec<PathInterval>> {
+        let intervals = self.intervals(name)?;
+        let segments = intervals
+            .iter()
+            .filter(|iv| iv.start < end && iv.end > start)
+            .map(|iv| PathInterval {
+                start: iv.start,
+                end: iv.end,
+                segment: iv.segment,
+                orient: Orientation::Forward,
+            })
+            .collect();
+        Some(segments)
+    }
+
+    fn stable_location(&self, segment: usize) -> Option<(&[u8], PathInterval)> {
+        let (name, iv) = self.segment_interval(segment)?;
+        let interval = PathInterval {
+            start: iv.start,
+            end: iv.end,
+            segment,
+            orient: Orientation::Forward,
+        };
+        Some((name, interval))
+    }
+
+    fn stable_len(&self, name: &[u8]) -> Option<usize> {
+        RGfa::stable_len(self, name)
+    }
+}
+
+impl StableSource for ReferencePath {
+    fn stable_segments(
+        &self,
+        name: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<Vec<PathInterval>> {
+        if name != self.name() {
+            return None;
+        }
+        let steps = self.steps();
+        let first = steps.partition_point(|s| s.end <= start);
+        let last = steps.partition_point(|s| s.start < end);
+        Some(steps[first..last.max(first)].to_vec())
+    }
+
+    fn stable_location(&self, segment: usize) -> Option<(&[u8], PathInterval)> {
+        let step = self.segment_step(segment)?;
+        Some((self.name(), *step))
+    }
+
+    fn stable_len(&self, name: &[u8]) -> Option<usize> {
+        if name == self.name() {
+            Some(self.len())
+        } else {
+            None
+        }
+    }
+}
+
+impl StableSource for [ReferencePath] {
+    fn stable_segments(
+        &self,
+        name: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Option<Vec<PathInterval>> {
+        let path = self.iter().find(|p| p.name() == name)?;
+        path.stable_segments(name, start, end)
+    }
+
+    fn stable_location(&self, segment: usize) -> Option<(&[u8], PathInterval)> {
+        self.iter().find_map(|path| path.stable_location(segment))
+    }
+
+    fn stable_len(&self, name: &[u8]) -> Option<usize> {
+        self.iter().find_map(|path| path.stable_len(name))
+    }
+}
+
+/// The segment steps covering an oriented interval of a stable
+/// sequence, together with the number of bases the steps extend
+/// before the start of the interval, and their total length.
+struct Resolved {
+    steps: Vec<GAFStep>,
+    offset: usize,
+    len: usize,
+}
+
+fn resolve_interval<N, T, S>(
+    gfa: &GFA<N, T>,
+    source: &S,
+    orient: Orientation,
+    name: &[u8],
+    start: usize,
+    end: usize,
+) -> Option<Resolved>
+where
+    N: SegmentId,
+    T: OptFields,
+    S: StableSource + ?Sized,
+{
+    let mut segments = source.stable_segments(name, start, end)?;
+    let first = *segments.first()?;
+    let last = *segments.last()?;
+
+    let offset = match orient {
+        Orientation::Forward => start - first.start,
+        Orientation::Backward => {
+            segments.reverse();
+            last.end - end
+        }
+    };
+
+    let mut len = 0;
+    let mut steps = Vec::with_capacity(segments.len());
+    for iv in segments {
+        let seg = gfa.segments.get(iv.segment)?;
+        let step_orient = match orient {
+            Orientation::Forward => iv.orient,
+            Orientation::Backward => iv.orient.flip(),
+        };
+        steps.push(GAFStep::SegId(step_orient, seg.name.display().into()));
+        len += iv.end - iv.start;
+    }
+
+    Some(Resolved { steps, offset, len })
+}
+
+/// Convert a GAF path into an explicit oriented segment path. A
+/// stable ID resolves to all segments on that stable sequence, and
+/// each stable interval to the segments overlapping it; segment steps
+/// are kept as is. Returns `None` if a stable sequence is unknown to
+/// `source`, or an interval doesn't overlap any segment.
+///
+/// The coordinates of a GAF record refer to its original path, so
+/// use `resolve_gaf` to convert whole records.
+pub fn resolve_gaf_path<N, T, S>(
+    gfa: &GFA<N, T>,
+    source: &S,
+    path: &GAFPath,
+) -> Option<GAFPath>
+where
+    N: SegmentId,
+    T: OptFields,
+    S: StableSource + ?Sized,
+{
+    let steps = match path {
+        GAFPath::StableId(id) => {
+            let forward = Orientation::Forward;
+            resolve_interval(gfa, source, forward, id, 0, usize::MAX)?.steps
+        }
+        GAFPath::OrientIntv(steps) => {
+            let mut resolved = Vec::with_capacity(steps.len());
+            for step in steps {
+                match step {
+                    GAFStep::SegId(..) => resolved.push(step.clone()),
+                    GAFStep::StableIntv(o, id, start, end) => {
+                        let r = resolve_interval(
+                            gfa, source, *o, id, *start, *end,
+                        )?;
+                        resolved.extend(r.steps);
+                    }
+                }
+            }
+            resolved
+        }
+    };
+    Some(GAFPath::OrientIntv(steps))
+}
+
+/// Convert the path of a GAF record into an explicit oriented
+/// segment path, like `resolve_gaf_path`, and update the path length
+/// and range so they refer to the new path. A stable ID only resolves
+/// to the segments covering the path range of the record.
+///
+/// Segment lengths are taken from the stable coordinates, so a
+/// record that mixes segment steps and stable intervals can't be
+/// resolved, and `None` is returned.
+pub fn resolve_gaf<N, T, S, U>(
+    gfa: &GFA<N, T>,
+    source: &S,
+    gaf: &GAF<U>,
+) -> Option<GAF<U>>
+where
+    N: SegmentId,
+    T: OptFields,
+    S: StableSource + ?Sized,
+    U: OptFields + Clone,
+{
+    let (start, end) = gaf.path_range;
+    let forward = Orientation::Forward;
+
+    let (steps, path_range, path_len) = match &gaf.path {
+        GAFPath::StableId(id) => {
+            let r = resolve_interval(gfa, source, forward, id, start, end)?;
+            let range = (r.offset, r.offset + end - start);
+            (r.steps, range, r.len)
+        }
+        GAFPath::OrientIntv(steps) => {
+            let mut resolved = Vec::with_capacity(steps.len());
+            // for each interval, where it starts in the original path,
+            // its length, and where it starts in the resolved path
+            let mut intervals = Vec::with_capacity(steps.len());
+            let (mut old_len, mut len) = (0, 0);
+            for step in steps {
+                match step {
+                    GAFStep::SegId(..) => return None,
+                    GAFStep::StableIntv(o, id, s, e) => {
+                        let r = resolve_interval(gfa, source, *o, id, *s, *e)?;
+                        let interval_len = e.checked_sub(*s)?;
+                        intervals.push((old_len, interval_len, len + r.offset));
+                        old_len += interval_len;
+                        len += r.len;
+                        resolved.extend(r.steps);
+                    }
+                }
+            }
+            // positions on the boundary of two intervals map to the
+            // end of the first for the end of the range, and to the
+            // start of the second for its start
+            let map = |pos: usize, is_end: bool| {
+                intervals
+                    .iter()
+                    .find(|&&(old_start, interval_len, _)| {
+                        let old_end = old_start + interval_len;
+                        pos >= old_start
+                            && (pos < old_end || (is_end && pos == old_end))
+                    })
+                    .map(|&(old_start, _, new_start)| {
+                        new_start + pos - old_start
+                    })
+            };
+            let range = (map(start, false)?, map(end, true)?);
+            (resolved, range, len)
+        }
+    };
+
+    let mut result = gaf.clone();
+    result.path = GAFPath::OrientIntv(steps);
+    result.path_range = path_range;
+    result.path_len = path_len;
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{gaf, parse, rgfa};
+
+    fn path_string(path: &GAFPath) -> String {
+        path.to_string()
+    }
+
+    #[test]
+    fn resolve_rgfa_paths() {
+        let rgfa = rgfa();
+        let gfa = rgfa.gfa();
+
+        let path = gaf("chr1", 12, 0, 12).path;
+        let resolved = resolve_gaf_path(gfa, &rgfa, &path).unwrap();
+        assert_eq!(path_string(&resolved), ">s1>s2>s4");
+
+        let path = gaf(">chr1:3-6<chr2:101-103", 5, 0, 5).path;
+        let resolved = resolve_gaf_path(gfa, &rgfa, &path).unwrap();
+        assert_eq!(path_string(&resolved), ">s1>s2<s5");
+
+        let path = gaf(">s1>chr2:100-101", 6, 0, 6).path;
+        let resolved = resolve_gaf_path(gfa, &rgfa, &path).unwrap();
+        assert_eq!(path_string(&resolved), ">s1>s5");
+
+        let path = gaf(">chr3:0-1", 1, 0, 1).path;
+        assert!(resolve_gaf_path(gfa, &rgfa, &path).is_none());
+        let path = gaf(">chr1:20-30", 10, 0, 10).path;
+        assert!(resolve_gaf_path(gfa, &rgfa, &path).is_none());
+    }
+
+    #[test]
+    fn resolve_gaf_coordinates() {
+        let rgfa = rgfa();
+        let gfa = rgfa.gfa();
+
+        // chr1:6-9 is covered by s2 (chr1:5-7) and s4 (chr1:7-12)
+        let record = gaf("chr1", 12, 6, 9);
+        let resolved = resolve_gaf(gfa, &rgfa, &record).unwrap();
+        assert_eq!(path_string(&resolved.path), ">s2>s4");
+        assert_eq!(resolved.path_len, 7);
+        assert_eq!(resolved.path_range, (1, 4));
+
+        // on the reverse strand, the path starts at the end of s4
+        let record = gaf("<chr1:6-9", 3, 0, 3);
+        let resolved = resolve_gaf(gfa, &rgfa, &record).unwrap();
+        assert_eq!(path_string(&resolved.path), "<s4<s2");
+        assert_eq!(resolved.path_len, 7);
+        assert_eq!(resolved.path_range, (3, 6));
+
+        // across intervals, the untrimmed end of s2 and start of s5
+        // are skipped: chr1:3-6 starts 3 bases into s1 (5 + 2 bases),
+        // and <chr2:101-103 starts 4 bases into the reversed s5
+        let record = gaf(">chr1:3-6<chr2:101-103", 5, 0, 5);
+        let resolved = resolve_gaf(gfa, &rgfa, &record).unwrap();
+        assert_eq!(path_string(&resolved.path), ">s1>s2<s5");
+        assert_eq!(resolved.path_len, 14);
+        assert_eq!(resolved.path_range, (3, 13));
+        let record = gaf(">chr1:3-6<chr2:101-103", 5, 3, 4);
+        let resolved = resolve_gaf(gfa, &rgfa, &record).unwrap();
+        assert_eq!(resolved.path_range, (11, 12));
+        let record = gaf(">chr1:3-6<chr2:101-103", 5, 0, 3);
+        let resolved = resolve_gaf(gfa, &rgfa, &record).unwrap();
+        assert_eq!(resolved.path_range, (3, 6));
+        let record = gaf(">chr1:3-6<chr2:101-103", 5, 0, 6);
+        assert!(resolve_gaf(gfa, &rgfa, &record).is_none());
+
+        let record = gaf(">s1>chr2:100-101", 6, 0, 6);
+        assert!(resolve_gaf(gfa, &rgfa, &record).is_none());
+    }
+
+    #[test]
+    fn resolve_with_reference_paths() {
+        let lines =
+            ["S\ta\tACGT", "S\tb\tGG", "S\tc\tTTA", "P\tref\ta+,b-,c+\t*"];
+        let gfa: GFA<Vec<u8>, ()> = parse(&lines);
+        let paths = vec![ReferencePath::new(&gfa, b"ref").unwrap()];
+
+        let path = gaf("ref", 9, 0, 9).path;
+        let resolved = resolve_gaf_path(&gfa, paths.as_slice(), &path).unwrap();
+        assert_eq!(path_string(&resolved), ">a<b>c");
+
+        let path = gaf("<ref:3-5", 2, 0, 2).path;
+        let resolved = resolve_gaf_path(&gfa, paths.as_slice(), &path).unwrap();
+        assert_eq!(path_string(&resolved), ">b<a");
+
+        let record = gaf("ref", 9, 5, 7);
+        let resolved = resolve_gaf(&gfa, &paths[0], &record).unwrap();
+        assert_eq!(path_string(&resolved.path), "<b>c");
+        assert_eq!(resolved.path_range, (1, 3));
+
+        let path = gaf("alt", 9, 0, 9).path;
+        assert!(resolve_gaf_path(&gfa, paths.as_slice(), &path).is_none());
+    }
+}
@@ -0,0 +1,74 @@
+//! Utilities for nucleotide sequences. Complements handle the IUPAC
+//! ambiguity codes and preserve case; any other byte, such as `-`,
+//! `.`, or `*`, is its own complement.
+
+use bstr::BString;
+
+/// The complement of a nucleotide, including the IUPAC ambiguity
+/// codes, e.g. `R` (A or G) complements to `Y` (C or T). `U` is
+/// complemented to `A`.
+pub fn complement(base: u8) -> u8 {
+    let comp = match base.to_ascii_uppercase() {
+        b'A' => b'T',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'T' | b'U' => b'A',
+        b'R' => b'Y',
+        b'Y' => b'R',
+        b'K' => b'M',
+        b'M' => b'K',
+        b'B' => b'V',
+        b'V' => b'B',
+        b'D' => b'H',
+        b'H' => b'D',
+        // S, W, and N are their own complements
+        _ => return base,
+    };
+    if base.is_ascii_lowercase() {
+        comp.to_ascii_lowercase()
+    } else {
+        comp
+    }
+}
+
+/// The reverse complement of a sequence.
+pub fn revcomp(seq: &[u8]) -> BString {
+    revcomp_iter(seq.iter().copied()).collect::<Vec<_>>().into()
+}
+
+/// Reverse complement a sequence in place.
+pub fn revcomp_in_place(seq: &mut [u8]) {
+    seq.reverse();
+    seq.iter_mut().for_each(|b| *b = complement(*b));
+}
+
+/// Iterate over the reverse complement of the bases produced by the
+/// iterator, without collecting them.
+pub fn revcomp_iter<I>(bases: I) -> impl Iterator<Item = u8>
+where
+    I: DoubleEndedIterator<Item = u8>,
+{
+    bases.rev().map(complement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reverse_complements() {
+        assert_eq!(revcomp(b"ACGTNacgtn"), "nacgtNACGT");
+        assert_eq!(revcomp(b"RYKMSWBDHV"), "BDHVWSKMRY");
+        assert_eq!(revcomp(b"AU-*"), "*-AT");
+        assert_eq!(revcomp(b""), "");
+
+        let mut seq = b"GATTACA".to_vec();
+        revcomp_in_place(&mut seq);
+        assert_eq!(seq, b"TGTAATC");
+        revcomp_in_place(&mut seq);
+        assert_eq!(seq, b"GATTACA");
+
+        let iter: Vec<u8> = revcomp_iter(b"AAC".iter().copied()).collect();
+        assert_eq!(iter, b"GTT");
+    }
+}
@@ -0,0 +1,147 @@
+//! Serde helpers for the byte string fields of the GFA types, used
+//! with `#[serde(with = "..")]`. Byte strings are serialized as
+//! strings when they're valid UTF-8, and as bytes otherwise, and can
+//! be deserialized from strings, bytes, or sequences of integers, so
+//! formats like JSON produce readable output that round-trips.
+
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserializer, Serializer,
+};
+
+use bstr::ByteSlice;
+
+use std::fmt;
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a string or a byte array")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(v.as_bytes().to_vec())
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(v.into_bytes())
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(
+        self,
+        v: Vec<u8>,
+    ) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(b) = seq.next_element()? {
+            bytes.push(b);
+        }
+        Ok(bytes)
+    }
+}
+
+pub(crate) mod bytes {
+    use super::*;
+
+    pub(crate) fn serialize<B, S>(
+        bytes: &B,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        B: AsRef<[u8]> + ?Sized,
+        S: Serializer,
+    {
+        let bytes = bytes.as_ref();
+        match bytes.to_str() {
+            Ok(s) => serializer.serialize_str(s),
+            Err(_) => serializer.serialize_bytes(bytes),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, B, D>(deserializer: D) -> Result<B, D::Error>
+    where
+        B: From<Vec<u8>>,
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_byte_buf(BytesVisitor).map(B::from)
+    }
+}
+
+pub(crate) mod opt_bytes {
+    use super::*;
+    use serde::Deserialize;
+
+    pub(crate) fn serialize<S: Serializer>(
+        bytes: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => {
+                serializer.serialize_some(&super::Wrapper(bytes.as_slice()))
+            }
+            None => serializer.serialize_none(),
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct Owned(#[serde(with = "super::bytes")] Vec<u8>);
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        let bytes: Option<Owned> = Option::deserialize(deserializer)?;
+        Ok(bytes.map(|b| b.0))
+    }
+}
+
+/// Serializes a byte slice with `bytes::serialize`.
+struct Wrapper<'a>(&'a [u8]);
+
+impl<'a> serde::Serialize for Wrapper<'a> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        bytes::serialize(self.0, serializer)
+    }
+}
+
+/// Optional field tags, as two-character strings.
+pub(crate) mod tag {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        tag: &[u8; 2],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        bytes::serialize(tag, serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<[u8; 2], D::Error> {
+        let tag: Vec<u8> = bytes::deserialize(deserializer)?;
+        match tag.as_slice() {
+            [a, b] if a.is_ascii_alphabetic() && b.is_ascii_alphanumeric() => {
+                Ok([*a, *b])
+            }
+            _ => Err(de::Error::invalid_value(
+                de::Unexpected::Bytes(&tag),
+                &"a tag matching [A-Za-z][A-Za-z0-9]",
+            )),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Sequence statistics of segments, such as the base composition, GC
+//! content, and the locations of runs of `N`, for assembly QC. All
+//! statistics are computed from borrowed sequences, so they can also
+//! be used on segments as they're streamed from a parser.
+
+use std::ops::Range;
+
+use crate::{gfa::*, optfields::*};
+
+/// The number of each base in a sequence, ignoring case. `U` is
+/// counted as `T`, and anything other than `ACGTUN` as `other`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Composition {
+    pub a: usize,
+    pub c: usize,
+    pub g: usize,
+    pub t: usize,
+    pub n: usize,
+    pub other: usize,
+}
+
+impl Composition {
+    pub fn of(seq: &[u8]) -> Self {
+        let mut comp = Composition::default();
+        for base in seq {
+            match base.to_ascii_uppercase() {
+                b'A' => comp.a += 1,
+                b'C' => comp.c += 1,
+                b'G' => comp.g += 1,
+                b'T' | b'U' => comp.t += 1,
+                b'N' => comp.n += 1,
+                _ => comp.other += 1,
+            }
+        }
+        comp
+    }
+
+    /// Add the counts of another composition, e.g. to sum up the
+    /// composition of a whole graph.
+    pub fn add(&mut self, other: &Composition) {
+        self.a += other.a;
+        self.c += other.c;
+        self.g += other.g;
+        self.t += other.t;
+        self.n += other.n;
+        self.other += other.other;
+    }
+
+    /// The total number of bases counted.
+    pub fn len(&self) -> usize {
+        self.a + self.c + self.g + self.t + self.n + self.other
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The fraction of G and C among the A, C, G, and T bases, or
+    /// `None` if there are none.
+    pub fn gc_content(&self) -> Option<f64> {
+        let acgt = self.a + self.c + self.g + self.t;
+        if acgt == 0 {
+            None
+        } else {
+            Some((self.g + self.c) as f64 / acgt as f64)
+        }
+    }
+}
+
+/// The GC content of a sequence, as in `Composition::gc_content`.
+pub fn gc_content(seq: &[u8]) -> Option<f64> {
+    Composition::of(seq).gc_content()
+}
+
+/// The 0-based, end-exclusive ranges of the maximal runs of `N` or
+/// `n` in a sequence.
+pub fn n_runs(seq: &[u8]) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut start = None;
+    for (ix, base) in seq.iter().enumerate() {
+        match (base.eq_ignore_ascii_case(&b'N'), start) {
+            (true, None) => start = Some(ix),
+            (false, Some(s)) => {
+                runs.push(s..ix);
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    if let Some(s) = start {
+        runs.push(s..seq.len());
+    }
+    runs
+}
+
+/// The composition statistics of a single segment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentStats<'a, N> {
+    pub name: &'a N,
+    pub composition: Composition,
+    pub n_runs: Vec<Range<usize>>,
+}
+
+impl<N> SegmentStats<'_, N> {
+    pub fn gc_content(&self) -> Option<f64> {
+        self.composition.gc_content()
+    }
+}
+
+/// Compute the statistics of each segment in the graph, one segment
+/// at a time. Segments without a sequence are skipped.
+pub fn segment_stats<'a, N, T>(
+    gfa: &'a GFA<N, T>,
+) -> impl Iterator<Item = SegmentStats<'a, N>> + 'a
+where
+    T: OptFields,
+{
+    gfa.segments
+        .iter()
+        .filter(|seg| seg.sequence != b"*")
+        .map(|seg| SegmentStats {
+            name: &seg.name,
+            composition: Composition::of(&seg.sequence),
+            n_runs: n_runs(&seg.sequence),
+        })
+}
+
+/// The composition of all segment sequences in the graph combined.
+pub fn graph_composition<N, T: OptFields>(gfa: &GFA<N, T>) -> Composition {
+    let mut total = Composition::default();
+    gfa.segments
+        .iter()
+        .filter(|seg| seg.sequence != b"*")
+        .for_each(|seg| total.add(&Composition::of(&seg.sequence)));
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    #[test]
+    fn composition_and_n_runs() {
+        let comp = Composition::of(b"ACGTNNacgun-");
+        assert_eq!(
+            comp,
+            Composition {
+                a: 2,
+                c: 2,
+                g: 2,
+                t: 2,
+                n: 3,
+                other: 1
+            }
+        );
+        assert_eq!(comp.len(), 12);
+        assert_eq!(comp.gc_content(), Some(0.5));
+        assert_eq!(gc_content(b"NNN"), None);
+        assert_eq!(gc_content(b"GGGC"), Some(1.0));
+
+        assert_eq!(n_runs(b"NNACnNNTN"), vec![0..2, 4..7, 8..9]);
+        assert!(n_runs(b"ACGT").is_empty());
+    }
+
+    #[test]
+    fn graph_statistics() {
+        let lines = ["S\t1\tGCNNA", "S\t2\t*", "S\t3\tAATT"];
+        let gfa: GFA<usize, ()> = parse(&lines);
+
+        let stats: Vec<_> = segment_stats(&gfa).collect();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(*stats[0].name, 1);
+        assert_eq!(stats[0].n_runs, vec![2..4]);
+        assert_eq!(stats[1].gc_content(), Some(0.0));
+
+        let total = graph_composition(&gfa);
+        assert_eq!(total.len(), 9);
+        assert_eq!(total.gc_content(), Some(2.0 / 7.0));
+    }
+}
@@ -0,0 +1,855 @@
+//! Validation of parsed GFA graphs. The parser only checks that each
+//! line is well-formed on its own; the functions in this module check
+//! the consistency of records with each other, and report every
+//! problem found along with the record it was found in.
+
+use std::{collections::HashMap, fmt, hash::Hash};
+
+use bstr::ByteSlice;
+
+use crate::{cigar::CIGAR, gfa::*, optfields::*};
+
+/// Identifies a record in a GFA by its line type and its index in
+/// the corresponding vector of the GFA struct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Record {
+    Header,
+    Segment(usize),
+    Link(usize),
+    Containment(usize),
+    Path(usize),
+    Walk(usize),
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Record::Header => write!(f, "header"),
+            Record::Segment(ix) => write!(f, "segment {}", ix),
+            Record::Link(ix) => write!(f, "link {}", ix),
+            Record::Containment(ix) => write!(f, "containment {}", ix),
+            Record::Path(ix) => write!(f, "path {}", ix),
+            Record::Walk(ix) => write!(f, "walk {}", ix),
+        }
+    }
+}
+
+/// The kinds of problems that validation can detect.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// A link or containment refers to a segment that isn't in the
+    /// GFA. Includes the segment name.
+    MissingSegment(String),
+    /// An overlap field is neither `*` nor a valid CIGAR string.
+    /// Includes the overlap as found in the record.
+    InvalidOverlap(String),
+    /// An overlap CIGAR consumes more of a segment than the segment
+    /// is long.
+    OverlapTooLong {
+        segment: String,
+        overlap_len: usize,
+        segment_len: usize,
+    },
+    /// A containment's overlap doesn't span the entire contained
+    /// segment.
+    ContainedLengthMismatch {
+        segment: String,
+        overlap_len: usize,
+        segment_len: usize,
+    },
+    /// A containment's position plus its overlap extends past the
+    /// end of the container.
+    ContainmentOutOfBounds {
+        segment: String,
+        end: usize,
+        segment_len: usize,
+    },
+    /// A containment's position plus its overlap is too large to be
+    /// represented, so it can't be within the container.
+    ContainmentOverflow {
+        segment: String,
+        pos: usize,
+        overlap_len: usize,
+    },
+    /// A segment sequence contains characters outside the alphabet
+    /// it was validated against. Includes the 0-based positions of
+    /// the offending characters.
+    InvalidSequence {
+        alphabet: Alphabet,
+        positions: Vec<usize>,
+    },
+    /// A segment's SH tag doesn't match the SHA-256 checksum of its
+    /// sequence. Both checksums are hex strings.
+    ChecksumMismatch { expected: String, computed: String },
+    /// A record has more than one optional field with the same tag.
+    /// Includes the tag.
+    DuplicateTag(String),
+    /// An optional field tag doesn't match `[A-Za-z][A-Za-z0-9]`.
+    InvalidTag(String),
+    /// An optional field defined by the spec for this record type has
+    /// a different type than the spec requires.
+    TagTypeMismatch {
+        tag: String,
+        expected: char,
+        found: char,
+    },
+    /// An optional field's value contains characters that aren't
+    /// allowed for its type.
+    InvalidTagValue(String),
+    /// A segment's LN tag doesn't match the length of its sequence.
+    LengthMismatch { tag_len: usize, sequence_len: usize },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ValidationError as VE;
+        match self {
+            VE::MissingSegment(seg) => {
+                write!(f, "Reference to missing segment `{}`", seg)
+            }
+            VE::InvalidOverlap(overlap) => {
+                write!(f, "Overlap `{}` is not a valid CIGAR", overlap)
+            }
+            VE::OverlapTooLong {
+                segment,
+                overlap_len,
+                segment_len,
+            } => write!(
+                f,
+                "Overlap covers {} bases of segment `{}`, which has length {}",
+                overlap_len, segment, segment_len
+            ),
+            VE::ContainedLengthMismatch {
+                segment,
+                overlap_len,
+                segment_len,
+            } => write!(
+                f,
+                "Overlap covers {} bases of contained segment `{}`, \
+                 which has length {}",
+                overlap_len, segment, segment_len
+            ),
+            VE::ContainmentOutOfBounds {
+                segment,
+                end,
+                segment_len,
+            } => write!(
+                f,
+                "Containment ends at {} in segment `{}`, which has length {}",
+                end, segment, segment_len
+            ),
+            VE::ContainmentOverflow {
+                segment,
+                pos,
+                overlap_len,
+            } => write!(
+                f,
+                "Containment at {} with an overlap of {} bases overflows \
+                 segment `{}`",
+                pos, overlap_len, segment
+            ),
+            VE::InvalidSequence {
+                alphabet,
+                positions,
+            } => write!(
+                f,
+                "Sequence has {} characters outside the {} alphabet",
+                positions.len(),
+                alphabet
+            ),
+            VE::ChecksumMismatch { expected, computed } => write!(
+                f,
+                "SH tag is {}, but the sequence checksum is {}",
+                expected, computed
+            ),
+            VE::DuplicateTag(tag) => {
+                write!(f, "Tag `{}` appears more than once", tag)
+            }
+            VE::InvalidTag(tag) => write!(f, "`{}` is not a valid tag", tag),
+            VE::TagTypeMismatch {
+                tag,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Tag `{}` should have type {}, but has type {}",
+                tag, expected, found
+            ),
+            VE::InvalidTagValue(tag) => {
+                write!(f, "Tag `{}` has an invalid value for its type", tag)
+            }
+            VE::LengthMismatch {
+                tag_len,
+                sequence_len,
+            } => write!(
+                f,
+                "LN tag is {}, but the sequence has length {}",
+                tag_len, sequence_len
+            ),
+        }
+    }
+}
+
+/// The alphabets segment sequences can be validated against. All
+/// alphabets accept both upper and lower case letters, as lower case
+/// is commonly used for soft-masking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Alphabet {
+    /// Only the four nucleotides `ACGT`.
+    Acgt,
+    /// The IUPAC nucleotide codes, including `U` and `N`.
+    Iupac,
+    /// The IUPAC amino acid codes, including the ambiguity codes
+    /// `BJZX`, and `U` and `O` for selenocysteine and pyrrolysine.
+    AminoAcid,
+    /// Anything allowed by the GFA1 spec, `[A-Za-z=.]`.
+    Permissive,
+}
+
+impl Alphabet {
+    /// Returns true if the byte is part of the alphabet.
+    pub fn contains(&self, byte: u8) -> bool {
+        let upper = byte.to_ascii_uppercase();
+        match self {
+            Alphabet::Acgt => matches!(upper, b'A' | b'C' | b'G' | b'T'),
+            Alphabet::Iupac => b"ACGTURYSWKMBDHVN".contains(&upper),
+            Alphabet::AminoAcid => {
+                b"ACDEFGHIKLMNPQRSTVWYBJZXUO".contains(&upper)
+            }
+            Alphabet::Permissive => {
+                byte.is_ascii_alphabetic() || byte == b'=' || byte == b'.'
+            }
+        }
+    }
+
+    /// Return the positions of all bytes in the sequence that are
+    /// not part of the alphabet.
+    pub fn invalid_positions(&self, seq: &[u8]) -> Vec<usize> {
+        seq.iter()
+            .enumerate()
+            .filter(|(_, &b)| !self.contains(b))
+            .map(|(ix, _)| ix)
+            .collect()
+    }
+}
+
+impl fmt::Display for Alphabet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Alphabet::Acgt => "ACGT",
+            Alphabet::Iupac => "IUPAC nucleotide",
+            Alphabet::AminoAcid => "amino acid",
+            Alphabet::Permissive => "GFA",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A single problem found by validation, and the record it was
+/// found in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    pub record: Record,
+    pub error: ValidationError,
+}
+
+impl ValidationIssue {
+    pub fn new(record: Record, error: ValidationError) -> Self {
+        ValidationIssue { record, error }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.record, self.error)
+    }
+}
+
+/// The length of a segment, taken from its sequence if present, or
+/// from its LN tag if the sequence is `*`.
+pub fn segment_length<N, T, S>(seg: &Segment<N, T, S>) -> Option<usize>
+where
+    T: OptFields,
+    S: AsRef<[u8]>,
+{
+    let sequence = seg.sequence.as_ref();
+    if sequence != b"*" {
+        return Some(sequence.len());
+    }
+    seg.optional.get_typed(b"LN")
+}
+
+/// Map from segment names to their lengths, where known
+fn segment_lengths<N, T>(gfa: &GFA<N, T>) -> HashMap<&N, Option<usize>>
+where
+    N: Eq + Hash,
+    T: OptFields,
+{
+    gfa.segments
+        .iter()
+        .map(|seg| (&seg.name, segment_length(seg)))
+        .collect()
+}
+
+/// Parse an overlap field, returning `Ok(None)` if it's `*`.
+fn parse_overlap(overlap: &[u8]) -> Result<Option<CIGAR>, ValidationError> {
+    crate::gfa::parse_overlap(overlap).map_err(|_| {
+        ValidationError::InvalidOverlap(overlap.to_str_lossy().into_owned())
+    })
+}
+
+/// Check that every link and containment refers to segments that
+/// exist in the GFA.
+pub fn validate_references<N, T>(gfa: &GFA<N, T>) -> Vec<ValidationIssue>
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    let lengths = segment_lengths(gfa);
+    let mut issues = Vec::new();
+
+    let mut check = |record: Record, name: &N| {
+        if !lengths.contains_key(name) {
+            let error = ValidationError::MissingSegment(name.display());
+            issues.push(ValidationIssue::new(record, error));
+        }
+    };
+
+    for (ix, link) in gfa.links.iter().enumerate() {
+        check(Record::Link(ix), &link.from_segment);
+        check(Record::Link(ix), &link.to_segment);
+    }
+
+    for (ix, cont) in gfa.containments.iter().enumerate() {
+        check(Record::Containment(ix), &cont.container_name);
+        check(Record::Containment(ix), &cont.contained_name);
+    }
+
+    issues
+}
+
+/// Parse the overlap CIGARs of links and containments, and check
+/// that their lengths are consistent with the lengths of the
+/// segments involved. Segment lengths are taken from the sequences,
+/// or from LN tags for segments without sequence; if a segment's
+/// length is unknown, or the segment is missing, the corresponding
+/// check is skipped.
+///
+/// For a link, the overlap is an alignment of the end of the `from`
+/// segment (the reference) to the start of the `to` segment (the
+/// query), so its reference length must fit in the former and its
+/// query length in the latter. For a containment, the overlap must
+/// cover the entire contained segment, and must fit in the container
+/// when starting at the containment position.
+pub fn validate_overlaps<N, T>(gfa: &GFA<N, T>) -> Vec<ValidationIssue>
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    let lengths = segment_lengths(gfa);
+    let length = |name: &N| lengths.get(name).copied().flatten();
+
+    let mut issues = Vec::new();
+
+    for (ix, link) in gfa.links.iter().enumerate() {
+        let record = Record::Link(ix);
+        let cigar = match parse_overlap(&link.overlap) {
+            Ok(Some(cigar)) => cigar,
+            Ok(None) => continue,
+            Err(error) => {
+                issues.push(ValidationIssue::new(record, error));
+                continue;
+            }
+        };
+
+        let ends = [
+            (&link.from_segment, cigar.target_len()),
+            (&link.to_segment, cigar.query_len()),
+        ];
+
+        for (name, overlap_len) in ends.iter() {
+            if let Some(segment_len) = length(name) {
+                if *overlap_len > segment_len {
+                    let error = ValidationError::OverlapTooLong {
+                        segment: name.display(),
+                        overlap_len: *overlap_len,
+                        segment_len,
+                    };
+                    issues.push(ValidationIssue::new(record, error));
+                }
+            }
+        }
+    }
+
+    for (ix, cont) in gfa.containments.iter().enumerate() {
+        let record = Record::Containment(ix);
+        let cigar = match parse_overlap(&cont.overlap) {
+            Ok(Some(cigar)) => cigar,
+            Ok(None) => continue,
+            Err(error) => {
+                issues.push(ValidationIssue::new(record, error));
+                continue;
+            }
+        };
+
+        if let Some(segment_len) = length(&cont.contained_name) {
+            let overlap_len = cigar.query_len();
+            if overlap_len != segment_len {
+                let error = ValidationError::ContainedLengthMismatch {
+                    segment: cont.contained_name.display(),
+                    overlap_len,
+                    segment_len,
+                };
+                issues.push(ValidationIssue::new(record, error));
+            }
+        }
+
+        if let Some(segment_len) = length(&cont.container_name) {
+            let overlap_len = cigar.target_len();
+            let error = match cont.pos.checked_add(overlap_len) {
+                Some(end) if end <= segment_len => None,
+                Some(end) => Some(ValidationError::ContainmentOutOfBounds {
+                    segment: cont.container_name.display(),
+                    end,
+                    segment_len,
+                }),
+                None => Some(ValidationError::ContainmentOverflow {
+                    segment: cont.container_name.display(),
+                    pos: cont.pos,
+                    overlap_len,
+                }),
+            };
+            if let Some(error) = error {
+                issues.push(ValidationIssue::new(record, error));
+            }
+        }
+    }
+
+    issues
+}
+
+/// Check that every segment sequence consists only of characters in
+/// the given alphabet, producing one issue per invalid segment.
+/// Segments without sequence (`*`) are skipped.
+pub fn validate_sequences<N, T>(
+    gfa: &GFA<N, T>,
+    alphabet: Alphabet,
+) -> Vec<ValidationIssue>
+where
+    T: OptFields,
+{
+    gfa.segments
+        .iter()
+        .enumerate()
+        .filter(|(_, seg)| seg.sequence.as_slice() != b"*")
+        .filter_map(|(ix, seg)| {
+            let positions = alphabet.invalid_positions(&seg.sequence);
+            if positions.is_empty() {
+                None
+            } else {
+                let error = ValidationError::InvalidSequence {
+                    alphabet,
+                    positions,
+                };
+                Some(ValidationIssue::new(Record::Segment(ix), error))
+            }
+        })
+        .collect()
+}
+
+/// The type the spec requires for a predefined tag on the given
+/// record type, including the rGFA tags on segments.
+fn predefined_tag_type(record: Record, tag: &[u8; 2]) -> Option<u8> {
+    let types: &[(&[u8; 2], u8)] = match record {
+        Record::Header => &[(b"VN", b'Z'), (b"TS", b'i')],
+        Record::Segment(_) => &[
+            (b"LN", b'i'),
+            (b"RC", b'i'),
+            (b"FC", b'i'),
+            (b"KC", b'i'),
+            (b"SH", b'H'),
+            (b"UR", b'Z'),
+            (b"SN", b'Z'),
+            (b"SO", b'i'),
+            (b"SR", b'i'),
+        ],
+        Record::Link(_) => &[
+            (b"MQ", b'i'),
+            (b"NM", b'i'),
+            (b"RC", b'i'),
+            (b"FC", b'i'),
+            (b"KC", b'i'),
+            (b"ID", b'Z'),
+        ],
+        Record::Containment(_) => {
+            &[(b"RC", b'i'), (b"NM", b'i'), (b"ID", b'Z')]
+        }
+        Record::Path(_) | Record::Walk(_) => &[],
+    };
+    types.iter().find(|(t, _)| *t == tag).map(|(_, ty)| *ty)
+}
+
+fn field_type(value: &OptFieldVal) -> u8 {
+    use OptFieldVal::*;
+    match value {
+        A(_) => b'A',
+        Int(_) => b'i',
+        Float(_) => b'f',
+        Z(_) => b'Z',
+        J(_) => b'J',
+        H(_) => b'H',
+        B(_) => b'B',
+    }
+}
+
+/// Check that a value only contains the characters its type allows.
+fn valid_field_value(value: &OptFieldVal) -> bool {
+    use OptFieldVal::*;
+    match value {
+        A(c) => c.is_ascii_graphic(),
+        Z(s) | J(s) => s.iter().all(|&c| c == b' ' || c.is_ascii_graphic()),
+        Float(x) => x.is_finite(),
+        _ => true,
+    }
+}
+
+fn optional_field_issues<T: OptFields>(
+    record: Record,
+    optional: &T,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let fields = optional.to_fields();
+    for (ix, field) in fields.iter().enumerate() {
+        let tag = field.tag.to_str_lossy().into_owned();
+        let mut issue =
+            |error| issues.push(ValidationIssue::new(record, error));
+
+        if !field.tag[0].is_ascii_alphabetic()
+            || !field.tag[1].is_ascii_alphanumeric()
+        {
+            issue(ValidationError::InvalidTag(tag.clone()));
+        }
+        // only report the first repeat of each tag
+        let first = fields.iter().position(|f| f.tag == field.tag);
+        let second = fields[ix + 1..].iter().any(|f| f.tag == field.tag);
+        if first == Some(ix) && second {
+            issue(ValidationError::DuplicateTag(tag.clone()));
+        }
+
+        let found = field_type(&field.value);
+        match predefined_tag_type(record, &field.tag) {
+            Some(expected) if expected != found => {
+                issue(ValidationError::TagTypeMismatch {
+                    tag,
+                    expected: char::from(expected),
+                    found: char::from(found),
+                })
+            }
+            _ if !valid_field_value(&field.value) => {
+                issue(ValidationError::InvalidTagValue(tag))
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Check the optional fields of every record: that tags are valid
+/// and not repeated on a record, that values are valid for their
+/// type, that the tags defined by the spec have the types it
+/// requires, and that segment LN tags match the sequence lengths.
+///
+/// The parser drops optional fields with invalid tags or values, so
+/// on parsed input those are only reported through the parser's
+/// warnings, e.g. by `GFAParser::parse_file_with_warnings`. The
+/// checks here catch them in fields that were constructed directly.
+pub fn validate_optional_fields<N, T>(gfa: &GFA<N, T>) -> Vec<ValidationIssue>
+where
+    T: OptFields,
+{
+    let mut issues = Vec::new();
+
+    optional_field_issues(Record::Header, &gfa.header.optional, &mut issues);
+
+    for (ix, seg) in gfa.segments.iter().enumerate() {
+        let record = Record::Segment(ix);
+        optional_field_issues(record, &seg.optional, &mut issues);
+
+        let tag_len = seg.optional.get_typed::<usize>(b"LN");
+        if let (Some(tag_len), true) = (tag_len, seg.sequence != b"*") {
+            let sequence_len = seg.sequence.len();
+            if tag_len != sequence_len {
+                let error = ValidationError::LengthMismatch {
+                    tag_len,
+                    sequence_len,
+                };
+                issues.push(ValidationIssue::new(record, error));
+            }
+        }
+    }
+
+    for (ix, link) in gfa.links.iter().enumerate() {
+        optional_field_issues(Record::Link(ix), &link.optional, &mut issues);
+    }
+    for (ix, cont) in gfa.containments.iter().enumerate() {
+        let record = Record::Containment(ix);
+        optional_field_issues(record, &cont.optional, &mut issues);
+    }
+    for (ix, path) in gfa.paths.iter().enumerate() {
+        optional_field_issues(Record::Path(ix), &path.optional, &mut issues);
+    }
+    for (ix, walk) in gfa.walks.iter().enumerate() {
+        optional_field_issues(Record::Walk(ix), &walk.optional, &mut issues);
+    }
+
+    issues
+}
+
+/// Run all validation checks on the GFA, returning every issue found.
+/// Sequences are checked against the permissive alphabet of the spec.
+pub fn validate<N, T>(gfa: &GFA<N, T>) -> Vec<ValidationIssue>
+where
+    N: SegmentId + Eq + Hash,
+    T: OptFields,
+{
+    let mut issues = validate_references(gfa);
+    issues.extend(validate_overlaps(gfa));
+    issues.extend(validate_sequences(gfa, Alphabet::Permissive));
+    issues.extend(validate_optional_fields(gfa));
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::GFAParser;
+    use crate::test_util::parse;
+
+    #[test]
+    fn valid_gfas_have_no_issues() {
+        let parser: GFAParser<Vec<u8>, OptionalFields> = GFAParser::new();
+        let gfa = parser.parse_file("./test/gfas/lil.gfa").unwrap();
+        assert!(validate(&gfa).is_empty());
+
+        let gfa: GFA<Vec<u8>, OptionalFields> = parse(&[
+            "S\t1\tACGTACGT",
+            "S\t2\tACG",
+            "S\t3\t*\tLN:i:10",
+            "L\t1\t+\t3\t+\t4M",
+            "L\t3\t-\t1\t+\t2M1I2M",
+            "C\t1\t+\t2\t-\t5\t3M",
+        ]);
+        assert!(validate(&gfa).is_empty());
+    }
+
+    #[test]
+    fn optional_field_issues() {
+        let mut gfa: GFA<Vec<u8>, OptionalFields> = parse(&[
+            "H	VN:Z:1.0	TS:Z:x",
+            "S	1	ACGT	LN:i:4	RC:i:3	RC:i:4	RC:i:5",
+            "S	2	ACG	LN:i:5	SH:Z:abc",
+            "S	3	*	LN:i:4",
+            "L	1	+	2	+	0M	MQ:f:0.5	XX:i:1",
+            "C	1	+	2	+	0	3M	NM:i:1",
+            "P	p	1+,2+	*	LN:Z:any",
+            "W	s	1	c	*	*	>1	XY:i:1	XY:i:2",
+        ]);
+        gfa.links[0].optional.push(OptField {
+            tag: *b"1X",
+            value: OptFieldVal::A(b' '),
+        });
+
+        let issues = validate_optional_fields(&gfa);
+        let expected = vec![
+            ValidationIssue::new(
+                Record::Header,
+                ValidationError::TagTypeMismatch {
+                    tag: "TS".into(),
+                    expected: 'i',
+                    found: 'Z',
+                },
+            ),
+            ValidationIssue::new(
+                Record::Segment(0),
+                ValidationError::DuplicateTag("RC".into()),
+            ),
+            ValidationIssue::new(
+                Record::Segment(1),
+                ValidationError::TagTypeMismatch {
+                    tag: "SH".into(),
+                    expected: 'H',
+                    found: 'Z',
+                },
+            ),
+            ValidationIssue::new(
+                Record::Segment(1),
+                ValidationError::LengthMismatch {
+                    tag_len: 5,
+                    sequence_len: 3,
+                },
+            ),
+            ValidationIssue::new(
+                Record::Link(0),
+                ValidationError::TagTypeMismatch {
+                    tag: "MQ".into(),
+                    expected: 'i',
+                    found: 'f',
+                },
+            ),
+            ValidationIssue::new(
+                Record::Link(0),
+                ValidationError::InvalidTag("1X".into()),
+            ),
+            ValidationIssue::new(
+                Record::Link(0),
+                ValidationError::InvalidTagValue("1X".into()),
+            ),
+            ValidationIssue::new(
+                Record::Walk(0),
+                ValidationError::DuplicateTag("XY".into()),
+            ),
+        ];
+        assert_eq!(issues, expected);
+        assert_eq!(
+            issues[1].to_string(),
+            "segment 0: Tag `RC` appears more than once"
+        );
+    }
+
+    #[test]
+    fn overlap_issues() {
+        let gfa: GFA<Vec<u8>, OptionalFields> = parse(&[
+            "S\t1\tACGTACGT",
+            "S\t2\tACG",
+            "S\t3\t*\tLN:i:4",
+            "S\t4\t*",
+            "L\t1\t+\t2\t+\t4M",
+            "L\t2\t+\t3\t+\t1M4I",
+            "L\t1\t+\t4\t+\t100M",
+            "L\t1\t+\t2\t+\t4Q",
+            "C\t1\t+\t2\t-\t6\t2M",
+            "C\t1\t+\t2\t-\t7\t3M",
+            "C\t1\t+\t2\t-\t18446744073709551615\t3M",
+        ]);
+
+        let issues = validate_overlaps(&gfa);
+
+        let expected = vec![
+            ValidationIssue::new(
+                Record::Link(0),
+                ValidationError::OverlapTooLong {
+                    segment: "2".into(),
+                    overlap_len: 4,
+                    segment_len: 3,
+                },
+            ),
+            ValidationIssue::new(
+                Record::Link(1),
+                ValidationError::OverlapTooLong {
+                    segment: "3".into(),
+                    overlap_len: 5,
+                    segment_len: 4,
+                },
+            ),
+            ValidationIssue::new(
+                Record::Link(2),
+                ValidationError::OverlapTooLong {
+                    segment: "1".into(),
+                    overlap_len: 100,
+                    segment_len: 8,
+                },
+            ),
+            ValidationIssue::new(
+                Record::Link(3),
+                ValidationError::InvalidOverlap("4Q".into()),
+            ),
+            ValidationIssue::new(
+                Record::Containment(0),
+                ValidationError::ContainedLengthMismatch {
+                    segment: "2".into(),
+                    overlap_len: 2,
+                    segment_len: 3,
+                },
+            ),
+            ValidationIssue::new(
+                Record::Containment(1),
+                ValidationError::ContainmentOutOfBounds {
+                    segment: "1".into(),
+                    end: 10,
+                    segment_len: 8,
+                },
+            ),
+            ValidationIssue::new(
+                Record::Containment(2),
+                ValidationError::ContainmentOverflow {
+                    segment: "1".into(),
+                    pos: usize::MAX,
+                    overlap_len: 3,
+                },
+            ),
+        ];
+
+        assert_eq!(issues, expected);
+    }
+
+    #[test]
+    fn sequence_alphabets() {
+        let gfa: GFA<Vec<u8>, OptionalFields> = parse(&[
+            "S\t1\tACGTacgt",
+            "S\t2\tACNNRY",
+            "S\t3\tMKVLE",
+            "S\t4\t*",
+        ]);
+
+        let invalid = |alphabet| -> Vec<(Record, Vec<usize>)> {
+            validate_sequences(&gfa, alphabet)
+                .into_iter()
+                .map(|issue| match issue.error {
+                    ValidationError::InvalidSequence { positions, .. } => {
+                        (issue.record, positions)
+                    }
+                    _ => panic!("unexpected validation error"),
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            invalid(Alphabet::Acgt),
+            vec![
+                (Record::Segment(1), vec![2, 3, 4, 5]),
+                (Record::Segment(2), vec![0, 1, 2, 3, 4]),
+            ]
+        );
+        assert_eq!(
+            invalid(Alphabet::Iupac),
+            vec![(Record::Segment(2), vec![3, 4])]
+        );
+        assert!(invalid(Alphabet::AminoAcid).is_empty());
+        assert!(invalid(Alphabet::Permissive).is_empty());
+
+        let mut gfa: GFA<Vec<u8>, OptionalFields> = parse(&["S\t1\tACGT"]);
+        gfa.segments[0].sequence = b"AC-GT".to_vec();
+        let issues = validate_sequences(&gfa, Alphabet::Permissive);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            issues[0].error,
+            ValidationError::InvalidSequence {
+                alphabet: Alphabet::Permissive,
+                positions: vec![2],
+            }
+        );
+    }
+
+    #[test]
+    fn missing_segments() {
+        let gfa: GFA<Vec<u8>, OptionalFields> =
+            parse(&["S\t1\tACGT", "L\t1\t+\t2\t+\t2M"]);
+        let issues = validate(&gfa);
+        assert_eq!(
+            issues,
+            vec![ValidationIssue::new(
+                Record::Link(0),
+                ValidationError::MissingSegment("2".into())
+            )]
+        );
+    }
+}
@@ -1,113 +1,326 @@
+pub mod table;
+
 use crate::{gfa::*, optfields::*};
 
 use bstr::ByteSlice;
-use std::fmt::Write;
+use std::{
+    collections::HashMap,
+    fmt::{self, Write},
+    hash::Hash,
+};
 
 /// This entire module will probably be removed, with the functions
 /// replaced by Display implementations on GFA and the GFA line types,
 /// but I haven't gotten around to it yet
 
-fn write_optional_fields<U: OptFields, T: Write>(opts: &U, stream: &mut T) {
-    for field in opts.fields() {
-        write!(stream, "\t{}", field).unwrap_or_else(|err| {
-            panic!(
-                "Error writing optional field '{:?}' to stream, {:?}",
-                field, err
-            )
-        })
+/// Writes the optional fields with `try_to_fields`, failing if one
+/// of them can't be written.
+#[allow(clippy::empty_line_after_doc_comments)]
+fn write_optional_fields<U: OptFields, T: Write>(
+    opts: &U,
+    stream: &mut T,
+) -> fmt::Result {
+    for field in opts.try_to_fields().map_err(|_| fmt::Error)?.iter() {
+        write!(stream, "\t{}", field)?;
     }
+    Ok(())
 }
 
-fn write_header<U: OptFields, T: Write>(header: &Header<U>, stream: &mut T) {
-    write!(stream, "H").unwrap();
+fn write_header<U: OptFields, T: Write>(
+    header: &Header<U>,
+    stream: &mut T,
+) -> fmt::Result {
+    write!(stream, "H")?;
     if let Some(v) = &header.version {
-        write!(stream, "\tVN:Z:{}", v.as_bstr()).unwrap();
+        write!(stream, "\tVN:Z:{}", v.as_bstr())?;
     }
-    write_optional_fields(&header.optional, stream);
+    write_optional_fields(&header.optional, stream)
 }
 
 // Write segment
-fn write_segment<N: SegmentId, T: Write, U: OptFields>(
-    seg: &Segment<N, U>,
+fn write_segment<N: SegmentId, T: Write, U: OptFields, S: AsRef<[u8]>>(
+    seg: &Segment<N, U, S>,
     stream: &mut T,
-) {
-    write!(
-        stream,
-        "S\t{}\t{}",
-        seg.name.display(),
-        seg.sequence.as_bstr()
-    )
-    .expect("Error writing segment to stream");
-
-    write_optional_fields(&seg.optional, stream);
+) -> fmt::Result {
+    write!(stream, "S\t")?;
+    seg.name.write_id(stream)?;
+    write!(stream, "\t{}", seg.sequence.as_ref().as_bstr())?;
+    write_optional_fields(&seg.optional, stream)
 }
 
 // Write link
 fn write_link<N: SegmentId, T: Write, U: OptFields>(
     link: &Link<N, U>,
     stream: &mut T,
-) {
+) -> fmt::Result {
+    write!(stream, "L\t")?;
+    link.from_segment.write_id(stream)?;
+    write!(stream, "\t{}\t", link.from_orient)?;
+    link.to_segment.write_id(stream)?;
+    write!(stream, "\t{}\t{}", link.to_orient, link.overlap.as_bstr())?;
+    write_optional_fields(&link.optional, stream)
+}
+
+// Write containment
+fn write_containment<N: SegmentId, T: Write, U: OptFields>(
+    cont: &Containment<N, U>,
+    stream: &mut T,
+) -> fmt::Result {
+    write!(stream, "C\t")?;
+    cont.container_name.write_id(stream)?;
+    write!(stream, "\t{}\t", cont.container_orient)?;
+    cont.contained_name.write_id(stream)?;
     write!(
         stream,
-        "L\t{}\t{}\t{}\t{}\t{}",
-        link.from_segment.display(),
-        link.from_orient,
-        link.to_segment.display(),
-        link.to_orient,
-        link.overlap.as_bstr(),
-    )
-    .expect("Error writing link to stream");
-
-    write_optional_fields(&link.optional, stream);
+        "\t{}\t{}\t{}",
+        cont.contained_orient,
+        cont.pos,
+        cont.overlap.as_bstr()
+    )?;
+    write_optional_fields(&cont.optional, stream)
 }
 
 // Write path
-fn write_path<N, U: OptFields, T: Write>(path: &Path<N, U>, stream: &mut T) {
-    write!(stream, "P\t{}\t", path.path_name.as_bstr())
-        .expect("Error writing path to stream");
-
-    write!(stream, "{}\t", path.segment_names.as_bstr()).unwrap();
+fn write_path<N, U: OptFields, T: Write>(
+    path: &Path<N, U>,
+    stream: &mut T,
+) -> fmt::Result {
+    write!(stream, "P\t{}\t", path.path_name.as_bstr())?;
+    write!(stream, "{}\t", path.segment_names.as_bstr())?;
 
-    path.overlaps.iter().enumerate().for_each(|(i, o)| {
+    for (i, o) in path.overlaps.iter().enumerate() {
         if i != 0 {
-            write!(stream, ",").unwrap();
+            write!(stream, ",")?;
         }
         match o {
-            None => write!(stream, "*").unwrap(),
-            // Some(o) => write!(stream, "{}", o.to_str().unwrap()).unwrap(),
-            Some(o) => write!(stream, "{}", o).unwrap(),
+            None => write!(stream, "*")?,
+            Some(o) => write!(stream, "{}", o)?,
         }
-    });
+    }
 
-    write_optional_fields(&path.optional, stream);
+    write_optional_fields(&path.optional, stream)
 }
 
-// Write GFA
-pub fn write_gfa<N: SegmentId, T: Write, U: OptFields>(
-    gfa: &GFA<N, U>,
+// Write walk
+fn write_walk<N, U: OptFields, T: Write>(
+    walk: &Walk<N, U>,
     stream: &mut T,
-) {
-    write_header(&gfa.header, stream);
-    writeln!(stream).unwrap();
-    gfa.segments.iter().for_each(|s| {
-        write_segment(s, stream);
-        writeln!(stream).unwrap();
-    });
-
-    gfa.paths.iter().for_each(|p| {
-        write_path(p, stream);
-        writeln!(stream).unwrap();
-    });
-
-    gfa.links.iter().for_each(|l| {
-        write_link(l, stream);
-        writeln!(stream).unwrap();
-    });
+) -> fmt::Result {
+    let coord = |c: Option<usize>| c.map_or("*".to_string(), |c| c.to_string());
+    write!(
+        stream,
+        "W\t{}\t{}\t{}\t{}\t{}\t{}",
+        walk.sample_id.as_bstr(),
+        walk.hap_index,
+        walk.seq_id.as_bstr(),
+        coord(walk.seq_start),
+        coord(walk.seq_end),
+        walk.walk.as_bstr()
+    )?;
+    write_optional_fields(&walk.optional, stream)
+}
+
+/// Write a GFA to the stream. Fails if the stream fails, or if an
+/// optional field can't be written, such as a typed field of a
+/// derived `OptFields` holding a `char` that isn't ASCII.
+pub fn write_gfa<N: SegmentId, T: Write, U: OptFields, S: AsRef<[u8]>>(
+    gfa: &GFA<N, U, S>,
+    stream: &mut T,
+) -> fmt::Result {
+    write_header(&gfa.header, stream)?;
+    writeln!(stream)?;
+    for s in gfa.segments.iter() {
+        write_segment(s, stream)?;
+        writeln!(stream)?;
+    }
+
+    for p in gfa.paths.iter() {
+        write_path(p, stream)?;
+        writeln!(stream)?;
+    }
+
+    for w in gfa.walks.iter() {
+        write_walk(w, stream)?;
+        writeln!(stream)?;
+    }
+
+    for l in gfa.links.iter() {
+        write_link(l, stream)?;
+        writeln!(stream)?;
+    }
+
+    for c in gfa.containments.iter() {
+        write_containment(c, stream)?;
+        writeln!(stream)?;
+    }
+    Ok(())
+}
+
+/// A GFA2 position: the offset, followed by `$` if it's the end of
+/// the segment.
+fn gfa2_pos(pos: usize, len: usize) -> String {
+    if pos == len {
+        format!("{}$", pos)
+    } else {
+        pos.to_string()
+    }
+}
+
+/// Write a GFA2 edge between the given ranges of two segments, with
+/// the overlap as its alignment.
+fn write_gfa2_edge<N: SegmentId, T: Write>(
+    (sid1, orient1, range1, len1): (&N, Orientation, (usize, usize), usize),
+    (sid2, orient2, range2, len2): (&N, Orientation, (usize, usize), usize),
+    overlap: &[u8],
+    stream: &mut T,
+) -> fmt::Result {
+    write!(stream, "E\t*\t")?;
+    sid1.write_id(stream)?;
+    write!(stream, "{}\t", orient1)?;
+    sid2.write_id(stream)?;
+    write!(
+        stream,
+        "{}\t{}\t{}\t{}\t{}\t{}",
+        orient2,
+        gfa2_pos(range1.0, len1),
+        gfa2_pos(range1.1, len1),
+        gfa2_pos(range2.0, len2),
+        gfa2_pos(range2.1, len2),
+        overlap.as_bstr()
+    )
+}
+
+/// Write a GFA in the GFA2 format. Links and containments become
+/// edges, and paths and walks become ordered groups named by their
+/// path name and PanSN name, respectively. Path overlaps and walk
+/// coordinates have no GFA2 equivalent and are dropped. An overlap of
+/// `*` is written as an edge of length zero.
+///
+/// Fails if the stream fails, if an optional field can't be written,
+/// or if an edge refers to a segment whose length isn't known, or
+/// has an invalid overlap or one longer than the segment.
+pub fn write_gfa2<N, T, U, S>(gfa: &GFA<N, U, S>, stream: &mut T) -> fmt::Result
+where
+    N: SegmentId + Eq + Hash,
+    T: Write,
+    U: OptFields,
+    S: AsRef<[u8]>,
+{
+    let lengths: HashMap<&N, Option<usize>> = gfa
+        .segments
+        .iter()
+        .map(|s| (&s.name, crate::validation::segment_length(s)))
+        .collect();
+    let length = |name: &N| lengths.get(name).copied().flatten();
+    let overlap = |overlap: &[u8]| {
+        crate::gfa::parse_overlap(overlap).map_err(|_| fmt::Error)
+    };
+
+    write!(stream, "H\tVN:Z:2.0")?;
+    write_optional_fields(&gfa.header.optional, stream)?;
+    writeln!(stream)?;
+
+    for seg in gfa.segments.iter() {
+        let len = length(&seg.name).ok_or(fmt::Error)?;
+        write!(stream, "S\t")?;
+        seg.name.write_id(stream)?;
+        write!(stream, "\t{}\t{}", len, seg.sequence.as_ref().as_bstr())?;
+        write_optional_fields(&seg.optional, stream)?;
+        writeln!(stream)?;
+    }
+
+    for link in gfa.links.iter() {
+        let from_len = length(&link.from_segment).ok_or(fmt::Error)?;
+        let to_len = length(&link.to_segment).ok_or(fmt::Error)?;
+        let (from_ov, to_ov) = match overlap(&link.overlap)? {
+            Some(cigar) => (cigar.target_len(), cigar.query_len()),
+            None => (0, 0),
+        };
+        if from_ov > from_len || to_ov > to_len {
+            return Err(fmt::Error);
+        }
+        // the overlap is at the end of the from segment and the start
+        // of the to segment, as they are oriented
+        let from_range = match link.from_orient {
+            Orientation::Forward => (from_len - from_ov, from_len),
+            Orientation::Backward => (0, from_ov),
+        };
+        let to_range = match link.to_orient {
+            Orientation::Forward => (0, to_ov),
+            Orientation::Backward => (to_len - to_ov, to_len),
+        };
+        write_gfa2_edge(
+            (&link.from_segment, link.from_orient, from_range, from_len),
+            (&link.to_segment, link.to_orient, to_range, to_len),
+            &link.overlap,
+            stream,
+        )?;
+        write_optional_fields(&link.optional, stream)?;
+        writeln!(stream)?;
+    }
+
+    for cont in gfa.containments.iter() {
+        let container_len = length(&cont.container_name).ok_or(fmt::Error)?;
+        let contained_len = length(&cont.contained_name).ok_or(fmt::Error)?;
+        let container_ov = match overlap(&cont.overlap)? {
+            Some(cigar) => cigar.target_len(),
+            None => contained_len,
+        };
+        let end = cont.pos.checked_add(container_ov).ok_or(fmt::Error)?;
+        if end > container_len {
+            return Err(fmt::Error);
+        }
+        write_gfa2_edge(
+            (
+                &cont.container_name,
+                cont.container_orient,
+                (cont.pos, end),
+                container_len,
+            ),
+            (
+                &cont.contained_name,
+                cont.contained_orient,
+                (0, contained_len),
+                contained_len,
+            ),
+            &cont.overlap,
+            stream,
+        )?;
+        write_optional_fields(&cont.optional, stream)?;
+        writeln!(stream)?;
+    }
+
+    for path in gfa.paths.iter() {
+        let refs = path.segment_names.replace(b",", b" ");
+        write!(
+            stream,
+            "O\t{}\t{}",
+            path.path_name.as_bstr(),
+            refs.as_bstr()
+        )?;
+        write_optional_fields(&path.optional, stream)?;
+        writeln!(stream)?;
+    }
+
+    for walk in gfa.walks.iter() {
+        write!(stream, "O\t{}\t", walk.name().as_bstr())?;
+        for (ix, step) in raw_walk_steps(&walk.walk).enumerate() {
+            let orient = Orientation::from_bytes_gt_ln(&step[..1]);
+            let orient = orient.ok_or(fmt::Error)?;
+            let space = if ix == 0 { "" } else { " " };
+            write!(stream, "{}{}{}", space, step[1..].as_bstr(), orient)?;
+        }
+        write_optional_fields(&walk.optional, stream)?;
+        writeln!(stream)?;
+    }
+    Ok(())
 }
 
 pub fn gfa_string(gfa: &GFA<Vec<u8>, OptionalFields>) -> String {
     let mut result = String::new();
-    write_gfa(gfa, &mut result);
+    // writing to a string can't fail, and neither can writing a
+    // vector of optional fields
+    let _ = write_gfa(gfa, &mut result);
     result
 }
 
@@ -122,14 +335,15 @@ mod tests {
         let mut segment: Segment<Vec<u8>, OptionalFields> =
             Segment::new(b"seg1", b"GCCCTA");
         let opt_ij = OptField::new(b"IJ", A(b'x'));
-        let opt_ab = OptField::new(b"AB", BInt(vec![1, 2, 3, 52124]));
+        let opt_ab =
+            OptField::new(b"AB", B(BArray::UInt32(vec![1, 2, 3, 52124])));
         let opt_ur =
             OptField::new(b"UR", Z(Vec::<u8>::from("http://test.com/")));
         let opt_rc = OptField::new(b"RC", Int(123));
         segment.optional = vec![opt_rc, opt_ur, opt_ij, opt_ab];
-        let expected = "S\tseg1\tGCCCTA\tRC:i:123\tUR:Z:http://test.com/\tIJ:A:x\tAB:B:I1,2,3,52124";
+        let expected = "S\tseg1\tGCCCTA\tRC:i:123\tUR:Z:http://test.com/\tIJ:A:x\tAB:B:I,1,2,3,52124";
         let mut string = String::new();
-        write_segment(&segment, &mut string);
+        write_segment(&segment, &mut string).unwrap();
         assert_eq!(string, expected);
     }
 
@@ -143,10 +357,11 @@ mod tests {
             b"0M",
         );
         let mut string = String::new();
-        write_link(&link, &mut string);
+        write_link(&link, &mut string).unwrap();
         assert_eq!(string, "L\t13\t+\t552\t-\t0M");
     }
 
+    #[allow(clippy::useless_vec)]
     #[test]
     fn print_path() {
         use crate::cigar::CIGAR;
@@ -160,10 +375,101 @@ mod tests {
             Path::new("path1".into(), "13+,51-,241+".into(), cigars, ());
 
         let mut string = String::new();
-        write_path(&path, &mut string);
+        write_path(&path, &mut string).unwrap();
         assert_eq!(string, "P\tpath1\t13+,51-,241+\t8M,1M,3M");
     }
 
+    #[test]
+    fn print_walk() {
+        let walk: Walk<Vec<u8>, _> = Walk::new(
+            "HG002".into(),
+            1,
+            "chr1".into(),
+            Some(0),
+            None,
+            ">13<51>241".into(),
+            (),
+        );
+
+        let mut string = String::new();
+        write_walk(&walk, &mut string).unwrap();
+        assert_eq!(string, "W\tHG002\t1\tchr1\t0\t*\t>13<51>241");
+    }
+
+    #[test]
+    fn print_containment() {
+        let cont: Containment<Vec<u8>, ()> = Containment {
+            container_name: b"1".to_vec(),
+            container_orient: Orientation::Forward,
+            contained_name: b"2".to_vec(),
+            contained_orient: Orientation::Backward,
+            pos: 4,
+            overlap: b"3M".to_vec(),
+            optional: (),
+        };
+        let mut string = String::new();
+        write_containment(&cont, &mut string).unwrap();
+        assert_eq!(string, "C\t1\t+\t2\t-\t4\t3M");
+    }
+
+    #[test]
+    fn print_gfa2() {
+        let gfa: GFA<Vec<u8>, OptionalFields> = crate::test_util::parse(&[
+            "H\tVN:Z:1.0",
+            "S\t1\tACGTACGT",
+            "S\t2\t*\tLN:i:5",
+            "S\t3\tACG",
+            "L\t1\t+\t2\t-\t2M",
+            "L\t2\t-\t1\t-\t*",
+            "C\t1\t+\t3\t+\t2\t3M\tNM:i:0",
+            "P\tp\t1+,2-\t2M",
+            "W\ts\t1\tc\t0\t13\t>1<2",
+        ]);
+        let mut string = String::new();
+        write_gfa2(&gfa, &mut string).unwrap();
+        let expected = [
+            "H\tVN:Z:2.0",
+            "S\t1\t8\tACGTACGT",
+            "S\t2\t5\t*\tLN:i:5",
+            "S\t3\t3\tACG",
+            "E\t*\t1+\t2-\t6\t8$\t3\t5$\t2M",
+            "E\t*\t2-\t1-\t0\t0\t8$\t8$\t*",
+            "E\t*\t1+\t3+\t2\t5\t0\t3$\t3M\tNM:i:0",
+            "O\tp\t1+ 2-",
+            "O\ts#1#c\t1+ 2-",
+        ];
+        let lines: Vec<_> = string.lines().collect();
+        assert_eq!(lines, expected);
+
+        // the length of a segment without a sequence must be known
+        let gfa: GFA<Vec<u8>, OptionalFields> =
+            crate::test_util::parse(&["S\t1\t*"]);
+        assert!(write_gfa2(&gfa, &mut String::new()).is_err());
+    }
+
+    #[test]
+    fn print_gfa_custom_ids() {
+        use std::sync::Arc;
+
+        let file_string =
+            std::fs::read_to_string("./test/gfas/lil.gfa").unwrap();
+
+        let parser = crate::parser::GFAParser::new();
+        let arc_gfa: GFA<Arc<str>, ()> =
+            parser.parse_file("./test/gfas/lil.gfa").unwrap();
+        let mut string = String::new();
+        write_gfa(&arc_gfa, &mut string).unwrap();
+        assert_eq!(string, file_string);
+
+        let parser = crate::parser::GFAParser::new();
+        let bstring_gfa: GFA<bstr::BString, ()> =
+            parser.parse_file("./test/gfas/lil.gfa").unwrap();
+        let mut string = String::new();
+        write_gfa(&bstring_gfa, &mut string).unwrap();
+        assert_eq!(string, file_string);
+    }
+
+    #[allow(clippy::needless_borrows_for_generic_args)]
     #[test]
     fn print_gfa() {
         use std::io::Read;
@@ -179,7 +485,7 @@ mod tests {
         file.read_to_string(&mut file_string).unwrap();
 
         let mut string = String::new();
-        write_gfa(&in_gfa, &mut string);
+        write_gfa(&in_gfa, &mut string).unwrap();
 
         assert_eq!(string, file_string);
     }
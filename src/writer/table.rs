@@ -0,0 +1,287 @@
+//! Export of the segments and links of a GFA as two delimited text
+//! tables, one for nodes and one for edges, in the form expected by
+//! tools such as Gephi, or Bandage's CSV label import. Both tables
+//! start with a header row; the node table uses `Id` for the segment
+//! name, and the edge table `Source` and `Target` for the endpoints.
+
+use crate::{
+    gfa::{Link, Segment, SegmentId, GFA},
+    optfields::*,
+    validation::segment_length,
+};
+
+use bstr::ByteSlice;
+
+use std::io::{self, Write};
+
+/// A column of the node table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeColumn {
+    Name,
+    /// The length of the sequence, or the LN tag if the sequence is
+    /// missing.
+    Length,
+    Sequence,
+    /// The value of a single tag, empty if the segment doesn't have
+    /// it.
+    Tag([u8; 2]),
+    /// All the optional fields, separated by spaces, as they'd be
+    /// written in GFA.
+    Tags,
+}
+
+/// A column of the edge table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdgeColumn {
+    Source,
+    SourceOrient,
+    Target,
+    TargetOrient,
+    Overlap,
+    /// The value of a single tag, empty if the link doesn't have it.
+    Tag([u8; 2]),
+    /// All the optional fields, separated by spaces, as they'd be
+    /// written in GFA.
+    Tags,
+}
+
+/// The delimiter and the columns of the exported tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableOptions {
+    pub delimiter: u8,
+    pub node_columns: Vec<NodeColumn>,
+    pub edge_columns: Vec<EdgeColumn>,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        use EdgeColumn::*;
+        TableOptions {
+            delimiter: b',',
+            node_columns: vec![
+                NodeColumn::Name,
+                NodeColumn::Length,
+                NodeColumn::Tags,
+            ],
+            edge_columns: vec![
+                Source,
+                SourceOrient,
+                Target,
+                TargetOrient,
+                Overlap,
+                Tags,
+            ],
+        }
+    }
+}
+
+impl TableOptions {
+    /// Comma-separated tables with the default columns.
+    pub fn csv() -> Self {
+        Default::default()
+    }
+
+    /// Tab-separated tables with the default columns.
+    pub fn tsv() -> Self {
+        TableOptions {
+            delimiter: b'\t',
+            ..Default::default()
+        }
+    }
+}
+
+fn tag_name(tag: &[u8; 2]) -> String {
+    tag.as_bstr().to_string()
+}
+
+fn tag_value<T: OptFields>(optional: &T, tag: &[u8; 2]) -> String {
+    let fields = optional.to_fields();
+    match fields.iter().find(|f| &f.tag == tag) {
+        // skip the tag and type in the GFA representation
+        Some(field) => field.to_string()[5..].to_string(),
+        None => String::new(),
+    }
+}
+
+fn all_tags<T: OptFields>(optional: &T) -> String {
+    let fields: Vec<_> =
+        optional.to_fields().iter().map(|f| f.to_string()).collect();
+    fields.join(" ")
+}
+
+/// Write a row, quoting the cells that contain the delimiter, quotes,
+/// or line breaks.
+pub(crate) fn write_row<W: Write>(
+    stream: &mut W,
+    delimiter: u8,
+    cells: &[String],
+) -> io::Result<()> {
+    for (i, cell) in cells.iter().enumerate() {
+        if i > 0 {
+            stream.write_all(&[delimiter])?;
+        }
+        let needs_quotes = cell
+            .bytes()
+            .any(|b| b == delimiter || matches!(b, b'"' | b'\n' | b'\r'));
+        if needs_quotes {
+            write!(stream, "\"{}\"", cell.replace('"', "\"\""))?;
+        } else {
+            stream.write_all(cell.as_bytes())?;
+        }
+    }
+    writeln!(stream)
+}
+
+fn node_cell<N: SegmentId, T: OptFields>(
+    seg: &Segment<N, T>,
+    column: &NodeColumn,
+) -> String {
+    match column {
+        NodeColumn::Name => seg.name.display(),
+        NodeColumn::Length => segment_length(seg)
+            .map(|len| len.to_string())
+            .unwrap_or_default(),
+        NodeColumn::Sequence => seg.sequence.as_bstr().to_string(),
+        NodeColumn::Tag(tag) => tag_value(&seg.optional, tag),
+        NodeColumn::Tags => all_tags(&seg.optional),
+    }
+}
+
+fn edge_cell<N: SegmentId, T: OptFields>(
+    link: &Link<N, T>,
+    column: &EdgeColumn,
+) -> String {
+    match column {
+        EdgeColumn::Source => link.from_segment.display(),
+        EdgeColumn::SourceOrient => link.from_orient.to_string(),
+        EdgeColumn::Target => link.to_segment.display(),
+        EdgeColumn::TargetOrient => link.to_orient.to_string(),
+        EdgeColumn::Overlap => link.overlap.as_bstr().to_string(),
+        EdgeColumn::Tag(tag) => tag_value(&link.optional, tag),
+        EdgeColumn::Tags => all_tags(&link.optional),
+    }
+}
+
+/// Write the node table, with one row per segment, returning the
+/// number of rows written, excluding the header.
+pub fn write_node_table<N, T, W>(
+    gfa: &GFA<N, T>,
+    options: &TableOptions,
+    stream: &mut W,
+) -> io::Result<usize>
+where
+    N: SegmentId,
+    T: OptFields,
+    W: Write,
+{
+    let header: Vec<_> = options
+        .node_columns
+        .iter()
+        .map(|column| match column {
+            NodeColumn::Name => "Id".to_string(),
+            NodeColumn::Length => "Length".to_string(),
+            NodeColumn::Sequence => "Sequence".to_string(),
+            NodeColumn::Tag(tag) => tag_name(tag),
+            NodeColumn::Tags => "Tags".to_string(),
+        })
+        .collect();
+    write_row(stream, options.delimiter, &header)?;
+
+    for seg in gfa.segments.iter() {
+        let row: Vec<_> = options
+            .node_columns
+            .iter()
+            .map(|column| node_cell(seg, column))
+            .collect();
+        write_row(stream, options.delimiter, &row)?;
+    }
+    Ok(gfa.segments.len())
+}
+
+/// Write the edge table, with one row per link, returning the number
+/// of rows written, excluding the header.
+pub fn write_edge_table<N, T, W>(
+    gfa: &GFA<N, T>,
+    options: &TableOptions,
+    stream: &mut W,
+) -> io::Result<usize>
+where
+    N: SegmentId,
+    T: OptFields,
+    W: Write,
+{
+    let header: Vec<_> = options
+        .edge_columns
+        .iter()
+        .map(|column| match column {
+            EdgeColumn::Source => "Source".to_string(),
+            EdgeColumn::SourceOrient => "SourceOrient".to_string(),
+            EdgeColumn::Target => "Target".to_string(),
+            EdgeColumn::TargetOrient => "TargetOrient".to_string(),
+            EdgeColumn::Overlap => "Overlap".to_string(),
+            EdgeColumn::Tag(tag) => tag_name(tag),
+            EdgeColumn::Tags => "Tags".to_string(),
+        })
+        .collect();
+    write_row(stream, options.delimiter, &header)?;
+
+    for link in gfa.links.iter() {
+        let row: Vec<_> = options
+            .edge_columns
+            .iter()
+            .map(|column| edge_cell(link, column))
+            .collect();
+        write_row(stream, options.delimiter, &row)?;
+    }
+    Ok(gfa.links.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    #[test]
+    fn node_and_edge_tables() {
+        let lines = [
+            "S\t1\tACGT\tRC:i:3\tUR:Z:a,b",
+            "S\t2\t*\tLN:i:12",
+            "L\t1\t+\t2\t-\t4M\tMQ:i:60",
+        ];
+        let gfa: GFA<Vec<u8>, OptionalFields> = parse(&lines);
+
+        let mut nodes = Vec::new();
+        let rows =
+            write_node_table(&gfa, &TableOptions::csv(), &mut nodes).unwrap();
+        assert_eq!(rows, 2);
+        assert_eq!(
+            nodes.as_bstr(),
+            "Id,Length,Tags\n1,4,\"RC:i:3 UR:Z:a,b\"\n2,12,LN:i:12\n"
+        );
+
+        let options = TableOptions {
+            node_columns: vec![NodeColumn::Name, NodeColumn::Tag(*b"RC")],
+            edge_columns: vec![
+                EdgeColumn::Source,
+                EdgeColumn::Target,
+                EdgeColumn::Tag(*b"MQ"),
+            ],
+            ..TableOptions::tsv()
+        };
+        let mut nodes = Vec::new();
+        write_node_table(&gfa, &options, &mut nodes).unwrap();
+        assert_eq!(nodes.as_bstr(), "Id\tRC\n1\t3\n2\t\n");
+
+        let mut edges = Vec::new();
+        write_edge_table(&gfa, &TableOptions::tsv(), &mut edges).unwrap();
+        assert_eq!(
+            edges.as_bstr(),
+            "Source\tSourceOrient\tTarget\tTargetOrient\tOverlap\tTags\n\
+             1\t+\t2\t-\t4M\tMQ:i:60\n"
+        );
+
+        let mut edges = Vec::new();
+        write_edge_table(&gfa, &options, &mut edges).unwrap();
+        assert_eq!(edges.as_bstr(), "Source\tTarget\tMQ\n1\t2\t60\n");
+    }
+}
@@ -0,0 +1,139 @@
+#![cfg(feature = "cli")]
+
+use std::{
+    path::PathBuf,
+    process::{Command, Output},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use gfa::{gfa::GFA, optfields::OptionalFields, parser::GFAParser};
+
+const LIL: &str = "./test/gfas/lil.gfa";
+
+fn gfa(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gfa"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Run the tool, checking that it succeeds, and return its stdout.
+fn gfa_ok(args: &[&str]) -> String {
+    let output = gfa(args);
+    assert!(
+        output.status.success(),
+        "gfa {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// A path in the temporary directory that no other test uses.
+fn temp_path(name: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let id = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let name = format!("rs-gfa-cli-{}-{}-{}", std::process::id(), id, name);
+    std::env::temp_dir().join(name)
+}
+
+fn parse(gfa: &str) -> GFA<Vec<u8>, OptionalFields> {
+    let parser = GFAParser::new();
+    parser
+        .parse_lines(gfa.lines().map(|l| l.as_bytes()))
+        .unwrap()
+}
+
+#[test]
+fn stats() {
+    let stats = gfa_ok(&["stats", LIL]);
+    let lines: Vec<_> = stats.lines().collect();
+    assert!(lines.contains(&"segments\t15"));
+    assert!(lines.contains(&"links\t20"));
+    assert!(lines.contains(&"paths\t3"));
+    assert!(lines.contains(&"walks\t0"));
+    assert!(lines.contains(&"total_length\t55"));
+    assert!(lines.contains(&"n50\t11"));
+}
+
+#[test]
+fn validate() {
+    assert!(gfa_ok(&["validate", LIL]).is_empty());
+
+    let broken = temp_path("broken.gfa");
+    std::fs::write(&broken, "S\t1\tA\nL\t1\t+\t2\t+\t0M\n").unwrap();
+    let output = gfa(&["validate", broken.to_str().unwrap()]);
+    std::fs::remove_file(&broken).unwrap();
+    assert!(!output.status.success());
+    assert!(!output.stdout.is_empty());
+
+    // the parser drops this field, and reports it as a warning
+    let malformed = temp_path("malformed.gfa");
+    std::fs::write(&malformed, "S\t1\tA\tLN:i:abc\n").unwrap();
+    let output = gfa(&["validate", malformed.to_str().unwrap()]);
+    std::fs::remove_file(&malformed).unwrap();
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "line 1: Skipped invalid optional field `LN:i:abc`\n"
+    );
+}
+
+#[test]
+fn convert_round_trips() {
+    let original: GFA<Vec<u8>, OptionalFields> =
+        GFAParser::new().parse_file(LIL).unwrap();
+    assert_eq!(parse(&gfa_ok(&["convert", LIL])), original);
+
+    let json = temp_path("lil.json");
+    let gfa_gz = temp_path("lil.gfa.gz");
+    let json_str = json.to_str().unwrap();
+    let gfa_gz_str = gfa_gz.to_str().unwrap();
+
+    gfa_ok(&["convert", "-t", "json", "-o", json_str, LIL]);
+    gfa_ok(&["convert", "-o", gfa_gz_str, json_str]);
+    let parsed = GFAParser::new().parse_file(&gfa_gz).unwrap();
+    std::fs::remove_file(&json).unwrap();
+    std::fs::remove_file(&gfa_gz).unwrap();
+    assert_eq!(parsed, original);
+
+    // containments and walks are written as well
+    let input = temp_path("cont.gfa");
+    let gfa_str = "S\t1\tACGT\nS\t2\tCG\nC\t1\t+\t2\t+\t1\t2M\n\
+                   W\ts\t1\tc\t*\t*\t>1<2\n";
+    std::fs::write(&input, gfa_str).unwrap();
+    let converted = gfa_ok(&["convert", input.to_str().unwrap()]);
+    let gfa2 = gfa_ok(&["convert", "-t", "gfa2", input.to_str().unwrap()]);
+    std::fs::remove_file(&input).unwrap();
+    assert_eq!(parse(&converted), parse(gfa_str));
+    assert!(gfa2.lines().any(|l| l == "E\t*\t1+\t2+\t1\t3\t0\t2$\t2M"));
+    assert!(gfa2.lines().any(|l| l == "O\ts#1#c\t1+ 2-"));
+}
+
+#[test]
+fn extract() {
+    let range = parse(&gfa_ok(&[
+        "extract", "-p", "x", "--start", "0", "--end", "10", LIL,
+    ]));
+    let names: Vec<_> = range.segments.iter().map(|s| &s.name[..]).collect();
+    assert_eq!(names, vec![&b"1"[..], b"3", b"5"]);
+    assert_eq!(range.links.len(), 2);
+
+    let around = parse(&gfa_ok(&["extract", "-s", "6", LIL]));
+    let mut names: Vec<_> =
+        around.segments.iter().map(|s| &s.name[..]).collect();
+    names.sort();
+    assert_eq!(names, vec![&b"4"[..], b"5", b"6", b"7", b"8"]);
+
+    assert!(!gfa(&["extract", "-p", "missing", LIL]).status.success());
+}
+
+#[test]
+fn paths_to_fasta() {
+    let fasta = gfa_ok(&["paths-to-fasta", "-w", "20", "--lengths", LIL]);
+    let lines: Vec<_> = fasta.lines().collect();
+    assert_eq!(lines.len(), 12);
+    assert_eq!(lines[0], ">x LN:i:50");
+    assert_eq!(lines[1], "CAAATAAGGCTTGGAAATTT");
+    assert_eq!(lines[3], "CAACTCTCTG");
+}
@@ -0,0 +1,118 @@
+#![cfg(feature = "derive")]
+
+use bstr::{BStr, BString};
+use gfa::{
+    gfa::GFA, optfields::*, parser::GFAParser, validation::segment_length,
+    writer::write_gfa,
+};
+
+#[derive(Debug, Default, Clone, PartialEq, OptFields)]
+struct Tags {
+    ln: Option<usize>,
+    #[opt(tag = "cg")]
+    cigar: Option<BString>,
+    rest: OptionalFields,
+}
+
+#[derive(Debug, Default, Clone, OptFields)]
+struct OnlyRC {
+    rc: Option<i64>,
+}
+
+#[derive(Debug, Default, Clone, OptFields)]
+struct Strand {
+    #[opt(tag = "XS")]
+    strand: Option<char>,
+}
+
+#[test]
+fn derived_opt_fields() {
+    let input = vec!["cg:Z:4M", "LN:i:-1", "LN:i:4", "XA:A:x", "RC:i:2"];
+    let tags = Tags::parse(input.clone());
+    assert_eq!(tags.ln, Some(4));
+    assert_eq!(tags.cigar, Some("4M".into()));
+    // LN with a negative length doesn't fit in a usize
+    assert_eq!(tags.rest.len(), 3);
+    assert_eq!(tags.get_field(b"XA").unwrap().value, OptFieldVal::A(b'x'));
+    assert!(tags.get_field(b"LN").is_some());
+
+    let written: Vec<_> =
+        tags.to_fields().iter().map(|f| f.to_string()).collect();
+    assert_eq!(
+        written,
+        vec!["LN:i:4", "cg:Z:4M", "LN:i:-1", "XA:A:x", "RC:i:2"]
+    );
+
+    let mut merged = Tags::parse(vec!["LN:i:10", "XB:i:3"]);
+    merged.merge(tags);
+    assert_eq!(merged.ln, Some(10));
+    assert_eq!(merged.cigar, Some("4M".into()));
+    assert_eq!(merged.rest.len(), 4);
+
+    merged.sort_by_tag();
+    let written: Vec<_> =
+        merged.to_fields().iter().map(|f| f.to_string()).collect();
+    assert_eq!(
+        written,
+        vec!["LN:i:10", "cg:Z:4M", "LN:i:-1", "RC:i:2", "XA:A:x", "XB:i:3"]
+    );
+
+    let rc = OnlyRC::parse(input);
+    assert_eq!(rc.rc, Some(2));
+    assert!(rc.fields().is_empty());
+    assert_eq!(rc.to_fields().len(), 1);
+}
+
+#[test]
+fn parse_and_write_with_derived_fields() {
+    let parser: GFAParser<Vec<u8>, Tags> = GFAParser::new();
+    let lines = ["S\t1\t*\tLN:i:8\tUR:Z:x.fa"];
+    let gfa = parser
+        .parse_lines(lines.iter().map(|l| l.as_bytes()))
+        .unwrap();
+    assert_eq!(gfa.segments[0].optional.ln, Some(8));
+    assert_eq!(gfa.segments[0].optional.rest.len(), 1);
+
+    let mut written = String::new();
+    write_gfa(&gfa, &mut written).unwrap();
+    assert!(written.ends_with("S\t1\t*\tLN:i:8\tUR:Z:x.fa\n"));
+}
+
+#[test]
+fn typed_fields_are_visible_to_lookups() {
+    let tags = Tags::parse(vec!["LN:i:4", "cg:Z:4M", "XA:A:x"]);
+    assert_eq!(tags.get_typed::<i64>(b"LN"), Some(4));
+    assert_eq!(tags.get_typed::<usize>(b"LN"), Some(4));
+    assert_eq!(tags.get_typed::<&BStr>(b"cg"), Some("4M".into()));
+    assert_eq!(tags.get_typed::<&BStr>(b"LN"), None);
+    assert_eq!(tags.get_typed::<char>(b"XA"), Some('x'));
+    assert_eq!(
+        tags.get_value(b"LN").unwrap().as_ref(),
+        &OptFieldVal::Int(4)
+    );
+    assert!(tags.get_value(b"XB").is_none());
+
+    let parser: GFAParser<Vec<u8>, Tags> = GFAParser::new();
+    let gfa: GFA<Vec<u8>, Tags> = parser
+        .parse_lines(["S\t1\t*\tLN:i:8"].iter().map(|l| l.as_bytes()))
+        .unwrap();
+    assert_eq!(segment_length(&gfa.segments[0]), Some(8));
+}
+
+#[test]
+fn unwritable_typed_fields() {
+    let mut gfa: GFA<Vec<u8>, Strand> = GFA::new();
+    let mut segment = gfa::gfa::Segment::new(b"1", b"A");
+    segment.optional = Strand { strand: Some('+') };
+    gfa.segments.push(segment);
+
+    let mut written = String::new();
+    write_gfa(&gfa, &mut written).unwrap();
+    assert!(written.contains("S\t1\tA\tXS:A:+\n"));
+
+    gfa.segments[0].optional.strand = Some('→');
+    let err = gfa.segments[0].optional.try_to_fields().unwrap_err();
+    assert_eq!(err.tag, *b"XS");
+    assert!(gfa.segments[0].optional.to_fields().is_empty());
+    assert!(write_gfa(&gfa, &mut String::new()).is_err());
+}